@@ -0,0 +1,32 @@
+//! `dns-sign <zone file> --key <key file>... [--nsec3] [--origin <name>]
+//! [--inception <time>] [--expiration <time>] [--output <file>]` signs a master
+//! file with keys written by BIND's `dnssec-keygen`, like the `dns-sign`
+//! subcommand of the server.
+//!
+//! The DNSKEY, RRSIG and NSEC or NSEC3 records are added, and the signed zone
+//! is written to `<zone file>.signed` by default. Times are `YYYYMMDDHHmmSS`
+//! or seconds since the epoch; signatures are valid from an hour ago for 30
+//! days unless given. When the output already holds a signed version of the
+//! zone, the signatures of unchanged RRsets are kept until they near expiry.
+
+use dns_starter_rust::{dns_name::name::name_to_string, dnssec::signer::SignJob};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn main() {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |x| x.as_secs());
+    match SignJob::from_args(std::env::args().skip(1)).and_then(|job| job.run(now)) {
+        Ok((origin, output)) => {
+            println!(
+                "Signed {} into {}",
+                name_to_string(&origin),
+                output.display()
+            )
+        }
+        Err(e) => {
+            eprintln!("dns-sign: {:#}", e);
+            std::process::exit(1);
+        }
+    }
+}
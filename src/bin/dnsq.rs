@@ -0,0 +1,119 @@
+//! `dnsq [@<server>] [-p <port>] [-t <type>] [-c <class>] [-q <name>] [<name>]
+//! [<type>] [<class>] [+tcp] [+norec] [+dnssec] [+bufsize=<size>] [+short]
+//! [+json]` queries a server as `dig` does.
+//!
+//! Queries go to port 53 of the local host unless given, for the A records of
+//! the name or the root name servers when there is no name. The response is
+//! printed in dig's format, its answer data alone with `+short`, or as RFC 8427
+//! JSON with `+json` in builds with `--features json`.
+
+use anyhow::{bail, Context};
+use dns_starter_rust::{
+    dns_client::client::Client,
+    dns_message::builder::MessageBuilder,
+    dns_name::name::name_from_string,
+    resrec::{QClass, QType},
+};
+use std::{net::ToSocketAddrs, time::Instant};
+
+fn main() {
+    if let Err(e) = dnsq(std::env::args().skip(1)) {
+        eprintln!("dnsq: {:#}", e);
+        std::process::exit(1);
+    }
+}
+
+fn dnsq(mut args: impl Iterator<Item = String>) -> anyhow::Result<()> {
+    let mut host = String::from("127.0.0.1");
+    let mut port = 53;
+    let mut name = None;
+    let mut typ = None;
+    let mut class = QClass::IN;
+    let mut tcp = false;
+    let mut recursion = true;
+    let mut dnssec = false;
+    let mut bufsize = None;
+    let mut short = false;
+    let mut json = false;
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .with_context(|| format!("{} expects a value", arg))
+        };
+        match arg.as_str() {
+            "-p" => port = value()?.parse().context("invalid port")?,
+            "-t" => typ = Some(value()?.parse()?),
+            "-c" => class = value()?.parse()?,
+            "-q" => name = Some(value()?),
+            "+tcp" => tcp = true,
+            "+notcp" => tcp = false,
+            "+rec" => recursion = true,
+            "+norec" => recursion = false,
+            "+dnssec" => dnssec = true,
+            "+nodnssec" => dnssec = false,
+            "+short" => short = true,
+            "+json" if cfg!(feature = "json") => json = true,
+            "+json" => bail!("+json needs a build with --features json"),
+            _ if arg.starts_with("+bufsize=") => {
+                let size = arg["+bufsize=".len()..].parse();
+                bufsize = Some(size.context("invalid +bufsize")?);
+            }
+            _ if arg.starts_with('@') => host = arg[1..].to_string(),
+            _ if arg.starts_with(['-', '+']) => bail!("unexpected argument {}", arg),
+            // Types and classes are told from names as dig does, `-q` taking
+            // names that look like them.
+            _ => match (arg.parse::<QType>(), arg.parse::<QClass>()) {
+                (Ok(parsed), _) if typ.is_none() => typ = Some(parsed),
+                (_, Ok(parsed)) => class = parsed,
+                _ if name.is_none() => name = Some(arg),
+                _ => bail!("unexpected argument {}", arg),
+            },
+        }
+    }
+
+    let (name, typ) = match (name, typ) {
+        (None, None) => (String::from("."), QType::NS),
+        (name, typ) => (name.unwrap_or_default(), typ.unwrap_or(QType::A)),
+    };
+    let address = (host.as_str(), port)
+        .to_socket_addrs()
+        .with_context(|| host.clone())?
+        .next()
+        .with_context(|| format!("no address for {}", host))?;
+    let mut client = Client::new(address);
+    client.tcp = tcp;
+    // The client advertises our usual payload size unless told otherwise.
+    if let Some(edns) = &mut client.edns {
+        edns.udp_payload_size = bufsize.unwrap_or(edns.udp_payload_size);
+        edns.dnssec_ok = dnssec;
+    }
+    let labels = name_from_string(&name, &[]).with_context(|| format!("invalid name {}", name))?;
+    let mut query = MessageBuilder::query(labels, typ)
+        .recursion_desired(recursion)
+        .build();
+    query.questions[0].qclass = class;
+
+    let start = Instant::now();
+    let response = client.send(&query)?;
+    let elapsed = start.elapsed();
+    if json {
+        #[cfg(feature = "json")]
+        println!("{}", serde_json::to_string(&response)?);
+    } else if short {
+        for record in &response.answers {
+            println!("{}", record.rdata);
+        }
+    } else {
+        println!("{}", response);
+        println!(";; Query time: {} msec", elapsed.as_millis());
+        let transport = if tcp { "TCP" } else { "UDP" };
+        println!(
+            ";; SERVER: {}#{}({}) ({})",
+            address.ip(),
+            address.port(),
+            host,
+            transport
+        );
+    }
+    Ok(())
+}
@@ -1,2 +0,0 @@
-pub mod answer;
-pub mod answer_impl;
@@ -0,0 +1,29 @@
+use crate::dns_record::record::Opt;
+use std::{net::SocketAddr, time::Duration};
+
+/// A stub resolver, sending queries to a recursive server and checking that the
+/// answers come from it (RFC 5452 section 9.1). Queries go over UDP, sent again
+/// when no answer comes in time, and are retried over TCP when the answer is
+/// truncated. A server answering FORMERR to a query with EDNS is asked again
+/// without it (RFC 6891 section 7).
+///
+/// ```no_run
+/// use dns_starter_rust::{dns_client::client::Client, QType};
+///
+/// let client = Client::new("192.0.2.53:53".parse().unwrap());
+/// let response = client.query(vec!["example".into(), "com".into()], QType::A)?;
+/// println!("{}", response);
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct Client {
+    pub server: SocketAddr,
+    /// How long to wait for each answer.
+    pub timeout: Duration,
+    /// How many times a query is sent over UDP before giving up.
+    pub attempts: usize,
+    /// The EDNS parameters added to queries, or `None` to send them without.
+    pub edns: Option<Opt>,
+    /// Whether queries go over TCP from the start rather than UDP.
+    pub tcp: bool,
+}
@@ -0,0 +1,233 @@
+use super::client::Client;
+use crate::{
+    dns_header::header::{QueryResponse, ResponseCode, Truncated},
+    dns_message::{builder::MessageBuilder, message::DnsMessage, message_ref::MessageRef},
+    dns_record::record::Opt,
+    dns_resolver::resolver_impl::EDNS_PAYLOAD_SIZE,
+    resrec::QType,
+    tcp::{read_message, write_message},
+};
+use anyhow::{anyhow, bail, ensure};
+use std::{
+    io::ErrorKind,
+    net::{IpAddr, SocketAddr, TcpStream, UdpSocket},
+    time::{Duration, Instant},
+};
+
+/// The largest message a client without EDNS receives over UDP (RFC 1035
+/// section 2.3.4).
+const UDP_MESSAGE_SIZE: usize = 512;
+
+impl Client {
+    /// A client of `server` waiting 5 seconds for each of 2 attempts, the
+    /// defaults of resolv.conf(5), and advertising EDNS.
+    pub fn new(server: SocketAddr) -> Self {
+        Client {
+            server,
+            timeout: Duration::from_secs(5),
+            attempts: 2,
+            edns: Some(Opt::new(EDNS_PAYLOAD_SIZE, false)),
+            tcp: false,
+        }
+    }
+
+    /// Asks the server for the records of type `qtype` at `qname`, with
+    /// recursion desired and a random ID.
+    pub fn query(&self, qname: Vec<String>, qtype: QType) -> anyhow::Result<DnsMessage> {
+        let query = MessageBuilder::query(qname, qtype)
+            .recursion_desired(true)
+            .build();
+        self.send(&query)
+    }
+
+    /// Sends `query` with the EDNS parameters of the client, if any, and
+    /// returns the answer. Servers that do not understand EDNS are asked again
+    /// without it, under a new ID.
+    pub fn send(&self, query: &DnsMessage) -> anyhow::Result<DnsMessage> {
+        let mut query = query.clone();
+        if let Some(edns) = &self.edns {
+            query.set_edns(edns.clone());
+        }
+        let response = self.exchange(&query)?;
+        if response.header.rcode != ResponseCode::FormatError || query.edns().is_none() {
+            return Ok(response);
+        }
+
+        query.additionals.retain(|r| r.typ != QType::OPT);
+        query.header.packet_id = rand::random();
+        query.update_counts();
+        self.exchange(&query)
+    }
+
+    /// Sends `query` as it is over UDP, retrying over TCP when the answer is
+    /// truncated, or over TCP alone if the client is set to.
+    pub fn exchange(&self, query: &DnsMessage) -> anyhow::Result<DnsMessage> {
+        if self.tcp {
+            return self.exchange_tcp(query);
+        }
+        let response = self.exchange_udp(query)?;
+        match response.header.tc {
+            Truncated::NotTruncated => Ok(response),
+            Truncated::Truncated => self.exchange_tcp(query),
+        }
+    }
+
+    fn exchange_udp(&self, query: &DnsMessage) -> anyhow::Result<DnsMessage> {
+        let local = match self.server.ip() {
+            IpAddr::V4(_) => "0.0.0.0:0",
+            IpAddr::V6(_) => "[::]:0",
+        };
+        let socket = UdpSocket::bind(local)?;
+        let bytes = query.as_bytes();
+        let payload_size = query.edns().map_or(0, |edns| edns.udp_payload_size);
+        let mut buf = vec![0; usize::from(payload_size).max(UDP_MESSAGE_SIZE)];
+
+        for _ in 0..self.attempts {
+            socket.send_to(&bytes, self.server)?;
+            let deadline = Instant::now() + self.timeout;
+            // Stray packets are matched against the query in place, only the
+            // answer being copied out.
+            loop {
+                let left = deadline.saturating_duration_since(Instant::now());
+                if left.is_zero() {
+                    break;
+                }
+                socket.set_read_timeout(Some(left))?;
+                let (size, source) = match socket.recv_from(&mut buf) {
+                    Ok(received) => received,
+                    Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                        break
+                    }
+                    Err(e) => return Err(e.into()),
+                };
+                let answer = MessageRef::new(&buf[..size])
+                    .filter(|x| source == self.server && answers(query, x))
+                    .and_then(|x| x.to_message());
+                if let Some(response) = answer {
+                    return Ok(response);
+                }
+            }
+        }
+        bail!(
+            "no answer from {} after {} attempts",
+            self.server,
+            self.attempts
+        )
+    }
+
+    fn exchange_tcp(&self, query: &DnsMessage) -> anyhow::Result<DnsMessage> {
+        let mut stream = TcpStream::connect_timeout(&self.server, self.timeout)?;
+        stream.set_read_timeout(Some(self.timeout))?;
+        write_message(&mut stream, &query.as_bytes())?;
+        let bytes = read_message(&mut stream)?;
+        let response = MessageRef::new(&bytes).ok_or_else(|| anyhow!("malformed response"))?;
+        ensure!(answers(query, &response), "response does not match query");
+        response
+            .to_message()
+            .ok_or_else(|| anyhow!("malformed response"))
+    }
+}
+
+/// Whether `response` is the answer to `query`, rather than a stray or spoofed
+/// packet.
+pub fn answers(query: &DnsMessage, response: &MessageRef) -> bool {
+    let header = response.header();
+    header.qr == QueryResponse::Reply
+        && header.packet_id == query.header.packet_id
+        && usize::from(header.qdcount) == query.questions.len()
+        && response
+            .questions()
+            .zip(&query.questions)
+            .all(|(x, question)| x.is(question))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        dns_client::client::Client,
+        dns_header::header::{ResponseCode, Truncated},
+        dns_message::{builder::MessageBuilder, message::DnsMessage},
+        dns_record::record::{DnsRecord, RData},
+        resrec::{QClass, QType},
+        tcp::{read_message, write_message},
+    };
+    use std::{
+        net::{Ipv4Addr, TcpListener, UdpSocket},
+        thread,
+        time::Duration,
+    };
+
+    fn name() -> Vec<String> {
+        vec!["www".to_string(), "example".to_string()]
+    }
+
+    fn answer(query: &DnsMessage) -> DnsMessage {
+        MessageBuilder::response_to(query)
+            .answer(DnsRecord {
+                name: name(),
+                typ: QType::A,
+                class: QClass::IN,
+                ttl: 60,
+                rdata: RData::A(Ipv4Addr::new(192, 0, 2, 1)),
+            })
+            .build()
+    }
+
+    fn receive(socket: &UdpSocket) -> (DnsMessage, std::net::SocketAddr) {
+        let mut buf = [0; 512];
+        let (size, source) = socket.recv_from(&mut buf).unwrap();
+        (DnsMessage::parse(&buf[..size]).unwrap(), source)
+    }
+
+    #[test]
+    fn retry_test() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut client = Client::new(server.local_addr().unwrap());
+        client.timeout = Duration::from_millis(200);
+        thread::spawn(move || {
+            // The first query is lost, and the answer to the second is spoofed
+            // from another port before the real one arrives.
+            receive(&server);
+            let (query, source) = receive(&server);
+            let spoofer = UdpSocket::bind("127.0.0.1:0").unwrap();
+            spoofer.send_to(&answer(&query).as_bytes(), source).unwrap();
+            let mut stray = answer(&query);
+            stray.header.packet_id = query.header.packet_id.wrapping_add(1);
+            server.send_to(&stray.as_bytes(), source).unwrap();
+            server.send_to(&answer(&query).as_bytes(), source).unwrap();
+        });
+
+        let response = client.query(name(), QType::A).unwrap();
+        assert_eq!(response.answers.len(), 1);
+        assert!(client.query(name(), QType::A).is_err());
+    }
+
+    #[test]
+    fn fallback_test() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let address = server.local_addr().unwrap();
+        let listener = TcpListener::bind(address).unwrap();
+        thread::spawn(move || {
+            let (query, source) = receive(&server);
+            assert!(query.edns().is_some());
+            let refused = MessageBuilder::response_to(&query)
+                .rcode(ResponseCode::FormatError)
+                .build();
+            server.send_to(&refused.as_bytes(), source).unwrap();
+
+            let (query, source) = receive(&server);
+            assert!(query.edns().is_none());
+            let truncated = MessageBuilder::response_to(&query).truncated(true).build();
+            server.send_to(&truncated.as_bytes(), source).unwrap();
+
+            let (mut stream, _) = listener.accept().unwrap();
+            let query = DnsMessage::parse(&read_message(&mut stream).unwrap()).unwrap();
+            write_message(&mut stream, &answer(&query).as_bytes()).unwrap();
+        });
+
+        let response = Client::new(address).query(name(), QType::A).unwrap();
+        assert_eq!(response.header.tc, Truncated::NotTruncated);
+        assert_eq!(response.answers.len(), 1);
+        assert!(response.edns().is_none());
+    }
+}
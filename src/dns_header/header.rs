@@ -6,7 +6,7 @@
 /// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
 /// |                      ID                       |
 /// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
-/// |QR|   Opcode  |AA|TC|RD|RA|   Z    |   RCODE   |
+/// |QR|   Opcode  |AA|TC|RD|RA| Z|AD|CD|   RCODE   |
 /// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
 /// |                    QDCOUNT                    |
 /// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
@@ -17,7 +17,7 @@
 /// |                    ARCOUNT                    |
 /// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
 /// ```
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct DnsHeader {
     /// A random ID assigned to query packets. Response packets must reply with the same ID.
     pub packet_id: u16,
@@ -35,6 +35,10 @@ pub struct DnsHeader {
     pub ra: RecursionAvailability,
     /// At inception, it was reserved for future use
     pub z: Z,
+    /// Whether all the data in the response was validated with DNSSEC (RFC 4035).
+    pub ad: AuthenticData,
+    /// Whether the client disables DNSSEC validation, accepting bogus data (RFC 4035).
+    pub cd: CheckingDisabled,
     /// Response code indicating the status of the response
     pub rcode: ResponseCode,
     /// Number of questions in the Question section.
@@ -63,7 +67,11 @@ pub enum OpCode {
     IQuery,
     /// (2) Server status request
     Status,
-    /// (3 - 15) reserved for future use
+    /// (4) Zone change notification (RFC 1996)
+    Notify,
+    /// (5) Dynamic update (RFC 2136)
+    Update,
+    /// (3, 6 - 15) reserved for future use
     Reserved(u8),
 }
 
@@ -101,10 +109,26 @@ pub enum RecursionAvailability {
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Z {
-    /// (0 - 7) Reserved
+    /// (0) Reserved
     Reserved,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthenticData {
+    /// (0) Data not validated
+    NotAuthentic,
+    /// (1) Data validated by the server
+    Authentic,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckingDisabled {
+    /// (0) Validation wanted
+    Enabled,
+    /// (1) Validation disabled by the client
+    Disabled,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ResponseCode {
     /// (0) No error condition
@@ -123,6 +147,17 @@ pub enum ResponseCode {
     /// particular requester, or a name server may not wish to perform a particular
     /// operation (e.g., zone transfer) for particular data.
     Refused,
-    // (6 - 15) reserved for future use
+    /// (6) Some name that ought not to exist, does exist.
+    YXDomain,
+    /// (7) Some RRset that ought not to exist, does exist.
+    YXRRSet,
+    /// (8) Some RRset that ought to exist, does not exist.
+    NXRRSet,
+    /// (9) The server is not authoritative for the zone named in the Zone Section.
+    NotAuth,
+    /// (10) A name used in the Prerequisite or Update Section is not within the
+    /// zone denoted by the Zone Section.
+    NotZone,
+    // (11 - 15) reserved for future use
     Reserved(u8),
 }
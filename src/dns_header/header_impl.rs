@@ -1,39 +1,57 @@
 use super::header::{
-    AuthoritativeAnswer, DnsHeader, OpCode, QueryResponse, RecursionAvailability, RecursionDesire,
-    ResponseCode, Truncated, Z,
+    AuthenticData, AuthoritativeAnswer, CheckingDisabled, DnsHeader, OpCode, QueryResponse,
+    RecursionAvailability, RecursionDesire, ResponseCode, Truncated, Z,
 };
-use bytes::{Buf, BufMut, BytesMut};
+use crate::dns_message::message::EncodeError;
+use bytes::{Buf, BufMut};
+use std::fmt;
 
 /// Six sections in the header, each 2 bytes long
-const HEADER_SIZE_IN_BYTES: usize = 2 * 6;
+pub const HEADER_SIZE_IN_BYTES: usize = 2 * 6;
 
 impl DnsHeader {
     pub fn as_bytes(&self) -> Vec<u8> {
         let mut bytes = Vec::with_capacity(HEADER_SIZE_IN_BYTES);
-        bytes.put_u16(self.packet_id);
+        self.write(&mut bytes);
+        bytes
+    }
+
+    /// Writes the header to `buf`, failing when it has less than
+    /// the 12 octets of a header. Returns the octets written.
+    pub fn encode_into(&self, buf: &mut impl BufMut) -> Result<usize, EncodeError> {
+        EncodeError::check(HEADER_SIZE_IN_BYTES, buf)?;
+        self.write(buf);
+        Ok(HEADER_SIZE_IN_BYTES)
+    }
+
+    /// Writes the header to `buf`, which must have room for it.
+    pub fn write(&self, buf: &mut impl BufMut) {
+        buf.put_u16(self.packet_id);
 
         let left_meta = self.qr.as_byte()
             | self.opcode.as_byte()
             | self.aa.as_byte()
             | self.tc.as_byte()
             | self.rd.as_byte();
-        bytes.put_u8(left_meta);
-
-        let right_meta = self.ra.as_byte() | self.z.as_byte() | self.rcode.as_byte();
-        bytes.put_u8(right_meta);
-
-        bytes.put_u16(self.qdcount);
-        bytes.put_u16(self.ancount);
-        bytes.put_u16(self.nscount);
-        bytes.put_u16(self.arcount);
-        bytes
+        buf.put_u8(left_meta);
+
+        let right_meta = self.ra.as_byte()
+            | self.z.as_byte()
+            | self.ad.as_byte()
+            | self.cd.as_byte()
+            | self.rcode.as_byte();
+        buf.put_u8(right_meta);
+
+        buf.put_u16(self.qdcount);
+        buf.put_u16(self.ancount);
+        buf.put_u16(self.nscount);
+        buf.put_u16(self.arcount);
     }
 }
 
 impl From<&[u8]> for DnsHeader {
     fn from(val: &[u8]) -> Self {
-        let mut header = BytesMut::with_capacity(HEADER_SIZE_IN_BYTES);
-        header.put(&val[0..HEADER_SIZE_IN_BYTES]);
+        let mut header = &val[0..HEADER_SIZE_IN_BYTES];
         let packet_id = header.get_u16();
         let left_meta = header.get_u8();
         let right_meta = header.get_u8();
@@ -50,6 +68,8 @@ impl From<&[u8]> for DnsHeader {
 
         let ra = RecursionAvailability::from(right_meta);
         let z = Z::from(right_meta);
+        let ad = AuthenticData::from(right_meta);
+        let cd = CheckingDisabled::from(right_meta);
 
         let rcode = match opcode {
             OpCode::IQuery => ResponseCode::NotImplemented,
@@ -67,6 +87,8 @@ impl From<&[u8]> for DnsHeader {
             rd,
             ra,
             z,
+            ad,
+            cd,
             rcode,
             qdcount,
             ancount,
@@ -97,11 +119,13 @@ impl From<u8> for QueryResponse {
 }
 
 impl OpCode {
-    fn as_byte(&self) -> u8 {
+    pub fn as_byte(&self) -> u8 {
         let val = match self {
             OpCode::Query => 0,
             OpCode::IQuery => 1,
             OpCode::Status => 2,
+            OpCode::Notify => 4,
+            OpCode::Update => 5,
             OpCode::Reserved(value) => *value,
         };
         val << 3
@@ -115,6 +139,8 @@ impl From<u8> for OpCode {
             0 => OpCode::Query,
             1 => OpCode::IQuery,
             2 => OpCode::Status,
+            4 => OpCode::Notify,
+            5 => OpCode::Update,
             _ => OpCode::Reserved(val),
         }
     }
@@ -201,7 +227,7 @@ impl From<u8> for RecursionAvailability {
 
 impl Z {
     fn as_byte(&self) -> u8 {
-        0 << 4
+        0 << 6
     }
 }
 
@@ -211,8 +237,48 @@ impl From<u8> for Z {
     }
 }
 
-impl ResponseCode {
+impl AuthenticData {
     fn as_byte(&self) -> u8 {
+        let val = match self {
+            AuthenticData::NotAuthentic => 0,
+            AuthenticData::Authentic => 1,
+        };
+        val << 5
+    }
+}
+
+impl From<u8> for AuthenticData {
+    fn from(val: u8) -> Self {
+        let val = (val & 0b0010_0000) >> 5;
+        match val {
+            0 => AuthenticData::NotAuthentic,
+            _ => AuthenticData::Authentic,
+        }
+    }
+}
+
+impl CheckingDisabled {
+    fn as_byte(&self) -> u8 {
+        let val = match self {
+            CheckingDisabled::Enabled => 0,
+            CheckingDisabled::Disabled => 1,
+        };
+        val << 4
+    }
+}
+
+impl From<u8> for CheckingDisabled {
+    fn from(val: u8) -> Self {
+        let val = (val & 0b0001_0000) >> 4;
+        match val {
+            0 => CheckingDisabled::Enabled,
+            _ => CheckingDisabled::Disabled,
+        }
+    }
+}
+
+impl ResponseCode {
+    pub fn as_byte(&self) -> u8 {
         match self {
             ResponseCode::NoErrorCondition => 0,
             ResponseCode::FormatError => 1,
@@ -220,6 +286,11 @@ impl ResponseCode {
             ResponseCode::NameError => 3,
             ResponseCode::NotImplemented => 4,
             ResponseCode::Refused => 5,
+            ResponseCode::YXDomain => 6,
+            ResponseCode::YXRRSet => 7,
+            ResponseCode::NXRRSet => 8,
+            ResponseCode::NotAuth => 9,
+            ResponseCode::NotZone => 10,
             ResponseCode::Reserved(value) => *value,
         }
     }
@@ -235,16 +306,102 @@ impl From<u8> for ResponseCode {
             3 => ResponseCode::NameError,
             4 => ResponseCode::NotImplemented,
             5 => ResponseCode::Refused,
+            6 => ResponseCode::YXDomain,
+            7 => ResponseCode::YXRRSet,
+            8 => ResponseCode::NXRRSet,
+            9 => ResponseCode::NotAuth,
+            10 => ResponseCode::NotZone,
             _ => ResponseCode::Reserved(val),
         }
     }
 }
 
+/// The two header lines of `dig`:
+///
+/// ```text
+/// ;; ->>HEADER<<- opcode: QUERY, status: NOERROR, id: 4660
+/// ;; flags: qr rd ra; QUERY: 1, ANSWER: 1, AUTHORITY: 0, ADDITIONAL: 1
+/// ```
+impl fmt::Display for DnsHeader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            ";; ->>HEADER<<- opcode: {}, status: {}, id: {}",
+            self.opcode, self.rcode, self.packet_id
+        )?;
+        let flags = [
+            ("qr", self.qr == QueryResponse::Reply),
+            ("aa", self.aa == AuthoritativeAnswer::Authoritative),
+            ("tc", self.tc == Truncated::Truncated),
+            ("rd", self.rd == RecursionDesire::Desired),
+            ("ra", self.ra == RecursionAvailability::Available),
+            ("ad", self.ad == AuthenticData::Authentic),
+            ("cd", self.cd == CheckingDisabled::Disabled),
+        ];
+        let flags = flags
+            .iter()
+            .filter(|(_, set)| *set)
+            .map(|(flag, _)| *flag)
+            .collect::<Vec<_>>();
+        // UPDATE names its sections differently (RFC 2136 section 2).
+        let sections = match self.opcode {
+            OpCode::Update => ["ZONE", "PREREQ", "UPDATE", "ADDITIONAL"],
+            _ => ["QUERY", "ANSWER", "AUTHORITY", "ADDITIONAL"],
+        };
+        write!(
+            f,
+            ";; flags: {}; {}: {}, {}: {}, {}: {}, {}: {}",
+            flags.join(" "),
+            sections[0],
+            self.qdcount,
+            sections[1],
+            self.ancount,
+            sections[2],
+            self.nscount,
+            sections[3],
+            self.arcount
+        )
+    }
+}
+
+impl fmt::Display for OpCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OpCode::Query => write!(f, "QUERY"),
+            OpCode::IQuery => write!(f, "IQUERY"),
+            OpCode::Status => write!(f, "STATUS"),
+            OpCode::Notify => write!(f, "NOTIFY"),
+            OpCode::Update => write!(f, "UPDATE"),
+            OpCode::Reserved(value) => write!(f, "RESERVED{}", value),
+        }
+    }
+}
+
+/// The mnemonics of the IANA DNS RCODE registry.
+impl fmt::Display for ResponseCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResponseCode::NoErrorCondition => write!(f, "NOERROR"),
+            ResponseCode::FormatError => write!(f, "FORMERR"),
+            ResponseCode::ServerFailure => write!(f, "SERVFAIL"),
+            ResponseCode::NameError => write!(f, "NXDOMAIN"),
+            ResponseCode::NotImplemented => write!(f, "NOTIMP"),
+            ResponseCode::Refused => write!(f, "REFUSED"),
+            ResponseCode::YXDomain => write!(f, "YXDOMAIN"),
+            ResponseCode::YXRRSet => write!(f, "YXRRSET"),
+            ResponseCode::NXRRSet => write!(f, "NXRRSET"),
+            ResponseCode::NotAuth => write!(f, "NOTAUTH"),
+            ResponseCode::NotZone => write!(f, "NOTZONE"),
+            ResponseCode::Reserved(value) => write!(f, "RESERVED{}", value),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::dns_header::header::{
-        AuthoritativeAnswer, DnsHeader, OpCode, QueryResponse, RecursionAvailability,
-        RecursionDesire, ResponseCode, Truncated, Z,
+        AuthenticData, AuthoritativeAnswer, CheckingDisabled, DnsHeader, OpCode, QueryResponse,
+        RecursionAvailability, RecursionDesire, ResponseCode, Truncated, Z,
     };
 
     #[test]
@@ -258,6 +415,8 @@ mod tests {
             rd: RecursionDesire::NotDesired,
             ra: RecursionAvailability::NotAvailable,
             z: Z::Reserved,
+            ad: AuthenticData::NotAuthentic,
+            cd: CheckingDisabled::Enabled,
             rcode: ResponseCode::NoErrorCondition,
             qdcount: 1,
             ancount: 1,
@@ -294,6 +453,16 @@ mod tests {
         assert_eq!(OpCode::IQuery.as_byte(), 0b0000_1000);
         assert_eq!(OpCode::Status.as_byte(), 0b0001_0000);
         assert_eq!(OpCode::Reserved(3).as_byte(), 0b0001_1000);
+        assert_eq!(OpCode::Notify.as_byte(), 0b0010_0000);
+        assert_eq!(OpCode::Update.as_byte(), 0b0010_1000);
+    }
+
+    #[test]
+    fn opcode_from_byte_test() {
+        assert_eq!(OpCode::from(0b0000_0000), OpCode::Query);
+        assert_eq!(OpCode::from(0b0010_0000), OpCode::Notify);
+        assert_eq!(OpCode::from(0b0010_1000), OpCode::Update);
+        assert_eq!(OpCode::from(0b0001_1000), OpCode::Reserved(3));
     }
 
     #[test]
@@ -325,6 +494,23 @@ mod tests {
         assert_eq!(Z::Reserved.as_byte(), 0b0000_0000);
     }
 
+    #[test]
+    fn authentic_data_test() {
+        assert_eq!(AuthenticData::NotAuthentic.as_byte(), 0b0000_0000);
+        assert_eq!(AuthenticData::Authentic.as_byte(), 0b0010_0000);
+        assert_eq!(AuthenticData::from(0b1010_0011), AuthenticData::Authentic);
+    }
+
+    #[test]
+    fn checking_disabled_test() {
+        assert_eq!(CheckingDisabled::Enabled.as_byte(), 0b0000_0000);
+        assert_eq!(CheckingDisabled::Disabled.as_byte(), 0b0001_0000);
+        assert_eq!(
+            CheckingDisabled::from(0b1001_0011),
+            CheckingDisabled::Disabled
+        );
+    }
+
     #[test]
     fn response_code_as_byte_test() {
         assert_eq!(ResponseCode::NoErrorCondition.as_byte(), 0b0000_0000);
@@ -333,6 +519,18 @@ mod tests {
         assert_eq!(ResponseCode::NameError.as_byte(), 0b0000_0011);
         assert_eq!(ResponseCode::NotImplemented.as_byte(), 0b0000_0100);
         assert_eq!(ResponseCode::Refused.as_byte(), 0b0000_0101);
-        assert_eq!(ResponseCode::Reserved(6).as_byte(), 0b0000_0110);
+        assert_eq!(ResponseCode::YXDomain.as_byte(), 0b0000_0110);
+        assert_eq!(ResponseCode::YXRRSet.as_byte(), 0b0000_0111);
+        assert_eq!(ResponseCode::NXRRSet.as_byte(), 0b0000_1000);
+        assert_eq!(ResponseCode::NotAuth.as_byte(), 0b0000_1001);
+        assert_eq!(ResponseCode::NotZone.as_byte(), 0b0000_1010);
+        assert_eq!(ResponseCode::Reserved(11).as_byte(), 0b0000_1011);
+    }
+
+    #[test]
+    fn response_code_from_byte_test() {
+        assert_eq!(ResponseCode::from(0b0000_0110), ResponseCode::YXDomain);
+        assert_eq!(ResponseCode::from(0b0000_1010), ResponseCode::NotZone);
+        assert_eq!(ResponseCode::from(0b0000_1011), ResponseCode::Reserved(11));
     }
 }
@@ -1,2 +1,2 @@
 pub mod header;
-pub mod header_impl;
+pub(crate) mod header_impl;
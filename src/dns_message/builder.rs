@@ -0,0 +1,24 @@
+use super::message::DnsMessage;
+use crate::dns_record::record::Opt;
+
+/// Builds a [`DnsMessage`] a section at a time, with the header counts computed
+/// by `build`. Starts from a query or from the response to one:
+///
+/// ```
+/// use dns_starter_rust::{dns_message::builder::MessageBuilder, QType};
+///
+/// let query = MessageBuilder::query(vec!["example".into(), "com".into()], QType::A)
+///     .recursion_desired(true)
+///     .build();
+/// let response = MessageBuilder::response_to(&query)
+///     .recursion_available(true)
+///     .build();
+/// assert_eq!(response.header.packet_id, query.header.packet_id);
+/// assert_eq!(response.header.qdcount, 1);
+/// ```
+#[derive(Debug, Clone)]
+pub struct MessageBuilder {
+    pub message: DnsMessage,
+    /// The EDNS parameters, added as an OPT record ahead of any TSIG one.
+    pub edns: Option<Opt>,
+}
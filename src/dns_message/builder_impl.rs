@@ -0,0 +1,290 @@
+use super::{builder::MessageBuilder, message::DnsMessage};
+use crate::{
+    dns_header::header::{
+        AuthenticData, AuthoritativeAnswer, CheckingDisabled, DnsHeader, OpCode, QueryResponse,
+        RecursionAvailability, RecursionDesire, ResponseCode, Truncated, Z,
+    },
+    dns_question::question::DnsQuestion,
+    dns_record::record::{DnsRecord, Opt},
+    resrec::{QClass, QType},
+};
+
+impl MessageBuilder {
+    /// A standard query for `qname` in class IN with a random ID and every flag
+    /// clear.
+    pub fn query(qname: Vec<String>, qtype: QType) -> Self {
+        let message = DnsMessage {
+            header: DnsHeader {
+                packet_id: rand::random(),
+                qr: QueryResponse::Question,
+                opcode: OpCode::Query,
+                aa: AuthoritativeAnswer::NonAuthoritative,
+                tc: Truncated::NotTruncated,
+                rd: RecursionDesire::NotDesired,
+                ra: RecursionAvailability::NotAvailable,
+                z: Z::Reserved,
+                ad: AuthenticData::NotAuthentic,
+                cd: CheckingDisabled::Enabled,
+                rcode: ResponseCode::NoErrorCondition,
+                qdcount: 0,
+                ancount: 0,
+                nscount: 0,
+                arcount: 0,
+            },
+            questions: Vec::new(),
+            answers: Vec::new(),
+            authorities: Vec::new(),
+            additionals: Vec::new(),
+        };
+        MessageBuilder {
+            message,
+            edns: None,
+        }
+        .question(qname, qtype)
+    }
+
+    /// A response to `query`, echoing its ID, opcode, questions and the RD and
+    /// CD flags (RFC 1035 section 4.1.1, RFC 4035 section 3.1.6). The EDNS
+    /// parameters are the responder's own to add.
+    pub fn response_to(query: &DnsMessage) -> Self {
+        let mut builder = MessageBuilder::query(Vec::new(), QType::A);
+        builder.message.questions = query.questions.clone();
+        let header = &mut builder.message.header;
+        header.packet_id = query.header.packet_id;
+        header.qr = QueryResponse::Reply;
+        header.opcode = query.header.opcode;
+        header.rd = query.header.rd;
+        header.cd = query.header.cd;
+        builder
+    }
+
+    pub fn id(mut self, packet_id: u16) -> Self {
+        self.message.header.packet_id = packet_id;
+        self
+    }
+
+    pub fn opcode(mut self, opcode: OpCode) -> Self {
+        self.message.header.opcode = opcode;
+        self
+    }
+
+    pub fn rcode(mut self, rcode: ResponseCode) -> Self {
+        self.message.header.rcode = rcode;
+        self
+    }
+
+    pub fn authoritative(mut self, set: bool) -> Self {
+        self.message.header.aa = match set {
+            true => AuthoritativeAnswer::Authoritative,
+            false => AuthoritativeAnswer::NonAuthoritative,
+        };
+        self
+    }
+
+    pub fn truncated(mut self, set: bool) -> Self {
+        self.message.header.tc = match set {
+            true => Truncated::Truncated,
+            false => Truncated::NotTruncated,
+        };
+        self
+    }
+
+    pub fn recursion_desired(mut self, set: bool) -> Self {
+        self.message.header.rd = match set {
+            true => RecursionDesire::Desired,
+            false => RecursionDesire::NotDesired,
+        };
+        self
+    }
+
+    pub fn recursion_available(mut self, set: bool) -> Self {
+        self.message.header.ra = match set {
+            true => RecursionAvailability::Available,
+            false => RecursionAvailability::NotAvailable,
+        };
+        self
+    }
+
+    pub fn authentic_data(mut self, set: bool) -> Self {
+        self.message.header.ad = match set {
+            true => AuthenticData::Authentic,
+            false => AuthenticData::NotAuthentic,
+        };
+        self
+    }
+
+    pub fn checking_disabled(mut self, set: bool) -> Self {
+        self.message.header.cd = match set {
+            true => CheckingDisabled::Disabled,
+            false => CheckingDisabled::Enabled,
+        };
+        self
+    }
+
+    /// Adds a question in class IN. For UPDATE messages this is the zone.
+    pub fn question(mut self, qname: Vec<String>, qtype: QType) -> Self {
+        self.message.questions.push(DnsQuestion {
+            qname,
+            qtype,
+            qclass: QClass::IN,
+        });
+        self
+    }
+
+    /// Adds a record to the Answer section, the Prerequisite section of UPDATE
+    /// messages.
+    pub fn answer(mut self, record: DnsRecord) -> Self {
+        self.message.answers.push(record);
+        self
+    }
+
+    /// Adds a record to the Authority section, the Update section of UPDATE
+    /// messages.
+    pub fn authority(mut self, record: DnsRecord) -> Self {
+        self.message.authorities.push(record);
+        self
+    }
+
+    pub fn additional(mut self, record: DnsRecord) -> Self {
+        self.message.additionals.push(record);
+        self
+    }
+
+    pub fn answers(mut self, records: impl IntoIterator<Item = DnsRecord>) -> Self {
+        self.message.answers.extend(records);
+        self
+    }
+
+    pub fn authorities(mut self, records: impl IntoIterator<Item = DnsRecord>) -> Self {
+        self.message.authorities.extend(records);
+        self
+    }
+
+    pub fn additionals(mut self, records: impl IntoIterator<Item = DnsRecord>) -> Self {
+        self.message.additionals.extend(records);
+        self
+    }
+
+    /// Sets the EDNS parameters, replacing any OPT record already added.
+    pub fn edns(mut self, opt: Opt) -> Self {
+        self.edns = Some(opt);
+        self
+    }
+
+    /// The message, with the OPT record placed and the header counts set to the
+    /// number of entries in each section.
+    pub fn build(self) -> DnsMessage {
+        let mut message = self.message;
+        match self.edns {
+            Some(opt) => message.set_edns(opt),
+            None => message.update_counts(),
+        }
+        message
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        dns_header::header::{
+            AuthoritativeAnswer, CheckingDisabled, OpCode, QueryResponse, RecursionAvailability,
+            RecursionDesire, ResponseCode,
+        },
+        dns_message::{builder::MessageBuilder, message::DnsMessage},
+        dns_record::record::{DnsRecord, Opt, RData, Tsig},
+        resrec::{QClass, QType},
+    };
+    use std::net::Ipv4Addr;
+
+    fn name(text: &str) -> Vec<String> {
+        text.split('.').map(String::from).collect()
+    }
+
+    fn a(owner: &str) -> DnsRecord {
+        DnsRecord {
+            name: name(owner),
+            typ: QType::A,
+            class: QClass::IN,
+            ttl: 60,
+            rdata: RData::A(Ipv4Addr::new(192, 0, 2, 1)),
+        }
+    }
+
+    #[test]
+    fn query_test() {
+        let query = MessageBuilder::query(name("example.com"), QType::MX)
+            .id(7)
+            .recursion_desired(true)
+            .checking_disabled(true)
+            .edns(Opt::new(1232, true))
+            .build();
+        assert_eq!(query.header.packet_id, 7);
+        assert_eq!(query.header.qr, QueryResponse::Question);
+        assert_eq!(query.header.rd, RecursionDesire::Desired);
+        assert_eq!(query.questions[0].qtype, QType::MX);
+        assert_eq!((query.header.qdcount, query.header.arcount), (1, 1));
+
+        let parsed = DnsMessage::parse(&query.as_bytes()).unwrap();
+        assert_eq!(parsed.edns(), Some(&Opt::new(1232, true)));
+    }
+
+    #[test]
+    fn response_to_test() {
+        let query = MessageBuilder::query(name("www.example.com"), QType::A)
+            .recursion_desired(true)
+            .checking_disabled(true)
+            .edns(Opt::new(4096, false))
+            .build();
+        let tsig = DnsRecord {
+            name: name("key"),
+            typ: QType::TSIG,
+            class: QClass::WildCard,
+            ttl: 0,
+            rdata: RData::TSIG(Tsig {
+                algorithm: name("hmac-sha256"),
+                time_signed: 0,
+                fudge: 300,
+                mac: Vec::new(),
+                original_id: 0,
+                error: 0,
+                other: Vec::new(),
+            }),
+        };
+        let response = MessageBuilder::response_to(&query)
+            .authoritative(true)
+            .recursion_available(true)
+            .rcode(ResponseCode::NameError)
+            .answer(a("www.example.com"))
+            .authorities([a("ns1.example.com"), a("ns2.example.com")])
+            .additional(tsig)
+            .edns(Opt::new(1232, true))
+            .build();
+
+        let header = &response.header;
+        assert_eq!(header.packet_id, query.header.packet_id);
+        assert_eq!(header.qr, QueryResponse::Reply);
+        assert_eq!(header.opcode, OpCode::Query);
+        assert_eq!(header.aa, AuthoritativeAnswer::Authoritative);
+        assert_eq!(header.rd, RecursionDesire::Desired);
+        assert_eq!(header.ra, RecursionAvailability::Available);
+        assert_eq!(header.cd, CheckingDisabled::Disabled);
+        assert_eq!(header.rcode, ResponseCode::NameError);
+        assert_eq!(response.questions, query.questions);
+        assert_eq!(
+            (
+                header.qdcount,
+                header.ancount,
+                header.nscount,
+                header.arcount
+            ),
+            (1, 1, 2, 2)
+        );
+        // The OPT record goes ahead of the TSIG one, which must stay last.
+        let types = response
+            .additionals
+            .iter()
+            .map(|r| r.typ)
+            .collect::<Vec<_>>();
+        assert_eq!(types, vec![QType::OPT, QType::TSIG]);
+    }
+}
@@ -0,0 +1,108 @@
+//! The RFC 8427 objects DNS messages are written to and read from as JSON,
+//! named as the RFC names their members. [`DnsMessage`], [`DnsQuestion`] and
+//! [`DnsRecord`] serialize through them.
+//!
+//! [`DnsMessage`]: super::message::DnsMessage
+//! [`DnsQuestion`]: crate::dns_question::question::DnsQuestion
+//! [`DnsRecord`]: crate::dns_record::record::DnsRecord
+#![allow(non_snake_case)]
+
+use serde::{Deserialize, Serialize};
+
+/// A message object. `messageOctetsHEX`, when present, is the message and the
+/// other members are ignored. A single question is given by the `Q*` members,
+/// others by `questionRRs`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JsonMessage {
+    #[serde(flatten)]
+    pub header: JsonHeader,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub QNAME: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub QTYPE: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub QTYPEname: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub QCLASS: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub QCLASSname: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub questionRRs: Option<Vec<JsonQuestion>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub answerRRs: Option<Vec<JsonRecord>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub authorityRRs: Option<Vec<JsonRecord>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub additionalRRs: Option<Vec<JsonRecord>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub messageOctetsHEX: Option<String>,
+}
+
+/// The header members of a message object, zero when absent.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JsonHeader {
+    pub ID: u16,
+    pub QR: Flag,
+    pub Opcode: u8,
+    pub AA: Flag,
+    pub TC: Flag,
+    pub RD: Flag,
+    pub RA: Flag,
+    pub AD: Flag,
+    pub CD: Flag,
+    pub RCODE: u8,
+    pub QDCOUNT: u16,
+    pub ANCOUNT: u16,
+    pub NSCOUNT: u16,
+    pub ARCOUNT: u16,
+}
+
+/// A header flag, written as 0 or 1 as in the examples of the RFC and also
+/// read as a boolean.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Flag {
+    Number(u8),
+    Bool(bool),
+}
+
+/// A question as an RR object of `questionRRs`, without TTL and RDATA. The
+/// type is given by its code or its mnemonic, and the class defaults to IN.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JsonQuestion {
+    pub NAME: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub TYPE: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub TYPEname: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub CLASS: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub CLASSname: Option<String>,
+}
+
+/// An RR object. The RDATA is in `RDATAHEX` or, for a few types, in its
+/// presentation format under `rdata<TYPE>`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JsonRecord {
+    #[serde(flatten)]
+    pub question: JsonQuestion,
+    pub TTL: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub RDLENGTH: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub RDATAHEX: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rdataA: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rdataAAAA: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rdataCNAME: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rdataNS: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rdataPTR: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rdataTXT: Option<String>,
+}
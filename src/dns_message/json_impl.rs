@@ -0,0 +1,505 @@
+use super::{
+    json::{Flag, JsonHeader, JsonMessage, JsonQuestion, JsonRecord},
+    message::DnsMessage,
+};
+use crate::{
+    dns_header::header::{
+        AuthenticData, AuthoritativeAnswer, CheckingDisabled, DnsHeader, OpCode, QueryResponse,
+        RecursionAvailability, RecursionDesire, ResponseCode, Truncated, Z,
+    },
+    dns_name::name::{name_as_bytes, name_from_string, name_to_string},
+    dns_question::question::DnsQuestion,
+    dns_record::record::{DnsRecord, RData},
+    encoding::{hex_decode, hex_encode},
+    resrec::{QClass, QType},
+};
+use anyhow::{anyhow, bail, ensure, Context};
+use bytes::BufMut;
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+/// An RFC 8427 message object, with the message also as `messageOctetsHEX`.
+impl Serialize for DnsMessage {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        JsonMessage::from(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for DnsMessage {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let json = JsonMessage::deserialize(deserializer)?;
+        DnsMessage::try_from(json).map_err(|e| D::Error::custom(format!("{:#}", e)))
+    }
+}
+
+/// An RR object of `questionRRs`.
+impl Serialize for DnsQuestion {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        JsonQuestion::from(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for DnsQuestion {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let json = JsonQuestion::deserialize(deserializer)?;
+        DnsQuestion::try_from(json).map_err(|e| D::Error::custom(format!("{:#}", e)))
+    }
+}
+
+/// An RFC 8427 RR object.
+impl Serialize for DnsRecord {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        JsonRecord::from(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for DnsRecord {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let json = JsonRecord::deserialize(deserializer)?;
+        DnsRecord::try_from(json).map_err(|e| D::Error::custom(format!("{:#}", e)))
+    }
+}
+
+impl From<&DnsMessage> for JsonMessage {
+    fn from(message: &DnsMessage) -> Self {
+        let mut json = JsonMessage {
+            header: JsonHeader::from(&message.header),
+            ..Default::default()
+        };
+        match &message.questions[..] {
+            [question] => {
+                json.QNAME = Some(name_to_json(&question.qname));
+                json.QTYPE = Some(u16::from(question.qtype));
+                json.QTYPEname = Some(question.qtype.to_string());
+                json.QCLASS = Some(u16::from(question.qclass));
+                json.QCLASSname = Some(question.qclass.to_string());
+            }
+            [] => {}
+            questions => {
+                json.questionRRs = Some(questions.iter().map(JsonQuestion::from).collect())
+            }
+        }
+        let records = |records: &[DnsRecord]| match records {
+            [] => None,
+            records => Some(records.iter().map(JsonRecord::from).collect()),
+        };
+        json.answerRRs = records(&message.answers);
+        json.authorityRRs = records(&message.authorities);
+        json.additionalRRs = records(&message.additionals);
+        json.messageOctetsHEX = Some(hex_encode(&message.as_bytes()));
+        json
+    }
+}
+
+/// Rebuilds a message from `messageOctetsHEX` or else from the other members,
+/// the header counts then following the sections given.
+impl TryFrom<JsonMessage> for DnsMessage {
+    type Error = anyhow::Error;
+
+    fn try_from(json: JsonMessage) -> anyhow::Result<Self> {
+        if let Some(octets) = json.messageOctetsHEX {
+            let bytes =
+                hex_decode(&octets).ok_or_else(|| anyhow!("messageOctetsHEX is not hex"))?;
+            return DnsMessage::parse(&bytes)
+                .ok_or_else(|| anyhow!("messageOctetsHEX is not a DNS message"));
+        }
+        let questions = match (json.QNAME, json.questionRRs) {
+            (Some(qname), _) => vec![DnsQuestion {
+                qname: json_name(&qname)?,
+                qtype: json_type(json.QTYPE, json.QTYPEname, "QTYPE")?,
+                qclass: json_class(json.QCLASS, json.QCLASSname)?,
+            }],
+            (None, Some(questions)) => questions
+                .into_iter()
+                .map(DnsQuestion::try_from)
+                .collect::<anyhow::Result<_>>()?,
+            (None, None) => Vec::new(),
+        };
+        let records = |records: Option<Vec<JsonRecord>>, key: &str| {
+            records
+                .unwrap_or_default()
+                .into_iter()
+                .enumerate()
+                .map(|(i, r)| DnsRecord::try_from(r).with_context(|| format!("{}[{}]", key, i)))
+                .collect::<anyhow::Result<Vec<_>>>()
+        };
+        let mut message = DnsMessage {
+            header: DnsHeader::try_from(json.header)?,
+            questions,
+            answers: records(json.answerRRs, "answerRRs")?,
+            authorities: records(json.authorityRRs, "authorityRRs")?,
+            additionals: records(json.additionalRRs, "additionalRRs")?,
+        };
+        message.update_counts();
+        Ok(message)
+    }
+}
+
+impl From<&DnsHeader> for JsonHeader {
+    fn from(header: &DnsHeader) -> Self {
+        let flag = |set: bool| Flag::Number(u8::from(set));
+        JsonHeader {
+            ID: header.packet_id,
+            QR: flag(header.qr == QueryResponse::Reply),
+            Opcode: header.opcode.as_byte() >> 3,
+            AA: flag(header.aa == AuthoritativeAnswer::Authoritative),
+            TC: flag(header.tc == Truncated::Truncated),
+            RD: flag(header.rd == RecursionDesire::Desired),
+            RA: flag(header.ra == RecursionAvailability::Available),
+            AD: flag(header.ad == AuthenticData::Authentic),
+            CD: flag(header.cd == CheckingDisabled::Disabled),
+            RCODE: header.rcode.as_byte(),
+            QDCOUNT: header.qdcount,
+            ANCOUNT: header.ancount,
+            NSCOUNT: header.nscount,
+            ARCOUNT: header.arcount,
+        }
+    }
+}
+
+impl TryFrom<JsonHeader> for DnsHeader {
+    type Error = anyhow::Error;
+
+    fn try_from(json: JsonHeader) -> anyhow::Result<Self> {
+        ensure!(json.Opcode < 16, "Opcode {} is out of range", json.Opcode);
+        ensure!(json.RCODE < 16, "RCODE {} is out of range", json.RCODE);
+        Ok(DnsHeader {
+            packet_id: json.ID,
+            qr: match json.QR.is_set("QR")? {
+                true => QueryResponse::Reply,
+                false => QueryResponse::Question,
+            },
+            opcode: OpCode::from(json.Opcode << 3),
+            aa: match json.AA.is_set("AA")? {
+                true => AuthoritativeAnswer::Authoritative,
+                false => AuthoritativeAnswer::NonAuthoritative,
+            },
+            tc: match json.TC.is_set("TC")? {
+                true => Truncated::Truncated,
+                false => Truncated::NotTruncated,
+            },
+            rd: match json.RD.is_set("RD")? {
+                true => RecursionDesire::Desired,
+                false => RecursionDesire::NotDesired,
+            },
+            ra: match json.RA.is_set("RA")? {
+                true => RecursionAvailability::Available,
+                false => RecursionAvailability::NotAvailable,
+            },
+            z: Z::Reserved,
+            ad: match json.AD.is_set("AD")? {
+                true => AuthenticData::Authentic,
+                false => AuthenticData::NotAuthentic,
+            },
+            cd: match json.CD.is_set("CD")? {
+                true => CheckingDisabled::Disabled,
+                false => CheckingDisabled::Enabled,
+            },
+            rcode: ResponseCode::from(json.RCODE),
+            qdcount: json.QDCOUNT,
+            ancount: json.ANCOUNT,
+            nscount: json.NSCOUNT,
+            arcount: json.ARCOUNT,
+        })
+    }
+}
+
+impl Flag {
+    fn is_set(self, key: &str) -> anyhow::Result<bool> {
+        match self {
+            Flag::Number(0) | Flag::Bool(false) => Ok(false),
+            Flag::Number(1) | Flag::Bool(true) => Ok(true),
+            Flag::Number(_) => bail!("{} is not 0 or 1", key),
+        }
+    }
+}
+
+impl Default for Flag {
+    fn default() -> Self {
+        Flag::Number(0)
+    }
+}
+
+impl From<&DnsQuestion> for JsonQuestion {
+    fn from(question: &DnsQuestion) -> Self {
+        JsonQuestion {
+            NAME: name_to_json(&question.qname),
+            TYPE: Some(u16::from(question.qtype)),
+            TYPEname: Some(question.qtype.to_string()),
+            CLASS: Some(u16::from(question.qclass)),
+            CLASSname: Some(question.qclass.to_string()),
+        }
+    }
+}
+
+impl TryFrom<JsonQuestion> for DnsQuestion {
+    type Error = anyhow::Error;
+
+    fn try_from(json: JsonQuestion) -> anyhow::Result<Self> {
+        Ok(DnsQuestion {
+            qname: json_name(&json.NAME)?,
+            qtype: json_type(json.TYPE, json.TYPEname, "TYPE")?,
+            qclass: json_class(json.CLASS, json.CLASSname)?,
+        })
+    }
+}
+
+/// CLASS and TTL are the wire fields, which OPT fills with EDNS parameters. The
+/// RDATA is always in `RDATAHEX`, and also in `rdataA`, `rdataAAAA`,
+/// `rdataCNAME`, `rdataNS`, `rdataPTR` or `rdataTXT` for those types, TXT in
+/// presentation format.
+impl From<&DnsRecord> for JsonRecord {
+    fn from(record: &DnsRecord) -> Self {
+        let (class, ttl) = match &record.rdata {
+            RData::OPT(opt) => (opt.udp_payload_size, opt.ttl()),
+            _ => (u16::from(record.class), record.ttl),
+        };
+        let rdata = record.rdata.as_bytes();
+        let mut json = JsonRecord {
+            question: JsonQuestion {
+                NAME: name_to_json(&record.name),
+                TYPE: Some(u16::from(record.typ)),
+                TYPEname: Some(record.typ.to_string()),
+                CLASS: Some(class),
+                CLASSname: match record.rdata {
+                    RData::OPT(_) => None,
+                    _ => Some(record.class.to_string()),
+                },
+            },
+            TTL: ttl,
+            RDLENGTH: Some(rdata.len() as u16),
+            RDATAHEX: Some(hex_encode(&rdata)),
+            ..Default::default()
+        };
+        match &record.rdata {
+            RData::A(addr) => json.rdataA = Some(addr.to_string()),
+            RData::AAAA(addr) => json.rdataAAAA = Some(addr.to_string()),
+            RData::CNAME(name) => json.rdataCNAME = Some(name_to_json(name)),
+            RData::NS(name) => json.rdataNS = Some(name_to_json(name)),
+            RData::PTR(name) => json.rdataPTR = Some(name_to_json(name)),
+            RData::TXT(txt) => json.rdataTXT = Some(txt.to_string()),
+            _ => {}
+        }
+        json
+    }
+}
+
+/// Takes the RDATA from `RDATAHEX` or, for the types written with one, from
+/// their `rdata<TYPE>` member.
+impl TryFrom<JsonRecord> for DnsRecord {
+    type Error = anyhow::Error;
+
+    fn try_from(json: JsonRecord) -> anyhow::Result<Self> {
+        let question = json.question;
+        let name = json_name(&question.NAME)?;
+        let typ = json_type(question.TYPE, question.TYPEname, "TYPE")?;
+        let class = match question.CLASS {
+            Some(class) => class,
+            None => u16::from(json_class(None, question.CLASSname)?),
+        };
+        let rdata = match json.RDATAHEX {
+            Some(hex) => hex_decode(&hex).ok_or_else(|| anyhow!("RDATAHEX is not hex"))?,
+            None => {
+                let presentation = match typ {
+                    QType::A => json.rdataA,
+                    QType::AAAA => json.rdataAAAA,
+                    QType::CNAME => json.rdataCNAME,
+                    QType::NS => json.rdataNS,
+                    QType::PTR => json.rdataPTR,
+                    QType::TXT => json.rdataTXT,
+                    _ => bail!("missing RDATAHEX"),
+                };
+                let text = presentation.ok_or_else(|| anyhow!("missing rdata{}", typ))?;
+                let rdata = match typ {
+                    QType::A => RData::A(text.parse()?),
+                    QType::AAAA => RData::AAAA(text.parse()?),
+                    QType::CNAME => RData::CNAME(json_name(&text)?),
+                    QType::NS => RData::NS(json_name(&text)?),
+                    QType::PTR => RData::PTR(json_name(&text)?),
+                    _ => format!(". 0 IN TXT {}", text).parse::<DnsRecord>()?.rdata,
+                };
+                rdata.as_bytes()
+            }
+        };
+        ensure!(rdata.len() <= usize::from(u16::MAX), "RDATA too long");
+
+        let mut bytes = name_as_bytes(&name);
+        bytes.put_u16(u16::from(typ));
+        bytes.put_u16(class);
+        bytes.put_u32(json.TTL);
+        bytes.put_u16(rdata.len() as u16);
+        bytes.put(&rdata[..]);
+        match DnsRecord::parse(&bytes, 0) {
+            Some((record, end)) if end == bytes.len() => Ok(record),
+            _ => bail!("invalid RDATA for {}", typ),
+        }
+    }
+}
+
+/// A name in presentation format without the trailing dot, as in the examples
+/// of RFC 8427, except for the root.
+fn name_to_json(name: &[String]) -> String {
+    let text = name_to_string(name);
+    match name.is_empty() {
+        true => text,
+        false => text[..text.len() - 1].to_string(),
+    }
+}
+
+/// Reads a name in presentation format, absolute with or without the trailing
+/// dot.
+fn json_name(text: &str) -> anyhow::Result<Vec<String>> {
+    name_from_string(text, &[]).ok_or_else(|| anyhow!("invalid name {}", text))
+}
+
+/// Reads a type from its code, or failing that its mnemonic.
+fn json_type(code: Option<u16>, name: Option<String>, key: &str) -> anyhow::Result<QType> {
+    match (code, name) {
+        (Some(code), _) => Ok(QType::from(code)),
+        (None, Some(name)) => name.parse(),
+        (None, None) => bail!("missing {}", key),
+    }
+}
+
+/// Reads a class from its code or its mnemonic, IN when there is neither.
+fn json_class(code: Option<u16>, name: Option<String>) -> anyhow::Result<QClass> {
+    match (code, name) {
+        (Some(code), _) => Ok(QClass::from(code)),
+        (None, Some(name)) => name.parse(),
+        (None, None) => Ok(QClass::IN),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        dns_header::header::RecursionDesire,
+        dns_message::message::DnsMessage,
+        dns_record::record::{DnsRecord, Opt, RData, Txt},
+        resrec::{QClass, QType},
+    };
+    use serde_json::{json, Value};
+    use std::net::Ipv4Addr;
+
+    fn name(text: &str) -> Vec<String> {
+        text.split('.').map(String::from).collect()
+    }
+
+    #[test]
+    fn rfc8427_example_test() {
+        // The query of RFC 8427 section 6.1.
+        let text = r#"{ "ID": 19678, "QR": 0, "Opcode": 0,
+            "AA": 0, "TC": 0, "RD": 0, "RA": 0, "AD": 0, "CD": 0, "RCODE": 0,
+            "QDCOUNT": 1, "ANCOUNT": 0, "NSCOUNT": 0, "ARCOUNT": 0,
+            "QNAME": "example.com", "QTYPE": 1, "QCLASS": 1 }"#;
+        let message = serde_json::from_str::<DnsMessage>(text).unwrap();
+        let mut query = DnsMessage::query(name("example.com"), QType::A);
+        query.header.packet_id = 19678;
+        assert_eq!(message.as_bytes(), query.as_bytes());
+
+        let json = serde_json::to_value(&query).unwrap();
+        assert_eq!(json["QNAME"], "example.com");
+        assert_eq!(json["RD"], 0);
+        assert_eq!(
+            json["messageOctetsHEX"],
+            "4CDE00000001000000000000076578616D706C6503636F6D0000010001"
+        );
+
+        // Flags may be booleans too, and absent members are zero.
+        let text = r#"{"ID": 1, "RD": true, "QNAME": "example.com", "QTYPEname": "MX"}"#;
+        let message = serde_json::from_str::<DnsMessage>(text).unwrap();
+        assert_eq!(message.header.rd, RecursionDesire::Desired);
+        assert_eq!(message.questions[0].qtype, QType::MX);
+        assert!(serde_json::from_str::<DnsMessage>(r#"{"RD": 2}"#)
+            .unwrap_err()
+            .to_string()
+            .starts_with("RD is not 0 or 1"));
+        assert!(serde_json::from_str::<DnsMessage>(r#"{"Opcode": 16}"#)
+            .unwrap_err()
+            .to_string()
+            .starts_with("Opcode 16 is out of range"));
+    }
+
+    #[test]
+    fn message_json_test() {
+        let mut response = DnsMessage::query(name("example.com"), QType::TXT);
+        response.header.rd = RecursionDesire::Desired;
+        response.answers = vec![
+            DnsRecord {
+                name: name("example.com"),
+                typ: QType::TXT,
+                class: QClass::IN,
+                ttl: 300,
+                rdata: RData::TXT(Txt {
+                    strings: vec![b"v=spf1 -all".to_vec(), b"say \"hi\"".to_vec()],
+                }),
+            },
+            DnsRecord {
+                name: name("www.example.com"),
+                typ: QType::A,
+                class: QClass::IN,
+                ttl: 60,
+                rdata: RData::A(Ipv4Addr::new(192, 0, 2, 1)),
+            },
+        ];
+        response.set_edns(Opt::new(1232, true));
+        response.to_response();
+
+        let text = serde_json::to_string(&response).unwrap();
+        let parsed = serde_json::from_str::<DnsMessage>(&text).unwrap();
+        assert_eq!(parsed.as_bytes(), response.as_bytes());
+
+        // Without the octets, the members alone rebuild the message.
+        let mut json = serde_json::to_value(&response).unwrap();
+        json.as_object_mut().unwrap().remove("messageOctetsHEX");
+        let parsed = serde_json::from_value::<DnsMessage>(json.clone()).unwrap();
+        assert_eq!(parsed.as_bytes(), response.as_bytes());
+        assert_eq!(parsed.edns(), Some(&Opt::new(1232, true)));
+
+        let answers = &json["answerRRs"];
+        assert_eq!(answers[0]["rdataTXT"], "\"v=spf1 -all\" \"say \\\"hi\\\"\"");
+        assert_eq!(answers[1]["RDATAHEX"], "C0000201");
+        assert_eq!(json["additionalRRs"][0].get("CLASSname"), None);
+
+        // Presentation members stand in for RDATAHEX.
+        let record = json!({"NAME": "www.example.com", "TYPE": 1, "CLASS": 1, "TTL": 60,
+            "rdataA": "192.0.2.1"});
+        let record = serde_json::from_value::<DnsRecord>(record).unwrap();
+        assert_eq!(record, response.answers[1]);
+        let record = json!({"NAME": "example.com", "TYPEname": "TXT", "CLASSname": "IN",
+            "TTL": 300, "rdataTXT": "\"v=spf1 -all\" \"say \\\"hi\\\"\""});
+        let record = serde_json::from_value::<DnsRecord>(record).unwrap();
+        assert_eq!(record, response.answers[0]);
+
+        let error = |json: Value| {
+            serde_json::from_value::<DnsRecord>(json)
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            error(json!({"NAME": "a", "TYPE": 1, "TTL": 1})),
+            "missing rdataA"
+        );
+        assert_eq!(
+            error(json!({"NAME": "a", "TYPE": 15, "TTL": 1})),
+            "missing RDATAHEX"
+        );
+        assert_eq!(
+            error(json!({"NAME": "a", "TYPE": 1, "TTL": 1, "RDATAHEX": "C000"})),
+            "invalid RDATA for A"
+        );
+        assert_eq!(
+            error(json!({"NAME": "a", "TYPE": 1})),
+            "missing field `TTL`"
+        );
+
+        // Types without their own variant keep their code.
+        let record = json!({"NAME": "a", "TYPE": 65280, "CLASS": 3, "TTL": 1, "RDATAHEX": "01"});
+        let record = serde_json::from_value::<DnsRecord>(record).unwrap();
+        assert_eq!(record.typ, QType::Unknown(65280));
+        assert_eq!(record.class, QClass::CH);
+        assert_eq!(
+            serde_json::to_value(&record).unwrap()["TYPEname"],
+            "TYPE65280"
+        );
+    }
+}
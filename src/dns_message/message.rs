@@ -1,11 +1,44 @@
 use crate::{
-    dns_answer::answer::DnsAnswer, dns_header::header::DnsHeader,
-    dns_question::question::DnsQuestion,
+    dns_header::header::DnsHeader, dns_question::question::DnsQuestion,
+    dns_record::record::DnsRecord,
 };
+use thiserror::Error;
 
-#[derive(Debug)]
+/// ## Message format
+///
+/// ```text
+/// +---------------------+
+/// |        Header       |
+/// +---------------------+
+/// |       Question      | the question for the name server
+/// +---------------------+
+/// |        Answer       | RRs answering the question
+/// +---------------------+
+/// |      Authority      | RRs pointing toward an authority
+/// +---------------------+
+/// |      Additional     | RRs holding additional information
+/// +---------------------+
+/// ```
+///
+/// UPDATE messages (RFC 2136) reuse the same layout, with the sections read as
+/// Zone, Prerequisite, Update and Additional respectively.
+#[derive(Debug, Clone)]
 pub struct DnsMessage {
     pub header: DnsHeader,
     pub questions: Vec<DnsQuestion>,
-    pub answer: DnsAnswer,
+    pub answers: Vec<DnsRecord>,
+    pub authorities: Vec<DnsRecord>,
+    pub additionals: Vec<DnsRecord>,
+}
+
+/// Why a message, or a part of one, could not be written. Nothing is written
+/// then.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum EncodeError {
+    /// Too long for the buffer it was to be written to.
+    #[error("{needed} octets do not fit in the {available} left in the buffer")]
+    BufferTooSmall { needed: usize, available: usize },
+    /// RDATA longer than its two-octet RDLENGTH can tell.
+    #[error("RDATA of {0} octets is longer than 65535")]
+    RdataTooLong(usize),
 }
@@ -1,102 +1,307 @@
-use super::message::DnsMessage;
+use super::{
+    builder::MessageBuilder,
+    message::{DnsMessage, EncodeError},
+};
 use crate::{
-    dns_answer::answer::DnsAnswer,
-    dns_header::header::{DnsHeader, QueryResponse},
+    dns_header::{
+        header::{DnsHeader, OpCode, QueryResponse, ResponseCode},
+        header_impl::HEADER_SIZE_IN_BYTES,
+    },
     dns_question::question::DnsQuestion,
+    dns_record::record::{DnsRecord, Opt, RData},
+    encoding::hex_encode,
     resrec::{QClass, QType},
 };
-use std::net::Ipv4Addr;
+use bytes::BufMut;
+use std::fmt;
 
 impl DnsMessage {
+    /// A query for `qname` with a random ID.
+    pub fn query(qname: Vec<String>, qtype: QType) -> Self {
+        MessageBuilder::query(qname, qtype).build()
+    }
+
     pub fn as_bytes(&self) -> Vec<u8> {
-        let mut bytes = vec![];
-        bytes.append(&mut self.header.as_bytes());
-        self.questions.iter().for_each(|q| {
-            bytes.append(&mut q.as_bytes());
-        });
-        bytes.append(&mut self.answer.as_bytes());
+        let mut bytes = Vec::new();
+        self.write(&mut bytes);
         bytes
     }
 
+    /// The length of the message in wire format, uncompressed.
+    pub fn encoded_len(&self) -> usize {
+        let questions = self.questions.iter().map(DnsQuestion::encoded_len);
+        let records = self.records().map(DnsRecord::encoded_len);
+        HEADER_SIZE_IN_BYTES + questions.sum::<usize>() + records.sum::<usize>()
+    }
+
+    /// Writes the message to `buf`, such as a buffer reused for every response,
+    /// failing without writing anything when it has no room for the whole
+    /// message. Returns the octets written.
+    pub fn encode_into(&self, buf: &mut impl BufMut) -> Result<usize, EncodeError> {
+        for record in self.records() {
+            record.check_rdlength()?;
+        }
+        let len = self.encoded_len();
+        EncodeError::check(len, buf)?;
+        self.write(buf);
+        Ok(len)
+    }
+
+    /// Writes the message to `buf`, which must have room for it.
+    pub fn write(&self, buf: &mut impl BufMut) {
+        self.header.write(buf);
+        for question in &self.questions {
+            question.write(buf);
+        }
+        for record in self.records() {
+            record.write(buf);
+        }
+    }
+
+    /// The records of the Answer, Authority and Additional sections, in order.
+    fn records(&self) -> impl Iterator<Item = &DnsRecord> {
+        self.answers
+            .iter()
+            .chain(&self.authorities)
+            .chain(&self.additionals)
+    }
+
     pub fn to_response(&mut self) {
         self.header.qr = QueryResponse::Reply;
-        if let Ok(ancount) = self.answer.rddata.len().try_into() {
-            self.header.ancount = ancount;
+        self.update_counts();
+    }
+
+    /// Turns the message into a response echoing only its Question section, as
+    /// used for UPDATE results (where it is the Zone section) and errors.
+    pub fn to_bare_response(&mut self, rcode: ResponseCode) {
+        self.answers.clear();
+        self.authorities.clear();
+        self.additionals.clear();
+        self.header.rcode = rcode;
+        self.to_response();
+    }
+
+    /// Parses a whole message, returning `None` if any section is malformed or the
+    /// section counts run past the end of `bytes`.
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < HEADER_SIZE_IN_BYTES {
+            return None;
+        }
+        let header = DnsHeader::from(bytes);
+        let mut offset = HEADER_SIZE_IN_BYTES;
+
+        let mut questions = Vec::new();
+        for _ in 0..header.qdcount {
+            let (question, next) = DnsQuestion::parse(bytes, offset)?;
+            questions.push(question);
+            offset = next;
+        }
+
+        let mut sections = [Vec::new(), Vec::new(), Vec::new()];
+        let counts = [header.ancount, header.nscount, header.arcount];
+        for (section, count) in sections.iter_mut().zip(counts) {
+            for _ in 0..count {
+                let (record, next) = DnsRecord::parse(bytes, offset)?;
+                section.push(record);
+                offset = next;
+            }
         }
+        let [answers, authorities, additionals] = sections;
+
+        Some(DnsMessage {
+            header,
+            questions,
+            answers,
+            authorities,
+            additionals,
+        })
+    }
+
+    /// Builds the FORMERR response to a message whose header could be read but whose
+    /// body could not be parsed.
+    pub fn format_error(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < HEADER_SIZE_IN_BYTES {
+            return None;
+        }
+        let mut message = DnsMessage {
+            header: DnsHeader::from(bytes),
+            questions: vec![],
+            answers: vec![],
+            authorities: vec![],
+            additionals: vec![],
+        };
+        message.header.rcode = ResponseCode::FormatError;
+        message.to_response();
+        Some(message)
+    }
+
+    /// The EDNS(0) parameters of the sender, from the OPT record in the Additional
+    /// section.
+    pub fn edns(&self) -> Option<&Opt> {
+        self.additionals.iter().find_map(|r| match &r.rdata {
+            RData::OPT(opt) => Some(opt),
+            _ => None,
+        })
+    }
+
+    /// Replaces the OPT record of the message, or adds one ahead of any TSIG
+    /// record, which must stay last.
+    pub fn set_edns(&mut self, opt: Opt) {
+        self.additionals.retain(|r| r.typ != QType::OPT);
+        let record = DnsRecord {
+            name: vec![],
+            typ: QType::OPT,
+            class: QClass::from(opt.udp_payload_size),
+            ttl: opt.ttl(),
+            rdata: RData::OPT(opt),
+        };
+        let position = self
+            .additionals
+            .iter()
+            .position(|r| r.typ == QType::TSIG)
+            .unwrap_or(self.additionals.len());
+        self.additionals.insert(position, record);
+        self.update_counts();
+    }
+
+    /// Sets the header counts to the number of entries in each section.
+    pub fn update_counts(&mut self) {
+        let count = |len: usize| u16::try_from(len).unwrap_or(u16::MAX);
+        self.header.qdcount = count(self.questions.len());
+        self.header.ancount = count(self.answers.len());
+        self.header.nscount = count(self.authorities.len());
+        self.header.arcount = count(self.additionals.len());
     }
 }
 
-impl From<&[u8]> for DnsMessage {
-    fn from(bytes: &[u8]) -> Self {
-        let header = DnsHeader::from(&bytes[0..12]);
-        let question = DnsQuestion::from(&bytes[12..]);
-        let answer = DnsAnswer {
-            name: question.qname.clone(),
-            typ: QType::A,
-            class: QClass::IN,
-            ttl: 60,
-            rdlength: 4,
-            rddata: vec![Ipv4Addr::new(8, 8, 8, 8)],
+impl EncodeError {
+    /// Fails unless `buf` has room for `needed` more octets.
+    pub fn check(needed: usize, buf: &impl BufMut) -> Result<(), EncodeError> {
+        let available = buf.remaining_mut();
+        match needed <= available {
+            true => Ok(()),
+            false => Err(EncodeError::BufferTooSmall { needed, available }),
+        }
+    }
+}
+
+/// The message as `dig` prints it: the header, the OPT record as a
+/// pseudo-section, then each non-empty section with a record per line.
+///
+/// ```text
+/// ;; ->>HEADER<<- opcode: QUERY, status: NOERROR, id: 4660
+/// ;; flags: qr rd ra; QUERY: 1, ANSWER: 1, AUTHORITY: 0, ADDITIONAL: 1
+///
+/// ;; OPT PSEUDOSECTION:
+/// ; EDNS: version: 0, flags: do; udp: 1232
+///
+/// ;; QUESTION SECTION:
+/// ;www.example.com. IN A
+///
+/// ;; ANSWER SECTION:
+/// www.example.com. 3600 IN A 192.0.2.1
+/// ```
+impl fmt::Display for DnsMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.header)?;
+        if let Some(opt) = self.edns() {
+            writeln!(f, "\n;; OPT PSEUDOSECTION:")?;
+            let flags = match opt.dnssec_ok {
+                true => " do",
+                false => "",
+            };
+            write!(
+                f,
+                "; EDNS: version: {}, flags:{}; udp: {}",
+                opt.version, flags, opt.udp_payload_size
+            )?;
+            if opt.extended_rcode != 0 {
+                // The header holds the lower four bits (RFC 6891 section 6.1.3).
+                let rcode =
+                    u16::from(opt.extended_rcode) << 4 | u16::from(self.header.rcode.as_byte());
+                write!(f, ", extended rcode: {}", rcode)?;
+            }
+            writeln!(f)?;
+            for option in &opt.options {
+                writeln!(f, "; OPT={}: {}", option.code, hex_encode(&option.data))?;
+            }
+        }
+
+        let names = match self.header.opcode {
+            OpCode::Update => ["ZONE", "PREREQUISITE", "UPDATE", "ADDITIONAL"],
+            _ => ["QUESTION", "ANSWER", "AUTHORITY", "ADDITIONAL"],
         };
-        DnsMessage {
-            header,
-            questions: vec![question],
-            answer,
+        if !self.questions.is_empty() {
+            writeln!(f, "\n;; {} SECTION:", names[0])?;
+            for question in &self.questions {
+                writeln!(f, ";{}", question)?;
+            }
+        }
+        let (tsig, additionals): (Vec<_>, Vec<_>) = self
+            .additionals
+            .iter()
+            .filter(|r| r.typ != QType::OPT)
+            .partition(|r| r.typ == QType::TSIG);
+        let sections = [
+            (
+                format!("{} SECTION", names[1]),
+                self.answers.iter().collect(),
+            ),
+            (
+                format!("{} SECTION", names[2]),
+                self.authorities.iter().collect(),
+            ),
+            (format!("{} SECTION", names[3]), additionals),
+            ("TSIG PSEUDOSECTION".to_string(), tsig),
+        ];
+        for (name, records) in sections {
+            if records.is_empty() {
+                continue;
+            }
+            writeln!(f, "\n;; {}:", name)?;
+            for record in records {
+                writeln!(f, "{}", record)?;
+            }
         }
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::{
-        dns_answer::answer::DnsAnswer,
         dns_header::header::{
-            AuthoritativeAnswer, DnsHeader, OpCode, QueryResponse, RecursionAvailability,
-            RecursionDesire, ResponseCode, Truncated, Z,
+            OpCode, QueryResponse, RecursionAvailability, RecursionDesire, ResponseCode,
         },
-        dns_message::message::DnsMessage,
-        dns_question::question::DnsQuestion,
+        dns_message::{
+            builder::MessageBuilder,
+            message::{DnsMessage, EncodeError},
+        },
+        dns_record::record::{DnsRecord, EdnsOption, Opt, RData, Soa, Srv, Txt},
         resrec::{QClass, QType},
     };
     use std::net::Ipv4Addr;
 
+    fn message() -> DnsMessage {
+        let qname = vec!["codecrafters".to_string(), "io".to_string()];
+        let query = MessageBuilder::query(qname.clone(), QType::A)
+            .id(1234)
+            .opcode(OpCode::IQuery)
+            .build();
+        MessageBuilder::response_to(&query)
+            .answer(DnsRecord {
+                name: qname,
+                typ: QType::A,
+                class: QClass::IN,
+                ttl: 60,
+                rdata: RData::A(Ipv4Addr::new(8, 8, 8, 8)),
+            })
+            .build()
+    }
+
     #[test]
     fn test_as_bytes() {
-        let header = DnsHeader {
-            packet_id: 1234,
-            qr: QueryResponse::Reply,
-            opcode: OpCode::IQuery,
-            aa: AuthoritativeAnswer::NonAuthoritative,
-            tc: Truncated::NotTruncated,
-            rd: RecursionDesire::NotDesired,
-            ra: RecursionAvailability::NotAvailable,
-            z: Z::Reserved,
-            rcode: ResponseCode::NoErrorCondition,
-            qdcount: 1,
-            ancount: 1,
-            nscount: 0,
-            arcount: 0,
-        };
-        let question = DnsQuestion {
-            qname: vec!["codecrafters".to_string(), "io".to_string()],
-            qtype: QType::A,
-            qclass: QClass::IN,
-        };
-        let answer = DnsAnswer {
-            name: vec!["codecrafters".to_string(), "io".to_string()],
-            typ: QType::A,
-            class: QClass::IN,
-            ttl: 60,
-            rdlength: 4,
-            rddata: vec![Ipv4Addr::new(8, 8, 8, 8)],
-        };
-
-        let message = DnsMessage {
-            header,
-            questions: vec![question],
-            answer,
-        };
-        let bytes = message.as_bytes();
+        let bytes = message().as_bytes();
 
         assert_eq!(
             bytes,
@@ -107,4 +312,200 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_parse() {
+        let message = message();
+        let parsed = DnsMessage::parse(&message.as_bytes()).unwrap();
+
+        assert_eq!(parsed.header.packet_id, 1234);
+        assert_eq!(parsed.questions, message.questions);
+        assert_eq!(parsed.answers, message.answers);
+        assert!(parsed.authorities.is_empty());
+        assert!(parsed.additionals.is_empty());
+    }
+
+    #[test]
+    fn test_parse_truncated() {
+        let bytes = message().as_bytes();
+        assert!(DnsMessage::parse(&bytes[..bytes.len() - 1]).is_none());
+        assert!(DnsMessage::parse(&bytes[..5]).is_none());
+    }
+
+    #[test]
+    fn test_encode_into() {
+        let name = |text: &str| text.split('.').map(String::from).collect::<Vec<_>>();
+        let record = |typ, rdata| DnsRecord {
+            name: name("example.com"),
+            typ,
+            class: QClass::IN,
+            ttl: 60,
+            rdata,
+        };
+        let mut edns = Opt::new(1232, true);
+        edns.options.push(EdnsOption {
+            code: 10,
+            data: vec![1; 8],
+        });
+        let message = MessageBuilder::response_to(&message())
+            .answer(record(QType::TXT, RData::TXT(Txt::new(&[b'x'; 300]))))
+            .answer(record(
+                QType::MX,
+                RData::MX {
+                    preference: 10,
+                    exchange: name("mail.example.com"),
+                },
+            ))
+            .authority(record(
+                QType::SOA,
+                RData::SOA(Soa {
+                    mname: name("ns.example.com"),
+                    rname: name("hostmaster.example.com"),
+                    serial: 1,
+                    refresh: 2,
+                    retry: 3,
+                    expire: 4,
+                    minimum: 5,
+                }),
+            ))
+            .additional(record(
+                QType::SRV,
+                RData::SRV(Srv {
+                    priority: 1,
+                    weight: 2,
+                    port: 53,
+                    target: name("ns.example.com"),
+                }),
+            ))
+            .edns(edns)
+            .build();
+        let bytes = message.as_bytes();
+        assert_eq!(message.encoded_len(), bytes.len());
+
+        let mut out = [0; 1232];
+        assert_eq!(message.encode_into(&mut &mut out[..]), Ok(bytes.len()));
+        assert_eq!(out[..bytes.len()], bytes[..]);
+
+        // Nothing is written unless the whole message fits.
+        let mut out = vec![0; bytes.len() - 1];
+        assert_eq!(
+            message.encode_into(&mut &mut out[..]),
+            Err(EncodeError::BufferTooSmall {
+                needed: bytes.len(),
+                available: bytes.len() - 1,
+            })
+        );
+        assert!(out.iter().all(|&x| x == 0));
+
+        let mut long = message.clone();
+        long.answers[0].rdata = RData::TXT(Txt::new(&[b'x'; 65536]));
+        let mut out = Vec::new();
+        assert_eq!(
+            long.encode_into(&mut out),
+            Err(EncodeError::RdataTooLong(65536 + 65536 / 255 + 1))
+        );
+        assert!(out.is_empty());
+
+        let mut out = Vec::new();
+        assert_eq!(message.answers[1].encode_into(&mut out), Ok(out.len()));
+        assert_eq!(out, message.answers[1].as_bytes());
+    }
+
+    #[test]
+    fn test_query() {
+        let query = DnsMessage::query(vec!["example".to_string()], QType::SOA);
+        assert_eq!(query.header.qr, QueryResponse::Question);
+        assert_eq!(query.header.qdcount, 1);
+        assert_eq!(query.questions[0].qtype, QType::SOA);
+        assert_eq!(
+            DnsMessage::parse(&query.as_bytes()).unwrap().questions,
+            query.questions
+        );
+    }
+
+    #[test]
+    fn test_format_error() {
+        let bytes = message().as_bytes();
+        let response = DnsMessage::format_error(&bytes[..20]).unwrap();
+
+        assert_eq!(response.header.packet_id, 1234);
+        assert_eq!(response.header.rcode, ResponseCode::FormatError);
+        assert_eq!(response.header.qdcount, 0);
+        assert!(DnsMessage::format_error(&bytes[..5]).is_none());
+    }
+
+    #[test]
+    fn test_to_bare_response() {
+        let mut message = message();
+        message.header.qr = QueryResponse::Question;
+        message.header.opcode = OpCode::Update;
+        message.to_bare_response(ResponseCode::NotAuth);
+
+        assert_eq!(message.header.qr, QueryResponse::Reply);
+        assert_eq!(message.header.rcode, ResponseCode::NotAuth);
+        assert_eq!(message.header.qdcount, 1);
+        assert_eq!(message.header.ancount, 0);
+        assert!(message.answers.is_empty());
+    }
+
+    #[test]
+    fn test_edns() {
+        let mut query = DnsMessage::query(vec!["example".to_string()], QType::SOA);
+        assert_eq!(query.edns(), None);
+
+        query.set_edns(Opt::new(1232, true));
+        query.set_edns(Opt::new(4096, true));
+        assert_eq!(query.header.arcount, 1);
+
+        let parsed = DnsMessage::parse(&query.as_bytes()).unwrap();
+        assert_eq!(parsed.edns(), Some(&Opt::new(4096, true)));
+    }
+
+    #[test]
+    fn test_display() {
+        let mut response = DnsMessage::query(
+            vec!["www".to_string(), "example".to_string(), "com".to_string()],
+            QType::A,
+        );
+        response.header.packet_id = 4660;
+        response.header.rd = RecursionDesire::Desired;
+        response.header.ra = RecursionAvailability::Available;
+        response.answers.push(DnsRecord {
+            name: response.questions[0].qname.clone(),
+            typ: QType::A,
+            class: QClass::IN,
+            ttl: 3600,
+            rdata: RData::A(Ipv4Addr::new(192, 0, 2, 1)),
+        });
+        response.set_edns(Opt::new(1232, true));
+        response.to_response();
+        assert_eq!(
+            response.to_string(),
+            ";; ->>HEADER<<- opcode: QUERY, status: NOERROR, id: 4660\n\
+             ;; flags: qr rd ra; QUERY: 1, ANSWER: 1, AUTHORITY: 0, ADDITIONAL: 1\n\
+             \n\
+             ;; OPT PSEUDOSECTION:\n\
+             ; EDNS: version: 0, flags: do; udp: 1232\n\
+             \n\
+             ;; QUESTION SECTION:\n\
+             ;www.example.com. IN A\n\
+             \n\
+             ;; ANSWER SECTION:\n\
+             www.example.com. 3600 IN A 192.0.2.1\n"
+        );
+
+        let mut update = message();
+        update.header.opcode = OpCode::Update;
+        update.header.rcode = ResponseCode::NotZone;
+        update.answers.clear();
+        update.update_counts();
+        assert_eq!(
+            update.to_string(),
+            ";; ->>HEADER<<- opcode: UPDATE, status: NOTZONE, id: 1234\n\
+             ;; flags: qr; ZONE: 1, PREREQ: 0, UPDATE: 0, ADDITIONAL: 0\n\
+             \n\
+             ;; ZONE SECTION:\n\
+             ;codecrafters.io. IN A\n"
+        );
+    }
 }
@@ -0,0 +1,57 @@
+use crate::resrec::{QClass, QType};
+
+/// A message read in place from the buffer it arrived in. [`MessageRef::new`]
+/// checks that every section is laid out within the buffer once, without
+/// copying anything. Names are then decoded only when asked for, and RDATA is
+/// only checked when a record is converted to a [`DnsRecord`].
+///
+/// [`DnsRecord`]: crate::dns_record::record::DnsRecord
+#[derive(Debug, Clone, Copy)]
+pub struct MessageRef<'a> {
+    pub bytes: &'a [u8],
+    /// Where the Question, Answer, Authority and Additional sections start, and
+    /// where the last one ends.
+    pub sections: [usize; 5],
+}
+
+/// A domain name at `offset` in `message`, possibly compressed.
+#[derive(Debug, Clone, Copy)]
+pub struct NameRef<'a> {
+    pub message: &'a [u8],
+    pub offset: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct QuestionRef<'a> {
+    pub qname: NameRef<'a>,
+    pub qtype: QType,
+    pub qclass: QClass,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RecordRef<'a> {
+    pub name: NameRef<'a>,
+    pub typ: QType,
+    /// The CLASS field as sent, which OPT records use for the UDP payload size.
+    pub class: u16,
+    pub ttl: u32,
+    pub rdata: &'a [u8],
+    /// Where the record starts in the message.
+    pub offset: usize,
+}
+
+/// The questions of a [`MessageRef`], read one at a time.
+#[derive(Debug, Clone)]
+pub struct Questions<'a> {
+    pub message: &'a [u8],
+    pub offset: usize,
+    pub end: usize,
+}
+
+/// The records of one section of a [`MessageRef`], read one at a time.
+#[derive(Debug, Clone)]
+pub struct Records<'a> {
+    pub message: &'a [u8],
+    pub offset: usize,
+    pub end: usize,
+}
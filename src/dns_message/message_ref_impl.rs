@@ -0,0 +1,331 @@
+use super::{
+    message::DnsMessage,
+    message_ref::{MessageRef, NameRef, QuestionRef, Questions, RecordRef, Records},
+};
+use crate::{
+    dns_header::{header::DnsHeader, header_impl::HEADER_SIZE_IN_BYTES},
+    dns_name::name::{name_to_string, parse_name, visit_name},
+    dns_question::question::DnsQuestion,
+    dns_record::{
+        record::{DnsRecord, Opt},
+        record_impl::FIXED_FIELDS_SIZE_IN_BYTES,
+    },
+    resrec::{QClass, QType},
+};
+use bytes::Buf;
+use std::fmt;
+
+impl<'a> MessageRef<'a> {
+    /// Checks that `bytes` holds a header and as many questions and records as
+    /// its counts say. Returns `None` where [`DnsMessage::parse`] would for a
+    /// malformed layout.
+    pub fn new(bytes: &'a [u8]) -> Option<Self> {
+        if bytes.len() < HEADER_SIZE_IN_BYTES {
+            return None;
+        }
+        let mut counts = &bytes[4..HEADER_SIZE_IN_BYTES];
+        let mut sections = [HEADER_SIZE_IN_BYTES; 5];
+        let mut offset = HEADER_SIZE_IN_BYTES;
+        for _ in 0..counts.get_u16() {
+            (_, offset) = QuestionRef::read(bytes, offset)?;
+        }
+        for section in &mut sections[1..4] {
+            *section = offset;
+            for _ in 0..counts.get_u16() {
+                (_, offset) = RecordRef::read(bytes, offset)?;
+            }
+        }
+        sections[4] = offset;
+        Some(MessageRef { bytes, sections })
+    }
+
+    pub fn header(&self) -> DnsHeader {
+        DnsHeader::from(self.bytes)
+    }
+
+    pub fn questions(&self) -> Questions<'a> {
+        Questions {
+            message: self.bytes,
+            offset: self.sections[0],
+            end: self.sections[1],
+        }
+    }
+
+    pub fn answers(&self) -> Records<'a> {
+        self.records(1)
+    }
+
+    pub fn authorities(&self) -> Records<'a> {
+        self.records(2)
+    }
+
+    pub fn additionals(&self) -> Records<'a> {
+        self.records(3)
+    }
+
+    fn records(&self, section: usize) -> Records<'a> {
+        Records {
+            message: self.bytes,
+            offset: self.sections[section],
+            end: self.sections[section + 1],
+        }
+    }
+
+    /// The EDNS(0) parameters of the sender, like [`DnsMessage::edns`].
+    pub fn edns(&self) -> Option<Opt> {
+        let opt = self.additionals().find(|r| r.typ == QType::OPT)?;
+        Opt::parse(opt.class, opt.ttl, opt.rdata)
+    }
+
+    /// The message copied out of the buffer section by section, without
+    /// checking its layout again, or `None` if some RDATA is malformed.
+    pub fn to_message(&self) -> Option<DnsMessage> {
+        let records = |records: Records| records.map(|r| r.to_record()).collect::<Option<_>>();
+        Some(DnsMessage {
+            header: self.header(),
+            questions: self
+                .questions()
+                .map(|q| q.to_question())
+                .collect::<Option<_>>()?,
+            answers: records(self.answers())?,
+            authorities: records(self.authorities())?,
+            additionals: records(self.additionals())?,
+        })
+    }
+}
+
+impl NameRef<'_> {
+    /// The labels of the name, or `None` if it is malformed.
+    pub fn to_labels(&self) -> Option<Vec<String>> {
+        parse_name(self.message, self.offset).map(|(labels, _)| labels)
+    }
+
+    /// Whether the name is `name`, ignoring ASCII case as [`names_equal`]
+    /// does, without copying its labels.
+    ///
+    /// [`names_equal`]: crate::dns_name::name::names_equal
+    pub fn equals(&self, name: &[String]) -> bool {
+        let mut count = 0;
+        let mut same = true;
+        let end = visit_name(self.message, self.offset, |label| {
+            same &= name
+                .get(count)
+                .is_some_and(|x| x.eq_ignore_ascii_case(label));
+            count += 1;
+        });
+        end.is_some() && same && count == name.len()
+    }
+}
+
+/// The name in presentation format, as [`name_to_string`] writes it, or
+/// `<malformed>`.
+impl fmt::Display for NameRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.to_labels() {
+            Some(labels) => write!(f, "{}", name_to_string(&labels)),
+            None => write!(f, "<malformed>"),
+        }
+    }
+}
+
+impl<'a> QuestionRef<'a> {
+    /// Reads the question at `offset` in `message`. Returns the question and the
+    /// offset of whatever follows it.
+    pub fn read(message: &'a [u8], offset: usize) -> Option<(Self, usize)> {
+        let end = visit_name(message, offset, |_| {})?;
+        let mut fixed = message.get(end..end + 4)?;
+        let question = QuestionRef {
+            qname: NameRef { message, offset },
+            qtype: QType::from(fixed.get_u16()),
+            qclass: QClass::from(fixed.get_u16()),
+        };
+        Some((question, end + 4))
+    }
+
+    /// The question with its name copied out, or `None` if the name is
+    /// malformed.
+    pub fn to_question(&self) -> Option<DnsQuestion> {
+        Some(DnsQuestion {
+            qname: self.qname.to_labels()?,
+            qtype: self.qtype,
+            qclass: self.qclass,
+        })
+    }
+
+    /// Whether this is `question`, the name compared as [`NameRef::equals`]
+    /// does.
+    pub fn is(&self, question: &DnsQuestion) -> bool {
+        self.qtype == question.qtype
+            && self.qclass == question.qclass
+            && self.qname.equals(&question.qname)
+    }
+}
+
+impl<'a> RecordRef<'a> {
+    /// Reads the record at `offset` in `message`, leaving its RDATA as it is.
+    /// Returns the record and the offset of whatever follows it.
+    pub fn read(message: &'a [u8], offset: usize) -> Option<(Self, usize)> {
+        let end = visit_name(message, offset, |_| {})?;
+        let mut fixed = message.get(end..end + FIXED_FIELDS_SIZE_IN_BYTES)?;
+        let typ = QType::from(fixed.get_u16());
+        let class = fixed.get_u16();
+        let ttl = fixed.get_u32();
+        let rdata_start = end + FIXED_FIELDS_SIZE_IN_BYTES;
+        let rdata_end = rdata_start + usize::from(fixed.get_u16());
+        let record = RecordRef {
+            name: NameRef { message, offset },
+            typ,
+            class,
+            ttl,
+            rdata: message.get(rdata_start..rdata_end)?,
+            offset,
+        };
+        Some((record, rdata_end))
+    }
+
+    /// The record with its RDATA parsed, or `None` if the RDATA is malformed.
+    pub fn to_record(&self) -> Option<DnsRecord> {
+        DnsRecord::parse(self.name.message, self.offset).map(|(record, _)| record)
+    }
+}
+
+impl<'a> Iterator for Questions<'a> {
+    type Item = QuestionRef<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.offset >= self.end {
+            return None;
+        }
+        let (question, next) = QuestionRef::read(self.message, self.offset)?;
+        self.offset = next;
+        Some(question)
+    }
+}
+
+impl<'a> Iterator for Records<'a> {
+    type Item = RecordRef<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.offset >= self.end {
+            return None;
+        }
+        let (record, next) = RecordRef::read(self.message, self.offset)?;
+        self.offset = next;
+        Some(record)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        dns_message::{
+            builder::MessageBuilder,
+            message::DnsMessage,
+            message_ref::{MessageRef, NameRef},
+        },
+        dns_record::record::{DnsRecord, Opt, RData},
+        resrec::{QClass, QType},
+    };
+    use std::net::Ipv4Addr;
+
+    fn name(text: &str) -> Vec<String> {
+        text.split('.').map(String::from).collect()
+    }
+
+    fn response() -> Vec<u8> {
+        let query = MessageBuilder::query(name("www.example.com"), QType::A)
+            .id(77)
+            .build();
+        MessageBuilder::response_to(&query)
+            .answer(DnsRecord {
+                name: name("www.example.com"),
+                typ: QType::CNAME,
+                class: QClass::IN,
+                ttl: 300,
+                rdata: RData::CNAME(name("web.example.com")),
+            })
+            .answer(DnsRecord {
+                name: name("web.example.com"),
+                typ: QType::A,
+                class: QClass::IN,
+                ttl: 60,
+                rdata: RData::A(Ipv4Addr::new(192, 0, 2, 1)),
+            })
+            .edns(Opt::new(1232, true))
+            .build()
+            .as_bytes()
+    }
+
+    #[test]
+    fn message_ref_test() {
+        let bytes = response();
+        let message = MessageRef::new(&bytes).unwrap();
+        assert_eq!(message.header().packet_id, 77);
+        assert_eq!(message.header().ancount, 2);
+
+        let questions = message.questions().collect::<Vec<_>>();
+        assert_eq!(questions.len(), 1);
+        assert!(questions[0].qname.equals(&name("WWW.Example.com")));
+        assert!(!questions[0].qname.equals(&name("example.com")));
+        assert!(!questions[0].qname.equals(&name("www.example.com.au")));
+        assert_eq!(questions[0].qname.to_string(), "www.example.com.");
+        assert_eq!(questions[0].qtype, QType::A);
+
+        let owned = message.to_message().unwrap();
+        assert_eq!(
+            owned.as_bytes(),
+            DnsMessage::parse(&bytes).unwrap().as_bytes()
+        );
+        assert_eq!(questions[0].to_question(), Some(owned.questions[0].clone()));
+        let mut question = owned.questions[0].clone();
+        question.qname = name("www.EXAMPLE.com");
+        assert!(questions[0].is(&question));
+        question.qtype = QType::AAAA;
+        assert!(!questions[0].is(&question));
+        let answers = message
+            .answers()
+            .map(|r| r.to_record().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(answers, owned.answers);
+        assert_eq!(message.authorities().count(), 0);
+        assert_eq!(message.additionals().count(), 1);
+        assert_eq!(message.edns(), Some(Opt::new(1232, true)));
+
+        let a = message.answers().nth(1).unwrap();
+        assert_eq!((a.typ, a.class, a.ttl), (QType::A, 1, 60));
+        assert_eq!(a.rdata, [192, 0, 2, 1]);
+
+        // Every section must fit in the buffer, as for DnsMessage::parse.
+        for len in 0..bytes.len() {
+            assert!(MessageRef::new(&bytes[..len]).is_none(), "length {}", len);
+        }
+    }
+
+    #[test]
+    fn compressed_names_test() {
+        let mut bytes = response();
+        // Point the owner of the first answer back at the question name.
+        let answer = MessageRef::new(&bytes).unwrap().sections[1];
+        let name_len = "www.example.com".len() + 2;
+        bytes.splice(answer..answer + name_len, [0xc0, 12]);
+
+        let message = MessageRef::new(&bytes).unwrap();
+        let cname = message.answers().next().unwrap();
+        assert!(cname.name.equals(&name("www.example.com")));
+        assert_eq!(cname.name.to_labels(), Some(name("www.example.com")));
+        assert_eq!(
+            cname.to_record().unwrap().rdata,
+            RData::CNAME(name("web.example.com"))
+        );
+
+        // A pointer to itself never ends.
+        bytes[answer + 1] = answer as u8;
+        assert!(MessageRef::new(&bytes).is_none());
+        let looping = NameRef {
+            message: &bytes,
+            offset: answer,
+        };
+        assert_eq!(looping.to_labels(), None);
+        assert_eq!(looping.to_string(), "<malformed>");
+    }
+}
@@ -1,2 +1,12 @@
+pub mod builder;
+pub(crate) mod builder_impl;
+#[cfg(feature = "json")]
+pub mod json;
+#[cfg(feature = "json")]
+pub(crate) mod json_impl;
 pub mod message;
-pub mod message_impl;
+pub(crate) mod message_impl;
+pub mod message_ref;
+pub(crate) mod message_ref_impl;
+pub mod tsig;
+pub(crate) mod tsig_impl;
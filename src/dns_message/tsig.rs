@@ -0,0 +1,67 @@
+use thiserror::Error;
+
+/// A shared secret used to sign and verify messages with TSIG (RFC 8945).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TsigKey {
+    /// The key name, sent as the owner name of the TSIG record.
+    pub name: Vec<String>,
+    pub algorithm: TsigAlgorithm,
+    pub secret: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TsigAlgorithm {
+    /// hmac-sha256.
+    HmacSha256,
+    /// hmac-sha512.
+    HmacSha512,
+}
+
+/// The keys the server accepts, looked up by the key name of incoming TSIG records.
+#[derive(Debug, Clone, Default)]
+pub struct TsigKeyring {
+    pub keys: Vec<TsigKey>,
+}
+
+/// The TSIG state of one exchange. A request and its responses are signed as a
+/// chain: each MAC covers the previous one, which is how multi-message responses
+/// such as TCP zone transfers stay authenticated end to end.
+#[derive(Debug, Clone)]
+pub struct TsigSession {
+    /// The key name named by the request.
+    pub key_name: Vec<String>,
+    /// The algorithm named by the request.
+    pub algorithm: Vec<String>,
+    /// The matching local key, `None` when the request named an unknown key.
+    pub key: Option<TsigKey>,
+    /// Why the request failed verification, reported back in the response TSIG.
+    pub error: Option<TsigError>,
+    /// The last MAC of the chain, covered by the next signature.
+    pub previous_mac: Vec<u8>,
+    /// Messages verified in this session but not signed yet, covered by the next
+    /// signature of a multi-message stream.
+    pub unsigned: Vec<u8>,
+    /// Number of messages in `unsigned`.
+    pub unsigned_count: usize,
+    /// Number of MACs in the chain so far, the request counting as the first.
+    pub chain: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum TsigError {
+    /// The TSIG record is malformed or not the last record of the message.
+    #[error("malformed TSIG record")]
+    FormErr,
+    /// (16) The MAC does not match.
+    #[error("TSIG signature failure")]
+    BadSig,
+    /// (17) The key is not known to the receiver.
+    #[error("TSIG key not recognized")]
+    BadKey,
+    /// (18) The signing time is outside the fudge window.
+    #[error("TSIG signature out of time window")]
+    BadTime,
+    /// (22) The MAC is truncated below what the receiver accepts.
+    #[error("TSIG MAC truncated too far")]
+    BadTrunc,
+}
@@ -0,0 +1,602 @@
+use super::{
+    message::DnsMessage,
+    message_ref::MessageRef,
+    tsig::{TsigAlgorithm, TsigError, TsigKey, TsigKeyring, TsigSession},
+};
+use crate::{
+    dns_header::header::ResponseCode,
+    dns_name::name::{name_as_bytes, names_equal},
+    dns_record::record::{DnsRecord, RData, Tsig},
+    encoding::base64_decode,
+    resrec::{QClass, QType},
+};
+use anyhow::{anyhow, bail};
+use bytes::BufMut;
+use hmac::{Hmac, Mac};
+use sha2::{Sha256, Sha512};
+use std::str::FromStr;
+
+/// Seconds of clock skew tolerated between signer and verifier, as recommended by
+/// RFC 8945.
+pub const DEFAULT_FUDGE: u16 = 300;
+
+/// Messages of a multi-message response that may follow each other unsigned.
+pub const MAX_UNSIGNED_MESSAGES: usize = 99;
+
+/// The shortest truncated MAC RFC 8945 allows, in bytes.
+const MIN_MAC_SIZE: usize = 10;
+
+impl TsigAlgorithm {
+    pub fn name(&self) -> Vec<String> {
+        let name = match self {
+            TsigAlgorithm::HmacSha256 => "hmac-sha256",
+            TsigAlgorithm::HmacSha512 => "hmac-sha512",
+        };
+        vec![name.to_string()]
+    }
+
+    pub fn from_name(name: &[String]) -> Option<Self> {
+        [TsigAlgorithm::HmacSha256, TsigAlgorithm::HmacSha512]
+            .into_iter()
+            .find(|x| names_equal(&x.name(), name))
+    }
+
+    /// Length of an untruncated MAC, in bytes.
+    pub fn mac_size(&self) -> usize {
+        match self {
+            TsigAlgorithm::HmacSha256 => 32,
+            TsigAlgorithm::HmacSha512 => 64,
+        }
+    }
+}
+
+impl TsigKey {
+    /// A fresh session for signing a request with this key and verifying its
+    /// responses.
+    pub fn session(&self) -> TsigSession {
+        TsigSession {
+            key_name: self.name.clone(),
+            algorithm: self.algorithm.name(),
+            key: Some(self.clone()),
+            error: None,
+            previous_mac: Vec::new(),
+            unsigned: Vec::new(),
+            unsigned_count: 0,
+            chain: 0,
+        }
+    }
+
+    fn mac(&self, data: &[u8]) -> Vec<u8> {
+        match self.algorithm {
+            TsigAlgorithm::HmacSha256 => {
+                let mut mac = Hmac::<Sha256>::new_from_slice(&self.secret)
+                    .expect("HMAC accepts keys of any size");
+                mac.update(data);
+                mac.finalize().into_bytes().to_vec()
+            }
+            TsigAlgorithm::HmacSha512 => {
+                let mut mac = Hmac::<Sha512>::new_from_slice(&self.secret)
+                    .expect("HMAC accepts keys of any size");
+                mac.update(data);
+                mac.finalize().into_bytes().to_vec()
+            }
+        }
+    }
+
+    /// Compares in constant time, accepting MACs truncated to their leftmost bytes.
+    fn verify_mac(&self, data: &[u8], expected: &[u8]) -> bool {
+        match self.algorithm {
+            TsigAlgorithm::HmacSha256 => {
+                let mut mac = Hmac::<Sha256>::new_from_slice(&self.secret)
+                    .expect("HMAC accepts keys of any size");
+                mac.update(data);
+                mac.verify_truncated_left(expected).is_ok()
+            }
+            TsigAlgorithm::HmacSha512 => {
+                let mut mac = Hmac::<Sha512>::new_from_slice(&self.secret)
+                    .expect("HMAC accepts keys of any size");
+                mac.update(data);
+                mac.verify_truncated_left(expected).is_ok()
+            }
+        }
+    }
+}
+
+/// Parses keys in the `[algorithm:]name:base64-secret` form used by `dig -y`,
+/// defaulting to hmac-sha256.
+impl FromStr for TsigKey {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts = s.split(':').collect::<Vec<_>>();
+        let (algorithm, name, secret) = match parts[..] {
+            [name, secret] => (TsigAlgorithm::HmacSha256, name, secret),
+            [algorithm, name, secret] => {
+                let algorithm = TsigAlgorithm::from_name(&[algorithm.to_string()])
+                    .ok_or_else(|| anyhow!("unsupported TSIG algorithm {}", algorithm))?;
+                (algorithm, name, secret)
+            }
+            _ => bail!("expected [algorithm:]name:secret, got {}", s),
+        };
+        let secret = base64_decode(secret).ok_or_else(|| anyhow!("secret is not base64"))?;
+        let name = name
+            .split('.')
+            .filter(|x| !x.is_empty())
+            .map(String::from)
+            .collect();
+        Ok(TsigKey {
+            name,
+            algorithm,
+            secret,
+        })
+    }
+}
+
+impl TsigKeyring {
+    pub fn find(&self, name: &[String], algorithm: &[String]) -> Option<&TsigKey> {
+        self.keys
+            .iter()
+            .find(|k| names_equal(&k.name, name) && names_equal(&k.algorithm.name(), algorithm))
+    }
+
+    /// Verifies the TSIG record closing a request, if it has one. Verification
+    /// failures are recorded in the returned session so the response can report
+    /// them; only a malformed TSIG record is an error.
+    pub fn verify(&self, request: &[u8], now: u64) -> Result<Option<TsigSession>, TsigError> {
+        let Some((offset, record)) = find_tsig(request)? else {
+            return Ok(None);
+        };
+        let RData::TSIG(tsig) = &record.rdata else {
+            return Err(TsigError::FormErr);
+        };
+
+        let mut session = TsigSession {
+            key_name: record.name.clone(),
+            algorithm: tsig.algorithm.clone(),
+            key: self.find(&record.name, &tsig.algorithm).cloned(),
+            error: None,
+            previous_mac: Vec::new(),
+            unsigned: Vec::new(),
+            unsigned_count: 0,
+            chain: 0,
+        };
+        match session.check(request, offset, tsig, now) {
+            Err(TsigError::FormErr) => Err(TsigError::FormErr),
+            result => {
+                session.error = result.err();
+                Ok(Some(session))
+            }
+        }
+    }
+}
+
+impl TsigSession {
+    /// Appends a TSIG record to `message`, whose header counts must already be
+    /// final. Sessions whose request failed verification produce an unsigned TSIG
+    /// carrying the error, except for BADTIME which is signed with the server time.
+    pub fn sign(&mut self, message: &mut DnsMessage, now: u64) {
+        let mut tsig = self.unsigned_tsig(message.header.packet_id, now);
+        if let (Some(key), true) = (&self.key, self.signable()) {
+            let digest = self.digest(&message.as_bytes(), &tsig);
+            tsig.mac = key.mac(&digest);
+            self.previous_mac = tsig.mac.clone();
+            self.unsigned.clear();
+            self.unsigned_count = 0;
+            self.chain += 1;
+        }
+        message.additionals.push(self.record(tsig));
+        message.header.arcount += 1;
+    }
+
+    /// Length of the TSIG record [`TsigSession::sign`] appends, which a response
+    /// truncated to fit a UDP datagram must leave room for.
+    pub fn record_len(&self) -> usize {
+        let mut tsig = self.unsigned_tsig(0, 0);
+        if let (Some(key), true) = (&self.key, self.signable()) {
+            tsig.mac = vec![0; key.algorithm.mac_size()];
+        }
+        self.record(tsig).encoded_len()
+    }
+
+    /// Whether responses carry a MAC: always, except when the request failed
+    /// verification with anything but BADTIME.
+    fn signable(&self) -> bool {
+        matches!(self.error, None | Some(TsigError::BadTime))
+    }
+
+    /// The TSIG RDATA of a response, before its MAC is computed.
+    fn unsigned_tsig(&self, original_id: u16, now: u64) -> Tsig {
+        let mut tsig = Tsig {
+            algorithm: self.algorithm.clone(),
+            time_signed: now,
+            fudge: DEFAULT_FUDGE,
+            mac: Vec::new(),
+            original_id,
+            error: self.error.map_or(0, |e| e.code()),
+            other: Vec::new(),
+        };
+        if self.error == Some(TsigError::BadTime) {
+            tsig.other.put_uint(now, 6);
+        }
+        tsig
+    }
+
+    fn record(&self, tsig: Tsig) -> DnsRecord {
+        DnsRecord {
+            name: self.key_name.clone(),
+            typ: QType::TSIG,
+            class: QClass::WildCard,
+            ttl: 0,
+            rdata: RData::TSIG(tsig),
+        }
+    }
+
+    /// Leaves a message of a multi-message response unsigned, to be covered by the
+    /// MAC of the next signed one. RFC 8945 allows at most 99 in a row.
+    pub fn skip(&mut self, message: &DnsMessage) {
+        self.unsigned.extend_from_slice(&message.as_bytes());
+        self.unsigned_count += 1;
+    }
+
+    /// Verifies one response to a request signed in this session. In a
+    /// multi-message response only the first message must be signed, the ones
+    /// following it may be left unsigned and are then covered by the next MAC, up
+    /// to 99 in a row (RFC 8945 section 5.3.1).
+    pub fn verify(&mut self, response: &[u8], now: u64) -> Result<(), TsigError> {
+        let Some((offset, record)) = find_tsig(response)? else {
+            if self.chain < 2 || self.unsigned_count >= MAX_UNSIGNED_MESSAGES {
+                return Err(TsigError::BadSig);
+            }
+            self.unsigned.extend_from_slice(response);
+            self.unsigned_count += 1;
+            return Ok(());
+        };
+        let RData::TSIG(tsig) = &record.rdata else {
+            return Err(TsigError::FormErr);
+        };
+        if !names_equal(&record.name, &self.key_name) {
+            return Err(TsigError::BadKey);
+        }
+        if let Some(error) = TsigError::from_code(tsig.error) {
+            return Err(error);
+        }
+        self.check(response, offset, tsig, now)
+    }
+
+    /// Checks the MAC and signing time of a message whose TSIG record starts at
+    /// `offset`, extending the chain when the MAC matches.
+    fn check(
+        &mut self,
+        bytes: &[u8],
+        offset: usize,
+        tsig: &Tsig,
+        now: u64,
+    ) -> Result<(), TsigError> {
+        let key = self.key.as_ref().ok_or(TsigError::BadKey)?;
+        let full = key.algorithm.mac_size();
+        if tsig.mac.len() > full || tsig.mac.len() < MIN_MAC_SIZE.max(full / 2) {
+            return Err(TsigError::FormErr);
+        }
+
+        let mut message = bytes[..offset].to_vec();
+        message[0..2].copy_from_slice(&tsig.original_id.to_be_bytes());
+        let arcount = u16::from_be_bytes([message[10], message[11]]).saturating_sub(1);
+        message[10..12].copy_from_slice(&arcount.to_be_bytes());
+
+        let digest = self.digest(&message, tsig);
+        if !key.verify_mac(&digest, &tsig.mac) {
+            return Err(TsigError::BadSig);
+        }
+        self.previous_mac = tsig.mac.clone();
+        self.unsigned.clear();
+        self.unsigned_count = 0;
+        self.chain += 1;
+
+        if now.abs_diff(tsig.time_signed) > u64::from(tsig.fudge) {
+            return Err(TsigError::BadTime);
+        }
+        Ok(())
+    }
+
+    /// The data covered by the MAC of the next message of the chain: the previous
+    /// MAC, any unsigned messages since, the message itself and the TSIG variables.
+    /// Past the first response only the timers are covered.
+    fn digest(&self, message: &[u8], tsig: &Tsig) -> Vec<u8> {
+        let canonical = |name: &[String]| {
+            name_as_bytes(
+                &name
+                    .iter()
+                    .map(|x| x.to_ascii_lowercase())
+                    .collect::<Vec<_>>(),
+            )
+        };
+        let timers_only = self.chain > 1;
+
+        let mut digest = Vec::new();
+        if self.chain > 0 {
+            digest.put_u16(self.previous_mac.len() as u16);
+            digest.put(&self.previous_mac[..]);
+        }
+        digest.put(&self.unsigned[..]);
+        digest.put(message);
+        if !timers_only {
+            digest.put(&canonical(&self.key_name)[..]);
+            digest.put_u16(u16::from(QClass::WildCard));
+            digest.put_u32(0);
+            digest.put(&canonical(&tsig.algorithm)[..]);
+        }
+        digest.put_uint(tsig.time_signed, 6);
+        digest.put_u16(tsig.fudge);
+        if !timers_only {
+            digest.put_u16(tsig.error);
+            digest.put_u16(tsig.other.len() as u16);
+            digest.put(&tsig.other[..]);
+        }
+        digest
+    }
+}
+
+impl TsigError {
+    /// The extended RCODE carried in the Error field of the TSIG record.
+    pub fn code(&self) -> u16 {
+        match self {
+            TsigError::FormErr => 1,
+            TsigError::BadSig => 16,
+            TsigError::BadKey => 17,
+            TsigError::BadTime => 18,
+            TsigError::BadTrunc => 22,
+        }
+    }
+
+    pub fn from_code(code: u16) -> Option<Self> {
+        match code {
+            1 => Some(TsigError::FormErr),
+            16 => Some(TsigError::BadSig),
+            17 => Some(TsigError::BadKey),
+            18 => Some(TsigError::BadTime),
+            22 => Some(TsigError::BadTrunc),
+            _ => None,
+        }
+    }
+
+    /// The header RCODE of a response reporting this error.
+    pub fn rcode(&self) -> ResponseCode {
+        match self {
+            TsigError::FormErr => ResponseCode::FormatError,
+            _ => ResponseCode::NotAuth,
+        }
+    }
+}
+
+/// Locates the TSIG record of a message, returning its offset. A TSIG record must
+/// be the last record of the Additional section, anywhere else is malformed.
+fn find_tsig(bytes: &[u8]) -> Result<Option<(usize, DnsRecord)>, TsigError> {
+    let message = MessageRef::new(bytes).ok_or(TsigError::FormErr)?;
+    let mut records = message
+        .answers()
+        .chain(message.authorities())
+        .chain(message.additionals());
+    let Some(tsig) = records.find(|r| r.typ == QType::TSIG) else {
+        return Ok(None);
+    };
+    if records.next().is_some() {
+        return Err(TsigError::FormErr);
+    }
+    let record = tsig.to_record().ok_or(TsigError::FormErr)?;
+    Ok(Some((tsig.offset, record)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        dns_header::header::{QueryResponse, RecursionDesire},
+        dns_message::{
+            builder::MessageBuilder,
+            message::DnsMessage,
+            tsig::{TsigAlgorithm, TsigError, TsigKey, TsigKeyring},
+            tsig_impl::MAX_UNSIGNED_MESSAGES,
+        },
+        dns_record::record::RData,
+        resrec::QType,
+    };
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn key(algorithm: TsigAlgorithm) -> TsigKey {
+        TsigKey {
+            name: vec!["transfer".to_string(), "example".to_string()],
+            algorithm,
+            secret: b"0123456789abcdef".to_vec(),
+        }
+    }
+
+    fn message(id: u16) -> DnsMessage {
+        let qname = vec!["example".to_string(), "com".to_string()];
+        MessageBuilder::query(qname, QType::SOA).id(id).build()
+    }
+
+    fn keyring() -> TsigKeyring {
+        TsigKeyring {
+            keys: vec![
+                key(TsigAlgorithm::HmacSha256),
+                key(TsigAlgorithm::HmacSha512),
+            ],
+        }
+    }
+
+    #[test]
+    fn sign_and_verify_request_test() {
+        for algorithm in [TsigAlgorithm::HmacSha256, TsigAlgorithm::HmacSha512] {
+            let mut request = message(42);
+            key(algorithm).session().sign(&mut request, NOW);
+            let session = keyring()
+                .verify(&request.as_bytes(), NOW + 10)
+                .unwrap()
+                .unwrap();
+            assert_eq!(session.error, None);
+            assert_eq!(session.key.unwrap().algorithm, algorithm);
+        }
+    }
+
+    #[test]
+    fn unsigned_request_test() {
+        assert!(keyring()
+            .verify(&message(1).as_bytes(), NOW)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn tampered_request_test() {
+        let mut request = message(42);
+        key(TsigAlgorithm::HmacSha256)
+            .session()
+            .sign(&mut request, NOW);
+        request.questions[0].qtype = QType::A;
+
+        let session = keyring().verify(&request.as_bytes(), NOW).unwrap().unwrap();
+        assert_eq!(session.error, Some(TsigError::BadSig));
+    }
+
+    #[test]
+    fn unknown_key_test() {
+        let mut request = message(42);
+        let mut unknown = key(TsigAlgorithm::HmacSha256);
+        unknown.name = vec!["other".to_string()];
+        unknown.session().sign(&mut request, NOW);
+
+        let session = keyring().verify(&request.as_bytes(), NOW).unwrap().unwrap();
+        assert_eq!(session.error, Some(TsigError::BadKey));
+    }
+
+    #[test]
+    fn bad_time_test() {
+        let mut request = message(42);
+        key(TsigAlgorithm::HmacSha256)
+            .session()
+            .sign(&mut request, NOW);
+
+        let mut session = keyring()
+            .verify(&request.as_bytes(), NOW + 301)
+            .unwrap()
+            .unwrap();
+        assert_eq!(session.error, Some(TsigError::BadTime));
+
+        let mut response = message(42);
+        session.sign(&mut response, NOW + 301);
+        let RData::TSIG(tsig) = &response.additionals[0].rdata else {
+            panic!("expected a TSIG record");
+        };
+        assert_eq!(tsig.error, 18);
+        assert_eq!(tsig.mac.len(), 32);
+        assert_eq!(tsig.other, vec![0, 0, 0x65, 0x53, 0xf2, 0x2d]);
+    }
+
+    #[test]
+    fn error_response_is_unsigned_test() {
+        let mut request = message(42);
+        key(TsigAlgorithm::HmacSha256)
+            .session()
+            .sign(&mut request, NOW);
+        request.header.rd = RecursionDesire::Desired;
+
+        let mut session = keyring().verify(&request.as_bytes(), NOW).unwrap().unwrap();
+        let mut response = message(42);
+        session.sign(&mut response, NOW);
+        let RData::TSIG(tsig) = &response.additionals[0].rdata else {
+            panic!("expected a TSIG record");
+        };
+        assert_eq!(tsig.error, 16);
+        assert!(tsig.mac.is_empty());
+    }
+
+    #[test]
+    fn tsig_not_last_test() {
+        let mut request = message(42);
+        key(TsigAlgorithm::HmacSha256)
+            .session()
+            .sign(&mut request, NOW);
+        request.additionals.push(request.additionals[0].clone());
+        request.header.arcount += 1;
+
+        assert_eq!(
+            keyring().verify(&request.as_bytes(), NOW).err(),
+            Some(TsigError::FormErr)
+        );
+    }
+
+    #[test]
+    fn multi_message_stream_test() {
+        let mut client = key(TsigAlgorithm::HmacSha256).session();
+        let mut request = message(7);
+        client.sign(&mut request, NOW);
+
+        let mut server = keyring().verify(&request.as_bytes(), NOW).unwrap().unwrap();
+        let mut responses = Vec::new();
+        for i in 0..4 {
+            let mut response = message(7);
+            response.header.qr = QueryResponse::Reply;
+            // The third message is left unsigned, covered by the fourth.
+            match i {
+                2 => server.skip(&response),
+                _ => server.sign(&mut response, NOW + i),
+            }
+            responses.push(response.as_bytes());
+        }
+
+        for response in &responses {
+            assert_eq!(client.verify(response, NOW + 5), Ok(()));
+        }
+    }
+
+    #[test]
+    fn unsigned_limit_test() {
+        let mut client = key(TsigAlgorithm::HmacSha256).session();
+        let mut request = message(7);
+        client.sign(&mut request, NOW);
+
+        let mut server = keyring().verify(&request.as_bytes(), NOW).unwrap().unwrap();
+        let mut first = message(7);
+        server.sign(&mut first, NOW);
+        assert_eq!(client.verify(&first.as_bytes(), NOW), Ok(()));
+        let unsigned = message(7).as_bytes();
+        for _ in 0..MAX_UNSIGNED_MESSAGES {
+            assert_eq!(client.verify(&unsigned, NOW), Ok(()));
+        }
+        // The 100th message in a row must be signed.
+        assert_eq!(client.verify(&unsigned, NOW), Err(TsigError::BadSig));
+        assert_eq!(client.unsigned_count, MAX_UNSIGNED_MESSAGES);
+    }
+
+    #[test]
+    fn stream_out_of_order_test() {
+        let mut client = key(TsigAlgorithm::HmacSha256).session();
+        let mut request = message(7);
+        client.sign(&mut request, NOW);
+
+        let mut server = keyring().verify(&request.as_bytes(), NOW).unwrap().unwrap();
+        let mut first = message(7);
+        server.sign(&mut first, NOW);
+        let mut second = message(7);
+        server.sign(&mut second, NOW);
+
+        assert_eq!(
+            client.verify(&second.as_bytes(), NOW),
+            Err(TsigError::BadSig)
+        );
+    }
+
+    #[test]
+    fn key_from_str_test() {
+        let key = "hmac-sha512:transfer.example.:MDEyMzQ1Njc4OWFiY2RlZg=="
+            .parse::<TsigKey>()
+            .unwrap();
+        assert_eq!(key, self::key(TsigAlgorithm::HmacSha512));
+
+        let key = "transfer.example:MDEyMzQ1Njc4OWFiY2RlZg==".parse::<TsigKey>();
+        assert_eq!(key.unwrap().algorithm, TsigAlgorithm::HmacSha256);
+
+        assert!("hmac-md5:key:MDEy".parse::<TsigKey>().is_err());
+        assert!("key".parse::<TsigKey>().is_err());
+    }
+}
@@ -0,0 +1,298 @@
+use bytes::BufMut;
+
+/// Upper bound on compression pointers followed while reading a single name,
+/// so a pointer loop in a malformed message cannot hang the parser.
+const MAX_POINTER_JUMPS: usize = 64;
+
+/// The two high bits of a length octet marking a compression pointer.
+const POINTER_MASK: u8 = 0b1100_0000;
+
+/// Longest label allowed by RFC 1035 section 2.3.4.
+const MAX_LABEL_LENGTH: usize = 63;
+
+/// Encodes a domain name as a sequence of length-prefixed labels terminated by
+/// the zero-length root label.
+pub fn name_as_bytes(labels: &[String]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(name_len(labels));
+    write_name(labels, &mut bytes);
+    bytes
+}
+
+/// Writes a domain name to `buf` as [`name_as_bytes`] encodes it. `buf` must
+/// have room for [`name_len`] more octets.
+pub fn write_name(labels: &[String], buf: &mut impl BufMut) {
+    for label in labels {
+        let Ok(len) = u8::try_from(label.len()) else {
+            continue;
+        };
+        buf.put_u8(len);
+        buf.put(label.as_bytes());
+    }
+    buf.put_u8(0);
+}
+
+/// The length of a domain name as [`name_as_bytes`] encodes it.
+pub fn name_len(labels: &[String]) -> usize {
+    let labels = labels.iter().filter(|x| x.len() <= usize::from(u8::MAX));
+    labels.map(|x| x.len() + 1).sum::<usize>() + 1
+}
+
+/// Reads a domain name starting at `offset` in `message`, following compression
+/// pointers. Returns the labels and the offset right after the name in the
+/// original position.
+pub fn parse_name(message: &[u8], offset: usize) -> Option<(Vec<String>, usize)> {
+    let mut labels = Vec::new();
+    let end = visit_name(message, offset, |label| labels.push(label.to_string()))?;
+    Some((labels, end))
+}
+
+/// Reads a domain name like [`parse_name`], handing each label to `visit` where
+/// it lies in `message` instead of copying it. Returns the offset right after
+/// the name in the original position.
+pub fn visit_name<'a>(
+    message: &'a [u8],
+    offset: usize,
+    mut visit: impl FnMut(&'a str),
+) -> Option<usize> {
+    let mut pos = offset;
+    let mut end = None;
+    let mut jumps = 0;
+
+    loop {
+        let len = *message.get(pos)?;
+        if len & POINTER_MASK == POINTER_MASK {
+            let low = *message.get(pos + 1)?;
+            end.get_or_insert(pos + 2);
+            jumps += 1;
+            if jumps > MAX_POINTER_JUMPS {
+                return None;
+            }
+            pos = (usize::from(len & !POINTER_MASK) << 8) | usize::from(low);
+            continue;
+        }
+        if len == 0 {
+            end.get_or_insert(pos + 1);
+            break;
+        }
+        let len = usize::from(len);
+        let label = message.get(pos + 1..pos + 1 + len)?;
+        visit(std::str::from_utf8(label).ok()?);
+        pos += len + 1;
+    }
+
+    end
+}
+
+/// Compares two domain names, ignoring ASCII case as required by RFC 1035.
+pub fn names_equal(a: &[String], b: &[String]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(x, y)| x.eq_ignore_ascii_case(y))
+}
+
+/// Whether `name` is `zone` itself or lies below it.
+pub fn is_subdomain(name: &[String], zone: &[String]) -> bool {
+    name.len() >= zone.len() && names_equal(&name[name.len() - zone.len()..], zone)
+}
+
+/// Presents a domain name as dotted labels ending with the root dot, escaping
+/// dots, backslashes and unprintable octets inside labels (RFC 1035 section 5.1).
+pub fn name_to_string(labels: &[String]) -> String {
+    if labels.is_empty() {
+        return ".".to_string();
+    }
+    let mut text = String::new();
+    for label in labels {
+        for byte in label.bytes() {
+            match byte {
+                b'.' | b'\\' | b'"' | b'(' | b')' | b';' => {
+                    text.push('\\');
+                    text.push(byte as char);
+                }
+                0x21..=0x7e => text.push(byte as char),
+                _ => text.push_str(&format!("\\{:03}", byte)),
+            }
+        }
+        text.push('.');
+    }
+    text
+}
+
+/// Reads a domain name in presentation format, the reverse of [`name_to_string`].
+/// Names without the trailing root dot are relative to `origin`, and `@` is the
+/// origin itself. Returns `None` for empty or oversized labels, and for escaped
+/// octets outside ASCII, which labels cannot hold.
+pub fn name_from_string(text: &str, origin: &[String]) -> Option<Vec<String>> {
+    match text {
+        "@" => return Some(origin.to_vec()),
+        "." => return Some(Vec::new()),
+        _ => {}
+    }
+    let mut labels = Vec::new();
+    let mut label = String::new();
+    let mut chars = text.chars();
+    let mut absolute = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '.' => {
+                if label.is_empty() {
+                    return None;
+                }
+                labels.push(std::mem::take(&mut label));
+                absolute = chars.as_str().is_empty();
+            }
+            '\\' => {
+                let rest = chars.as_str();
+                match rest
+                    .get(..3)
+                    .filter(|x| x.bytes().all(|b| b.is_ascii_digit()))
+                {
+                    Some(digits) => {
+                        let byte = digits.parse::<u8>().ok().filter(u8::is_ascii)?;
+                        label.push(byte as char);
+                        chars = rest[3..].chars();
+                    }
+                    None => label.push(chars.next()?),
+                }
+            }
+            _ => label.push(c),
+        }
+        if label.len() > MAX_LABEL_LENGTH {
+            return None;
+        }
+    }
+    if !label.is_empty() {
+        labels.push(label);
+    }
+    if !absolute {
+        labels.extend_from_slice(origin);
+    }
+    Some(labels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        is_subdomain, name_as_bytes, name_from_string, name_to_string, names_equal, parse_name,
+    };
+
+    fn name(labels: &[&str]) -> Vec<String> {
+        labels.iter().map(|x| x.to_string()).collect()
+    }
+
+    #[test]
+    fn name_as_bytes_test() {
+        assert_eq!(
+            name_as_bytes(&name(&["www", "example", "com"])),
+            vec![
+                3, b'w', b'w', b'w', 7, b'e', b'x', b'a', b'm', b'p', b'l', b'e', 3, b'c', b'o',
+                b'm', 0
+            ]
+        );
+        assert_eq!(name_as_bytes(&[]), vec![0]);
+    }
+
+    #[test]
+    fn parse_name_test() {
+        let bytes = vec![
+            3, b'w', b'w', b'w', 7, b'e', b'x', b'a', b'm', b'p', b'l', b'e', 3, b'c', b'o', b'm',
+            0, 0, 1,
+        ];
+        assert_eq!(
+            parse_name(&bytes, 0),
+            Some((name(&["www", "example", "com"]), 17))
+        );
+    }
+
+    #[test]
+    fn parse_compressed_name_test() {
+        let bytes = vec![
+            7,
+            b'e',
+            b'x',
+            b'a',
+            b'm',
+            b'p',
+            b'l',
+            b'e',
+            3,
+            b'c',
+            b'o',
+            b'm',
+            0,
+            3,
+            b'w',
+            b'w',
+            b'w',
+            0b1100_0000,
+            0,
+        ];
+        assert_eq!(
+            parse_name(&bytes, 13),
+            Some((name(&["www", "example", "com"]), 19))
+        );
+    }
+
+    #[test]
+    fn parse_name_pointer_loop_test() {
+        let bytes = vec![0b1100_0000, 0];
+        assert_eq!(parse_name(&bytes, 0), None);
+    }
+
+    #[test]
+    fn parse_truncated_name_test() {
+        let bytes = vec![3, b'w', b'w'];
+        assert_eq!(parse_name(&bytes, 0), None);
+    }
+
+    #[test]
+    fn names_equal_test() {
+        assert!(names_equal(
+            &name(&["Example", "COM"]),
+            &name(&["example", "com"])
+        ));
+        assert!(!names_equal(
+            &name(&["example"]),
+            &name(&["example", "com"])
+        ));
+    }
+
+    #[test]
+    fn is_subdomain_test() {
+        let zone = name(&["example", "com"]);
+        assert!(is_subdomain(&name(&["www", "example", "com"]), &zone));
+        assert!(is_subdomain(&zone, &zone));
+        assert!(!is_subdomain(&name(&["example", "org"]), &zone));
+        assert!(!is_subdomain(&name(&["com"]), &zone));
+    }
+
+    #[test]
+    fn name_to_string_test() {
+        assert_eq!(name_to_string(&[]), ".");
+        assert_eq!(
+            name_to_string(&name(&["www", "example", "com"])),
+            "www.example.com."
+        );
+        assert_eq!(name_to_string(&name(&["a.b", "c d"])), "a\\.b.c\\032d.");
+    }
+
+    #[test]
+    fn name_from_string_test() {
+        let origin = name(&["example", "com"]);
+        assert_eq!(
+            name_from_string("www.example.com.", &[]),
+            Some(name(&["www", "example", "com"]))
+        );
+        assert_eq!(
+            name_from_string("www", &origin),
+            Some(name(&["www", "example", "com"]))
+        );
+        assert_eq!(name_from_string("@", &origin), Some(origin.clone()));
+        assert_eq!(name_from_string(".", &origin), Some(vec![]));
+        assert_eq!(
+            name_from_string("a\\.b.c\\032d.", &[]),
+            Some(name(&["a.b", "c d"]))
+        );
+        assert_eq!(name_from_string("a..b", &origin), None);
+        assert_eq!(name_from_string("\\200.", &origin), None);
+        assert_eq!(name_from_string(&"a".repeat(64), &origin), None);
+    }
+}
@@ -1,2 +1,2 @@
 pub mod question;
-pub mod question_impl;
+pub(crate) mod question_impl;
@@ -1,51 +1,76 @@
 use super::question::DnsQuestion;
-use crate::resrec::{QClass, QType};
-use bytes::BufMut;
+use crate::{
+    dns_message::message::EncodeError,
+    dns_name::name::{name_len, name_to_string, parse_name, write_name},
+    resrec::{QClass, QType},
+};
+use bytes::{Buf, BufMut};
+use std::fmt;
 
 impl DnsQuestion {
     pub fn as_bytes(&self) -> Vec<u8> {
-        let mut labels = self
-            .qname
-            .iter()
-            .flat_map(|x| {
-                let len = x.len().try_into().ok().and_then(char::from_u32)?;
-                let mut label = String::new();
-                label.push(len);
-                label.push_str(x);
-                Some(label)
-            })
-            .collect::<Vec<_>>()
-            .join("");
-        labels.push('\0');
-
-        let mut bytes = Vec::new();
-        bytes.put(labels.as_bytes());
-        bytes.put_u16(self.qtype as u16);
-        bytes.put_u16(self.qclass as u16);
+        let mut bytes = Vec::with_capacity(self.encoded_len());
+        self.write(&mut bytes);
         bytes
     }
+
+    /// The length of the question in wire format.
+    pub fn encoded_len(&self) -> usize {
+        name_len(&self.qname) + 4
+    }
+
+    /// Writes the question to `buf`, failing when it has no room for it. Returns
+    /// the octets written.
+    pub fn encode_into(&self, buf: &mut impl BufMut) -> Result<usize, EncodeError> {
+        let len = self.encoded_len();
+        EncodeError::check(len, buf)?;
+        self.write(buf);
+        Ok(len)
+    }
+
+    /// Writes the question to `buf`, which must have room for it.
+    pub fn write(&self, buf: &mut impl BufMut) {
+        write_name(&self.qname, buf);
+        buf.put_u16(u16::from(self.qtype));
+        buf.put_u16(u16::from(self.qclass));
+    }
+
+    /// Reads a question starting at `offset` in `message`. Returns the question and
+    /// the offset of whatever follows it.
+    pub fn parse(message: &[u8], offset: usize) -> Option<(Self, usize)> {
+        let (qname, offset) = parse_name(message, offset)?;
+        let mut fixed = message.get(offset..offset + 4)?;
+        let question = DnsQuestion {
+            qname,
+            qtype: QType::from(fixed.get_u16()),
+            qclass: QClass::from(fixed.get_u16()),
+        };
+        Some((question, offset + 4))
+    }
 }
 
 impl From<&[u8]> for DnsQuestion {
     fn from(val: &[u8]) -> Self {
-        let mut qname = Vec::new();
-        let mut i = 0;
-        while i < val.len() {
-            let len: usize = val[i].into();
-            if len == 0 {
-                break;
-            }
-            let Ok(label) = String::from_utf8(val[i + 1..i + 1 + len].to_vec()) else {
-                break;
-            };
-            qname.push(label);
-            i += len + 1;
-        }
-        DnsQuestion {
-            qname,
-            qtype: QType::A,
-            qclass: QClass::IN,
-        }
+        DnsQuestion::parse(val, 0)
+            .map(|(question, _)| question)
+            .unwrap_or(DnsQuestion {
+                qname: Vec::new(),
+                qtype: QType::A,
+                qclass: QClass::IN,
+            })
+    }
+}
+
+/// `www.example.com. IN A`, the owner, class and type of a master file line.
+impl fmt::Display for DnsQuestion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {}",
+            name_to_string(&self.qname),
+            self.qclass,
+            self.qtype
+        )
     }
 }
 
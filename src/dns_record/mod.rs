@@ -0,0 +1,2 @@
+pub mod record;
+pub(crate) mod record_impl;
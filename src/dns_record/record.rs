@@ -0,0 +1,640 @@
+use crate::resrec::{QClass, QType};
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+/// ## Resource record format
+///
+/// Shared by the Answer, Authority and Additional sections.
+///
+/// ```text
+///                                 1  1  1  1  1  1
+///   0  1  2  3  4  5  6  7  8  9  0  1  2  3  4  5
+/// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+/// |                                               |
+/// /                                               /
+/// /                      NAME                     /
+/// |                                               |
+/// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+/// |                      TYPE                     |
+/// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+/// |                     CLASS                     |
+/// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+/// |                      TTL                      |
+/// |                                               |
+/// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+/// |                   RDLENGTH                    |
+/// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--|
+/// /                     RDATA                     /
+/// /                                               /
+/// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DnsRecord {
+    /// an owner name, i.e., the name of the node to which this resource record pertains.
+    pub name: Vec<String>,
+    /// two octets containing one of the RR TYPE codes.
+    pub typ: QType,
+    /// two octets containing one of the RR CLASS codes.
+    pub class: QClass,
+    /// the time interval (in seconds) that the resource record may be cached before
+    /// the source of the information should again be consulted. Zero values mean the
+    /// RR can only be used for the transaction in progress.
+    pub ttl: u32,
+    /// a variable length string of octets that describes the resource. The format of
+    /// this information varies according to the TYPE and CLASS of the resource record.
+    /// RDLENGTH is derived from it when serializing.
+    pub rdata: RData,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RData {
+    /// a 32 bit Internet address
+    A(Ipv4Addr),
+    /// a host which should be authoritative for the specified class and domain
+    NS(Vec<String>),
+    /// the canonical or primary name for the owner, the owner name is an alias
+    CNAME(Vec<String>),
+    /// the start of a zone of authority
+    SOA(Soa),
+    /// a pointer to some location in the domain name space
+    PTR(Vec<String>),
+    /// a host willing to act as a mail exchange for the owner name
+    MX {
+        /// the preference given to this RR among others at the same owner, lower
+        /// values are preferred
+        preference: u16,
+        /// a host willing to act as a mail exchange for the owner name
+        exchange: Vec<String>,
+    },
+    /// descriptive text, e.g. the SPF policy or DKIM key of a domain
+    TXT(Txt),
+    /// a 128 bit IPv6 address
+    AAAA(Ipv6Addr),
+    /// the host and port of a service offered under the owner name
+    SRV(Srv),
+    /// a rule rewriting a string into a domain name or URI, for applications
+    /// looking services up through DDDS
+    NAPTR(Naptr),
+    /// a URI the service of the owner name is reachable at
+    URI(Uri),
+    /// EDNS(0) parameters of the sender, whose fields also take over the CLASS and
+    /// TTL of the record
+    OPT(Opt),
+    /// a public key of the zone, used to verify its signatures
+    DNSKEY(Dnskey),
+    /// the digest of a key of the child zone, held by the parent at a delegation
+    DS(Ds),
+    /// a signature over the RRset of the owner name with the covered type
+    RRSIG(Rrsig),
+    /// the next owner name in the zone, proving the names in between do not exist
+    NSEC(Nsec),
+    /// the next hashed owner name in the zone, proving nonexistence without
+    /// listing the zone's names
+    NSEC3(Nsec3),
+    /// the hashing parameters of the zone's NSEC3 records
+    NSEC3PARAM(Nsec3Param),
+    /// the fingerprint of the SSH host key of the owner name
+    SSHFP(Sshfp),
+    /// the certificate or public key the TLS server of the owner name presents,
+    /// or the authority it chains to
+    TLSA(Tlsa),
+    /// the OpenPGP key of the email address hashed into the owner name
+    OPENPGPKEY(Vec<u8>),
+    /// a property of the owner name certification authorities must honour, e.g.
+    /// which of them may issue certificates for it
+    CAA(Caa),
+    /// an endpoint of the service of the owner name, or an alias of it
+    SVCB(Svcb),
+    /// an endpoint of the HTTPS origin of the owner name, or an alias of it
+    HTTPS(Svcb),
+    /// a transaction signature, only ever found last in the Additional section
+    TSIG(Tsig),
+    /// uninterpreted RDATA, for types without a dedicated representation and for
+    /// the empty RDATA used by dynamic update prerequisites and deletions
+    Raw(Vec<u8>),
+}
+
+/// ## SOA RDATA format
+///
+/// ```text
+/// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+/// /                     MNAME                     /
+/// /                                               /
+/// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+/// /                     RNAME                     /
+/// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+/// |                    SERIAL                     |
+/// |                                               |
+/// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+/// |                    REFRESH                    |
+/// |                                               |
+/// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+/// |                     RETRY                     |
+/// |                                               |
+/// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+/// |                    EXPIRE                     |
+/// |                                               |
+/// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+/// |                    MINIMUM                    |
+/// |                                               |
+/// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Soa {
+    /// The name server that was the original or primary source of data for this zone.
+    pub mname: Vec<String>,
+    /// The mailbox of the person responsible for this zone.
+    pub rname: Vec<String>,
+    /// The version number of the original copy of the zone, compared with
+    /// sequence space arithmetic.
+    pub serial: u32,
+    /// Time interval before the zone should be refreshed.
+    pub refresh: u32,
+    /// Time interval that should elapse before a failed refresh should be retried.
+    pub retry: u32,
+    /// Upper limit on the time interval that can elapse before the zone is no
+    /// longer authoritative.
+    pub expire: u32,
+    /// The minimum TTL field that should be exported with any RR from this zone.
+    pub minimum: u32,
+}
+
+/// ## TXT RDATA format (RFC 1035 section 3.3.14)
+///
+/// ```text
+/// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+/// /                   TXT-DATA                    /
+/// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+/// ```
+///
+/// TXT-DATA is one or more character-strings, each a length octet followed by
+/// that many octets of any value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Txt {
+    /// The character-strings, of at most 255 octets each. SPF and DKIM read
+    /// them joined together, so longer text is split across several.
+    pub strings: Vec<Vec<u8>>,
+}
+
+/// ## SRV RDATA format (RFC 2782)
+///
+/// The owner name is `_<service>._<proto>.<name>`, e.g. `_sip._udp.example.com`.
+///
+/// ```text
+///                      1 1 1 1 1 1 1 1 1 1 2 2 2 2 2 2 2 2 2 2 3 3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |           Priority            |            Weight             |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |             Port              |                               /
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+            Target             /
+/// /                                                               /
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Srv {
+    /// Clients try the targets with the lowest priority first.
+    pub priority: u16,
+    /// The relative share of clients a target gets among those of the same
+    /// priority, zero when there is no preference.
+    pub weight: u16,
+    /// The port the service listens on at the target.
+    pub port: u16,
+    /// The host providing the service, the root when the service is not offered
+    /// at this name. Never compressed.
+    pub target: Vec<String>,
+}
+
+/// ## NAPTR RDATA format (RFC 3403 section 4.1)
+///
+/// ```text
+///                                 1  1  1  1  1  1
+///   0  1  2  3  4  5  6  7  8  9  0  1  2  3  4  5
+/// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+/// |                     ORDER                     |
+/// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+/// |                   PREFERENCE                  |
+/// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+/// /                     FLAGS                     /
+/// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+/// /                   SERVICES                    /
+/// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+/// /                    REGEXP                     /
+/// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+/// /                  REPLACEMENT                  /
+/// /                                               /
+/// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Naptr {
+    /// Rules are applied in increasing order, later ones only when earlier
+    /// ones did not lead anywhere.
+    pub order: u16,
+    /// The preference among rules of the same order, lower values first.
+    pub preference: u16,
+    /// A character-string of flags: `S` leads to SRV records, `A` to address
+    /// records, `U` to a URI and `P` to a protocol-specific step.
+    pub flags: Vec<u8>,
+    /// A character-string naming the services available down this rewrite
+    /// path, e.g. `SIP+D2U`.
+    pub services: Vec<u8>,
+    /// A character-string holding a substitution expression applied to the
+    /// original string of the client, empty when `replacement` is used.
+    pub regexp: Vec<u8>,
+    /// The next name to look up, the root when `regexp` is used. Never
+    /// compressed.
+    pub replacement: Vec<String>,
+}
+
+/// ## URI RDATA format (RFC 7553 section 4.5)
+///
+/// ```text
+///                      1 1 1 1 1 1 1 1 1 1 2 2 2 2 2 2 2 2 2 2 3 3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |          Priority             |          Weight               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// /                                                               /
+/// /                             Target                            /
+/// /                                                               /
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Uri {
+    /// As in [`Srv::priority`].
+    pub priority: u16,
+    /// As in [`Srv::weight`].
+    pub weight: u16,
+    /// The URI, filling the rest of the RDATA without a length octet.
+    pub target: Vec<u8>,
+}
+
+/// ## TSIG RDATA format
+///
+/// ```text
+///                      1 1 1 1 1 1 1 1 1 1 2 2 2 2 2 2 2 2 2 2 3 3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// /                         Algorithm Name                        /
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                                                               |
+/// |          Time Signed          +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                               |            Fudge              |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |          MAC Size             |                               /
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+             MAC               /
+/// /                                                               /
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |          Original ID          |            Error              |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |          Other Len            |                               /
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+           Other Data          /
+/// /                                                               /
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tsig {
+    /// The MAC algorithm, e.g. `hmac-sha256.`
+    pub algorithm: Vec<String>,
+    /// Seconds since the epoch at which the message was signed, 48 bits on the wire.
+    pub time_signed: u64,
+    /// Seconds of error permitted in `time_signed`.
+    pub fudge: u16,
+    /// The message authentication code, possibly truncated.
+    pub mac: Vec<u8>,
+    /// The ID of the message before any forwarder rewrote it.
+    pub original_id: u16,
+    /// An extended RCODE covering TSIG processing, e.g. BADSIG (16).
+    pub error: u16,
+    /// Empty, except for BADTIME errors where it holds the server's time.
+    pub other: Vec<u8>,
+}
+
+/// ## OPT pseudo-record (RFC 6891 section 6.1.2)
+///
+/// The CLASS field holds the UDP payload size and the TTL field the extended
+/// RCODE, version and flags, while the RDATA is a sequence of options:
+///
+/// ```text
+///                +0 (MSB)                            +1 (LSB)
+///     +---+---+---+---+---+---+---+---+---+---+---+---+---+---+---+---+
+///  0: |                          OPTION-CODE                          |
+///     +---+---+---+---+---+---+---+---+---+---+---+---+---+---+---+---+
+///  2: |                         OPTION-LENGTH                         |
+///     +---+---+---+---+---+---+---+---+---+---+---+---+---+---+---+---+
+///  4: |                                                               |
+///     /                          OPTION-DATA                          /
+///     /                                                               /
+///     +---+---+---+---+---+---+---+---+---+---+---+---+---+---+---+---+
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Opt {
+    /// The largest UDP payload the sender can reassemble, from the CLASS field.
+    pub udp_payload_size: u16,
+    /// The upper eight bits of a 12-bit RCODE, from the TTL field.
+    pub extended_rcode: u8,
+    /// The EDNS version, from the TTL field.
+    pub version: u8,
+    /// The DO bit of the TTL field: the sender wants DNSSEC records.
+    pub dnssec_ok: bool,
+    pub options: Vec<EdnsOption>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EdnsOption {
+    pub code: u16,
+    pub data: Vec<u8>,
+}
+
+/// ## DNSKEY RDATA format (RFC 4034 section 2.1)
+///
+/// ```text
+///                      1 1 1 1 1 1 1 1 1 1 2 2 2 2 2 2 2 2 2 2 3 3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |              Flags            |    Protocol   |   Algorithm   |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// /                                                               /
+/// /                            Public Key                         /
+/// /                                                               /
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dnskey {
+    /// Bit 7 marks a zone key, bit 15 a secure entry point (a key signing key).
+    pub flags: u16,
+    /// Always 3.
+    pub protocol: u8,
+    /// The signing algorithm, e.g. 8 for RSA/SHA-256 or 13 for ECDSA P-256/SHA-256.
+    pub algorithm: u8,
+    /// The public key, in a format specific to the algorithm.
+    pub public_key: Vec<u8>,
+}
+
+/// ## DS RDATA format (RFC 4034 section 5.1)
+///
+/// ```text
+///                      1 1 1 1 1 1 1 1 1 1 2 2 2 2 2 2 2 2 2 2 3 3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |           Key Tag             |  Algorithm    |  Digest Type  |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// /                                                               /
+/// /                            Digest                             /
+/// /                                                               /
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ds {
+    /// The key tag of the DNSKEY the digest was computed over.
+    pub key_tag: u16,
+    /// The algorithm of that DNSKEY.
+    pub algorithm: u8,
+    /// The digest algorithm, e.g. 2 for SHA-256.
+    pub digest_type: u8,
+    /// The digest of the owner name and RDATA of the DNSKEY.
+    pub digest: Vec<u8>,
+}
+
+/// ## RRSIG RDATA format (RFC 4034 section 3.1)
+///
+/// ```text
+///                      1 1 1 1 1 1 1 1 1 1 2 2 2 2 2 2 2 2 2 2 3 3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |        Type Covered           |  Algorithm    |     Labels    |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                         Original TTL                          |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                      Signature Expiration                     |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                      Signature Inception                      |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |            Key Tag            |                               /
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+         Signer's Name         /
+/// /                                                               /
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// /                                                               /
+/// /                            Signature                          /
+/// /                                                               /
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rrsig {
+    /// The type of the RRset signed, kept as a code so signatures over types
+    /// without a [`QType`] are carried untouched.
+    pub type_covered: u16,
+    /// The signing algorithm, as in [`Dnskey::algorithm`].
+    pub algorithm: u8,
+    /// The number of labels in the original owner name, fewer than the owner's
+    /// when the RRset was synthesized from a wildcard.
+    pub labels: u8,
+    /// The TTL of the RRset as it appears in the zone.
+    pub original_ttl: u32,
+    /// End of the validity period, in seconds since the epoch (serial arithmetic).
+    pub expiration: u32,
+    /// Start of the validity period, in seconds since the epoch (serial arithmetic).
+    pub inception: u32,
+    /// The key tag of the DNSKEY verifying the signature.
+    pub key_tag: u16,
+    /// The owner of that DNSKEY, the apex of the zone. Never compressed.
+    pub signer: Vec<String>,
+    pub signature: Vec<u8>,
+}
+
+/// ## NSEC RDATA format (RFC 4034 section 4.1)
+///
+/// ```text
+///                      1 1 1 1 1 1 1 1 1 1 2 2 2 2 2 2 2 2 2 2 3 3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// /                      Next Domain Name                         /
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// /                       Type Bit Maps                           /
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Nsec {
+    /// The next owner name in canonical order, the apex for the last one. Never
+    /// compressed.
+    pub next: Vec<String>,
+    /// The type codes present at the owner name, sorted.
+    pub types: Vec<u16>,
+}
+
+/// ## NSEC3 RDATA format (RFC 5155 section 3.2)
+///
+/// ```text
+///                      1 1 1 1 1 1 1 1 1 1 2 2 2 2 2 2 2 2 2 2 3 3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |   Hash Alg.   |     Flags     |          Iterations           |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |  Salt Length  |                     Salt                      /
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |  Hash Length  |             Next Hashed Owner Name            /
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// /                         Type Bit Maps                         /
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Nsec3 {
+    /// The hash function, 1 for SHA-1.
+    pub hash_algorithm: u8,
+    /// Bit 0 is the opt-out flag: unsigned delegations may be skipped.
+    pub flags: u8,
+    /// Additional times the hash function is applied.
+    pub iterations: u16,
+    pub salt: Vec<u8>,
+    /// The next hashed owner name in hash order, unencoded.
+    pub next_hashed: Vec<u8>,
+    /// The type codes present at the original owner name, sorted.
+    pub types: Vec<u16>,
+}
+
+/// ## NSEC3PARAM RDATA format (RFC 5155 section 4.2)
+///
+/// ```text
+///                      1 1 1 1 1 1 1 1 1 1 2 2 2 2 2 2 2 2 2 2 3 3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |   Hash Alg.   |     Flags     |          Iterations           |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |  Salt Length  |                     Salt                      /
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Nsec3Param {
+    /// The hash function, 1 for SHA-1.
+    pub hash_algorithm: u8,
+    /// Zero, the opt-out flag only has a meaning in NSEC3 records.
+    pub flags: u8,
+    pub iterations: u16,
+    pub salt: Vec<u8>,
+}
+
+/// ## SSHFP RDATA format (RFC 4255 section 3.1)
+///
+/// ```text
+///                      1 1 1 1 1 1 1 1 1 1 2 2 2 2 2 2 2 2 2 2 3 3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |   algorithm   |    fp type    |                               /
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+                               /
+/// /                                                               /
+/// /                          fingerprint                          /
+/// /                                                               /
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sshfp {
+    /// The algorithm of the host key, e.g. 1 for RSA or 4 for Ed25519.
+    pub algorithm: u8,
+    /// The digest of the fingerprint, 1 for SHA-1 or 2 for SHA-256.
+    pub fingerprint_type: u8,
+    pub fingerprint: Vec<u8>,
+}
+
+/// ## TLSA RDATA format (RFC 6698 section 2.1)
+///
+/// ```text
+///                      1 1 1 1 1 1 1 1 1 1 2 2 2 2 2 2 2 2 2 2 3 3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |  Cert. Usage  |   Selector    | Matching Type |               /
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+               /
+/// /                                                               /
+/// /                 Certificate Association Data                  /
+/// /                                                               /
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tlsa {
+    /// What the data must match: 0 and 1 a CA or the server certificate validated
+    /// with the usual PKIX checks, 2 and 3 a trust anchor or the server
+    /// certificate on their own (DANE-TA and DANE-EE).
+    pub usage: u8,
+    /// 0 to match the full certificate, 1 its SubjectPublicKeyInfo.
+    pub selector: u8,
+    /// 0 when the data is the selected content itself, 1 its SHA-256 and 2 its
+    /// SHA-512.
+    pub matching_type: u8,
+    pub data: Vec<u8>,
+}
+
+/// ## CAA RDATA format (RFC 8659 section 4.1)
+///
+/// ```text
+/// +0-1-2-3-4-5-6-7-|0-1-2-3-4-5-6-7-|
+/// | Flags          | Tag Length = n |
+/// +----------------|----------------+...+---------------+
+/// | Tag char 0     | Tag char 1     |...| Tag char n-1  |
+/// +----------------|----------------+...+---------------+
+/// +----------------|----------------+.....+----------------+
+/// | Value byte 0   | Value byte 1   |.....| Value byte m-1 |
+/// +----------------|----------------+.....+----------------+
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Caa {
+    /// Bit 7 is the Issuer Critical flag: CAs not understanding the tag must not
+    /// issue.
+    pub flags: u8,
+    /// The property, ASCII letters and digits, e.g. `issue`, `issuewild` or
+    /// `iodef`.
+    pub tag: String,
+    /// The value of the property, filling the rest of the RDATA without a
+    /// length octet.
+    pub value: Vec<u8>,
+}
+
+/// ## SVCB and HTTPS RDATA format (RFC 9460 section 2.2)
+///
+/// ```text
+///                      1 1 1 1 1 1 1 1 1 1 2 2 2 2 2 2 2 2 2 2 3 3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |          SvcPriority          |                               /
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+           TargetName          /
+/// /                                                               /
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |          SvcParamKey          |         SvcParamLength        |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// /                         SvcParamValue                         /
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// /                              ...                              /
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Svcb {
+    /// Zero for AliasMode, where the record only points at `target`. Otherwise
+    /// ServiceMode, clients trying the endpoints with the lowest priority first.
+    pub priority: u16,
+    /// The alias or the host of the endpoint. The root stands for the owner name
+    /// in ServiceMode, and for a service that does not exist in AliasMode. Never
+    /// compressed.
+    pub target: Vec<String>,
+    /// The parameters of the endpoint, in increasing key order on the wire.
+    pub params: Vec<SvcParam>,
+}
+
+/// A parameter of an SVCB endpoint (RFC 9460 section 7), with its SvcParamKey.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SvcParam {
+    /// Key 0: the keys a client must understand to use the endpoint, sorted.
+    Mandatory(Vec<u16>),
+    /// Key 1: the ALPN protocol identifiers the endpoint supports.
+    Alpn(Vec<Vec<u8>>),
+    /// Key 2: the default protocol of the scheme is not supported, only `Alpn`.
+    NoDefaultAlpn,
+    /// Key 3: the port to connect to instead of the default one of the scheme.
+    Port(u16),
+    /// Key 4: IPv4 addresses the target may be reached at, until its A records
+    /// are known.
+    Ipv4Hint(Vec<Ipv4Addr>),
+    /// Key 5: an Encrypted ClientHello configuration list.
+    Ech(Vec<u8>),
+    /// Key 6: IPv6 addresses the target may be reached at, until its AAAA
+    /// records are known.
+    Ipv6Hint(Vec<Ipv6Addr>),
+    /// A key without a dedicated representation, with its uninterpreted value.
+    Unknown { key: u16, value: Vec<u8> },
+}
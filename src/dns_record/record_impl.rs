@@ -0,0 +1,2124 @@
+use super::record::{
+    Caa, DnsRecord, Dnskey, Ds, EdnsOption, Naptr, Nsec, Nsec3, Nsec3Param, Opt, RData, Rrsig, Soa,
+    Srv, Sshfp, SvcParam, Svcb, Tlsa, Tsig, Txt, Uri,
+};
+use crate::{
+    dns_message::message::EncodeError,
+    dns_name::name::{name_len, name_to_string, parse_name, write_name},
+    encoding::{base32hex_encode, base64_encode, format_timestamp, hex_encode},
+    resrec::{type_name, QClass, QType},
+};
+use anyhow::ensure;
+use bytes::{Buf, BufMut};
+use std::{
+    fmt,
+    net::{Ipv4Addr, Ipv6Addr},
+};
+
+/// TYPE, CLASS, TTL and RDLENGTH following the owner name
+pub const FIXED_FIELDS_SIZE_IN_BYTES: usize = 2 + 2 + 4 + 2;
+
+/// The DO bit in the TTL field of an OPT record.
+const DNSSEC_OK_MASK: u32 = 0x8000;
+
+/// The presentation names of SvcParamKeys 0 to 6 (RFC 9460 section 14.3.2).
+const SVC_PARAM_KEY_NAMES: [&str; 7] = [
+    "mandatory",
+    "alpn",
+    "no-default-alpn",
+    "port",
+    "ipv4hint",
+    "ech",
+    "ipv6hint",
+];
+
+/// The longest character-string (RFC 1035 section 3.3).
+pub const MAX_CHARACTER_STRING: usize = 255;
+
+/// The longest CAA property tag (RFC 8659 section 4.1).
+const MAX_CAA_TAG_LENGTH: usize = 15;
+
+/// The SvcParamKey reserved as invalid.
+const INVALID_SVC_PARAM_KEY: u16 = 65535;
+
+impl DnsRecord {
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.encoded_len());
+        self.write(&mut bytes);
+        bytes
+    }
+
+    /// The length of the record in wire format.
+    pub fn encoded_len(&self) -> usize {
+        name_len(&self.name) + FIXED_FIELDS_SIZE_IN_BYTES + self.rdata.encoded_len()
+    }
+
+    /// Writes the record to `buf`, failing when it has no room for it or its
+    /// RDATA is too long for the wire. Returns the octets written.
+    pub fn encode_into(&self, buf: &mut impl BufMut) -> Result<usize, EncodeError> {
+        self.check_rdlength()?;
+        let len = self.encoded_len();
+        EncodeError::check(len, buf)?;
+        self.write(buf);
+        Ok(len)
+    }
+
+    /// Fails when the RDATA does not fit in the 16 bits of RDLENGTH.
+    pub fn check_rdlength(&self) -> Result<(), EncodeError> {
+        let len = self.rdata.encoded_len();
+        match u16::try_from(len) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(EncodeError::RdataTooLong(len)),
+        }
+    }
+
+    /// Writes the record to `buf`, which must have room for it and RDATA of at
+    /// most 65535 octets.
+    pub fn write(&self, buf: &mut impl BufMut) {
+        let (class, ttl) = match &self.rdata {
+            RData::OPT(opt) => (opt.udp_payload_size, opt.ttl()),
+            _ => (u16::from(self.class), self.ttl),
+        };
+
+        write_name(&self.name, buf);
+        buf.put_u16(u16::from(self.typ));
+        buf.put_u16(class);
+        buf.put_u32(ttl);
+        buf.put_u16(self.rdata.encoded_len() as u16);
+        self.rdata.write(buf);
+    }
+
+    /// Reads a resource record starting at `offset` in `message`. Returns the record
+    /// and the offset of whatever follows it.
+    pub fn parse(message: &[u8], offset: usize) -> Option<(Self, usize)> {
+        let (name, offset) = parse_name(message, offset)?;
+        let mut fixed = message.get(offset..offset + FIXED_FIELDS_SIZE_IN_BYTES)?;
+        let typ = QType::from(fixed.get_u16());
+        let raw_class = fixed.get_u16();
+        let class = QClass::from(raw_class);
+        let ttl = fixed.get_u32();
+        let rdlength = usize::from(fixed.get_u16());
+
+        let rdata_start = offset + FIXED_FIELDS_SIZE_IN_BYTES;
+        let rdata_end = rdata_start + rdlength;
+        if rdata_end > message.len() {
+            return None;
+        }
+        let rdata = match typ {
+            QType::OPT => RData::OPT(Opt::parse(
+                raw_class,
+                ttl,
+                &message[rdata_start..rdata_end],
+            )?),
+            _ => RData::parse(typ, message, rdata_start, rdata_end)?,
+        };
+
+        let record = DnsRecord {
+            name,
+            typ,
+            class,
+            ttl,
+            rdata,
+        };
+        Some((record, rdata_end))
+    }
+}
+
+impl RData {
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.encoded_len());
+        self.write(&mut bytes);
+        bytes
+    }
+
+    /// The length of the RDATA in wire format, measured without encoding it.
+    pub fn encoded_len(&self) -> usize {
+        match self {
+            RData::A(_) => 4,
+            RData::NS(name) | RData::CNAME(name) | RData::PTR(name) => name_len(name),
+            RData::SOA(soa) => soa.encoded_len(),
+            RData::MX { exchange, .. } => 2 + name_len(exchange),
+            RData::TXT(txt) => txt.encoded_len(),
+            RData::AAAA(_) => 16,
+            RData::SRV(srv) => srv.encoded_len(),
+            RData::NAPTR(naptr) => naptr.encoded_len(),
+            RData::URI(uri) => uri.encoded_len(),
+            RData::OPT(opt) => opt.encoded_len(),
+            RData::DNSKEY(dnskey) => dnskey.encoded_len(),
+            RData::DS(ds) => ds.encoded_len(),
+            RData::RRSIG(rrsig) => rrsig.encoded_len(),
+            RData::NSEC(nsec) => nsec.encoded_len(),
+            RData::NSEC3(nsec3) => nsec3.encoded_len(),
+            RData::NSEC3PARAM(param) => param.encoded_len(),
+            RData::SSHFP(sshfp) => sshfp.encoded_len(),
+            RData::TLSA(tlsa) => tlsa.encoded_len(),
+            RData::OPENPGPKEY(key) => key.len(),
+            RData::CAA(caa) => caa.encoded_len(),
+            RData::SVCB(svcb) | RData::HTTPS(svcb) => svcb.encoded_len(),
+            RData::TSIG(tsig) => tsig.encoded_len(),
+            RData::Raw(raw) => raw.len(),
+        }
+    }
+
+    /// Writes the RDATA to `buf`, which must have room for it.
+    pub fn write(&self, bytes: &mut impl BufMut) {
+        match self {
+            RData::A(addr) => bytes.put_slice(&addr.octets()),
+            RData::NS(name) | RData::CNAME(name) | RData::PTR(name) => write_name(name, bytes),
+            RData::SOA(soa) => soa.write(bytes),
+            RData::MX {
+                preference,
+                exchange,
+            } => {
+                bytes.put_u16(*preference);
+                write_name(exchange, bytes);
+            }
+            RData::TXT(txt) => txt.write(bytes),
+            RData::AAAA(addr) => bytes.put_slice(&addr.octets()),
+            RData::SRV(srv) => srv.write(bytes),
+            RData::NAPTR(naptr) => naptr.write(bytes),
+            RData::URI(uri) => uri.write(bytes),
+            RData::OPT(opt) => opt.write(bytes),
+            RData::DNSKEY(dnskey) => dnskey.write(bytes),
+            RData::DS(ds) => ds.write(bytes),
+            RData::RRSIG(rrsig) => rrsig.write(bytes),
+            RData::NSEC(nsec) => nsec.write(bytes),
+            RData::NSEC3(nsec3) => nsec3.write(bytes),
+            RData::NSEC3PARAM(param) => param.write(bytes),
+            RData::SSHFP(sshfp) => sshfp.write(bytes),
+            RData::TLSA(tlsa) => tlsa.write(bytes),
+            RData::OPENPGPKEY(key) => bytes.put_slice(key),
+            RData::CAA(caa) => caa.write(bytes),
+            RData::SVCB(svcb) | RData::HTTPS(svcb) => svcb.write(bytes),
+            RData::TSIG(tsig) => tsig.write(bytes),
+            RData::Raw(raw) => bytes.put_slice(raw),
+        }
+    }
+
+    /// Whether this is the empty RDATA used by dynamic update prerequisites and
+    /// deletions.
+    pub fn is_empty(&self) -> bool {
+        matches!(self, RData::Raw(raw) if raw.is_empty())
+    }
+
+    /// Interprets `message[start..end]` as the RDATA of a record of type `typ`.
+    /// Names are decoded against the whole message since they may be compressed.
+    /// Empty RDATA is always kept raw, as dynamic updates use it regardless of type.
+    fn parse(typ: QType, message: &[u8], start: usize, end: usize) -> Option<Self> {
+        let raw = &message[start..end];
+        if raw.is_empty() {
+            return Some(RData::Raw(Vec::new()));
+        }
+
+        let rdata = match typ {
+            QType::A => {
+                let octets: [u8; 4] = raw.try_into().ok()?;
+                RData::A(Ipv4Addr::from(octets))
+            }
+            QType::NS => RData::NS(parse_name_within(message, start, end)?.0),
+            QType::CNAME => RData::CNAME(parse_name_within(message, start, end)?.0),
+            QType::PTR => RData::PTR(parse_name_within(message, start, end)?.0),
+            QType::MX => {
+                let mut fixed = message.get(start..start + 2)?;
+                let preference = fixed.get_u16();
+                let (exchange, _) = parse_name_within(message, start + 2, end)?;
+                RData::MX {
+                    preference,
+                    exchange,
+                }
+            }
+            QType::TXT => RData::TXT(Txt {
+                strings: character_strings(raw)?
+                    .into_iter()
+                    .map(<[u8]>::to_vec)
+                    .collect(),
+            }),
+            QType::AAAA => {
+                let octets: [u8; 16] = raw.try_into().ok()?;
+                RData::AAAA(Ipv6Addr::from(octets))
+            }
+            QType::SRV => {
+                let mut fixed = message.get(start..start + 6)?;
+                let priority = fixed.get_u16();
+                let weight = fixed.get_u16();
+                let port = fixed.get_u16();
+                let (target, _) = parse_name_within(message, start + 6, end)?;
+                RData::SRV(Srv {
+                    priority,
+                    weight,
+                    port,
+                    target,
+                })
+            }
+            QType::NAPTR => {
+                let mut fixed = raw;
+                if fixed.len() < 4 {
+                    return None;
+                }
+                let order = fixed.get_u16();
+                let preference = fixed.get_u16();
+                let flags = parse_character_string(&mut fixed)?;
+                let services = parse_character_string(&mut fixed)?;
+                let regexp = parse_character_string(&mut fixed)?;
+                let (replacement, _) = parse_name_within(message, end - fixed.len(), end)?;
+                RData::NAPTR(Naptr {
+                    order,
+                    preference,
+                    flags,
+                    services,
+                    regexp,
+                    replacement,
+                })
+            }
+            QType::URI => {
+                let mut fixed = raw;
+                if fixed.len() < 4 {
+                    return None;
+                }
+                RData::URI(Uri {
+                    priority: fixed.get_u16(),
+                    weight: fixed.get_u16(),
+                    target: fixed.to_vec(),
+                })
+            }
+            QType::SOA => {
+                let (mname, offset) = parse_name_within(message, start, end)?;
+                let (rname, offset) = parse_name_within(message, offset, end)?;
+                let mut fixed = message.get(offset..end)?;
+                if fixed.len() != 5 * 4 {
+                    return None;
+                }
+                RData::SOA(Soa {
+                    mname,
+                    rname,
+                    serial: fixed.get_u32(),
+                    refresh: fixed.get_u32(),
+                    retry: fixed.get_u32(),
+                    expire: fixed.get_u32(),
+                    minimum: fixed.get_u32(),
+                })
+            }
+            QType::TSIG => {
+                let (algorithm, offset) = parse_name_within(message, start, end)?;
+                let mut fixed = message.get(offset..end)?;
+                if fixed.len() < 10 {
+                    return None;
+                }
+                let time_signed = fixed.get_uint(6);
+                let fudge = fixed.get_u16();
+                let mac_size = usize::from(fixed.get_u16());
+                if fixed.len() < mac_size + 6 {
+                    return None;
+                }
+                let mac = fixed.copy_to_bytes(mac_size).to_vec();
+                let original_id = fixed.get_u16();
+                let error = fixed.get_u16();
+                let other_len = usize::from(fixed.get_u16());
+                if fixed.len() != other_len {
+                    return None;
+                }
+                RData::TSIG(Tsig {
+                    algorithm,
+                    time_signed,
+                    fudge,
+                    mac,
+                    original_id,
+                    error,
+                    other: fixed.to_vec(),
+                })
+            }
+            QType::DNSKEY => {
+                let mut fixed = raw;
+                if fixed.len() < 4 {
+                    return None;
+                }
+                RData::DNSKEY(Dnskey {
+                    flags: fixed.get_u16(),
+                    protocol: fixed.get_u8(),
+                    algorithm: fixed.get_u8(),
+                    public_key: fixed.to_vec(),
+                })
+            }
+            QType::DS => {
+                let mut fixed = raw;
+                if fixed.len() < 4 {
+                    return None;
+                }
+                RData::DS(Ds {
+                    key_tag: fixed.get_u16(),
+                    algorithm: fixed.get_u8(),
+                    digest_type: fixed.get_u8(),
+                    digest: fixed.to_vec(),
+                })
+            }
+            QType::RRSIG => {
+                let mut fixed = message.get(start..start + 18)?;
+                let type_covered = fixed.get_u16();
+                let algorithm = fixed.get_u8();
+                let labels = fixed.get_u8();
+                let original_ttl = fixed.get_u32();
+                let expiration = fixed.get_u32();
+                let inception = fixed.get_u32();
+                let key_tag = fixed.get_u16();
+                let (signer, offset) = parse_name_within(message, start + 18, end)?;
+                RData::RRSIG(Rrsig {
+                    type_covered,
+                    algorithm,
+                    labels,
+                    original_ttl,
+                    expiration,
+                    inception,
+                    key_tag,
+                    signer,
+                    signature: message[offset..end].to_vec(),
+                })
+            }
+            QType::NSEC => {
+                let (next, offset) = parse_name_within(message, start, end)?;
+                RData::NSEC(Nsec {
+                    next,
+                    types: parse_type_bitmap(&message[offset..end])?,
+                })
+            }
+            QType::NSEC3 => {
+                let mut fixed = raw;
+                let (hash_algorithm, flags, iterations, salt) = parse_nsec3_params(&mut fixed)?;
+                let hash_len = usize::from(*fixed.first()?);
+                let next_hashed = fixed.get(1..1 + hash_len)?.to_vec();
+                RData::NSEC3(Nsec3 {
+                    hash_algorithm,
+                    flags,
+                    iterations,
+                    salt,
+                    next_hashed,
+                    types: parse_type_bitmap(&fixed[1 + hash_len..])?,
+                })
+            }
+            QType::NSEC3PARAM => {
+                let mut fixed = raw;
+                let (hash_algorithm, flags, iterations, salt) = parse_nsec3_params(&mut fixed)?;
+                if !fixed.is_empty() {
+                    return None;
+                }
+                RData::NSEC3PARAM(Nsec3Param {
+                    hash_algorithm,
+                    flags,
+                    iterations,
+                    salt,
+                })
+            }
+            QType::SSHFP => {
+                let mut fixed = raw;
+                if fixed.len() < 2 {
+                    return None;
+                }
+                RData::SSHFP(Sshfp {
+                    algorithm: fixed.get_u8(),
+                    fingerprint_type: fixed.get_u8(),
+                    fingerprint: fixed.to_vec(),
+                })
+            }
+            QType::TLSA => {
+                let mut fixed = raw;
+                if fixed.len() < 3 {
+                    return None;
+                }
+                RData::TLSA(Tlsa {
+                    usage: fixed.get_u8(),
+                    selector: fixed.get_u8(),
+                    matching_type: fixed.get_u8(),
+                    data: fixed.to_vec(),
+                })
+            }
+            QType::OPENPGPKEY => RData::OPENPGPKEY(raw.to_vec()),
+            QType::CAA => {
+                let mut fixed = raw;
+                if fixed.len() < 2 {
+                    return None;
+                }
+                let flags = fixed.get_u8();
+                let tag = parse_character_string(&mut fixed)?;
+                // Tags are only held to `check` in zone files, those that are not
+                // even text are kept as they came.
+                match String::from_utf8(tag) {
+                    Ok(tag) => RData::CAA(Caa {
+                        flags,
+                        tag,
+                        value: fixed.to_vec(),
+                    }),
+                    Err(_) => RData::Raw(raw.to_vec()),
+                }
+            }
+            QType::SVCB => RData::SVCB(Svcb::parse(message, start, end)?),
+            QType::HTTPS => RData::HTTPS(Svcb::parse(message, start, end)?),
+            _ => RData::Raw(raw.to_vec()),
+        };
+        Some(rdata)
+    }
+}
+
+impl Soa {
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.encoded_len());
+        self.write(&mut bytes);
+        bytes
+    }
+
+    pub fn encoded_len(&self) -> usize {
+        name_len(&self.mname) + name_len(&self.rname) + 20
+    }
+
+    pub fn write(&self, bytes: &mut impl BufMut) {
+        write_name(&self.mname, bytes);
+        write_name(&self.rname, bytes);
+        bytes.put_u32(self.serial);
+        bytes.put_u32(self.refresh);
+        bytes.put_u32(self.retry);
+        bytes.put_u32(self.expire);
+        bytes.put_u32(self.minimum);
+    }
+}
+
+impl Txt {
+    /// Text as character-strings, split every 255 octets.
+    pub fn new(text: &[u8]) -> Self {
+        let strings = match text.is_empty() {
+            true => vec![Vec::new()],
+            false => text
+                .chunks(MAX_CHARACTER_STRING)
+                .map(<[u8]>::to_vec)
+                .collect(),
+        };
+        Txt { strings }
+    }
+
+    /// The character-strings concatenated, as SPF (RFC 7208 section 3.3) and
+    /// DKIM (RFC 6376 section 3.6.2.2) read them.
+    pub fn joined(&self) -> Vec<u8> {
+        self.strings.concat()
+    }
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.encoded_len());
+        self.write(&mut bytes);
+        bytes
+    }
+
+    pub fn encoded_len(&self) -> usize {
+        self.strings.iter().map(|x| 1 + x.len()).sum()
+    }
+
+    pub fn write(&self, bytes: &mut impl BufMut) {
+        for string in &self.strings {
+            bytes.put_u8(string.len() as u8);
+            bytes.put_slice(string);
+        }
+    }
+}
+
+impl Srv {
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.encoded_len());
+        self.write(&mut bytes);
+        bytes
+    }
+
+    pub fn encoded_len(&self) -> usize {
+        6 + name_len(&self.target)
+    }
+
+    pub fn write(&self, bytes: &mut impl BufMut) {
+        bytes.put_u16(self.priority);
+        bytes.put_u16(self.weight);
+        bytes.put_u16(self.port);
+        write_name(&self.target, bytes);
+    }
+}
+
+impl Naptr {
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.encoded_len());
+        self.write(&mut bytes);
+        bytes
+    }
+
+    pub fn encoded_len(&self) -> usize {
+        let strings = [&self.flags, &self.services, &self.regexp];
+        4 + strings.iter().map(|x| 1 + x.len()).sum::<usize>() + name_len(&self.replacement)
+    }
+
+    pub fn write(&self, bytes: &mut impl BufMut) {
+        bytes.put_u16(self.order);
+        bytes.put_u16(self.preference);
+        for string in [&self.flags, &self.services, &self.regexp] {
+            bytes.put_u8(string.len() as u8);
+            bytes.put_slice(string);
+        }
+        write_name(&self.replacement, bytes);
+    }
+}
+
+impl Uri {
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.encoded_len());
+        self.write(&mut bytes);
+        bytes
+    }
+
+    pub fn encoded_len(&self) -> usize {
+        4 + self.target.len()
+    }
+
+    pub fn write(&self, bytes: &mut impl BufMut) {
+        bytes.put_u16(self.priority);
+        bytes.put_u16(self.weight);
+        bytes.put_slice(&self.target);
+    }
+}
+
+impl Tsig {
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.encoded_len());
+        self.write(&mut bytes);
+        bytes
+    }
+
+    pub fn encoded_len(&self) -> usize {
+        name_len(&self.algorithm) + 16 + self.mac.len() + self.other.len()
+    }
+
+    pub fn write(&self, bytes: &mut impl BufMut) {
+        write_name(&self.algorithm, bytes);
+        bytes.put_uint(self.time_signed, 6);
+        bytes.put_u16(self.fudge);
+        bytes.put_u16(self.mac.len() as u16);
+        bytes.put_slice(&self.mac);
+        bytes.put_u16(self.original_id);
+        bytes.put_u16(self.error);
+        bytes.put_u16(self.other.len() as u16);
+        bytes.put_slice(&self.other);
+    }
+}
+
+impl Opt {
+    /// EDNS(0) with the payload size we accept and no options.
+    pub fn new(udp_payload_size: u16, dnssec_ok: bool) -> Self {
+        Opt {
+            udp_payload_size,
+            extended_rcode: 0,
+            version: 0,
+            dnssec_ok,
+            options: Vec::new(),
+        }
+    }
+
+    /// The options, the CLASS and TTL fields being written by [`DnsRecord::write`].
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.encoded_len());
+        self.write(&mut bytes);
+        bytes
+    }
+
+    pub fn encoded_len(&self) -> usize {
+        self.options.iter().map(|x| 4 + x.data.len()).sum()
+    }
+
+    pub fn write(&self, bytes: &mut impl BufMut) {
+        for option in &self.options {
+            bytes.put_u16(option.code);
+            bytes.put_u16(option.data.len() as u16);
+            bytes.put_slice(&option.data);
+        }
+    }
+
+    /// The TTL field carrying the extended RCODE, version and flags.
+    pub fn ttl(&self) -> u32 {
+        let flags = if self.dnssec_ok { DNSSEC_OK_MASK } else { 0 };
+        (u32::from(self.extended_rcode) << 24) | (u32::from(self.version) << 16) | flags
+    }
+
+    /// Reads the EDNS parameters from the CLASS, TTL and RDATA of an OPT record.
+    pub fn parse(class: u16, ttl: u32, mut rdata: &[u8]) -> Option<Self> {
+        let mut options = Vec::new();
+        while !rdata.is_empty() {
+            if rdata.len() < 4 {
+                return None;
+            }
+            let code = rdata.get_u16();
+            let len = usize::from(rdata.get_u16());
+            if rdata.len() < len {
+                return None;
+            }
+            let data = rdata.copy_to_bytes(len).to_vec();
+            options.push(EdnsOption { code, data });
+        }
+        Some(Opt {
+            udp_payload_size: class,
+            extended_rcode: (ttl >> 24) as u8,
+            version: (ttl >> 16) as u8,
+            dnssec_ok: ttl & DNSSEC_OK_MASK != 0,
+            options,
+        })
+    }
+}
+
+impl Dnskey {
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.encoded_len());
+        self.write(&mut bytes);
+        bytes
+    }
+
+    pub fn encoded_len(&self) -> usize {
+        4 + self.public_key.len()
+    }
+
+    pub fn write(&self, bytes: &mut impl BufMut) {
+        bytes.put_u16(self.flags);
+        bytes.put_u8(self.protocol);
+        bytes.put_u8(self.algorithm);
+        bytes.put_slice(&self.public_key);
+    }
+}
+
+impl Ds {
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.encoded_len());
+        self.write(&mut bytes);
+        bytes
+    }
+
+    pub fn encoded_len(&self) -> usize {
+        4 + self.digest.len()
+    }
+
+    pub fn write(&self, bytes: &mut impl BufMut) {
+        bytes.put_u16(self.key_tag);
+        bytes.put_u8(self.algorithm);
+        bytes.put_u8(self.digest_type);
+        bytes.put_slice(&self.digest);
+    }
+}
+
+impl Sshfp {
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.encoded_len());
+        self.write(&mut bytes);
+        bytes
+    }
+
+    pub fn encoded_len(&self) -> usize {
+        2 + self.fingerprint.len()
+    }
+
+    pub fn write(&self, bytes: &mut impl BufMut) {
+        bytes.put_u8(self.algorithm);
+        bytes.put_u8(self.fingerprint_type);
+        bytes.put_slice(&self.fingerprint);
+    }
+
+    /// Checks the fingerprint has the length of its digest.
+    pub fn check(&self) -> anyhow::Result<()> {
+        ensure!(!self.fingerprint.is_empty(), "empty fingerprint");
+        if let Some(len) = digest_length(&[(1, 20), (2, 32)], self.fingerprint_type) {
+            ensure!(
+                self.fingerprint.len() == len,
+                "fingerprint of type {} is {} octets, not {}",
+                self.fingerprint_type,
+                self.fingerprint.len(),
+                len
+            );
+        }
+        Ok(())
+    }
+}
+
+impl Tlsa {
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.encoded_len());
+        self.write(&mut bytes);
+        bytes
+    }
+
+    pub fn encoded_len(&self) -> usize {
+        3 + self.data.len()
+    }
+
+    pub fn write(&self, bytes: &mut impl BufMut) {
+        bytes.put_u8(self.usage);
+        bytes.put_u8(self.selector);
+        bytes.put_u8(self.matching_type);
+        bytes.put_slice(&self.data);
+    }
+
+    /// Checks the association data has the length of its digest.
+    pub fn check(&self) -> anyhow::Result<()> {
+        ensure!(!self.data.is_empty(), "empty certificate association data");
+        if let Some(len) = digest_length(&[(1, 32), (2, 64)], self.matching_type) {
+            ensure!(
+                self.data.len() == len,
+                "data of matching type {} is {} octets, not {}",
+                self.matching_type,
+                self.data.len(),
+                len
+            );
+        }
+        Ok(())
+    }
+}
+
+/// The length of the digests of type `typ`, among `(type, length)` pairs.
+fn digest_length(lengths: &[(u8, usize)], typ: u8) -> Option<usize> {
+    lengths.iter().find(|x| x.0 == typ).map(|x| x.1)
+}
+
+impl Caa {
+    /// The Issuer Critical flag.
+    pub const CRITICAL_FLAG: u8 = 0x80;
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.encoded_len());
+        self.write(&mut bytes);
+        bytes
+    }
+
+    pub fn encoded_len(&self) -> usize {
+        2 + self.tag.len() + self.value.len()
+    }
+
+    pub fn write(&self, bytes: &mut impl BufMut) {
+        bytes.put_u8(self.flags);
+        bytes.put_u8(self.tag.len() as u8);
+        bytes.put_slice(self.tag.as_bytes());
+        bytes.put_slice(&self.value);
+    }
+
+    /// Checks the tag is 1 to 15 ASCII letters and digits.
+    pub fn check(&self) -> anyhow::Result<()> {
+        ensure!(
+            (1..=MAX_CAA_TAG_LENGTH).contains(&self.tag.len()),
+            "CAA tag of {} characters, not 1 to {}",
+            self.tag.len(),
+            MAX_CAA_TAG_LENGTH
+        );
+        ensure!(
+            self.tag.bytes().all(|x| x.is_ascii_alphanumeric()),
+            "CAA tag {} is not only letters and digits",
+            self.tag
+        );
+        Ok(())
+    }
+
+    pub fn is_critical(&self) -> bool {
+        self.flags & Caa::CRITICAL_FLAG != 0
+    }
+}
+
+impl Svcb {
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.encoded_len());
+        self.write(&mut bytes);
+        bytes
+    }
+
+    pub fn encoded_len(&self) -> usize {
+        let params = self.params.iter().map(|x| 4 + x.value_len());
+        2 + name_len(&self.target) + params.sum::<usize>()
+    }
+
+    /// Writes the RDATA, with the parameters sorted by key.
+    pub fn write(&self, bytes: &mut impl BufMut) {
+        bytes.put_u16(self.priority);
+        write_name(&self.target, bytes);
+        for param in self.sorted_params() {
+            bytes.put_u16(param.key());
+            bytes.put_u16(param.value_len() as u16);
+            param.write_value(bytes);
+        }
+    }
+
+    /// Whether this is an AliasMode record, only pointing at the records of its
+    /// target.
+    pub fn is_alias(&self) -> bool {
+        self.priority == 0
+    }
+
+    /// Checks the parameters are self-consistent (RFC 9460 sections 7 and 8): no
+    /// key twice, every mandatory key present, `no-default-alpn` only along
+    /// `alpn` and no empty list.
+    pub fn check(&self) -> anyhow::Result<()> {
+        let keys = self
+            .sorted_params()
+            .iter()
+            .map(|x| x.key())
+            .collect::<Vec<_>>();
+        ensure!(
+            keys.windows(2).all(|x| x[0] != x[1]),
+            "repeated SvcParamKey"
+        );
+        for param in &self.params {
+            match param {
+                SvcParam::Mandatory(mandatory) => {
+                    ensure!(!mandatory.is_empty(), "mandatory lists no key");
+                    ensure!(
+                        mandatory.windows(2).all(|x| x[0] < x[1]),
+                        "mandatory keys are repeated or out of order"
+                    );
+                    for &key in mandatory {
+                        ensure!(key != SvcParam::MANDATORY, "mandatory lists itself");
+                        ensure!(
+                            keys.contains(&key),
+                            "mandatory key {} is missing",
+                            svc_param_key_name(key)
+                        );
+                    }
+                }
+                SvcParam::Alpn(ids) => ensure!(
+                    !ids.is_empty() && ids.iter().all(|x| !x.is_empty()),
+                    "empty alpn"
+                ),
+                SvcParam::NoDefaultAlpn => ensure!(
+                    keys.contains(&SvcParam::ALPN),
+                    "no-default-alpn without alpn"
+                ),
+                SvcParam::Ipv4Hint(addrs) => ensure!(!addrs.is_empty(), "empty ipv4hint"),
+                SvcParam::Ipv6Hint(addrs) => ensure!(!addrs.is_empty(), "empty ipv6hint"),
+                SvcParam::Unknown { key, .. } => {
+                    ensure!(*key != INVALID_SVC_PARAM_KEY, "invalid SvcParamKey {}", key)
+                }
+                SvcParam::Port(_) | SvcParam::Ech(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn sorted_params(&self) -> Vec<&SvcParam> {
+        let mut params = self.params.iter().collect::<Vec<_>>();
+        params.sort_by_key(|x| x.key());
+        params
+    }
+
+    /// Reads SVCB RDATA, `None` when malformed: keys must appear in increasing
+    /// order (RFC 9460 section 2.2) and pass [`Svcb::check`].
+    fn parse(message: &[u8], start: usize, end: usize) -> Option<Self> {
+        let mut fixed = message.get(start..start + 2)?;
+        let priority = fixed.get_u16();
+        let (target, offset) = parse_name_within(message, start + 2, end)?;
+        let mut rest = &message[offset..end];
+        let mut params: Vec<SvcParam> = Vec::new();
+        while !rest.is_empty() {
+            if rest.len() < 4 {
+                return None;
+            }
+            let key = rest.get_u16();
+            let len = usize::from(rest.get_u16());
+            if params.last().is_some_and(|x| x.key() >= key) {
+                return None;
+            }
+            params.push(SvcParam::parse(key, rest.get(..len)?)?);
+            rest = &rest[len..];
+        }
+        // Inconsistent parameters are accepted as they came: only zone files are
+        // held to `check`.
+        Some(Svcb {
+            priority,
+            target,
+            params,
+        })
+    }
+}
+
+impl SvcParam {
+    pub const MANDATORY: u16 = 0;
+    pub const ALPN: u16 = 1;
+
+    pub fn key(&self) -> u16 {
+        match self {
+            SvcParam::Mandatory(_) => SvcParam::MANDATORY,
+            SvcParam::Alpn(_) => SvcParam::ALPN,
+            SvcParam::NoDefaultAlpn => 2,
+            SvcParam::Port(_) => 3,
+            SvcParam::Ipv4Hint(_) => 4,
+            SvcParam::Ech(_) => 5,
+            SvcParam::Ipv6Hint(_) => 6,
+            SvcParam::Unknown { key, .. } => *key,
+        }
+    }
+
+    /// The length of the SvcParamValue.
+    pub fn value_len(&self) -> usize {
+        match self {
+            SvcParam::Mandatory(keys) => 2 * keys.len(),
+            SvcParam::Alpn(ids) => ids.iter().map(|x| 1 + x.len()).sum(),
+            SvcParam::NoDefaultAlpn => 0,
+            SvcParam::Port(_) => 2,
+            SvcParam::Ipv4Hint(addrs) => 4 * addrs.len(),
+            SvcParam::Ech(config) => config.len(),
+            SvcParam::Ipv6Hint(addrs) => 16 * addrs.len(),
+            SvcParam::Unknown { value, .. } => value.len(),
+        }
+    }
+
+    /// Writes the SvcParamValue, without the key and length preceding it.
+    pub fn write_value(&self, bytes: &mut impl BufMut) {
+        match self {
+            SvcParam::Mandatory(keys) => keys.iter().for_each(|&x| bytes.put_u16(x)),
+            SvcParam::Alpn(ids) => {
+                for id in ids {
+                    bytes.put_u8(id.len() as u8);
+                    bytes.put_slice(id);
+                }
+            }
+            SvcParam::NoDefaultAlpn => {}
+            SvcParam::Port(port) => bytes.put_u16(*port),
+            SvcParam::Ipv4Hint(addrs) => addrs.iter().for_each(|x| bytes.put_slice(&x.octets())),
+            SvcParam::Ech(config) => bytes.put_slice(config),
+            SvcParam::Ipv6Hint(addrs) => addrs.iter().for_each(|x| bytes.put_slice(&x.octets())),
+            SvcParam::Unknown { value, .. } => bytes.put_slice(value),
+        }
+    }
+
+    /// Interprets the value of a parameter with SvcParamKey `key`, `None` when it
+    /// does not have the format of the key.
+    pub fn parse(key: u16, value: &[u8]) -> Option<Self> {
+        let param = match key {
+            0 if value.chunks_exact(2).remainder().is_empty() => SvcParam::Mandatory(
+                value
+                    .chunks(2)
+                    .map(|x| u16::from_be_bytes([x[0], x[1]]))
+                    .collect(),
+            ),
+            1 => {
+                let mut rest = value;
+                let mut ids = Vec::new();
+                while !rest.is_empty() {
+                    ids.push(parse_character_string(&mut rest)?);
+                }
+                SvcParam::Alpn(ids)
+            }
+            2 if value.is_empty() => SvcParam::NoDefaultAlpn,
+            3 => SvcParam::Port(u16::from_be_bytes(value.try_into().ok()?)),
+            4 if value.chunks_exact(4).remainder().is_empty() => SvcParam::Ipv4Hint(
+                value
+                    .chunks(4)
+                    .map(|x| Ipv4Addr::new(x[0], x[1], x[2], x[3]))
+                    .collect(),
+            ),
+            5 => SvcParam::Ech(value.to_vec()),
+            6 if value.chunks_exact(16).remainder().is_empty() => SvcParam::Ipv6Hint(
+                value
+                    .chunks(16)
+                    .map(|x| <[u8; 16]>::try_from(x).ok().map(Ipv6Addr::from))
+                    .collect::<Option<_>>()?,
+            ),
+            0..=6 => return None,
+            key => SvcParam::Unknown {
+                key,
+                value: value.to_vec(),
+            },
+        };
+        Some(param)
+    }
+}
+
+/// The presentation name of a SvcParamKey, `key<n>` for keys without one.
+pub fn svc_param_key_name(key: u16) -> String {
+    match SVC_PARAM_KEY_NAMES.get(usize::from(key)) {
+        Some(name) => name.to_string(),
+        None => format!("key{}", key),
+    }
+}
+
+/// The SvcParamKey of a presentation name or of `key<n>`.
+pub fn svc_param_key(name: &str) -> Option<u16> {
+    match SVC_PARAM_KEY_NAMES.iter().position(|&x| x == name) {
+        Some(key) => Some(key as u16),
+        None => name.strip_prefix("key")?.parse().ok(),
+    }
+}
+
+impl Rrsig {
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.encoded_len());
+        self.write(&mut bytes);
+        bytes
+    }
+
+    pub fn encoded_len(&self) -> usize {
+        18 + name_len(&self.signer) + self.signature.len()
+    }
+
+    pub fn write(&self, bytes: &mut impl BufMut) {
+        bytes.put_u16(self.type_covered);
+        bytes.put_u8(self.algorithm);
+        bytes.put_u8(self.labels);
+        bytes.put_u32(self.original_ttl);
+        bytes.put_u32(self.expiration);
+        bytes.put_u32(self.inception);
+        bytes.put_u16(self.key_tag);
+        write_name(&self.signer, bytes);
+        bytes.put_slice(&self.signature);
+    }
+}
+
+impl Nsec {
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.encoded_len());
+        self.write(&mut bytes);
+        bytes
+    }
+
+    pub fn encoded_len(&self) -> usize {
+        name_len(&self.next) + type_bitmap_len(&self.types)
+    }
+
+    pub fn write(&self, bytes: &mut impl BufMut) {
+        write_name(&self.next, bytes);
+        write_type_bitmap(&self.types, bytes);
+    }
+}
+
+impl Nsec3 {
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.encoded_len());
+        self.write(&mut bytes);
+        bytes
+    }
+
+    pub fn encoded_len(&self) -> usize {
+        6 + self.salt.len() + self.next_hashed.len() + type_bitmap_len(&self.types)
+    }
+
+    pub fn write(&self, bytes: &mut impl BufMut) {
+        bytes.put_u8(self.hash_algorithm);
+        bytes.put_u8(self.flags);
+        bytes.put_u16(self.iterations);
+        bytes.put_u8(self.salt.len() as u8);
+        bytes.put_slice(&self.salt);
+        bytes.put_u8(self.next_hashed.len() as u8);
+        bytes.put_slice(&self.next_hashed);
+        write_type_bitmap(&self.types, bytes);
+    }
+}
+
+impl Nsec3Param {
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.encoded_len());
+        self.write(&mut bytes);
+        bytes
+    }
+
+    pub fn encoded_len(&self) -> usize {
+        5 + self.salt.len()
+    }
+
+    pub fn write(&self, bytes: &mut impl BufMut) {
+        bytes.put_u8(self.hash_algorithm);
+        bytes.put_u8(self.flags);
+        bytes.put_u16(self.iterations);
+        bytes.put_u8(self.salt.len() as u8);
+        bytes.put_slice(&self.salt);
+    }
+}
+
+/// `www.example.com. 3600 IN A 192.0.2.1`, a line of a master file (RFC 1035
+/// section 5.1) with the owner name absolute.
+impl fmt::Display for DnsRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {} {}",
+            name_to_string(&self.name),
+            self.ttl,
+            self.class,
+            self.typ,
+            self.rdata
+        )
+    }
+}
+
+/// The RDATA of a master file line. Types without a presentation format of
+/// their own use the generic `\# <length> <hex>` one (RFC 3597 section 5).
+impl fmt::Display for RData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RData::A(addr) => write!(f, "{}", addr),
+            RData::NS(name) | RData::CNAME(name) | RData::PTR(name) => {
+                write!(f, "{}", name_to_string(name))
+            }
+            RData::MX {
+                preference,
+                exchange,
+            } => write!(f, "{} {}", preference, name_to_string(exchange)),
+            RData::TXT(x) => write!(f, "{}", x),
+            RData::AAAA(addr) => write!(f, "{}", addr),
+            RData::SRV(x) => write!(f, "{}", x),
+            RData::NAPTR(x) => write!(f, "{}", x),
+            RData::URI(x) => write!(f, "{}", x),
+            RData::SOA(soa) => write!(
+                f,
+                "{} {} {} {} {} {} {}",
+                name_to_string(&soa.mname),
+                name_to_string(&soa.rname),
+                soa.serial,
+                soa.refresh,
+                soa.retry,
+                soa.expire,
+                soa.minimum
+            ),
+            RData::DNSKEY(x) => write!(f, "{}", x),
+            RData::DS(x) => write!(f, "{}", x),
+            RData::RRSIG(x) => write!(f, "{}", x),
+            RData::NSEC(x) => write!(f, "{}", x),
+            RData::NSEC3(x) => write!(f, "{}", x),
+            RData::NSEC3PARAM(x) => write!(f, "{}", x),
+            RData::SSHFP(x) => write!(f, "{}", x),
+            RData::TLSA(x) => write!(f, "{}", x),
+            RData::OPENPGPKEY(key) => write!(f, "{}", base64_encode(key)),
+            RData::CAA(x) => write!(f, "{}", x),
+            RData::SVCB(x) | RData::HTTPS(x) => write!(f, "{}", x),
+            RData::OPT(_) | RData::TSIG(_) | RData::Raw(_) => {
+                let bytes = self.as_bytes();
+                match bytes.is_empty() {
+                    true => write!(f, "\\# 0"),
+                    false => write!(f, "\\# {} {}", bytes.len(), hex_encode(&bytes)),
+                }
+            }
+        }
+    }
+}
+
+/// Splits RDATA into the length-prefixed character-strings of RFC 1035 section
+/// 3.3, or `None` when it does not consist of them exactly.
+fn character_strings(mut raw: &[u8]) -> Option<Vec<&[u8]>> {
+    let mut strings = Vec::new();
+    while let Some((&len, rest)) = raw.split_first() {
+        let len = usize::from(len);
+        strings.push(rest.get(..len)?);
+        raw = &rest[len..];
+    }
+    Some(strings)
+}
+
+/// A character-string between double quotes, escaping quotes, backslashes and
+/// unprintable octets.
+fn quote(bytes: &[u8]) -> String {
+    let mut text = String::from("\"");
+    for &byte in bytes {
+        match byte {
+            b'"' | b'\\' => {
+                text.push('\\');
+                text.push(byte as char);
+            }
+            0x20..=0x7e => text.push(byte as char),
+            _ => text.push_str(&format!("\\{:03}", byte)),
+        }
+    }
+    text.push('"');
+    text
+}
+
+/// `"v=DKIM1; k=rsa; p=MIIBIjANBgkqhkiG9w0BAQEFAAOCAQ8AMIIBCgKCAQEA..." "...IDAQAB"`
+impl fmt::Display for Txt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let quoted = self.strings.iter().map(|x| quote(x)).collect::<Vec<_>>();
+        write!(f, "{}", quoted.join(" "))
+    }
+}
+
+/// `10 60 5060 bigbox.example.com.`
+impl fmt::Display for Srv {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {}",
+            self.priority,
+            self.weight,
+            self.port,
+            name_to_string(&self.target)
+        )
+    }
+}
+
+/// `100 10 "S" "SIP+D2U" "" _sip._udp.example.com.`
+impl fmt::Display for Naptr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {} {} {}",
+            self.order,
+            self.preference,
+            quote(&self.flags),
+            quote(&self.services),
+            quote(&self.regexp),
+            name_to_string(&self.replacement)
+        )
+    }
+}
+
+/// `10 1 "ftp://ftp1.example.com/public"`
+impl fmt::Display for Uri {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {}",
+            self.priority,
+            self.weight,
+            quote(&self.target)
+        )
+    }
+}
+
+/// `257 3 13 mdsswUyr3DPW132mOi8V9xESWE8jTo0dxCjjnopKl+GqJxpVXckHAeF+KkxLbxILfDLUT0rAK9iUzy1L53eKGQ==`
+impl fmt::Display for Dnskey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {}",
+            self.flags,
+            self.protocol,
+            self.algorithm,
+            base64_encode(&self.public_key)
+        )
+    }
+}
+
+/// `60485 5 1 2BB183AF5F22588179A53B0A98631FAD1A292118`
+impl fmt::Display for Ds {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {}",
+            self.key_tag,
+            self.algorithm,
+            self.digest_type,
+            hex_encode(&self.digest)
+        )
+    }
+}
+
+/// `A 13 3 3600 20240101000000 20231201000000 2642 example.com. oJB1W6WNGv+ldvQ3WDG0MQkg5IEhjRip8WTrPYGv07h108dUKGMeDPKijVCHX3DDKdfb+v6oB9wfuh3DTJXUAfI/M0zmO/zz8bW0Rznl8O3tGNazPwQKkRN20XPXV6nwwfoXmJQbsLNrLfkGJ5D6fwFm8nN+6pBzeDQfsS3Ap3o=`
+impl fmt::Display for Rrsig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {} {} {} {} {} {}",
+            type_name(self.type_covered),
+            self.algorithm,
+            self.labels,
+            self.original_ttl,
+            format_timestamp(self.expiration),
+            format_timestamp(self.inception),
+            self.key_tag,
+            name_to_string(&self.signer),
+            base64_encode(&self.signature)
+        )
+    }
+}
+
+/// `host.example.com. A MX RRSIG NSEC TYPE1234`
+impl fmt::Display for Nsec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", name_to_string(&self.next))?;
+        write_types(f, &self.types)
+    }
+}
+
+/// `1 1 12 AABBCCDD 2T7B4G4VSA5SMI47K61MV5BV1A22BOJR A RRSIG`
+impl fmt::Display for Nsec3 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {} {}",
+            self.hash_algorithm,
+            self.flags,
+            self.iterations,
+            salt_to_string(&self.salt),
+            base32hex_encode(&self.next_hashed)
+        )?;
+        write_types(f, &self.types)
+    }
+}
+
+/// `1 0 12 AABBCCDD`
+impl fmt::Display for Nsec3Param {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {}",
+            self.hash_algorithm,
+            self.flags,
+            self.iterations,
+            salt_to_string(&self.salt)
+        )
+    }
+}
+
+/// `2 1 123456789ABCDEF67890123456789ABCDEF67890`
+impl fmt::Display for Sshfp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {}",
+            self.algorithm,
+            self.fingerprint_type,
+            hex_encode(&self.fingerprint)
+        )
+    }
+}
+
+/// `3 1 1 0C72AC70B745AC19998811B131D662C9AC69DBDBE7CB23E5B514B56664C5D3D6`
+impl fmt::Display for Tlsa {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {}",
+            self.usage,
+            self.selector,
+            self.matching_type,
+            hex_encode(&self.data)
+        )
+    }
+}
+
+/// `0 issue "ca.example.net; account=230123"`
+impl fmt::Display for Caa {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}", self.flags, self.tag, quote(&self.value))
+    }
+}
+
+/// `1 . alpn=h2,h3 port=8443 ipv4hint=192.0.2.1`, the parameters sorted by key.
+impl fmt::Display for Svcb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.priority, name_to_string(&self.target))?;
+        self.sorted_params()
+            .iter()
+            .try_for_each(|x| write!(f, " {}", x))
+    }
+}
+
+/// `key=value` in the syntax of RFC 9460 appendix A, lists separated by commas,
+/// or the bare key when there is no value.
+impl fmt::Display for SvcParam {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = svc_param_key_name(self.key());
+        let list = |items: Vec<String>| items.join(",");
+        let value = match self {
+            SvcParam::NoDefaultAlpn => return write!(f, "{}", name),
+            SvcParam::Unknown { value, .. } if value.is_empty() => return write!(f, "{}", name),
+            SvcParam::Mandatory(keys) => {
+                list(keys.iter().map(|&x| svc_param_key_name(x)).collect())
+            }
+            SvcParam::Alpn(ids) => list(ids.iter().map(|x| escape_value(x)).collect()),
+            SvcParam::Port(port) => port.to_string(),
+            SvcParam::Ipv4Hint(addrs) => list(addrs.iter().map(|x| x.to_string()).collect()),
+            SvcParam::Ech(config) => base64_encode(config),
+            SvcParam::Ipv6Hint(addrs) => list(addrs.iter().map(|x| x.to_string()).collect()),
+            SvcParam::Unknown { value, .. } => escape_value(value),
+        };
+        write!(f, "{}={}", name, value)
+    }
+}
+
+/// An SvcParamValue item as a single master file field: commas, quotes,
+/// backslashes and the characters ending fields are escaped with a backslash,
+/// unprintable octets as `\DDD`.
+fn escape_value(bytes: &[u8]) -> String {
+    let mut text = String::new();
+    for &byte in bytes {
+        match byte {
+            b',' | b'"' | b'\\' | b';' | b'(' | b')' => {
+                text.push('\\');
+                text.push(byte as char);
+            }
+            0x21..=0x7e => text.push(byte as char),
+            _ => text.push_str(&format!("\\{:03}", byte)),
+        }
+    }
+    text
+}
+
+/// An empty salt is presented as `-`.
+fn salt_to_string(salt: &[u8]) -> String {
+    if salt.is_empty() {
+        "-".to_string()
+    } else {
+        hex_encode(salt)
+    }
+}
+
+fn write_types(f: &mut fmt::Formatter<'_>, types: &[u16]) -> fmt::Result {
+    types
+        .iter()
+        .try_for_each(|&x| write!(f, " {}", type_name(x)))
+}
+
+/// Reads a length-prefixed character-string (RFC 1035 section 3.3), advancing
+/// `rdata` past it.
+fn parse_character_string(rdata: &mut &[u8]) -> Option<Vec<u8>> {
+    let (&len, rest) = rdata.split_first()?;
+    let string = rest.get(..usize::from(len))?.to_vec();
+    *rdata = &rest[usize::from(len)..];
+    Some(string)
+}
+
+/// Reads the hash algorithm, flags, iterations and salt shared by the start of
+/// NSEC3 and NSEC3PARAM RDATA, advancing `rdata` past them.
+fn parse_nsec3_params(rdata: &mut &[u8]) -> Option<(u8, u8, u16, Vec<u8>)> {
+    if rdata.len() < 5 {
+        return None;
+    }
+    let hash_algorithm = rdata.get_u8();
+    let flags = rdata.get_u8();
+    let iterations = rdata.get_u16();
+    let salt_len = usize::from(rdata.get_u8());
+    if rdata.len() < salt_len {
+        return None;
+    }
+    let salt = rdata.copy_to_bytes(salt_len).to_vec();
+    Some((hash_algorithm, flags, iterations, salt))
+}
+
+/// Writes type codes as the windowed bitmaps of NSEC and NSEC3 (RFC 4034
+/// section 4.1.2): for each block of 256 types in use, the block number, the
+/// length of its bitmap and the bitmap without trailing zero octets.
+fn write_type_bitmap(types: &[u16], bytes: &mut impl BufMut) {
+    let mut windows = [[0u8; 32]; 256];
+    for &typ in types {
+        let [window, low] = typ.to_be_bytes();
+        windows[usize::from(window)][usize::from(low / 8)] |= 0x80 >> (low % 8);
+    }
+
+    for (window, bitmap) in windows.iter().enumerate() {
+        let Some(len) = bitmap.iter().rposition(|&x| x != 0).map(|x| x + 1) else {
+            continue;
+        };
+        bytes.put_u8(window as u8);
+        bytes.put_u8(len as u8);
+        bytes.put_slice(&bitmap[..len]);
+    }
+}
+
+/// The length of the bitmaps [`write_type_bitmap`] writes for `types`.
+fn type_bitmap_len(types: &[u16]) -> usize {
+    let mut lens = [0; 256];
+    for &typ in types {
+        let [window, low] = typ.to_be_bytes();
+        let len = &mut lens[usize::from(window)];
+        *len = (*len).max(usize::from(low / 8) + 1);
+    }
+    lens.iter().filter(|&&x| x > 0).map(|x| 2 + x).sum()
+}
+
+/// Decodes the type bitmaps of NSEC and NSEC3 into sorted type codes, rejecting
+/// windows out of order or with an invalid length.
+fn parse_type_bitmap(mut bytes: &[u8]) -> Option<Vec<u16>> {
+    let mut types = Vec::new();
+    let mut previous_window = None;
+    while !bytes.is_empty() {
+        if bytes.len() < 2 {
+            return None;
+        }
+        let window = bytes.get_u8();
+        let len = usize::from(bytes.get_u8());
+        if !(1..=32).contains(&len) || bytes.len() < len || previous_window >= Some(window) {
+            return None;
+        }
+        previous_window = Some(window);
+
+        for (i, &octet) in bytes[..len].iter().enumerate() {
+            let base = (u16::from(window) << 8) | (i as u16 * 8);
+            types.extend(
+                (0..8)
+                    .filter(|bit| octet & (0x80 >> bit) != 0)
+                    .map(|bit| base + bit),
+            );
+        }
+        bytes.advance(len);
+    }
+    Some(types)
+}
+
+/// Reads a name embedded in RDATA, rejecting it if its uncompressed part runs past
+/// the end of the RDATA.
+fn parse_name_within(message: &[u8], offset: usize, end: usize) -> Option<(Vec<String>, usize)> {
+    let (name, next) = parse_name(message, offset)?;
+    (next <= end).then_some((name, next))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_type_bitmap, type_bitmap_len, write_type_bitmap};
+    use crate::{
+        dns_message::message::EncodeError,
+        dns_record::record::{
+            Caa, DnsRecord, Dnskey, Ds, Naptr, Nsec, Nsec3, Nsec3Param, RData, Rrsig, Soa, Srv,
+            Sshfp, SvcParam, Svcb, Tlsa, Tsig, Txt, Uri,
+        },
+        resrec::{QClass, QType},
+    };
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    fn name(labels: &[&str]) -> Vec<String> {
+        labels.iter().map(|x| x.to_string()).collect()
+    }
+
+    #[test]
+    fn test_as_bytes() {
+        let record = DnsRecord {
+            name: name(&["www", "example", "com"]),
+            typ: QType::A,
+            class: QClass::IN,
+            ttl: 3600,
+            rdata: RData::A(Ipv4Addr::new(192, 168, 1, 1)),
+        };
+        let bytes = record.as_bytes();
+        assert_eq!(
+            bytes,
+            vec![
+                3, b'w', b'w', b'w', 7, b'e', b'x', b'a', b'm', b'p', b'l', b'e', 3, b'c', b'o',
+                b'm', 0, 0, 1, 0, 1, 0, 0, 14, 16, 0, 4, 192, 168, 1, 1
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_round_trip() {
+        let records = vec![
+            DnsRecord {
+                name: name(&["example", "com"]),
+                typ: QType::SOA,
+                class: QClass::IN,
+                ttl: 3600,
+                rdata: RData::SOA(Soa {
+                    mname: name(&["ns", "example", "com"]),
+                    rname: name(&["hostmaster", "example", "com"]),
+                    serial: 2024010101,
+                    refresh: 3600,
+                    retry: 600,
+                    expire: 86400,
+                    minimum: 300,
+                }),
+            },
+            DnsRecord {
+                name: name(&["example", "com"]),
+                typ: QType::MX,
+                class: QClass::IN,
+                ttl: 300,
+                rdata: RData::MX {
+                    preference: 10,
+                    exchange: name(&["mail", "example", "com"]),
+                },
+            },
+            DnsRecord {
+                name: name(&["key", "example"]),
+                typ: QType::TSIG,
+                class: QClass::WildCard,
+                ttl: 0,
+                rdata: RData::TSIG(Tsig {
+                    algorithm: name(&["hmac-sha256"]),
+                    time_signed: 1_700_000_000,
+                    fudge: 300,
+                    mac: vec![0xab; 32],
+                    original_id: 1234,
+                    error: 18,
+                    other: vec![0, 0, 0x65, 0x53, 0xf1, 0x00],
+                }),
+            },
+            DnsRecord {
+                name: name(&["www", "example", "com"]),
+                typ: QType::A,
+                class: QClass::WildCard,
+                ttl: 0,
+                rdata: RData::Raw(vec![]),
+            },
+        ];
+
+        for record in records {
+            let bytes = record.as_bytes();
+            assert_eq!(DnsRecord::parse(&bytes, 0), Some((record, bytes.len())));
+        }
+    }
+
+    #[test]
+    fn test_parse_compressed_rdata() {
+        let mut bytes = vec![
+            7, b'e', b'x', b'a', b'm', b'p', b'l', b'e', 3, b'c', b'o', b'm', 0,
+        ];
+        bytes.extend([0b1100_0000, 0, 0, 2, 0, 1, 0, 0, 0, 60, 0, 5]);
+        bytes.extend([2, b'n', b's', 0b1100_0000, 0]);
+
+        let (record, next) = DnsRecord::parse(&bytes, 13).unwrap();
+        assert_eq!(next, bytes.len());
+        assert_eq!(record.name, name(&["example", "com"]));
+        assert_eq!(record.rdata, RData::NS(name(&["ns", "example", "com"])));
+    }
+
+    #[test]
+    fn test_parse_truncated_rdata() {
+        let bytes = vec![0, 0, 1, 0, 1, 0, 0, 0, 60, 0, 4, 127, 0];
+        assert_eq!(DnsRecord::parse(&bytes, 0), None);
+    }
+
+    fn dnssec_records() -> Vec<DnsRecord> {
+        let record = |typ, rdata| DnsRecord {
+            name: name(&["example", "com"]),
+            typ,
+            class: QClass::IN,
+            ttl: 3600,
+            rdata,
+        };
+        vec![
+            record(
+                QType::DNSKEY,
+                RData::DNSKEY(Dnskey {
+                    flags: 257,
+                    protocol: 3,
+                    algorithm: 13,
+                    public_key: vec![1, 2, 3, 4],
+                }),
+            ),
+            record(
+                QType::DS,
+                RData::DS(Ds {
+                    key_tag: 60485,
+                    algorithm: 5,
+                    digest_type: 1,
+                    digest: vec![0x2b, 0xb1, 0x83],
+                }),
+            ),
+            record(
+                QType::RRSIG,
+                RData::RRSIG(Rrsig {
+                    type_covered: 65280,
+                    algorithm: 13,
+                    labels: 2,
+                    original_ttl: 3600,
+                    expiration: 1_704_067_200,
+                    inception: 1_701_388_800,
+                    key_tag: 2642,
+                    signer: name(&["example", "com"]),
+                    signature: vec![0xa0, 0x90, 0x75],
+                }),
+            ),
+            record(
+                QType::NSEC,
+                RData::NSEC(Nsec {
+                    next: name(&["host", "example", "com"]),
+                    types: vec![1, 15, 46, 47, 1234],
+                }),
+            ),
+            record(
+                QType::NSEC3,
+                RData::NSEC3(Nsec3 {
+                    hash_algorithm: 1,
+                    flags: 1,
+                    iterations: 12,
+                    salt: vec![0xaa, 0xbb, 0xcc, 0xdd],
+                    next_hashed: vec![0x17, 0x4e, 0xb2, 0x40],
+                    types: vec![1, 46],
+                }),
+            ),
+            record(
+                QType::NSEC3PARAM,
+                RData::NSEC3PARAM(Nsec3Param {
+                    hash_algorithm: 1,
+                    flags: 0,
+                    iterations: 0,
+                    salt: vec![],
+                }),
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_parse_dnssec_round_trip() {
+        for record in dnssec_records() {
+            let bytes = record.as_bytes();
+            assert_eq!(DnsRecord::parse(&bytes, 0), Some((record, bytes.len())));
+        }
+    }
+
+    #[test]
+    fn test_encode_into() {
+        let mut records = dnssec_records();
+        records.push(DnsRecord {
+            name: name(&["example", "com"]),
+            typ: QType::HTTPS,
+            class: QClass::IN,
+            ttl: 300,
+            rdata: RData::HTTPS(Svcb {
+                priority: 1,
+                target: name(&["svc", "example", "com"]),
+                params: vec![
+                    SvcParam::Alpn(vec![b"h2".to_vec(), b"h3".to_vec()]),
+                    SvcParam::Port(8443),
+                    SvcParam::Ipv6Hint(vec![Ipv6Addr::LOCALHOST]),
+                ],
+            }),
+        });
+        for record in records {
+            let bytes = record.as_bytes();
+            assert_eq!(record.rdata.encoded_len(), record.rdata.as_bytes().len());
+            assert_eq!(record.encoded_len(), bytes.len());
+
+            let mut out = [0; 512];
+            assert_eq!(record.encode_into(&mut &mut out[..]), Ok(bytes.len()));
+            assert_eq!(out[..bytes.len()], bytes[..]);
+            assert_eq!(
+                DnsRecord::parse(&out, 0),
+                Some((record.clone(), bytes.len()))
+            );
+
+            let mut out = vec![0; bytes.len() - 1];
+            assert_eq!(
+                record.encode_into(&mut &mut out[..]),
+                Err(EncodeError::BufferTooSmall {
+                    needed: bytes.len(),
+                    available: bytes.len() - 1,
+                })
+            );
+        }
+
+        // RDLENGTH cannot tell more than 65535 octets.
+        let record = DnsRecord {
+            name: name(&["example", "com"]),
+            typ: QType::OPENPGPKEY,
+            class: QClass::IN,
+            ttl: 300,
+            rdata: RData::OPENPGPKEY(vec![0; 65536]),
+        };
+        let mut out = Vec::new();
+        assert_eq!(
+            record.encode_into(&mut out),
+            Err(EncodeError::RdataTooLong(65536))
+        );
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn test_dnssec_presentation() {
+        let presented = dnssec_records()
+            .iter()
+            .map(|r| match &r.rdata {
+                RData::DNSKEY(x) => x.to_string(),
+                RData::DS(x) => x.to_string(),
+                RData::RRSIG(x) => x.to_string(),
+                RData::NSEC(x) => x.to_string(),
+                RData::NSEC3(x) => x.to_string(),
+                RData::NSEC3PARAM(x) => x.to_string(),
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            presented,
+            [
+                "257 3 13 AQIDBA==",
+                "60485 5 1 2BB183",
+                "TYPE65280 13 2 3600 20240101000000 20231201000000 2642 example.com. oJB1",
+                "host.example.com. A MX RRSIG NSEC TYPE1234",
+                "1 1 12 AABBCCDD 2T7B4G0 A RRSIG",
+                "1 0 0 -",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_opt() {
+        let mut bytes = vec![0, 0, 41, 0x04, 0xd0, 0, 0, 0x80, 0, 0, 6];
+        bytes.extend([0, 10, 0, 2, 0xab, 0xcd]);
+        let (record, next) = DnsRecord::parse(&bytes, 0).unwrap();
+        assert_eq!(next, bytes.len());
+
+        let RData::OPT(opt) = &record.rdata else {
+            panic!("expected an OPT record");
+        };
+        assert_eq!(opt.udp_payload_size, 1232);
+        assert!(opt.dnssec_ok);
+        assert_eq!(opt.options[0].code, 10);
+        assert_eq!(opt.options[0].data, vec![0xab, 0xcd]);
+        assert_eq!(record.as_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_type_bitmap() {
+        // The example of RFC 4034 section 4.3: A MX RRSIG NSEC TYPE1234.
+        let mut bytes = Vec::new();
+        write_type_bitmap(&[1, 15, 46, 47, 1234], &mut bytes);
+        let mut expected = vec![0x00, 0x06, 0x40, 0x01, 0x00, 0x00, 0x00, 0x03];
+        expected.extend([0x04, 0x1b]);
+        expected.extend([0; 26]);
+        expected.push(0x20);
+        assert_eq!(bytes, expected);
+        assert_eq!(type_bitmap_len(&[1, 15, 46, 47, 1234]), bytes.len());
+        assert_eq!(parse_type_bitmap(&bytes), Some(vec![1, 15, 46, 47, 1234]));
+        assert_eq!(type_bitmap_len(&[]), 0);
+
+        // Windows out of order, and a zero-length window.
+        assert_eq!(parse_type_bitmap(&[4, 1, 0x20, 0, 1, 0x40]), None);
+        assert_eq!(parse_type_bitmap(&[0, 0]), None);
+    }
+
+    #[test]
+    fn test_txt() {
+        let txt = Txt::new(&[b'x'; 600]);
+        assert_eq!(
+            txt.strings.iter().map(Vec::len).collect::<Vec<_>>(),
+            vec![255, 255, 90]
+        );
+        assert_eq!(txt.joined(), vec![b'x'; 600]);
+        assert_eq!(Txt::new(b"").strings, vec![Vec::<u8>::new()]);
+
+        let record = DnsRecord {
+            name: name(&["example", "com"]),
+            typ: QType::TXT,
+            class: QClass::IN,
+            ttl: 300,
+            rdata: RData::TXT(Txt {
+                strings: vec![b"say \"hi\"".to_vec(), vec![0, 0xff, b'\\']],
+            }),
+        };
+        let bytes = record.as_bytes();
+        assert_eq!(
+            DnsRecord::parse(&bytes, 0),
+            Some((record.clone(), bytes.len()))
+        );
+        assert_eq!(
+            record.to_string(),
+            "example.com. 300 IN TXT \"say \\\"hi\\\"\" \"\\000\\255\\\\\""
+        );
+
+        // A length octet running past the end of the RDATA.
+        let mut bytes = record.as_bytes();
+        let end = bytes.len() - 4;
+        bytes[end] = 4;
+        assert_eq!(DnsRecord::parse(&bytes, 0), None);
+    }
+
+    #[test]
+    fn test_service_records() {
+        let record = |labels: &[&str], typ, rdata| DnsRecord {
+            name: name(labels),
+            typ,
+            class: QClass::IN,
+            ttl: 3600,
+            rdata,
+        };
+        let records = vec![
+            record(
+                &["_sip", "_udp", "example", "com"],
+                QType::SRV,
+                RData::SRV(Srv {
+                    priority: 10,
+                    weight: 60,
+                    port: 5060,
+                    target: name(&["bigbox", "example", "com"]),
+                }),
+            ),
+            record(
+                &["example", "com"],
+                QType::NAPTR,
+                RData::NAPTR(Naptr {
+                    order: 100,
+                    preference: 10,
+                    flags: b"S".to_vec(),
+                    services: b"SIP+D2U".to_vec(),
+                    regexp: Vec::new(),
+                    replacement: name(&["_sip", "_udp", "example", "com"]),
+                }),
+            ),
+            record(
+                &["_ftp", "_tcp", "example", "com"],
+                QType::URI,
+                RData::URI(Uri {
+                    priority: 10,
+                    weight: 1,
+                    target: b"ftp://ftp1.example.com/public".to_vec(),
+                }),
+            ),
+            record(
+                &["bigbox", "example", "com"],
+                QType::AAAA,
+                RData::AAAA(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)),
+            ),
+        ];
+        for record in &records {
+            let bytes = record.as_bytes();
+            assert_eq!(
+                DnsRecord::parse(&bytes, 0),
+                Some((record.clone(), bytes.len()))
+            );
+        }
+
+        let lines = records.iter().map(|r| r.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            vec![
+                "_sip._udp.example.com. 3600 IN SRV 10 60 5060 bigbox.example.com.",
+                "example.com. 3600 IN NAPTR 100 10 \"S\" \"SIP+D2U\" \"\" _sip._udp.example.com.",
+                "_ftp._tcp.example.com. 3600 IN URI 10 1 \"ftp://ftp1.example.com/public\"",
+                "bigbox.example.com. 3600 IN AAAA 2001:db8::1",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_svcb() {
+        let svcb = Svcb {
+            priority: 1,
+            target: Vec::new(),
+            params: vec![
+                SvcParam::Port(8443),
+                SvcParam::Mandatory(vec![1, 3]),
+                SvcParam::Alpn(vec![b"h2".to_vec(), b"h3,x".to_vec()]),
+                SvcParam::NoDefaultAlpn,
+                SvcParam::Ipv4Hint(vec![Ipv4Addr::new(192, 0, 2, 1)]),
+                SvcParam::Ech(vec![0, 0x45]),
+                SvcParam::Ipv6Hint(vec![Ipv6Addr::LOCALHOST]),
+                SvcParam::Unknown {
+                    key: 65333,
+                    value: b"ex".to_vec(),
+                },
+            ],
+        };
+        assert_eq!(
+            svcb.to_string(),
+            "1 . mandatory=alpn,port alpn=h2,h3\\,x no-default-alpn port=8443 \
+             ipv4hint=192.0.2.1 ech=AEU= ipv6hint=::1 key65333=ex"
+        );
+
+        let record = DnsRecord {
+            name: name(&["example", "com"]),
+            typ: QType::HTTPS,
+            class: QClass::IN,
+            ttl: 300,
+            rdata: RData::HTTPS(svcb.clone()),
+        };
+        let bytes = record.as_bytes();
+        let (parsed, _) = DnsRecord::parse(&bytes, 0).unwrap();
+        let RData::HTTPS(parsed) = parsed.rdata else {
+            panic!("not HTTPS");
+        };
+        let keys = parsed.params.iter().map(|x| x.key()).collect::<Vec<_>>();
+        assert_eq!(keys, vec![0, 1, 2, 3, 4, 5, 6, 65333]);
+        assert_eq!(parsed.as_bytes(), svcb.as_bytes());
+
+        // Keys out of order on the wire.
+        let mut rdata = vec![0, 1, 0];
+        rdata.extend([0, 3, 0, 2, 1, 187, 0, 1, 0, 3, 2, b'h', b'2']);
+        let mut wire = bytes[..bytes.len() - svcb.as_bytes().len() - 2].to_vec();
+        wire.extend((rdata.len() as u16).to_be_bytes());
+        wire.extend(&rdata);
+        assert_eq!(DnsRecord::parse(&wire, 0), None);
+
+        let invalid = |params| Svcb {
+            priority: 1,
+            target: Vec::new(),
+            params,
+        };
+        // Parsed from the wire even though a mandatory key is missing.
+        let mut missing = record.clone();
+        missing.rdata = RData::HTTPS(invalid(vec![SvcParam::Mandatory(vec![3])]));
+        let bytes = missing.as_bytes();
+        assert_eq!(DnsRecord::parse(&bytes, 0), Some((missing, bytes.len())));
+
+        let error = |svcb: Svcb| svcb.check().unwrap_err().to_string();
+        assert_eq!(
+            error(invalid(vec![SvcParam::Mandatory(vec![3])])),
+            "mandatory key port is missing"
+        );
+        assert_eq!(
+            error(invalid(vec![SvcParam::Port(1), SvcParam::Port(2)])),
+            "repeated SvcParamKey"
+        );
+        assert_eq!(
+            error(invalid(vec![SvcParam::NoDefaultAlpn])),
+            "no-default-alpn without alpn"
+        );
+        assert_eq!(
+            error(invalid(vec![SvcParam::Mandatory(vec![0])])),
+            "mandatory lists itself"
+        );
+    }
+
+    #[test]
+    fn test_security_records() {
+        let record = |typ, rdata| DnsRecord {
+            name: name(&["example", "com"]),
+            typ,
+            class: QClass::IN,
+            ttl: 3600,
+            rdata,
+        };
+        let records = vec![
+            record(
+                QType::SSHFP,
+                RData::SSHFP(Sshfp {
+                    algorithm: 4,
+                    fingerprint_type: 2,
+                    fingerprint: vec![0xab; 32],
+                }),
+            ),
+            record(
+                QType::TLSA,
+                RData::TLSA(Tlsa {
+                    usage: 3,
+                    selector: 1,
+                    matching_type: 1,
+                    data: vec![0x0c; 32],
+                }),
+            ),
+            record(QType::OPENPGPKEY, RData::OPENPGPKEY(vec![0x99, 1, 2, 3])),
+            record(
+                QType::CAA,
+                RData::CAA(Caa {
+                    flags: 128,
+                    tag: "issue".to_string(),
+                    value: b"ca.example.net; account=230123".to_vec(),
+                }),
+            ),
+        ];
+        for record in &records {
+            let bytes = record.as_bytes();
+            assert_eq!(
+                DnsRecord::parse(&bytes, 0),
+                Some((record.clone(), bytes.len()))
+            );
+        }
+
+        let presented = records
+            .iter()
+            .map(|r| r.rdata.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            presented,
+            vec![
+                format!("4 2 {}", "AB".repeat(32)),
+                format!("3 1 1 {}", "0C".repeat(32)),
+                "mQECAw==".to_string(),
+                "128 issue \"ca.example.net; account=230123\"".to_string(),
+            ]
+        );
+        let RData::CAA(caa) = &records[3].rdata else {
+            unreachable!()
+        };
+        assert!(caa.is_critical());
+
+        // Tags that are not letters and digits are accepted from the wire, and
+        // kept raw when they are not text.
+        let mut bytes = records[3].as_bytes();
+        let tag_start = bytes.len() - caa.value.len() - caa.tag.len();
+        bytes[tag_start] = b'-';
+        let (parsed, _) = DnsRecord::parse(&bytes, 0).unwrap();
+        let RData::CAA(parsed) = parsed.rdata else {
+            panic!("not CAA");
+        };
+        assert_eq!(parsed.tag, "-ssue");
+        assert!(parsed.check().is_err());
+        bytes[tag_start] = 0xff;
+        let (parsed, _) = DnsRecord::parse(&bytes, 0).unwrap();
+        let rdata_start = tag_start - 2;
+        assert_eq!(parsed.rdata, RData::Raw(bytes[rdata_start..].to_vec()));
+    }
+}
@@ -0,0 +1,21 @@
+use std::{collections::HashMap, net::SocketAddr, time::Duration};
+
+/// What is known of the upstream servers, from the queries forwarded to them
+/// and from probes. Upstreams failing `MAX_FAILURES` times in a row, by timing
+/// out or answering SERVFAIL, are taken out of rotation until they answer again.
+#[derive(Debug, Default)]
+pub struct UpstreamHealth {
+    pub upstreams: HashMap<SocketAddr, UpstreamState>,
+    /// Queries ordered round-robin so far, by the domain of their rule.
+    pub turns: HashMap<Vec<String>, usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct UpstreamState {
+    /// The smoothed round-trip time (RFC 6298 section 2), once measured.
+    pub srtt: Option<Duration>,
+    /// Failures since the last answer.
+    pub failures: u32,
+    /// Whether the upstream is out of rotation.
+    pub down: bool,
+}
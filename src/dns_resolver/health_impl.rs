@@ -0,0 +1,144 @@
+use super::{
+    health::{UpstreamHealth, UpstreamState},
+    resolver::{ForwardRule, Strategy},
+};
+use crate::{dns_header::header::ResponseCode, dns_message::message::DnsMessage};
+use rand::seq::SliceRandom;
+use std::{net::SocketAddr, time::Duration};
+
+/// Failures in a row taking an upstream out of rotation.
+pub const MAX_FAILURES: u32 = 3;
+
+impl UpstreamHealth {
+    /// The upstreams of `rule` in the order to try them: those in rotation as
+    /// its strategy orders them, then the others as listed, so that queries are
+    /// still sent somewhere when every upstream is down.
+    pub fn order(&mut self, rule: &ForwardRule) -> Vec<SocketAddr> {
+        let state = |upstream: &SocketAddr| self.upstreams.get(upstream).copied();
+        let (mut up, down): (Vec<_>, Vec<_>) = rule
+            .upstreams
+            .iter()
+            .copied()
+            .partition(|x| !state(x).is_some_and(|x| x.down));
+        match rule.strategy {
+            Strategy::Sequential => {}
+            Strategy::RoundRobin if !up.is_empty() => {
+                let turn = self.turns.entry(rule.domain.clone()).or_default();
+                let len = up.len();
+                up.rotate_left(*turn % len);
+                *turn = turn.wrapping_add(1);
+            }
+            Strategy::RoundRobin => {}
+            Strategy::Random => up.shuffle(&mut rand::thread_rng()),
+            Strategy::LowestLatency => up.sort_by_key(|x| state(x).and_then(|x| x.srtt)),
+        }
+        up.into_iter().chain(down).collect()
+    }
+
+    /// Records the answer of `upstream` after `rtt`, a failure if it is
+    /// SERVFAIL.
+    pub fn record_answer(&mut self, upstream: SocketAddr, response: &DnsMessage, rtt: Duration) {
+        if response.header.rcode == ResponseCode::ServerFailure {
+            self.record_failure(upstream);
+            return;
+        }
+        let state = self.upstreams.entry(upstream).or_default();
+        // RFC 6298 section 2.3, with alpha = 1/8.
+        state.srtt = Some(state.srtt.map_or(rtt, |srtt| (srtt * 7 + rtt) / 8));
+        state.failures = 0;
+        if state.down {
+            log::info!("Upstream {} is answering again", upstream);
+            state.down = false;
+        }
+    }
+
+    /// Records that `upstream` did not answer.
+    pub fn record_failure(&mut self, upstream: SocketAddr) {
+        let state = self.upstreams.entry(upstream).or_default();
+        state.failures += 1;
+        if state.failures >= MAX_FAILURES && !state.down {
+            log::warn!(
+                "Upstream {} failed {} times in a row, taking it out of rotation",
+                upstream,
+                state.failures
+            );
+            state.down = true;
+        }
+    }
+
+    pub fn state(&self, upstream: SocketAddr) -> UpstreamState {
+        self.upstreams.get(&upstream).copied().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        dns_header::header::ResponseCode,
+        dns_message::{builder::MessageBuilder, message::DnsMessage},
+        dns_resolver::{
+            health::UpstreamHealth,
+            health_impl::MAX_FAILURES,
+            resolver::{ForwardRule, Strategy},
+        },
+        resrec::QType,
+    };
+    use std::{net::SocketAddr, time::Duration};
+
+    fn rule(strategy: Strategy) -> (ForwardRule, [SocketAddr; 3]) {
+        let upstreams =
+            ["192.0.2.1:53", "192.0.2.2:53", "192.0.2.3:53"].map(|x| x.parse().unwrap());
+        let mut rule = ForwardRule::new(vec!["example".to_string()], upstreams.to_vec());
+        rule.strategy = strategy;
+        (rule, upstreams)
+    }
+
+    fn answer(rcode: ResponseCode) -> DnsMessage {
+        let query = DnsMessage::query(Vec::new(), QType::NS);
+        MessageBuilder::response_to(&query).rcode(rcode).build()
+    }
+
+    #[test]
+    fn strategies_test() {
+        let mut health = UpstreamHealth::default();
+        let (sequential, [a, b, c]) = rule(Strategy::Sequential);
+        assert_eq!(health.order(&sequential), [a, b, c]);
+
+        let (round_robin, _) = rule(Strategy::RoundRobin);
+        assert_eq!(health.order(&round_robin), [a, b, c]);
+        assert_eq!(health.order(&round_robin), [b, c, a]);
+        assert_eq!(health.order(&round_robin), [c, a, b]);
+
+        let (random, _) = rule(Strategy::Random);
+        let mut order = health.order(&random);
+        order.sort();
+        assert_eq!(order, [a, b, c]);
+
+        let (lowest_latency, _) = rule(Strategy::LowestLatency);
+        let ok = answer(ResponseCode::NoErrorCondition);
+        health.record_answer(a, &ok, Duration::from_millis(80));
+        health.record_answer(c, &ok, Duration::from_millis(20));
+        assert_eq!(health.order(&lowest_latency), [b, c, a]);
+        health.record_answer(c, &ok, Duration::from_millis(600));
+        assert_eq!(health.state(c).srtt, Some(Duration::from_micros(92_500)));
+        assert_eq!(health.order(&lowest_latency), [b, a, c]);
+    }
+
+    #[test]
+    fn removal_test() {
+        let mut health = UpstreamHealth::default();
+        let (rule, [a, b, c]) = rule(Strategy::Sequential);
+        for _ in 1..MAX_FAILURES {
+            health.record_failure(a);
+        }
+        assert!(!health.state(a).down);
+        health.record_answer(a, &answer(ResponseCode::ServerFailure), Duration::ZERO);
+        assert!(health.state(a).down);
+        assert_eq!(health.order(&rule), [b, c, a]);
+
+        // An answer resets the count and brings the upstream back.
+        health.record_answer(a, &answer(ResponseCode::NameError), Duration::ZERO);
+        assert_eq!(health.state(a).failures, 0);
+        assert_eq!(health.order(&rule), [a, b, c]);
+    }
+}
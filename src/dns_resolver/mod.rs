@@ -0,0 +1,4 @@
+pub mod health;
+pub(crate) mod health_impl;
+pub mod resolver;
+pub(crate) mod resolver_impl;
@@ -0,0 +1,70 @@
+use super::health::UpstreamHealth;
+use crate::{
+    dns_message::message::DnsMessage,
+    dnssec::validator::{Security, Validator},
+};
+use std::{collections::HashMap, net::SocketAddr, sync::Mutex};
+
+/// Answers the queries the server is not authoritative for by forwarding them
+/// to upstream recursive servers, chosen by the forwarding rule with the longest
+/// domain holding the question name. With a validator, upstream answers are
+/// checked with DNSSEC: bogus ones become SERVFAIL and secure ones get the AD
+/// bit (RFC 4035 section 3.2.3).
+#[derive(Debug)]
+pub struct Resolver {
+    /// The forwarding table, where a rule for the root domain holds the default
+    /// upstreams.
+    pub forwarders: Vec<ForwardRule>,
+    pub validator: Option<Mutex<Validator>>,
+    pub cache: Mutex<AnswerCache>,
+    pub health: Mutex<UpstreamHealth>,
+}
+
+/// Where queries for names at or below `domain` are forwarded, as
+/// `<domain>@<address>[,<address>...][@<strategy>][@forward-only][@no-cache]` on
+/// the command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForwardRule {
+    pub domain: Vec<String>,
+    /// Tried one after the other until one answers, in the order `strategy`
+    /// gives among those in rotation.
+    pub upstreams: Vec<SocketAddr>,
+    pub strategy: Strategy,
+    /// Whether queries fail when the upstreams do, rather than going to those of
+    /// the rule for the enclosing domain, like BIND's `forward only`.
+    pub forward_only: bool,
+    /// Whether answers are kept in the cache.
+    pub cache: bool,
+}
+
+/// How the upstreams of a rule are ordered for each query, named as on the
+/// command line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Strategy {
+    /// `sequential`: as listed, the next ones only serving when the first fail.
+    #[default]
+    Sequential,
+    /// `round-robin`: starting from the next one each time.
+    RoundRobin,
+    /// `random`: shuffled.
+    Random,
+    /// `lowest-latency`: by smoothed round-trip time, those not yet measured
+    /// first.
+    LowestLatency,
+}
+
+/// Upstream answers kept until their TTL runs out, by the name, type and class
+/// of their question and the DO and CD bits it was asked with.
+#[derive(Debug, Default)]
+pub struct AnswerCache {
+    pub entries: HashMap<(Vec<String>, u16, u16, bool, bool), CachedAnswer>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CachedAnswer {
+    pub response: DnsMessage,
+    pub security: Option<Security>,
+    /// When the answer was received, in seconds since the epoch.
+    pub stored: u64,
+    pub expires: u64,
+}
@@ -0,0 +1,863 @@
+use super::{
+    health::UpstreamHealth,
+    resolver::{AnswerCache, CachedAnswer, ForwardRule, Resolver, Strategy},
+};
+use crate::{
+    dns_client::client::Client,
+    dns_header::header::{
+        AuthenticData, CheckingDisabled, RecursionAvailability, RecursionDesire, ResponseCode,
+        Truncated,
+    },
+    dns_message::{builder::MessageBuilder, message::DnsMessage},
+    dns_name::name::{is_subdomain, name_to_string, names_equal},
+    dns_record::record::{DnsRecord, Opt, RData},
+    dnssec::{
+        validator::{Security, Validator},
+        validator_impl::Lookup,
+    },
+    resrec::QType,
+};
+use anyhow::{anyhow, bail, ensure, Context};
+use std::{
+    cmp::Reverse,
+    net::SocketAddr,
+    str::FromStr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// How long to wait on the upstream server before giving up on a query.
+const UPSTREAM_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The UDP payload size advertised upstream, small enough to avoid IP
+/// fragmentation (DNS flag day 2020).
+pub const EDNS_PAYLOAD_SIZE: u16 = 1232;
+
+/// Upper bound on the answers kept in the cache.
+const MAX_CACHE_ENTRIES: usize = 10_000;
+
+/// Upper bound on how long an answer is kept, whatever its TTL.
+const MAX_CACHE_TTL: u32 = 86_400;
+
+/// Upper bound on SVCB AliasMode records followed for one answer, as for CNAME
+/// chains (RFC 9460 section 3.1).
+const MAX_ALIAS_CHAIN: usize = 8;
+
+impl Resolver {
+    pub fn new(upstream: SocketAddr, validator: Option<Validator>) -> Self {
+        let default = ForwardRule::new(Vec::new(), vec![upstream]);
+        Resolver::with_forwarders(vec![default], validator)
+    }
+
+    /// A resolver forwarding by the `forwarders` table, where names no rule
+    /// holds are answered with SERVFAIL.
+    pub fn with_forwarders(forwarders: Vec<ForwardRule>, validator: Option<Validator>) -> Self {
+        Resolver {
+            forwarders,
+            validator: validator.map(Mutex::new),
+            cache: Mutex::new(AnswerCache::default()),
+            health: Mutex::new(UpstreamHealth::default()),
+        }
+    }
+
+    /// Forwards `request` upstream, or answers it from the cache, and builds
+    /// the response to its client. Upstream failures are answered with
+    /// SERVFAIL.
+    pub fn resolve(&self, request: &DnsMessage, now: u64) -> DnsMessage {
+        let mut query = request.clone();
+        query.header.packet_id = rand::random();
+        query.header.rd = RecursionDesire::Desired;
+        query.additionals.clear();
+        if self.validator.is_some() {
+            // Validation needs the signatures, and is done here even when the
+            // upstream server validates too.
+            query.header.cd = CheckingDisabled::Disabled;
+            query.set_edns(Opt::new(EDNS_PAYLOAD_SIZE, true));
+        } else if let Some(edns) = request.edns() {
+            query.set_edns(Opt::new(EDNS_PAYLOAD_SIZE, edns.dnssec_ok));
+        }
+        query.update_counts();
+
+        let qname = query.questions.first().map_or(&[][..], |q| &q.qname[..]);
+        let cacheable = query.questions.len() == 1
+            && self.rules_for(qname).first().is_some_and(|rule| rule.cache);
+        let key = cacheable.then(|| AnswerCache::key(&query));
+        if let Some(key) = &key {
+            if let Some((response, security)) = self.cache.lock().unwrap().get(key, now) {
+                return forwarded_response(request, response, security);
+            }
+        }
+
+        let mut response = match self.forward(qname, &query) {
+            Ok(response) => response,
+            Err(e) => {
+                log::warn!("Forwarding {} failed: {:#}", name_to_string(qname), e);
+                let mut response = request.clone();
+                response.to_bare_response(ResponseCode::ServerFailure);
+                response.header.ra = RecursionAvailability::Available;
+                return response;
+            }
+        };
+
+        let security = self.validator.as_ref().map(|validator| {
+            let mut lookup = |name: &[String], typ: QType| self.lookup(name, typ);
+            validator
+                .lock()
+                .unwrap()
+                .validate(&response, now, &mut lookup)
+        });
+        if !matches!(security, Some(Security::Bogus(_))) {
+            let mut follow_up = |name: &[String], typ: QType| self.follow_up(name, typ, now);
+            let additionals = service_additionals(&response, &mut follow_up);
+            response.additionals.extend(additionals);
+            if let Some(key) = key {
+                let mut cache = self.cache.lock().unwrap();
+                cache.insert(key, &response, security.clone(), now);
+            }
+        }
+        forwarded_response(request, response, security)
+    }
+
+    /// The rules whose domain holds `name`, longest first, up to the first one
+    /// forwarding only.
+    pub fn rules_for(&self, name: &[String]) -> Vec<&ForwardRule> {
+        let mut rules = self
+            .forwarders
+            .iter()
+            .filter(|rule| is_subdomain(name, &rule.domain))
+            .collect::<Vec<_>>();
+        rules.sort_by_key(|rule| Reverse(rule.domain.len()));
+        if let Some(only) = rules.iter().position(|rule| rule.forward_only) {
+            rules.truncate(only + 1);
+        }
+        rules
+    }
+
+    /// Sends `query` to the upstreams of the rules for `name` in turn, until one
+    /// answers other than SERVFAIL. Returns the last SERVFAIL when every
+    /// upstream fails.
+    fn forward(&self, name: &[String], query: &DnsMessage) -> anyhow::Result<DnsMessage> {
+        let mut failure = Err(anyhow!("no forwarding rule"));
+        for rule in self.rules_for(name) {
+            let upstreams = self.health.lock().unwrap().order(rule);
+            for upstream in upstreams {
+                let start = Instant::now();
+                let result = exchange(upstream, query);
+                let mut health = self.health.lock().unwrap();
+                match result {
+                    Ok(response) => {
+                        health.record_answer(upstream, &response, start.elapsed());
+                        if response.header.rcode != ResponseCode::ServerFailure {
+                            return Ok(response);
+                        }
+                        failure = Ok(response);
+                    }
+                    Err(e) => {
+                        health.record_failure(upstream);
+                        if failure.is_err() {
+                            failure = Err(e.context(format!("forwarding to {}", upstream)));
+                        }
+                    }
+                }
+            }
+        }
+        failure
+    }
+
+    /// Asks every upstream for the root name servers, as a health check taking
+    /// those failing out of rotation and bringing back those answering again.
+    pub fn probe(&self) {
+        let mut upstreams = self
+            .forwarders
+            .iter()
+            .flat_map(|rule| rule.upstreams.iter().copied())
+            .collect::<Vec<_>>();
+        upstreams.sort();
+        upstreams.dedup();
+        for upstream in upstreams {
+            let query = MessageBuilder::query(Vec::new(), QType::NS)
+                .recursion_desired(true)
+                .build();
+            let start = Instant::now();
+            let result = exchange(upstream, &query);
+            let mut health = self.health.lock().unwrap();
+            match result {
+                Ok(response) => health.record_answer(upstream, &response, start.elapsed()),
+                Err(_) => health.record_failure(upstream),
+            }
+        }
+    }
+
+    /// Queries upstream for the records of a name and type with the DO bit set,
+    /// as validation needs.
+    fn lookup(&self, name: &[String], typ: QType) -> anyhow::Result<DnsMessage> {
+        let mut query = DnsMessage::query(name.to_vec(), typ);
+        query.header.rd = RecursionDesire::Desired;
+        query.header.cd = CheckingDisabled::Disabled;
+        query.set_edns(Opt::new(EDNS_PAYLOAD_SIZE, true));
+        self.forward(name, &query)
+    }
+
+    /// Queries upstream for records added to an answer, failing on bogus ones
+    /// when validating.
+    fn follow_up(&self, name: &[String], typ: QType, now: u64) -> anyhow::Result<DnsMessage> {
+        let response = self.lookup(name, typ)?;
+        if let Some(validator) = &self.validator {
+            let mut lookup = |name: &[String], typ: QType| self.lookup(name, typ);
+            let security = validator
+                .lock()
+                .unwrap()
+                .validate(&response, now, &mut lookup);
+            ensure!(
+                !matches!(security, Security::Bogus(_)),
+                "bogus answer for {} {}",
+                name_to_string(name),
+                typ
+            );
+        }
+        Ok(response)
+    }
+}
+
+impl AnswerCache {
+    /// The entry for `query`, by its first question.
+    pub fn key(query: &DnsMessage) -> (Vec<String>, u16, u16, bool, bool) {
+        let question = &query.questions[0];
+        let qname = question.qname.iter().map(|x| x.to_ascii_lowercase());
+        (
+            qname.collect(),
+            u16::from(question.qtype),
+            u16::from(question.qclass),
+            query.edns().is_some_and(|x| x.dnssec_ok),
+            query.header.cd == CheckingDisabled::Disabled,
+        )
+    }
+
+    /// The answer under `key`, with its TTLs lowered by the time it spent in the
+    /// cache.
+    pub fn get(
+        &self,
+        key: &(Vec<String>, u16, u16, bool, bool),
+        now: u64,
+    ) -> Option<(DnsMessage, Option<Security>)> {
+        let entry = self.entries.get(key).filter(|x| x.expires > now)?;
+        let mut response = entry.response.clone();
+        let age = now.saturating_sub(entry.stored) as u32;
+        let records = response
+            .answers
+            .iter_mut()
+            .chain(&mut response.authorities)
+            .chain(&mut response.additionals);
+        for record in records {
+            if record.typ != QType::OPT {
+                record.ttl = record.ttl.saturating_sub(age);
+            }
+        }
+        Some((response, entry.security.clone()))
+    }
+
+    /// Keeps `response` for its lowest TTL, negative answers for no longer than
+    /// their SOA minimum (RFC 2308 section 5). Failures and answers without any
+    /// record are not kept, nor anything when the cache is full of live answers.
+    pub fn insert(
+        &mut self,
+        key: (Vec<String>, u16, u16, bool, bool),
+        response: &DnsMessage,
+        security: Option<Security>,
+        now: u64,
+    ) {
+        if !matches!(
+            response.header.rcode,
+            ResponseCode::NoErrorCondition | ResponseCode::NameError
+        ) {
+            return;
+        }
+        let ttl = response
+            .answers
+            .iter()
+            .chain(&response.authorities)
+            .chain(&response.additionals)
+            .filter(|r| r.typ != QType::OPT)
+            .map(|r| match &r.rdata {
+                RData::SOA(soa) => r.ttl.min(soa.minimum),
+                _ => r.ttl,
+            })
+            .min();
+        let Some(ttl) = ttl.filter(|&ttl| ttl > 0) else {
+            return;
+        };
+        if self.entries.len() >= MAX_CACHE_ENTRIES {
+            self.entries.retain(|_, entry| entry.expires > now);
+            if self.entries.len() >= MAX_CACHE_ENTRIES {
+                return;
+            }
+        }
+        let answer = CachedAnswer {
+            response: response.clone(),
+            security,
+            stored: now,
+            expires: now + u64::from(ttl.min(MAX_CACHE_TTL)),
+        };
+        self.entries.insert(key, answer);
+    }
+}
+
+impl ForwardRule {
+    /// A rule trying its upstreams in sequence, falling back to enclosing rules
+    /// and caching answers.
+    pub fn new(domain: Vec<String>, upstreams: Vec<SocketAddr>) -> Self {
+        ForwardRule {
+            domain,
+            upstreams,
+            strategy: Strategy::Sequential,
+            forward_only: false,
+            cache: true,
+        }
+    }
+}
+
+/// `<domain>@<address>[,<address>...][@<strategy>][@forward-only][@no-cache]`,
+/// with each address as `<ip>:<port>`.
+impl FromStr for ForwardRule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split('@');
+        let (Some(domain), Some(upstreams)) = (parts.next(), parts.next()) else {
+            bail!("expected <domain>@<address>[,<address>...], got {}", s);
+        };
+        let upstreams = upstreams
+            .split(',')
+            .map(|x| x.parse().with_context(|| format!("invalid address {}", x)))
+            .collect::<anyhow::Result<_>>()?;
+        let domain = domain
+            .split('.')
+            .filter(|x| !x.is_empty())
+            .map(String::from)
+            .collect();
+        let mut rule = ForwardRule::new(domain, upstreams);
+        for option in parts {
+            match option {
+                "sequential" => rule.strategy = Strategy::Sequential,
+                "round-robin" => rule.strategy = Strategy::RoundRobin,
+                "random" => rule.strategy = Strategy::Random,
+                "lowest-latency" => rule.strategy = Strategy::LowestLatency,
+                "forward-only" => rule.forward_only = true,
+                "no-cache" => rule.cache = false,
+                _ => bail!("unknown forwarding option {}", option),
+            }
+        }
+        Ok(rule)
+    }
+}
+
+/// The records a client of an SVCB or HTTPS answer would query next, fetched
+/// with `lookup` to save it the round trips (RFC 9460 section 4.2): the SVCB
+/// records AliasMode ones lead to, followed up to `MAX_ALIAS_CHAIN` times, and
+/// the addresses of the ServiceMode targets reached. Lookups that fail are left
+/// out, as are records already in the response.
+pub fn service_additionals(response: &DnsMessage, lookup: &mut Lookup) -> Vec<DnsRecord> {
+    let Some(question) = response.questions.first() else {
+        return Vec::new();
+    };
+    let typ = question.qtype;
+    if !matches!(typ, QType::SVCB | QType::HTTPS) {
+        return Vec::new();
+    }
+    // Records the upstream server already added save a query.
+    let found = |name: &[String], typ: QType, lookup: &mut Lookup| {
+        let known = response
+            .additionals
+            .iter()
+            .filter(|r| r.typ == typ && names_equal(&r.name, name))
+            .cloned()
+            .collect::<Vec<_>>();
+        if !known.is_empty() {
+            return known;
+        }
+        match lookup(name, typ) {
+            Ok(answer) => answer
+                .answers
+                .into_iter()
+                .filter(|r| r.typ == typ)
+                .collect(),
+            Err(e) => {
+                log::warn!(
+                    "Looking up {} {} failed: {:#}",
+                    name_to_string(name),
+                    typ,
+                    e
+                );
+                Vec::new()
+            }
+        }
+    };
+
+    let mut additionals = Vec::new();
+    let mut services = response
+        .answers
+        .iter()
+        .filter(|r| r.typ == typ)
+        .cloned()
+        .collect::<Vec<_>>();
+    let mut visited = vec![question.qname.clone()];
+    for _ in 0..MAX_ALIAS_CHAIN {
+        let alias = services.iter().find_map(|r| match &r.rdata {
+            RData::SVCB(svcb) | RData::HTTPS(svcb) if svcb.is_alias() => Some(svcb.target.clone()),
+            _ => None,
+        });
+        // An alias to the root means the service does not exist.
+        let Some(target) = alias.filter(|x| !x.is_empty()) else {
+            break;
+        };
+        if visited.iter().any(|x| names_equal(x, &target)) {
+            break;
+        }
+        services = found(&target, typ, lookup);
+        additionals.extend(services.iter().cloned());
+        visited.push(target);
+    }
+
+    let mut hosts: Vec<Vec<String>> = Vec::new();
+    for record in &services {
+        if let RData::SVCB(svcb) | RData::HTTPS(svcb) = &record.rdata {
+            let host = match svcb.target.is_empty() {
+                true => &record.name,
+                false => &svcb.target,
+            };
+            if !svcb.is_alias() && !hosts.iter().any(|x| names_equal(x, host)) {
+                hosts.push(host.clone());
+            }
+        }
+    }
+    for host in &hosts {
+        additionals.extend(found(host, QType::A, lookup));
+        additionals.extend(found(host, QType::AAAA, lookup));
+    }
+    let mut unique: Vec<DnsRecord> = Vec::new();
+    for record in additionals {
+        if !response.answers.contains(&record)
+            && !response.additionals.contains(&record)
+            && !unique.contains(&record)
+        {
+            unique.push(record);
+        }
+    }
+    unique
+}
+
+/// Builds the response to `request` from the upstream `response` and the outcome
+/// of its validation, if any.
+pub fn forwarded_response(
+    request: &DnsMessage,
+    mut response: DnsMessage,
+    security: Option<Security>,
+) -> DnsMessage {
+    let client_edns = request.edns().cloned();
+    let dnssec_ok = client_edns.as_ref().is_some_and(|x| x.dnssec_ok);
+    let checking_disabled = request.header.cd == CheckingDisabled::Disabled;
+
+    if let Some(Security::Bogus(e)) = &security {
+        let questions = request.questions.iter().map(|q| q.to_string());
+        log::warn!(
+            "Bogus answer to {}: {}",
+            questions.collect::<Vec<_>>().join(", "),
+            e
+        );
+        if !checking_disabled {
+            let mut response = request.clone();
+            response.to_bare_response(ResponseCode::ServerFailure);
+            response.header.ra = RecursionAvailability::Available;
+            return response;
+        }
+    }
+
+    // AD is only set for clients that showed they understand it (RFC 6840
+    // section 5.7).
+    let wants_ad = dnssec_ok || request.header.ad == AuthenticData::Authentic;
+    response.header.ad = match security {
+        Some(Security::Secure) if wants_ad => AuthenticData::Authentic,
+        _ => AuthenticData::NotAuthentic,
+    };
+    if !dnssec_ok {
+        let qtype = request.questions.first().map(|q| q.qtype);
+        for section in [&mut response.answers, &mut response.authorities] {
+            section.retain(|r| !is_dnssec_record(r) || Some(r.typ) == qtype);
+        }
+    }
+    response.additionals.retain(|r| r.typ != QType::OPT);
+    if let Some(edns) = client_edns {
+        response.set_edns(Opt::new(EDNS_PAYLOAD_SIZE, edns.dnssec_ok));
+    }
+
+    response.header.packet_id = request.header.packet_id;
+    response.header.rd = request.header.rd;
+    response.header.cd = request.header.cd;
+    response.header.ra = RecursionAvailability::Available;
+    response.header.tc = Truncated::NotTruncated;
+    response.questions = request.questions.clone();
+    response.to_response();
+    response
+}
+
+/// Sends `request` to `upstream` once over UDP, retrying over TCP when the
+/// answer is truncated.
+pub fn exchange(upstream: SocketAddr, request: &DnsMessage) -> anyhow::Result<DnsMessage> {
+    let client = Client {
+        server: upstream,
+        timeout: UPSTREAM_TIMEOUT,
+        attempts: 1,
+        edns: None,
+        tcp: false,
+    };
+    client.exchange(request)
+}
+
+/// Records only sent to clients setting the DO bit, unless asked for (RFC 4035
+/// section 3.2.1).
+fn is_dnssec_record(record: &DnsRecord) -> bool {
+    matches!(record.typ, QType::RRSIG | QType::NSEC | QType::NSEC3)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        dns_header::header::{
+            AuthenticData, CheckingDisabled, RecursionAvailability, ResponseCode,
+        },
+        dns_message::message::DnsMessage,
+        dns_record::record::{DnsRecord, Opt, RData, Rrsig, Svcb},
+        dns_resolver::{
+            resolver::{AnswerCache, ForwardRule, Resolver, Strategy},
+            resolver_impl::{exchange, forwarded_response, service_additionals},
+        },
+        dnssec::validator::{Security, ValidationError},
+        resrec::{QClass, QType},
+    };
+    use std::{net::Ipv4Addr, net::UdpSocket, sync::Mutex, thread};
+
+    fn name() -> Vec<String> {
+        vec!["www".to_string(), "example".to_string()]
+    }
+
+    fn upstream_response(request: &DnsMessage) -> DnsMessage {
+        let mut response = request.clone();
+        response.answers.push(DnsRecord {
+            name: name(),
+            typ: QType::A,
+            class: QClass::IN,
+            ttl: 60,
+            rdata: RData::A(Ipv4Addr::new(192, 0, 2, 1)),
+        });
+        response.answers.push(DnsRecord {
+            name: name(),
+            typ: QType::RRSIG,
+            class: QClass::IN,
+            ttl: 60,
+            rdata: RData::RRSIG(Rrsig {
+                type_covered: u16::from(QType::A),
+                algorithm: 13,
+                labels: 2,
+                original_ttl: 60,
+                expiration: 0,
+                inception: 0,
+                key_tag: 0,
+                signer: vec!["example".to_string()],
+                signature: vec![],
+            }),
+        });
+        response.to_response();
+        response
+    }
+
+    #[test]
+    fn secure_response_test() {
+        let mut request = DnsMessage::query(name(), QType::A);
+        let response = forwarded_response(
+            &request,
+            upstream_response(&request),
+            Some(Security::Secure),
+        );
+        // Without DO, neither AD nor signatures.
+        assert_eq!(response.header.ad, AuthenticData::NotAuthentic);
+        assert_eq!(response.header.ra, RecursionAvailability::Available);
+        assert_eq!(response.answers.len(), 1);
+        assert_eq!(response.edns(), None);
+
+        request.set_edns(Opt::new(4096, true));
+        let response = forwarded_response(
+            &request,
+            upstream_response(&request),
+            Some(Security::Secure),
+        );
+        assert_eq!(response.header.ad, AuthenticData::Authentic);
+        assert_eq!(response.answers.len(), 2);
+        assert!(response.edns().is_some_and(|x| x.dnssec_ok));
+
+        let response = forwarded_response(
+            &request,
+            upstream_response(&request),
+            Some(Security::Insecure),
+        );
+        assert_eq!(response.header.ad, AuthenticData::NotAuthentic);
+    }
+
+    #[test]
+    fn bogus_response_test() {
+        let mut request = DnsMessage::query(name(), QType::A);
+        let bogus = Security::Bogus(ValidationError::BadSignature);
+        let response =
+            forwarded_response(&request, upstream_response(&request), Some(bogus.clone()));
+        assert_eq!(response.header.rcode, ResponseCode::ServerFailure);
+        assert!(response.answers.is_empty());
+        assert_eq!(response.header.packet_id, request.header.packet_id);
+
+        // Clients setting CD validate themselves.
+        request.header.cd = CheckingDisabled::Disabled;
+        let response = forwarded_response(&request, upstream_response(&request), Some(bogus));
+        assert_eq!(response.header.rcode, ResponseCode::NoErrorCondition);
+        assert_eq!(response.header.ad, AuthenticData::NotAuthentic);
+        assert_eq!(response.answers.len(), 1);
+    }
+
+    #[test]
+    fn exchange_test() {
+        let upstream = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let address = upstream.local_addr().unwrap();
+        thread::spawn(move || {
+            let mut buf = [0; 512];
+            let (size, source) = upstream.recv_from(&mut buf).unwrap();
+            let request = DnsMessage::parse(&buf[..size]).unwrap();
+            let mut stray = upstream_response(&request);
+            stray.header.packet_id = request.header.packet_id.wrapping_add(1);
+            upstream.send_to(&stray.as_bytes(), source).unwrap();
+            let response = upstream_response(&request);
+            upstream.send_to(&response.as_bytes(), source).unwrap();
+        });
+
+        let request = DnsMessage::query(name(), QType::A);
+        let response = exchange(address, &request).unwrap();
+        assert_eq!(response.header.packet_id, request.header.packet_id);
+        assert_eq!(response.answers.len(), 2);
+    }
+
+    #[test]
+    fn unknown_type_test() {
+        // DNAME and CDS have no variant of their own, but keep their codes.
+        let records =
+            [(39, vec![0]), (59, vec![0xe5, 0x8e, 13, 2, 0xab])].map(|(code, rdata)| DnsRecord {
+                name: name(),
+                typ: QType::Unknown(code),
+                class: QClass::IN,
+                ttl: 60,
+                rdata: RData::Raw(rdata),
+            });
+        let upstream = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let address = upstream.local_addr().unwrap();
+        let answers = records.to_vec();
+        thread::spawn(move || {
+            let mut buf = [0; 512];
+            let (size, source) = upstream.recv_from(&mut buf).unwrap();
+            let mut response = DnsMessage::parse(&buf[..size]).unwrap();
+            response.answers = answers;
+            response.to_response();
+            upstream.send_to(&response.as_bytes(), source).unwrap();
+        });
+
+        let resolver = Resolver::new(address, None);
+        let request = DnsMessage::query(name(), QType::from(59));
+        let response = resolver.resolve(&request, 1000);
+        assert_eq!(response.questions[0].qtype, QType::Unknown(59));
+        assert_eq!(response.answers, records);
+        let bytes = response.as_bytes();
+        assert_eq!(DnsMessage::parse(&bytes).unwrap().answers, records);
+    }
+
+    #[test]
+    fn forwarding_rules_test() {
+        let rule = "Corp.Internal@10.0.0.53:53,[2001:db8::53]:53@forward-only@no-cache"
+            .parse::<ForwardRule>()
+            .unwrap();
+        assert_eq!(rule.domain, ["Corp", "Internal"]);
+        assert_eq!(rule.upstreams.len(), 2);
+        assert!(rule.forward_only && !rule.cache);
+        assert_eq!(rule.strategy, Strategy::Sequential);
+        let pooled = "example@192.0.2.1:53,192.0.2.2:53@lowest-latency"
+            .parse::<ForwardRule>()
+            .unwrap();
+        assert_eq!(pooled.strategy, Strategy::LowestLatency);
+        assert!("corp.internal".parse::<ForwardRule>().is_err());
+        assert!("corp.internal@10.0.0.53".parse::<ForwardRule>().is_err());
+        assert!("corp.internal@10.0.0.53:53@often"
+            .parse::<ForwardRule>()
+            .is_err());
+
+        let mut resolver = Resolver::new("192.0.2.53:53".parse().unwrap(), None);
+        resolver.forwarders.push(rule);
+        resolver
+            .forwarders
+            .push("lab.corp.internal@10.0.1.53:53".parse().unwrap());
+        let domains = |name: &str| {
+            let labels = name.split('.').map(String::from).collect::<Vec<_>>();
+            let rules = resolver.rules_for(&labels);
+            rules.iter().map(|r| r.domain.join(".")).collect::<Vec<_>>()
+        };
+        assert_eq!(domains("www.example"), [""]);
+        assert_eq!(domains("corp.internal"), ["Corp.Internal"]);
+        // A rule without forward-only falls back to the enclosing ones.
+        assert_eq!(
+            domains("host.LAB.corp.internal"),
+            ["lab.corp.internal", "Corp.Internal"]
+        );
+        assert_eq!(domains("notcorp.internal"), [""]);
+    }
+
+    #[test]
+    fn cache_test() {
+        let upstream = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let address = upstream.local_addr().unwrap();
+        thread::spawn(move || {
+            let mut buf = [0; 512];
+            while let Ok((size, source)) = upstream.recv_from(&mut buf) {
+                let request = DnsMessage::parse(&buf[..size]).unwrap();
+                let response = upstream_response(&request);
+                upstream.send_to(&response.as_bytes(), source).unwrap();
+            }
+        });
+
+        let mut resolver = Resolver::new(address, None);
+        let request = DnsMessage::query(name(), QType::A);
+        let response = resolver.resolve(&request, 1000);
+        assert_eq!(response.answers[0].ttl, 60);
+        assert_eq!(resolver.cache.lock().unwrap().entries.len(), 1);
+
+        // The same question is answered from the cache, under its own ID.
+        let mut request =
+            DnsMessage::query(vec!["WWW".to_string(), "example".to_string()], QType::A);
+        request.header.packet_id = 7;
+        let response = resolver.resolve(&request, 1030);
+        assert_eq!(response.header.packet_id, 7);
+        assert_eq!(response.questions, request.questions);
+        assert_eq!(response.answers.len(), 1);
+        assert_eq!(response.answers[0].ttl, 30);
+        let key = AnswerCache::key(&request);
+        assert!(resolver.cache.lock().unwrap().get(&key, 1060).is_none());
+
+        resolver.cache = Mutex::default();
+        resolver.forwarders[0].cache = false;
+        let response = resolver.resolve(&request, 1000);
+        assert_eq!(response.answers[0].ttl, 60);
+        assert!(resolver.cache.lock().unwrap().entries.is_empty());
+    }
+
+    #[test]
+    fn failover_test() {
+        let failing = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let working = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let upstreams = [failing.local_addr().unwrap(), working.local_addr().unwrap()];
+        for (upstream, rcode) in [
+            (failing, ResponseCode::ServerFailure),
+            (working, ResponseCode::NoErrorCondition),
+        ] {
+            thread::spawn(move || {
+                let mut buf = [0; 512];
+                while let Ok((size, source)) = upstream.recv_from(&mut buf) {
+                    let request = DnsMessage::parse(&buf[..size]).unwrap();
+                    let mut response = upstream_response(&request);
+                    response.header.rcode = rcode;
+                    upstream.send_to(&response.as_bytes(), source).unwrap();
+                }
+            });
+        }
+
+        let rule = ForwardRule::new(Vec::new(), upstreams.to_vec());
+        let resolver = Resolver::with_forwarders(vec![rule], None);
+        let request = DnsMessage::query(name(), QType::A);
+        let response = resolver.resolve(&request, 0);
+        assert_eq!(response.header.rcode, ResponseCode::NoErrorCondition);
+        let health = resolver.health.lock().unwrap();
+        assert_eq!(health.state(upstreams[0]).failures, 1);
+        assert!(health.state(upstreams[1]).srtt.is_some());
+        drop(health);
+
+        resolver.probe();
+        let health = resolver.health.lock().unwrap();
+        assert_eq!(health.state(upstreams[0]).failures, 2);
+        assert_eq!(health.state(upstreams[1]).failures, 0);
+    }
+
+    #[test]
+    fn service_additionals_test() {
+        let labels = |text: &str| text.split('.').map(String::from).collect::<Vec<_>>();
+        let https = |owner: &str, priority, target: &str| DnsRecord {
+            name: labels(owner),
+            typ: QType::HTTPS,
+            class: QClass::IN,
+            ttl: 300,
+            rdata: RData::HTTPS(Svcb {
+                priority,
+                target: labels(target)
+                    .into_iter()
+                    .filter(|x| !x.is_empty())
+                    .collect(),
+                params: Vec::new(),
+            }),
+        };
+        let a = |owner: &str| DnsRecord {
+            name: labels(owner),
+            typ: QType::A,
+            class: QClass::IN,
+            ttl: 300,
+            rdata: RData::A(Ipv4Addr::new(192, 0, 2, 1)),
+        };
+
+        let mut response = DnsMessage::query(labels("example.com"), QType::HTTPS);
+        response
+            .answers
+            .push(https("example.com", 0, "svc.example.net"));
+        response.to_response();
+        let mut queries = Vec::new();
+        let mut lookup = |name: &[String], typ: QType| {
+            queries.push((name.join("."), typ));
+            let mut answer = DnsMessage::query(name.to_vec(), typ);
+            match (name.join(".").as_str(), typ) {
+                ("svc.example.net", QType::HTTPS) => {
+                    answer
+                        .answers
+                        .push(https("svc.example.net", 1, "pool.example.net"));
+                    answer.answers.push(https("svc.example.net", 2, ""));
+                }
+                (host, QType::A) => answer.answers.push(a(host)),
+                _ => {}
+            }
+            Ok(answer)
+        };
+        let additionals = service_additionals(&response, &mut lookup);
+        let owners = additionals
+            .iter()
+            .map(|r| (r.name.join("."), r.typ))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            owners,
+            vec![
+                ("svc.example.net".to_string(), QType::HTTPS),
+                ("svc.example.net".to_string(), QType::HTTPS),
+                ("pool.example.net".to_string(), QType::A),
+                ("svc.example.net".to_string(), QType::A),
+            ]
+        );
+        assert_eq!(queries.len(), 5);
+
+        // An alias loop stops, and other types are left alone.
+        let mut response = DnsMessage::query(labels("example.com"), QType::HTTPS);
+        response
+            .answers
+            .push(https("example.com", 0, "example.com"));
+        let mut lookup = |_: &[String], _: QType| -> anyhow::Result<DnsMessage> {
+            anyhow::bail!("unexpected lookup")
+        };
+        assert!(service_additionals(&response, &mut lookup).is_empty());
+        let response = DnsMessage::query(labels("example.com"), QType::A);
+        assert!(service_additionals(&response, &mut lookup).is_empty());
+    }
+}
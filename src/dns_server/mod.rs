@@ -0,0 +1,2 @@
+pub mod server;
+pub(crate) mod server_impl;
@@ -0,0 +1,77 @@
+use crate::{
+    dns_message::tsig::TsigKeyring,
+    dns_resolver::resolver::{ForwardRule, Resolver},
+    dns_zone::{secondary::SecondaryZone, transfer::TransferAcl, zone::ZoneStore},
+    dnssec::{
+        key_manager::KeyManager,
+        signer::{Denial, ZoneKey, ZoneSigner},
+        validator::Validator,
+    },
+};
+use std::{net::SocketAddr, path::PathBuf, sync::Mutex};
+
+/// What a [`Server`] serves, and where it forwards the queries it is not
+/// authoritative for.
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    /// Zones served as a primary, accepting dynamic updates signed by a key of
+    /// the keyring.
+    pub zones: ZoneStore,
+    /// Keys updates, transfers and queries can be signed with.
+    pub keyring: TsigKeyring,
+    pub transfer_acl: TransferAcl,
+    pub secondaries: Vec<SecondaryConfig>,
+    /// Where secondary zones are persisted between restarts.
+    pub zone_dir: PathBuf,
+    /// Secondaries notified of every zone change, besides the NS hosts of the
+    /// zone.
+    pub also_notify: Vec<SocketAddr>,
+    /// How queries outside the served zones are forwarded, not at all when
+    /// empty.
+    pub forwarders: Vec<ForwardRule>,
+    /// Validates forwarded answers with DNSSEC, when given.
+    pub validator: Option<Validator>,
+    /// Keys signing the zones they belong to.
+    pub zone_keys: Vec<ZoneKey>,
+    /// How nonexistence is proven in signed zones.
+    pub denial: Denial,
+    /// Zones signed with keys generated and rolled over automatically, which
+    /// replace their `zone_keys`.
+    pub managed_zones: Vec<Vec<String>>,
+    /// Where the keys of managed zones are kept.
+    pub key_dir: PathBuf,
+}
+
+/// A zone copied from its primary with zone transfers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecondaryConfig {
+    pub origin: Vec<String>,
+    pub primary: SocketAddr,
+    /// The name of the key of the keyring signing transfers, when the primary
+    /// requires one.
+    pub key_name: Option<Vec<String>>,
+}
+
+/// An authoritative server and forwarder answering over UDP and TCP, with the
+/// state its listeners share.
+#[derive(Debug)]
+pub struct Server {
+    pub(crate) zones: Mutex<ZoneStore>,
+    pub(crate) keyring: TsigKeyring,
+    pub(crate) transfer_acl: TransferAcl,
+    pub(crate) secondaries: Mutex<Vec<SecondaryZone>>,
+    pub(crate) also_notify: Vec<SocketAddr>,
+    /// Where queries outside the served zones are forwarded, if anywhere.
+    pub(crate) resolver: Option<Resolver>,
+    /// Signers of the zones answered with DNSSEC records.
+    pub(crate) signers: Mutex<Vec<ZoneSigner>>,
+    /// Keys of signed zones rolled over automatically.
+    pub(crate) key_managers: Mutex<Vec<KeyManager>>,
+}
+
+/// How a request reached the server, which decides what fits in the response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transport {
+    Udp,
+    Tcp,
+}
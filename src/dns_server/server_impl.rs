@@ -0,0 +1,678 @@
+use super::server::{SecondaryConfig, Server, ServerConfig, Transport};
+use crate::{
+    dns_header::header::{AuthoritativeAnswer, OpCode, ResponseCode, Truncated},
+    dns_message::{
+        message::DnsMessage,
+        message_ref::MessageRef,
+        tsig::{TsigKeyring, TsigSession},
+    },
+    dns_name::name::{name_to_string, names_equal},
+    dns_record::record::{DnsRecord, Opt, RData},
+    dns_resolver::{resolver::Resolver, resolver_impl::EDNS_PAYLOAD_SIZE},
+    dns_zone::{
+        notifier::Notifier,
+        secondary::SecondaryZone,
+        secondary_impl::{check_due, notify},
+        transfer::TransferAcl,
+        zone::ZoneStore,
+    },
+    dnssec::{
+        key_manager::{KeyManager, KeyPolicy},
+        signer::{Denial, ZoneSigner},
+        zone_signing_impl::add_presigned_records,
+    },
+    resrec::{QClass, QType},
+    tcp,
+};
+use anyhow::{ensure, Context};
+use std::{
+    io,
+    net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Largest message sent over UDP to clients without EDNS.
+const UDP_MESSAGE_SIZE: usize = 512;
+
+/// Largest message received over UDP, whatever payload size the client
+/// advertises, so that large queries such as signed updates are not cut short.
+const UDP_RECEIVE_SIZE: usize = 65_535;
+
+/// How long an idle TCP connection is kept open.
+const TCP_IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How often secondary zones are checked for a due refresh, and changed zones
+/// for NOTIFY messages to send.
+const SECONDARY_TICK: Duration = Duration::from_secs(1);
+
+/// How often managed keys are checked for a due rollover step.
+const KEY_ROLLOVER_TICK: Duration = Duration::from_secs(60);
+
+/// How often upstream servers are probed for their health.
+const UPSTREAM_PROBE_TICK: Duration = Duration::from_secs(30);
+
+impl Default for ServerConfig {
+    /// No zones and no forwarding, with secondary zones and managed keys kept in
+    /// the working directory.
+    fn default() -> Self {
+        ServerConfig {
+            zones: ZoneStore::default(),
+            keyring: TsigKeyring::default(),
+            transfer_acl: TransferAcl::default(),
+            secondaries: Vec::new(),
+            zone_dir: PathBuf::from("."),
+            also_notify: Vec::new(),
+            forwarders: Vec::new(),
+            validator: None,
+            zone_keys: Vec::new(),
+            denial: Denial::Nsec,
+            managed_zones: Vec::new(),
+            key_dir: PathBuf::from("."),
+        }
+    }
+}
+
+impl Server {
+    /// Loads the secondary zones and the managed keys, and signs the zones
+    /// with keys. Fails when a key belongs to a zone not served, or a secondary
+    /// zone names a key not in the keyring.
+    pub fn new(config: ServerConfig) -> anyhow::Result<Self> {
+        let ServerConfig {
+            zones: mut store,
+            keyring,
+            transfer_acl,
+            secondaries,
+            zone_dir,
+            also_notify,
+            forwarders,
+            validator,
+            zone_keys,
+            denial,
+            managed_zones,
+            key_dir,
+        } = config;
+
+        let secondaries = secondaries
+            .into_iter()
+            .map(|secondary| load_secondary(secondary, &zone_dir, &keyring, &mut store))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let resolver =
+            (!forwarders.is_empty()).then(|| Resolver::with_forwarders(forwarders, validator));
+
+        let mut signers: Vec<ZoneSigner> = Vec::new();
+        for key in zone_keys {
+            match signers
+                .iter_mut()
+                .find(|s| names_equal(&s.origin, &key.owner))
+            {
+                Some(signer) => signer.keys.push(key),
+                None => signers.push(ZoneSigner::new(
+                    key.owner.clone(),
+                    vec![key],
+                    denial.clone(),
+                )),
+            }
+        }
+        for signer in &signers {
+            let zone = store.find_mut(&signer.origin).with_context(|| {
+                format!(
+                    "DNSSEC key for {}, a zone not served",
+                    name_to_string(&signer.origin)
+                )
+            })?;
+            signer.publish(zone);
+        }
+
+        let now = unix_time();
+        let mut key_managers = Vec::new();
+        for origin in managed_zones {
+            let mut manager = KeyManager::load(origin, key_dir.clone(), KeyPolicy::default())
+                .context("cannot manage keys")?;
+            manager.tick(now).context("cannot manage keys")?;
+            ensure!(
+                store.find(&manager.origin).is_some(),
+                "managed keys for {}, a zone not served",
+                name_to_string(&manager.origin)
+            );
+            let index = match signers
+                .iter()
+                .position(|s| names_equal(&s.origin, &manager.origin))
+            {
+                Some(index) => index,
+                None => {
+                    signers.push(ZoneSigner::new(
+                        manager.origin.clone(),
+                        vec![],
+                        denial.clone(),
+                    ));
+                    signers.len() - 1
+                }
+            };
+            manager.apply(&mut signers[index], &mut store);
+            log_ds_records(&manager);
+            key_managers.push(manager);
+        }
+
+        Ok(Server {
+            zones: Mutex::new(store),
+            keyring,
+            transfer_acl,
+            secondaries: Mutex::new(secondaries),
+            also_notify,
+            resolver,
+            signers: Mutex::new(signers),
+            key_managers: Mutex::new(key_managers),
+        })
+    }
+
+    /// Answers UDP and TCP requests on `address`, refreshing secondary zones,
+    /// notifying secondaries, rolling keys over and probing upstream servers in
+    /// the background. Returns only when the UDP socket fails.
+    pub fn run(self, address: impl ToSocketAddrs) -> io::Result<()> {
+        let server = Arc::new(self);
+        let udp_socket = UdpSocket::bind(address)?;
+        let tcp_listener = TcpListener::bind(udp_socket.local_addr()?)?;
+        let mut notifier = Notifier::new(server.also_notify.clone())?;
+
+        let tcp_server = server.clone();
+        thread::spawn(move || serve_tcp(tcp_listener, tcp_server));
+        let refresh_server = server.clone();
+        thread::spawn(move || loop {
+            check_due(
+                &refresh_server.secondaries,
+                &refresh_server.zones,
+                unix_time(),
+            );
+            thread::sleep(SECONDARY_TICK);
+        });
+        let notify_server = server.clone();
+        thread::spawn(move || loop {
+            notifier.tick(&notify_server.zones, unix_time());
+            thread::sleep(SECONDARY_TICK);
+        });
+        let rollover_server = server.clone();
+        thread::spawn(move || loop {
+            rollover_server.roll_keys(unix_time());
+            thread::sleep(KEY_ROLLOVER_TICK);
+        });
+        let probe_server = server.clone();
+        thread::spawn(move || loop {
+            thread::sleep(UPSTREAM_PROBE_TICK);
+            if let Some(resolver) = &probe_server.resolver {
+                resolver.probe();
+            }
+        });
+        serve_udp(udp_socket, &server)
+    }
+
+    /// Builds the responses to a single request: one message, except for zone
+    /// transfers. Requests too short to carry a header are dropped.
+    pub fn handle(
+        &self,
+        request: &[u8],
+        source: SocketAddr,
+        transport: Transport,
+    ) -> Vec<DnsMessage> {
+        let now = unix_time();
+        // The layout is checked once in place, and the copy taken from the view.
+        let Some(view) = MessageRef::new(request) else {
+            return DnsMessage::format_error(request).into_iter().collect();
+        };
+        let (Some(mut message), Ok(mut tsig)) =
+            (view.to_message(), self.keyring.verify(request, now))
+        else {
+            return DnsMessage::format_error(request).into_iter().collect();
+        };
+        if tsig.is_some() {
+            message.additionals.pop();
+        }
+        let transfer_type = match &message.questions[..] {
+            [q] if matches!(q.qtype, QType::AXFR | QType::IXFR) => Some(q.qtype),
+            _ => None,
+        };
+
+        let mut responses = match (message.header.opcode, &tsig) {
+            (
+                _,
+                Some(TsigSession {
+                    key_name,
+                    error: Some(error),
+                    ..
+                }),
+            ) => {
+                log::warn!("Rejected TSIG from {:?}: {:?}", key_name, error);
+                message.to_bare_response(error.rcode());
+                vec![message]
+            }
+            // Only updates signed by a key of the keyring are accepted, so none
+            // are without keys.
+            (OpCode::Update, None) => {
+                message.to_bare_response(ResponseCode::Refused);
+                vec![message]
+            }
+            // Secondary zones only change through transfers from their primary.
+            (OpCode::Update, _) if self.is_secondary(&message) => {
+                message.to_bare_response(ResponseCode::NotAuth);
+                vec![message]
+            }
+            (OpCode::Update, _) => {
+                let rcode = self.zones.lock().unwrap().apply_update(&message);
+                message.to_bare_response(rcode);
+                vec![message]
+            }
+            (OpCode::Query, _) if transfer_type.is_some() => {
+                // AXFR is refused over UDP, IXFR answers there when it fits.
+                let allowed = (transport == Transport::Tcp || transfer_type == Some(QType::IXFR))
+                    && self.transfer_acl.allows(source.ip(), tsig.as_ref());
+                let transfer = if allowed {
+                    let zones = self.zones.lock().unwrap();
+                    match (transfer_type, transport) {
+                        (Some(QType::AXFR), _) => zones.axfr(&message),
+                        (_, Transport::Udp) => {
+                            let reserved = tsig.as_ref().map_or(0, TsigSession::record_len);
+                            zones.ixfr(&message, Some(UDP_MESSAGE_SIZE - reserved))
+                        }
+                        (_, Transport::Tcp) => zones.ixfr(&message, None),
+                    }
+                } else {
+                    Err(ResponseCode::Refused)
+                };
+                match transfer {
+                    Ok(messages) => messages,
+                    Err(rcode) => {
+                        message.to_bare_response(rcode);
+                        vec![message]
+                    }
+                }
+            }
+            (OpCode::Notify, _) => {
+                let rcode = notify(&mut self.secondaries.lock().unwrap(), &message, source.ip());
+                message.to_bare_response(rcode);
+                if rcode == ResponseCode::NoErrorCondition {
+                    message.header.aa = AuthoritativeAnswer::Authoritative;
+                }
+                vec![message]
+            }
+            (OpCode::Query, _)
+                if self.authoritative_for(&mut message, transport, tsig.as_ref(), now) =>
+            {
+                vec![message]
+            }
+            (OpCode::Query, _) if self.resolver.is_some() => {
+                let resolver = self.resolver.as_ref().unwrap();
+                let mut response = resolver.resolve(&message, now);
+                if transport == Transport::Udp {
+                    truncate(
+                        &mut response,
+                        udp_payload_limit(message.edns()),
+                        tsig.as_ref(),
+                    );
+                }
+                vec![response]
+            }
+            _ => {
+                message.answers = message
+                    .questions
+                    .iter()
+                    .map(|q| DnsRecord {
+                        name: q.qname.clone(),
+                        typ: QType::A,
+                        class: QClass::IN,
+                        ttl: 60,
+                        rdata: RData::A(Ipv4Addr::new(8, 8, 8, 8)),
+                    })
+                    .collect();
+                message.to_response();
+                vec![message]
+            }
+        };
+
+        if let Some(session) = &mut tsig {
+            for response in responses.iter_mut() {
+                session.sign(response, now);
+            }
+        }
+        responses
+    }
+
+    /// Whether the zone of an UPDATE is served as a secondary.
+    fn is_secondary(&self, message: &DnsMessage) -> bool {
+        let Some(zone) = message.questions.first() else {
+            return false;
+        };
+        let secondaries = self.secondaries.lock().unwrap();
+        secondaries
+            .iter()
+            .any(|s| names_equal(&s.origin, &zone.qname))
+    }
+
+    /// Answers a query from the zone its name belongs to, returning false when
+    /// the server is not authoritative for it. Responses of signed zones carry
+    /// DNSSEC records when the query sets the DO bit, made on the fly or taken
+    /// from a zone signed by `dns-sign`.
+    fn authoritative_for(
+        &self,
+        message: &mut DnsMessage,
+        transport: Transport,
+        tsig: Option<&TsigSession>,
+        now: u64,
+    ) -> bool {
+        let zones = self.zones.lock().unwrap();
+        let Some(zone) = message
+            .questions
+            .first()
+            .and_then(|q| zones.authoritative_for(&q.qname))
+        else {
+            return false;
+        };
+        let edns = message.edns().cloned();
+        zone.answer(message);
+        if let Some(edns) = &edns {
+            if edns.dnssec_ok {
+                let mut signers = self.signers.lock().unwrap();
+                match signers.iter_mut().find(|s| zone.is_apex(&s.origin)) {
+                    Some(signer) => signer.sign_response(zone, message, now),
+                    None => add_presigned_records(zone, message),
+                }
+            }
+            message.set_edns(Opt::new(EDNS_PAYLOAD_SIZE, edns.dnssec_ok));
+        }
+        if transport == Transport::Udp {
+            truncate(message, udp_payload_limit(edns.as_ref()), tsig);
+        }
+        true
+    }
+
+    /// Moves the key rollovers of managed zones along, handing new keys to
+    /// their signers and publishing the DNSKEY changes.
+    fn roll_keys(&self, now: u64) {
+        let mut managers = self.key_managers.lock().unwrap();
+        for manager in managers.iter_mut() {
+            match manager.tick(now) {
+                Ok(true) => {
+                    let mut zones = self.zones.lock().unwrap();
+                    let mut signers = self.signers.lock().unwrap();
+                    if let Some(signer) = signers
+                        .iter_mut()
+                        .find(|s| names_equal(&s.origin, &manager.origin))
+                    {
+                        manager.apply(signer, &mut zones);
+                    }
+                    log_ds_records(manager);
+                }
+                Ok(false) => {}
+                Err(e) => log::error!(
+                    "Key rollover of {} failed: {:#}",
+                    name_to_string(&manager.origin),
+                    e
+                ),
+            }
+        }
+    }
+}
+
+/// The secondary zone of `config`, with the copy persisted in `zone_dir`
+/// loaded into `store`.
+fn load_secondary(
+    config: SecondaryConfig,
+    zone_dir: &std::path::Path,
+    keyring: &TsigKeyring,
+    store: &mut ZoneStore,
+) -> anyhow::Result<SecondaryZone> {
+    let file = zone_dir.join(format!("{}.db", config.origin.join(".")));
+    let mut secondary = SecondaryZone::new(config.origin, config.primary, file);
+    if let Some(name) = config.key_name {
+        let key = keyring.keys.iter().find(|k| names_equal(&k.name, &name));
+        let key = key.with_context(|| {
+            format!(
+                "unknown TSIG key {} for secondary zone {}",
+                name_to_string(&name),
+                name_to_string(&secondary.origin)
+            )
+        })?;
+        secondary.key = Some(key.clone());
+    }
+    secondary.load(store);
+    Ok(secondary)
+}
+
+fn serve_udp(udp_socket: UdpSocket, server: &Server) -> io::Result<()> {
+    let mut buf = vec![0; UDP_RECEIVE_SIZE];
+    let mut out = [0; EDNS_PAYLOAD_SIZE as usize];
+
+    loop {
+        let (size, source) = udp_socket.recv_from(&mut buf)?;
+        log::debug!("Received {} bytes from {}", size, source);
+        let responses = server.handle(&buf[..size], source, Transport::Udp);
+        let Some(message) = responses.first() else {
+            continue;
+        };
+
+        let size = match message.encode_into(&mut &mut out[..]) {
+            Ok(size) => size,
+            Err(e) => {
+                log::warn!("Dropping response to {}: {}", source, e);
+                continue;
+            }
+        };
+        if let Err(e) = udp_socket.send_to(&out[..size], source) {
+            log::warn!("Error sending response to {}: {}", source, e);
+        }
+    }
+}
+
+fn serve_tcp(tcp_listener: TcpListener, server: Arc<Server>) {
+    for stream in tcp_listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let server = server.clone();
+                thread::spawn(move || {
+                    if let Err(e) = serve_tcp_connection(stream, &server) {
+                        log::warn!("Error on TCP connection: {}", e);
+                    }
+                });
+            }
+            Err(e) => log::error!("Error accepting connection: {}", e),
+        }
+    }
+}
+
+/// Answers length-prefixed messages on a TCP connection until the client closes it.
+fn serve_tcp_connection(mut stream: TcpStream, server: &Server) -> io::Result<()> {
+    let source = stream.peer_addr()?;
+    stream.set_read_timeout(Some(TCP_IDLE_TIMEOUT))?;
+
+    loop {
+        let Ok(request) = tcp::read_message(&mut stream) else {
+            return Ok(());
+        };
+        log::debug!("Received {} bytes from {} over TCP", request.len(), source);
+
+        for message in server.handle(&request, source, Transport::Tcp) {
+            tcp::write_message(&mut stream, &message.as_bytes())?;
+        }
+    }
+}
+
+/// Logs the DS records of a zone whose keys changed, to be published by its
+/// parent.
+fn log_ds_records(manager: &KeyManager) {
+    log::info!(
+        "DS records of {} for its parent:",
+        name_to_string(&manager.origin)
+    );
+    for ds in manager.ds_records() {
+        log::info!("{}", ds);
+    }
+}
+
+/// The largest UDP response a client accepts: 512 bytes, or the payload size of
+/// its OPT record within what we send without fragmentation.
+fn udp_payload_limit(edns: Option<&Opt>) -> usize {
+    edns.map_or(UDP_MESSAGE_SIZE, |x| {
+        usize::from(
+            x.udp_payload_size
+                .clamp(UDP_MESSAGE_SIZE as u16, EDNS_PAYLOAD_SIZE),
+        )
+    })
+}
+
+/// Drops the additional data of a response too large for a UDP datagram of
+/// `limit` bytes, keeping its OPT record. When that is not enough, empties the
+/// Answer and Authority sections too and sets TC, so the client retries over TCP
+/// (RFC 2181 section 9). Room is left for the TSIG record `tsig` signs the
+/// response with afterwards.
+fn truncate(message: &mut DnsMessage, limit: usize, tsig: Option<&TsigSession>) {
+    let limit = limit - tsig.map_or(0, TsigSession::record_len);
+    if message.encoded_len() > limit {
+        message.additionals.retain(|r| r.typ == QType::OPT);
+        message.update_counts();
+    }
+    if message.encoded_len() > limit {
+        message.answers.clear();
+        message.authorities.clear();
+        message.header.tc = Truncated::Truncated;
+        message.update_counts();
+    }
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |x| x.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::truncate;
+    use crate::{
+        dns_header::header::{AuthoritativeAnswer, OpCode, ResponseCode, Truncated},
+        dns_message::{builder::MessageBuilder, tsig::TsigKey},
+        dns_record::record::{DnsRecord, RData, Txt},
+        dns_server::server::{SecondaryConfig, Server, ServerConfig, Transport},
+        dns_zone::zone::Zone,
+        resrec::{QClass, QType},
+    };
+    use std::net::{Ipv4Addr, SocketAddr};
+
+    const ZONE: &str = r#"
+$ORIGIN example.com.
+$TTL 1h
+@   IN  SOA ns hostmaster 1 3h 15m 1w 300
+    IN  NS  ns
+ns      A   192.0.2.1
+"#;
+
+    fn name(text: &str) -> Vec<String> {
+        text.split('.').map(String::from).collect()
+    }
+
+    fn server(mut config: ServerConfig) -> Server {
+        let zone = Zone::from_master_file(ZONE, None).unwrap();
+        config.zones.zones.push(zone);
+        Server::new(config).unwrap()
+    }
+
+    fn client() -> SocketAddr {
+        "192.0.2.53:5353".parse().unwrap()
+    }
+
+    #[test]
+    fn handle_query_test() {
+        let server = server(ServerConfig::default());
+        let query = MessageBuilder::query(name("ns.example.com"), QType::A).build();
+        let responses = server.handle(&query.as_bytes(), client(), Transport::Udp);
+        assert_eq!(responses.len(), 1);
+        let response = &responses[0];
+        assert_eq!(response.header.packet_id, query.header.packet_id);
+        assert_eq!(response.header.aa, AuthoritativeAnswer::Authoritative);
+        assert_eq!(response.answers.len(), 1);
+        assert_eq!(
+            response.answers[0].rdata,
+            RData::A(Ipv4Addr::new(192, 0, 2, 1))
+        );
+
+        // A header followed by a question cut short.
+        let bytes = query.as_bytes();
+        let responses = server.handle(&bytes[..bytes.len() - 2], client(), Transport::Udp);
+        assert_eq!(responses[0].header.rcode, ResponseCode::FormatError);
+        assert!(server
+            .handle(&bytes[..5], client(), Transport::Udp)
+            .is_empty());
+    }
+
+    #[test]
+    fn unsigned_update_test() {
+        let update = MessageBuilder::query(name("example.com"), QType::SOA)
+            .opcode(OpCode::Update)
+            .build();
+
+        // Refused without keys as well as with them.
+        let keyless = server(ServerConfig::default());
+        let responses = keyless.handle(&update.as_bytes(), client(), Transport::Tcp);
+        assert_eq!(responses[0].header.rcode, ResponseCode::Refused);
+
+        let mut config = ServerConfig::default();
+        let key = "update.example:MDEyMzQ1Njc4OWFiY2RlZg==".parse::<TsigKey>();
+        config.keyring.keys.push(key.unwrap());
+        let server = server(config);
+        let responses = server.handle(&update.as_bytes(), client(), Transport::Tcp);
+        assert_eq!(responses[0].header.rcode, ResponseCode::Refused);
+    }
+
+    #[test]
+    fn config_errors_test() {
+        let mut config = ServerConfig::default();
+        config.secondaries.push(SecondaryConfig {
+            origin: name("example.net"),
+            primary: "192.0.2.2:53".parse().unwrap(),
+            key_name: Some(name("missing")),
+        });
+        let error = Server::new(config).unwrap_err().to_string();
+        assert_eq!(
+            error,
+            "unknown TSIG key missing. for secondary zone example.net."
+        );
+    }
+
+    #[test]
+    fn truncate_test() {
+        let query = MessageBuilder::query(name("example.com"), QType::TXT).build();
+        let record = |text: &[u8]| DnsRecord {
+            name: name("example.com"),
+            typ: QType::TXT,
+            class: QClass::IN,
+            ttl: 60,
+            rdata: RData::TXT(Txt::new(text)),
+        };
+        let response = MessageBuilder::response_to(&query)
+            .answer(record(&[b'a'; 200]))
+            .additional(record(&[b'b'; 400]))
+            .build();
+
+        let mut message = response.clone();
+        truncate(&mut message, 512, None);
+        assert!(message.additionals.is_empty());
+        assert_eq!(message.answers.len(), 1);
+        assert_eq!(message.header.tc, Truncated::NotTruncated);
+
+        let mut emptied = response.clone();
+        truncate(&mut emptied, 200, None);
+        assert!(emptied.answers.is_empty());
+        assert_eq!(emptied.header.tc, Truncated::Truncated);
+        assert_eq!(emptied.header.ancount, 0);
+
+        // The answer fits, but not with the TSIG record signing it.
+        let key = "update.example:MDEyMzQ1Njc4OWFiY2RlZg==".parse::<TsigKey>();
+        let mut session = key.unwrap().session();
+        let limit = message.encoded_len() + 1;
+        let mut signed = response;
+        truncate(&mut signed, limit, Some(&session));
+        assert_eq!(signed.header.tc, Truncated::Truncated);
+        session.sign(&mut signed, 0);
+        assert!(signed.encoded_len() <= limit);
+    }
+}
@@ -0,0 +1,20 @@
+use crate::dns_record::record::DnsRecord;
+
+/// The changes between two consecutive versions of a zone, in the shape IXFR
+/// (RFC 1995) sends them: the old SOA, the records deleted, the new SOA and the
+/// records added.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZoneDiff {
+    pub old_soa: DnsRecord,
+    /// Records deleted from the zone, excluding the SOA.
+    pub deleted: Vec<DnsRecord>,
+    pub new_soa: DnsRecord,
+    /// Records added to the zone, excluding the SOA.
+    pub added: Vec<DnsRecord>,
+}
+
+/// The most recent changes of a zone, oldest first, used to answer IXFR queries.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Journal {
+    pub diffs: Vec<ZoneDiff>,
+}
@@ -0,0 +1,202 @@
+use super::journal::{Journal, ZoneDiff};
+use crate::{
+    dns_record::record::{DnsRecord, RData},
+    resrec::QType,
+};
+
+/// Number of diffs kept per zone. Clients further behind get a full AXFR.
+const MAX_JOURNAL_DIFFS: usize = 100;
+
+impl ZoneDiff {
+    /// The changes turning the `old` records of a zone into the `new` ones. `None`
+    /// when either version lacks an SOA record.
+    pub fn between(old: &[DnsRecord], new: &[DnsRecord]) -> Option<Self> {
+        let soa = |records: &[DnsRecord]| records.iter().find(|r| r.typ == QType::SOA).cloned();
+        let difference = |a: &[DnsRecord], b: &[DnsRecord]| {
+            a.iter()
+                .filter(|r| r.typ != QType::SOA && !b.contains(r))
+                .cloned()
+                .collect()
+        };
+        Some(ZoneDiff {
+            old_soa: soa(old)?,
+            deleted: difference(old, new),
+            new_soa: soa(new)?,
+            added: difference(new, old),
+        })
+    }
+
+    pub fn old_serial(&self) -> u32 {
+        soa_serial(&self.old_soa)
+    }
+
+    pub fn new_serial(&self) -> u32 {
+        soa_serial(&self.new_soa)
+    }
+
+    /// The records of this diff in IXFR order.
+    pub fn ixfr_records(&self) -> Vec<DnsRecord> {
+        let mut records = vec![self.old_soa.clone()];
+        records.extend(self.deleted.iter().cloned());
+        records.push(self.new_soa.clone());
+        records.extend(self.added.iter().cloned());
+        records
+    }
+}
+
+impl Journal {
+    /// Appends a diff, forgetting the oldest one once the journal is full.
+    pub fn record(&mut self, diff: ZoneDiff) {
+        self.diffs.push(diff);
+        if self.diffs.len() > MAX_JOURNAL_DIFFS {
+            self.diffs.remove(0);
+        }
+    }
+
+    /// The diffs leading from `serial` to the latest version, `None` when the
+    /// journal does not reach back to `serial` or has a gap since.
+    pub fn since(&self, serial: u32) -> Option<&[ZoneDiff]> {
+        let start = self.diffs.iter().position(|d| d.old_serial() == serial)?;
+        let diffs = &self.diffs[start..];
+        let contiguous = diffs
+            .windows(2)
+            .all(|x| x[0].new_serial() == x[1].old_serial());
+        contiguous.then_some(diffs)
+    }
+
+    /// The diffs since `serial` condensed into one, so a record added and deleted
+    /// again in between is not sent at all.
+    pub fn condensed_since(&self, serial: u32) -> Option<ZoneDiff> {
+        let diffs = self.since(serial)?;
+        let mut condensed = ZoneDiff {
+            old_soa: diffs.first()?.old_soa.clone(),
+            deleted: Vec::new(),
+            new_soa: diffs.last()?.new_soa.clone(),
+            added: Vec::new(),
+        };
+        for diff in diffs {
+            for record in &diff.deleted {
+                match condensed.added.iter().position(|r| r == record) {
+                    Some(i) => {
+                        condensed.added.remove(i);
+                    }
+                    None => condensed.deleted.push(record.clone()),
+                }
+            }
+            for record in &diff.added {
+                match condensed.deleted.iter().position(|r| r == record) {
+                    Some(i) => {
+                        condensed.deleted.remove(i);
+                    }
+                    None => condensed.added.push(record.clone()),
+                }
+            }
+        }
+        Some(condensed)
+    }
+}
+
+fn soa_serial(record: &DnsRecord) -> u32 {
+    match &record.rdata {
+        RData::SOA(soa) => soa.serial,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        dns_record::record::{DnsRecord, RData},
+        dns_zone::{
+            journal::{Journal, ZoneDiff},
+            zone::Zone,
+        },
+        resrec::{QClass, QType},
+    };
+    use std::net::Ipv4Addr;
+
+    fn a(host: &str, last: u8) -> DnsRecord {
+        DnsRecord {
+            name: vec![host.to_string(), "example".to_string(), "com".to_string()],
+            typ: QType::A,
+            class: QClass::IN,
+            ttl: 60,
+            rdata: RData::A(Ipv4Addr::new(10, 0, 0, last)),
+        }
+    }
+
+    /// Versions 1 to 4 of a zone: www is added, ftp is added, then www removed.
+    fn versions() -> Vec<Vec<DnsRecord>> {
+        let mut zone = Zone::with_default_soa(vec!["example".to_string(), "com".to_string()]);
+        let mut versions = vec![zone.records.clone()];
+        for change in [Some(a("www", 1)), Some(a("ftp", 2)), None] {
+            match change {
+                Some(record) => zone.records.push(record),
+                None => zone.records.retain(|r| r.name[0] != "www"),
+            }
+            zone.set_serial(zone.serial().unwrap() + 1);
+            versions.push(zone.records.clone());
+        }
+        versions
+    }
+
+    fn journal() -> Journal {
+        let versions = versions();
+        let mut journal = Journal::default();
+        for pair in versions.windows(2) {
+            journal.record(ZoneDiff::between(&pair[0], &pair[1]).unwrap());
+        }
+        journal
+    }
+
+    #[test]
+    fn between_test() {
+        let versions = versions();
+        let diff = ZoneDiff::between(&versions[0], &versions[1]).unwrap();
+        assert_eq!(diff.old_serial(), 1);
+        assert_eq!(diff.new_serial(), 2);
+        assert!(diff.deleted.is_empty());
+        assert_eq!(diff.added, vec![a("www", 1)]);
+
+        let types = diff
+            .ixfr_records()
+            .iter()
+            .map(|r| r.typ)
+            .collect::<Vec<_>>();
+        assert_eq!(types, vec![QType::SOA, QType::SOA, QType::A]);
+    }
+
+    #[test]
+    fn since_test() {
+        let journal = journal();
+        assert_eq!(journal.since(1).map(|d| d.len()), Some(3));
+        assert_eq!(journal.since(3).map(|d| d.len()), Some(1));
+        assert!(journal.since(4).is_none());
+        assert!(journal.since(0).is_none());
+    }
+
+    #[test]
+    fn condensed_since_test() {
+        let journal = journal();
+        let condensed = journal.condensed_since(1).unwrap();
+        assert_eq!(condensed.old_serial(), 1);
+        assert_eq!(condensed.new_serial(), 4);
+        assert!(condensed.deleted.is_empty());
+        assert_eq!(condensed.added, vec![a("ftp", 2)]);
+
+        let condensed = journal.condensed_since(2).unwrap();
+        assert_eq!(condensed.deleted, vec![a("www", 1)]);
+        assert_eq!(condensed.added, vec![a("ftp", 2)]);
+    }
+
+    #[test]
+    fn journal_is_bounded_test() {
+        let versions = versions();
+        let diff = ZoneDiff::between(&versions[0], &versions[1]).unwrap();
+        let mut journal = Journal::default();
+        for _ in 0..150 {
+            journal.record(diff.clone());
+        }
+        assert_eq!(journal.diffs.len(), 100);
+    }
+}
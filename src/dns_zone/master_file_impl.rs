@@ -0,0 +1,1108 @@
+use super::zone::Zone;
+use crate::{
+    dns_name::name::{name_from_string, name_to_string},
+    dns_question::question::DnsQuestion,
+    dns_record::{
+        record::{
+            Caa, DnsRecord, Dnskey, Ds, Naptr, Nsec, Nsec3, Nsec3Param, RData, Rrsig, Soa, Srv,
+            Sshfp, SvcParam, Svcb, Tlsa, Txt, Uri,
+        },
+        record_impl::{svc_param_key, MAX_CHARACTER_STRING},
+    },
+    encoding::{base32hex_decode, base64_decode, hex_decode, parse_timestamp},
+    resrec::{type_code, QClass, QType},
+};
+use anyhow::{anyhow, bail, ensure, Context};
+use bytes::BufMut;
+use std::str::FromStr;
+
+impl Zone {
+    /// Reads a zone from a master file (RFC 1035 section 5.1), with `$ORIGIN` and
+    /// `$TTL` (RFC 2308) directives, `;` comments and parentheses spanning lines.
+    /// The first record must be the SOA, whose owner is the zone apex. Relative
+    /// names before any `$ORIGIN` are relative to `origin`.
+    pub fn from_master_file(text: &str, origin: Option<&[String]>) -> anyhow::Result<Zone> {
+        let mut origin = origin.map(<[String]>::to_vec);
+        let mut default_ttl = None;
+        let mut owner: Option<Vec<String>> = None;
+        let mut records: Vec<DnsRecord> = Vec::new();
+
+        for (number, fields) in entries(text)? {
+            let fields = fields.iter().map(String::as_str).collect::<Vec<_>>();
+            let context = || format!("line {}", number);
+            match fields[..] {
+                ["$ORIGIN", name] => origin = Some(parse_name(name, None).with_context(context)?),
+                ["$TTL", ttl] => default_ttl = Some(parse_ttl(ttl).with_context(context)?),
+                [directive, ..] if directive.starts_with('$') => {
+                    bail!("{}: unsupported directive {}", context(), directive)
+                }
+                _ => {
+                    let ttl = default_ttl.or_else(|| records.last().map(|r| r.ttl));
+                    let record = parse_record(&fields, origin.as_deref(), owner.as_deref(), ttl)
+                        .with_context(context)?;
+                    owner = Some(record.name.clone());
+                    records.push(record);
+                }
+            }
+        }
+
+        let Some(soa) = records.first().filter(|r| r.typ == QType::SOA) else {
+            bail!("the zone does not start with its SOA record");
+        };
+        let zone = Zone {
+            origin: soa.name.clone(),
+            class: soa.class,
+            records: Vec::new(),
+            journal: Default::default(),
+            expired: false,
+        };
+        for record in &records {
+            ensure!(
+                zone.contains(&record.name),
+                "{} is outside the zone {}",
+                name_to_string(&record.name),
+                name_to_string(&zone.origin)
+            );
+            ensure!(
+                record.class == zone.class,
+                "{} is not in class {}",
+                name_to_string(&record.name),
+                zone.class
+            );
+        }
+        let zone = Zone { records, ..zone };
+        Ok(zone)
+    }
+
+    /// Writes the zone as a master file [`Zone::from_master_file`] reads back,
+    /// one record per line with absolute names.
+    pub fn to_master_file(&self) -> String {
+        let mut text = format!("$ORIGIN {}\n", name_to_string(&self.origin));
+        for record in &self.records {
+            text.push_str(&record.to_string());
+            text.push('\n');
+        }
+        text
+    }
+}
+
+/// Reads a record from a master file line with an absolute owner name and a TTL,
+/// as [`DnsRecord`] displays it. Parentheses may span lines.
+impl FromStr for DnsRecord {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let entries = entries(s)?;
+        let [(_, fields)] = &entries[..] else {
+            bail!("expected one record, got {}", entries.len());
+        };
+        let fields = fields.iter().map(String::as_str).collect::<Vec<_>>();
+        parse_record(&fields, None, None, None)
+    }
+}
+
+/// Reads `<name> [<class>] <type>` with an absolute name, as [`DnsQuestion`]
+/// displays it. The class defaults to IN.
+impl FromStr for DnsQuestion {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let entries = entries(s)?;
+        let (qname, qclass, qtype) = match &entries[..] {
+            [(_, fields)] => match &fields[..] {
+                [name, typ] => (name, "IN", typ),
+                [name, class, typ] => (name, class.as_str(), typ),
+                _ => bail!("expected <name> [<class>] <type>, got {}", s),
+            },
+            _ => bail!("expected one question, got {}", entries.len()),
+        };
+        Ok(DnsQuestion {
+            qname: parse_name(qname, None)?,
+            qtype: qtype.parse()?,
+            qclass: qclass.parse()?,
+        })
+    }
+}
+
+/// Splits a master file into entries, each with the number of the line it starts
+/// on and its fields. Parentheses join lines, quoted fields keep their quotes and
+/// escapes are left for the fields to resolve. Entries starting with a blank
+/// reuse the previous owner, marked by an empty first field.
+fn entries(text: &str) -> anyhow::Result<Vec<(usize, Vec<String>)>> {
+    let mut entries = Vec::new();
+    let mut fields: Vec<String> = Vec::new();
+    let mut field: Option<String> = None;
+    let mut start = 1;
+    let mut depth = 0;
+    let mut chars = text.chars().peekable();
+    let mut number = 1;
+    let mut line_start = true;
+
+    while let Some(c) = chars.next() {
+        if line_start && depth == 0 {
+            if !fields.is_empty() {
+                entries.push((start, std::mem::take(&mut fields)));
+            }
+            start = number;
+            if c == ' ' || c == '\t' {
+                fields.push(String::new());
+            }
+        }
+        line_start = false;
+        match c {
+            ';' => while chars.next_if(|&x| x != '\n').is_some() {},
+            '\\' => {
+                let text = field.get_or_insert_with(String::new);
+                text.push(c);
+                text.push(
+                    chars
+                        .next()
+                        .ok_or_else(|| anyhow!("line {}: trailing \\", number))?,
+                );
+            }
+            '"' if field.is_none() => {
+                let mut text = String::from('"');
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => {
+                            text.push('\\');
+                            text.extend(chars.next());
+                        }
+                        Some(x) => {
+                            number += usize::from(x == '\n');
+                            text.push(x);
+                        }
+                        None => bail!("line {}: unterminated quote", number),
+                    }
+                }
+                text.push('"');
+                fields.push(text);
+            }
+            '(' | ')' | ' ' | '\t' | '\r' | '\n' => {
+                fields.extend(field.take());
+                match c {
+                    '(' => depth += 1,
+                    ')' if depth == 0 => bail!("line {}: unbalanced )", number),
+                    ')' => depth -= 1,
+                    '\n' => {
+                        number += 1;
+                        line_start = true;
+                    }
+                    _ => {}
+                }
+            }
+            _ => field.get_or_insert_with(String::new).push(c),
+        }
+    }
+    ensure!(depth == 0, "line {}: unbalanced (", start);
+    fields.extend(field);
+    entries.push((start, fields));
+    // Lines holding nothing but blanks or comments.
+    entries.retain(|(_, fields)| fields.iter().any(|x| !x.is_empty()));
+    Ok(entries)
+}
+
+/// Reads `[<owner>] [<ttl>] [<class>] <type> <rdata>`, the TTL and class in
+/// either order.
+fn parse_record(
+    fields: &[&str],
+    origin: Option<&[String]>,
+    previous_owner: Option<&[String]>,
+    default_ttl: Option<u32>,
+) -> anyhow::Result<DnsRecord> {
+    let name = match fields[0] {
+        "" => previous_owner
+            .ok_or_else(|| anyhow!("no previous owner"))?
+            .to_vec(),
+        owner => parse_name(owner, origin)?,
+    };
+    let mut ttl = None;
+    let mut class = None;
+    let mut rest = fields[1..].iter();
+    let typ = loop {
+        let field = *rest.next().ok_or_else(|| anyhow!("missing type"))?;
+        if ttl.is_none() && field.starts_with(|c: char| c.is_ascii_digit()) {
+            ttl = Some(parse_ttl(field)?);
+            continue;
+        }
+        if class.is_none() {
+            if let Ok(x) = field.parse::<QClass>() {
+                class = Some(x);
+                continue;
+            }
+        }
+        break field.parse::<QType>()?;
+    };
+    let rdata = parse_rdata(typ, rest.as_slice(), origin)?;
+    let ttl = match (ttl.or(default_ttl), &rdata) {
+        (Some(ttl), _) => ttl,
+        // Without $TTL, the SOA minimum is the default (RFC 1035 section 3.3.13).
+        (None, RData::SOA(soa)) => soa.minimum,
+        (None, _) => bail!("missing TTL"),
+    };
+    Ok(DnsRecord {
+        name,
+        typ,
+        class: class.unwrap_or(QClass::IN),
+        ttl,
+        rdata,
+    })
+}
+
+/// Reads RDATA in the presentation format of its type, or the generic
+/// `\# <length> <hex>` (RFC 3597 section 5) for any type.
+fn parse_rdata(typ: QType, fields: &[&str], origin: Option<&[String]>) -> anyhow::Result<RData> {
+    if fields.first() == Some(&"\\#") {
+        return parse_generic_rdata(typ, &fields[1..]);
+    }
+    let field = |index: usize| {
+        fields
+            .get(index)
+            .copied()
+            .ok_or_else(|| anyhow!("missing field {} of {}", index + 1, typ))
+    };
+    let expect_fields = |count: usize| {
+        ensure!(
+            fields.len() == count,
+            "{} takes {} fields, not {}",
+            typ,
+            count,
+            fields.len()
+        );
+        Ok(())
+    };
+    let rest = |from: usize| fields.get(from..).unwrap_or_default().concat();
+
+    let rdata = match typ {
+        QType::A => {
+            expect_fields(1)?;
+            RData::A(field(0)?.parse().context("invalid IPv4 address")?)
+        }
+        QType::NS => {
+            expect_fields(1)?;
+            RData::NS(parse_name(field(0)?, origin)?)
+        }
+        QType::CNAME => {
+            expect_fields(1)?;
+            RData::CNAME(parse_name(field(0)?, origin)?)
+        }
+        QType::PTR => {
+            expect_fields(1)?;
+            RData::PTR(parse_name(field(0)?, origin)?)
+        }
+        QType::MX => {
+            expect_fields(2)?;
+            RData::MX {
+                preference: number(field(0)?)?,
+                exchange: parse_name(field(1)?, origin)?,
+            }
+        }
+        QType::AAAA => {
+            expect_fields(1)?;
+            RData::AAAA(field(0)?.parse().context("invalid IPv6 address")?)
+        }
+        QType::SRV => {
+            expect_fields(4)?;
+            RData::SRV(Srv {
+                priority: number(field(0)?)?,
+                weight: number(field(1)?)?,
+                port: number(field(2)?)?,
+                target: parse_name(field(3)?, origin)?,
+            })
+        }
+        QType::NAPTR => {
+            expect_fields(6)?;
+            RData::NAPTR(Naptr {
+                order: number(field(0)?)?,
+                preference: number(field(1)?)?,
+                flags: character_string(field(2)?)?,
+                services: character_string(field(3)?)?,
+                regexp: character_string(field(4)?)?,
+                replacement: parse_name(field(5)?, origin)?,
+            })
+        }
+        QType::URI => {
+            expect_fields(3)?;
+            let target = field(2)?;
+            let target = target
+                .strip_prefix('"')
+                .and_then(|x| x.strip_suffix('"'))
+                .ok_or_else(|| anyhow!("URI target {} is not quoted", target))?;
+            RData::URI(Uri {
+                priority: number(field(0)?)?,
+                weight: number(field(1)?)?,
+                target: unescape(target)?,
+            })
+        }
+        QType::SOA => {
+            expect_fields(7)?;
+            RData::SOA(Soa {
+                mname: parse_name(field(0)?, origin)?,
+                rname: parse_name(field(1)?, origin)?,
+                serial: number(field(2)?)?,
+                refresh: parse_ttl(field(3)?)?,
+                retry: parse_ttl(field(4)?)?,
+                expire: parse_ttl(field(5)?)?,
+                minimum: parse_ttl(field(6)?)?,
+            })
+        }
+        QType::TXT => {
+            ensure!(!fields.is_empty(), "TXT without strings");
+            // Strings too long for a character-string are split, as DKIM keys
+            // often are.
+            let mut strings = Vec::new();
+            for field in fields {
+                strings.extend(Txt::new(&unescape(unquote(field))?).strings);
+            }
+            RData::TXT(Txt { strings })
+        }
+        QType::DNSKEY => RData::DNSKEY(Dnskey {
+            flags: number(field(0)?)?,
+            protocol: number(field(1)?)?,
+            algorithm: number(field(2)?)?,
+            public_key: base64_decode(&rest(3)).ok_or_else(|| anyhow!("invalid public key"))?,
+        }),
+        QType::DS => RData::DS(Ds {
+            key_tag: number(field(0)?)?,
+            algorithm: number(field(1)?)?,
+            digest_type: number(field(2)?)?,
+            digest: hex_decode(&rest(3)).ok_or_else(|| anyhow!("invalid digest"))?,
+        }),
+        QType::RRSIG => RData::RRSIG(Rrsig {
+            type_covered: parse_type(field(0)?)?,
+            algorithm: number(field(1)?)?,
+            labels: number(field(2)?)?,
+            original_ttl: number(field(3)?)?,
+            expiration: parse_timestamp(field(4)?).ok_or_else(|| anyhow!("invalid expiration"))?,
+            inception: parse_timestamp(field(5)?).ok_or_else(|| anyhow!("invalid inception"))?,
+            key_tag: number(field(6)?)?,
+            signer: parse_name(field(7)?, origin)?,
+            signature: base64_decode(&rest(8)).ok_or_else(|| anyhow!("invalid signature"))?,
+        }),
+        QType::NSEC => RData::NSEC(Nsec {
+            next: parse_name(field(0)?, origin)?,
+            types: parse_types(&fields[1..])?,
+        }),
+        QType::NSEC3 => RData::NSEC3(Nsec3 {
+            hash_algorithm: number(field(0)?)?,
+            flags: number(field(1)?)?,
+            iterations: number(field(2)?)?,
+            salt: parse_salt(field(3)?)?,
+            next_hashed: base32hex_decode(field(4)?)
+                .ok_or_else(|| anyhow!("invalid next hashed owner"))?,
+            types: parse_types(&fields[5..])?,
+        }),
+        QType::NSEC3PARAM => {
+            expect_fields(4)?;
+            RData::NSEC3PARAM(Nsec3Param {
+                hash_algorithm: number(field(0)?)?,
+                flags: number(field(1)?)?,
+                iterations: number(field(2)?)?,
+                salt: parse_salt(field(3)?)?,
+            })
+        }
+        QType::SSHFP => {
+            let sshfp = Sshfp {
+                algorithm: number(field(0)?)?,
+                fingerprint_type: number(field(1)?)?,
+                fingerprint: hex_decode(&rest(2)).ok_or_else(|| anyhow!("invalid fingerprint"))?,
+            };
+            sshfp.check()?;
+            RData::SSHFP(sshfp)
+        }
+        QType::TLSA => {
+            let tlsa = Tlsa {
+                usage: number(field(0)?)?,
+                selector: number(field(1)?)?,
+                matching_type: number(field(2)?)?,
+                data: hex_decode(&rest(3))
+                    .ok_or_else(|| anyhow!("invalid certificate association data"))?,
+            };
+            tlsa.check()?;
+            RData::TLSA(tlsa)
+        }
+        QType::OPENPGPKEY => {
+            let key = base64_decode(&rest(0)).ok_or_else(|| anyhow!("invalid OpenPGP key"))?;
+            ensure!(!key.is_empty(), "empty OpenPGP key");
+            RData::OPENPGPKEY(key)
+        }
+        QType::CAA => {
+            expect_fields(3)?;
+            let caa = Caa {
+                flags: number(field(0)?)?,
+                tag: field(1)?.to_string(),
+                value: unescape(unquote(field(2)?))?,
+            };
+            caa.check()?;
+            RData::CAA(caa)
+        }
+        QType::SVCB | QType::HTTPS => {
+            let mut params = fields
+                .get(2..)
+                .unwrap_or_default()
+                .iter()
+                .map(|x| parse_svc_param(x))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            params.sort_by_key(SvcParam::key);
+            let svcb = Svcb {
+                priority: number(field(0)?)?,
+                target: parse_name(field(1)?, origin)?,
+                params,
+            };
+            svcb.check()?;
+            match typ {
+                QType::SVCB => RData::SVCB(svcb),
+                _ => RData::HTTPS(svcb),
+            }
+        }
+        _ => bail!("no presentation format for {}, use \\# instead", typ),
+    };
+    Ok(rdata)
+}
+
+/// Reads `<length> <hex>...` into the RDATA of `typ`, through the same parser as
+/// records received on the wire.
+fn parse_generic_rdata(typ: QType, fields: &[&str]) -> anyhow::Result<RData> {
+    let (length, hex) = fields
+        .split_first()
+        .ok_or_else(|| anyhow!("missing RDATA length"))?;
+    let length = number::<u16>(length)?;
+    let bytes = hex_decode(&hex.concat()).ok_or_else(|| anyhow!("invalid hex RDATA"))?;
+    ensure!(
+        bytes.len() == usize::from(length),
+        "RDATA of {} octets, not {}",
+        bytes.len(),
+        length
+    );
+
+    let mut wire = vec![0];
+    wire.put_u16(u16::from(typ));
+    wire.put_u16(u16::from(QClass::IN));
+    wire.put_u32(0);
+    wire.put_u16(length);
+    wire.put(&bytes[..]);
+    DnsRecord::parse(&wire, 0)
+        .map(|(record, _)| record.rdata)
+        .ok_or_else(|| anyhow!("invalid {} RDATA", typ))
+}
+
+/// Reads an SVCB parameter, `key=value` or a bare key (RFC 9460 appendix A),
+/// the value quoted or not. Lists are separated by commas, escaped ones
+/// belonging to the items.
+fn parse_svc_param(field: &str) -> anyhow::Result<SvcParam> {
+    let (name, value) = match field.split_once('=') {
+        Some((name, value)) => (name, Some(value)),
+        None => (field, None),
+    };
+    let key = svc_param_key(name).ok_or_else(|| anyhow!("unknown SvcParamKey {}", name))?;
+    let value = value.map(unquote);
+    let required = || value.ok_or_else(|| anyhow!("{} without a value", name));
+    let items = || required().map(split_list);
+
+    let param = match key {
+        0 => {
+            let mut keys = items()?
+                .into_iter()
+                .map(|x| svc_param_key(x).ok_or_else(|| anyhow!("unknown SvcParamKey {}", x)))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            keys.sort();
+            SvcParam::Mandatory(keys)
+        }
+        1 => SvcParam::Alpn(
+            items()?
+                .into_iter()
+                .map(unescape)
+                .collect::<anyhow::Result<_>>()?,
+        ),
+        2 => {
+            ensure!(value.is_none(), "no-default-alpn takes no value");
+            SvcParam::NoDefaultAlpn
+        }
+        3 => SvcParam::Port(number(required()?)?),
+        4 => SvcParam::Ipv4Hint(
+            items()?
+                .into_iter()
+                .map(|x| {
+                    x.parse()
+                        .with_context(|| format!("invalid IPv4 address {}", x))
+                })
+                .collect::<anyhow::Result<_>>()?,
+        ),
+        5 => SvcParam::Ech(base64_decode(required()?).ok_or_else(|| anyhow!("invalid ech"))?),
+        6 => SvcParam::Ipv6Hint(
+            items()?
+                .into_iter()
+                .map(|x| {
+                    x.parse()
+                        .with_context(|| format!("invalid IPv6 address {}", x))
+                })
+                .collect::<anyhow::Result<_>>()?,
+        ),
+        key => SvcParam::Unknown {
+            key,
+            value: unescape(value.unwrap_or_default())?,
+        },
+    };
+    Ok(param)
+}
+
+/// Splits a value list at the commas not escaped with a backslash.
+fn split_list(text: &str) -> Vec<&str> {
+    let mut items = Vec::new();
+    let mut start = 0;
+    let mut escaped = false;
+    for (index, c) in text.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            ',' => {
+                items.push(&text[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(&text[start..]);
+    items
+}
+
+/// Reads a name, relative to `origin` unless it ends with a dot.
+fn parse_name(text: &str, origin: Option<&[String]>) -> anyhow::Result<Vec<String>> {
+    let relative = text == "@" || !text.ends_with('.') || text.ends_with("\\.");
+    let origin = match origin {
+        Some(origin) => origin,
+        None if relative && text != "." => bail!("relative name {} without $ORIGIN", text),
+        None => &[],
+    };
+    name_from_string(text, origin).ok_or_else(|| anyhow!("invalid name {}", text))
+}
+
+/// Reads a TTL in seconds, or with units as BIND writes them: `1h30m`, `2d`.
+fn parse_ttl(text: &str) -> anyhow::Result<u32> {
+    if let Ok(seconds) = text.parse() {
+        return Ok(seconds);
+    }
+    let mut total: u32 = 0;
+    let mut value: u32 = 0;
+    for c in text.chars() {
+        let unit = match c.to_ascii_lowercase() {
+            digit @ '0'..='9' => {
+                value = value
+                    .checked_mul(10)
+                    .and_then(|x| x.checked_add(digit as u32 - '0' as u32))
+                    .ok_or_else(|| anyhow!("TTL {} too large", text))?;
+                continue;
+            }
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86400,
+            'w' => 7 * 86400,
+            _ => bail!("invalid TTL {}", text),
+        };
+        total = value
+            .checked_mul(unit)
+            .and_then(|x| x.checked_add(total))
+            .ok_or_else(|| anyhow!("TTL {} too large", text))?;
+        value = 0;
+    }
+    ensure!(value == 0, "TTL {} ends without a unit", text);
+    Ok(total)
+}
+
+fn parse_type(text: &str) -> anyhow::Result<u16> {
+    type_code(text).ok_or_else(|| anyhow!("unknown type {}", text))
+}
+
+fn parse_types(fields: &[&str]) -> anyhow::Result<Vec<u16>> {
+    let mut types = fields
+        .iter()
+        .map(|x| parse_type(x))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    types.sort();
+    types.dedup();
+    Ok(types)
+}
+
+/// An NSEC3 salt in hex, or `-` for none.
+fn parse_salt(text: &str) -> anyhow::Result<Vec<u8>> {
+    match text {
+        "-" => Ok(Vec::new()),
+        _ => hex_decode(text).ok_or_else(|| anyhow!("invalid salt {}", text)),
+    }
+}
+
+fn number<T: FromStr>(text: &str) -> anyhow::Result<T> {
+    text.parse().map_err(|_| anyhow!("invalid number {}", text))
+}
+
+/// The octets of a character-string, quoted or not.
+fn character_string(field: &str) -> anyhow::Result<Vec<u8>> {
+    let bytes = unescape(unquote(field))?;
+    ensure!(
+        bytes.len() <= MAX_CHARACTER_STRING,
+        "character-string longer than {} octets",
+        MAX_CHARACTER_STRING
+    );
+    Ok(bytes)
+}
+
+/// A field without the double quotes around it, if any.
+fn unquote(field: &str) -> &str {
+    field
+        .strip_prefix('"')
+        .and_then(|x| x.strip_suffix('"'))
+        .unwrap_or(field)
+}
+
+/// The octets of a character-string, resolving `\X` and `\DDD` escapes.
+fn unescape(text: &str) -> anyhow::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let mut rest = text.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        rest = tail;
+        if byte != b'\\' {
+            bytes.push(byte);
+            continue;
+        }
+        match rest.get(..3).filter(|x| x.iter().all(u8::is_ascii_digit)) {
+            Some(digits) => {
+                let value = std::str::from_utf8(digits)?.parse::<u8>()?;
+                bytes.push(value);
+                rest = &rest[3..];
+            }
+            None => {
+                let (&escaped, tail) = rest.split_first().ok_or_else(|| anyhow!("trailing \\"))?;
+                bytes.push(escaped);
+                rest = tail;
+            }
+        }
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        dns_question::question::DnsQuestion,
+        dns_record::record::{
+            Caa, DnsRecord, Naptr, RData, Soa, Srv, Sshfp, SvcParam, Svcb, Tlsa, Txt, Uri,
+        },
+        dns_zone::zone::Zone,
+        encoding::hex_decode,
+        resrec::{QClass, QType},
+    };
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    fn name(text: &str) -> Vec<String> {
+        text.split('.')
+            .filter(|x| !x.is_empty())
+            .map(String::from)
+            .collect()
+    }
+
+    const ZONE: &str = r#"
+$ORIGIN example.com.
+$TTL 1h
+@   IN  SOA ns hostmaster (
+            2024010101 ; serial
+            3h 15m 1w 300 )
+    IN  NS  ns
+    IN  MX  10 mail.example.com.
+ns      300 A   192.0.2.1
+mail    IN 600 A 192.0.2.2
+www     CNAME ns
+txt     TXT "v=spf1 -all" "semi;colon \"quoted\"" \065B
+raw     A   \# 4 C0000203
+"#;
+
+    #[test]
+    fn from_master_file_test() {
+        let zone = Zone::from_master_file(ZONE, None).unwrap();
+        assert_eq!(zone.origin, name("example.com"));
+        assert_eq!(zone.class, QClass::IN);
+        assert_eq!(zone.records.len(), 8);
+        assert_eq!(
+            zone.records[0],
+            DnsRecord {
+                name: name("example.com"),
+                typ: QType::SOA,
+                class: QClass::IN,
+                ttl: 3600,
+                rdata: RData::SOA(Soa {
+                    mname: name("ns.example.com"),
+                    rname: name("hostmaster.example.com"),
+                    serial: 2024010101,
+                    refresh: 10800,
+                    retry: 900,
+                    expire: 604800,
+                    minimum: 300,
+                }),
+            }
+        );
+        assert_eq!(zone.records[1].name, name("example.com"));
+        assert_eq!(zone.records[1].rdata, RData::NS(name("ns.example.com")));
+        assert_eq!(
+            (
+                zone.records[3].ttl,
+                zone.records[4].ttl,
+                zone.records[5].ttl
+            ),
+            (300, 600, 3600)
+        );
+        assert_eq!(
+            zone.records[6].rdata,
+            RData::TXT(Txt {
+                strings: vec![
+                    b"v=spf1 -all".to_vec(),
+                    b"semi;colon \"quoted\"".to_vec(),
+                    b"AB".to_vec(),
+                ],
+            })
+        );
+        assert_eq!(zone.records[7].rdata, RData::A(Ipv4Addr::new(192, 0, 2, 3)));
+    }
+
+    #[test]
+    fn master_file_round_trip_test() {
+        let zone = Zone::from_master_file(ZONE, None).unwrap();
+        let text = zone.to_master_file();
+        assert!(text.contains(
+            "txt.example.com. 3600 IN TXT \"v=spf1 -all\" \"semi;colon \\\"quoted\\\"\" \"AB\""
+        ));
+        assert_eq!(Zone::from_master_file(&text, None).unwrap(), zone);
+    }
+
+    #[test]
+    fn txt_records_test() {
+        let key = "MIIBIjANBgkqhkiG9w0BAQEFAAOCAQ8AMIIBCgKCAQEA".repeat(8);
+        let text = format!(
+            "$ORIGIN example.com.\n\
+             @ 3600 SOA ns hostmaster 1 2 3 4 5\n\
+             sel._domainkey 3600 TXT \"v=DKIM1; k=rsa; p={}\"\n\
+             bin 3600 TXT \"\\000\\255\\\\\" \"\"\n",
+            key
+        );
+        let zone = Zone::from_master_file(&text, None).unwrap();
+        let RData::TXT(dkim) = &zone.records[1].rdata else {
+            panic!("not TXT");
+        };
+        assert_eq!(
+            dkim.strings.iter().map(Vec::len).collect::<Vec<_>>(),
+            vec![255, 115]
+        );
+        assert_eq!(
+            dkim.joined(),
+            format!("v=DKIM1; k=rsa; p={}", key).into_bytes()
+        );
+        assert_eq!(
+            zone.records[2].rdata,
+            RData::TXT(Txt {
+                strings: vec![vec![0, 255, b'\\'], Vec::new()],
+            })
+        );
+        assert_eq!(
+            Zone::from_master_file(&zone.to_master_file(), None).unwrap(),
+            zone
+        );
+    }
+
+    #[test]
+    fn service_records_test() {
+        let text = r#"
+$ORIGIN example.com.
+$TTL 3600
+@           SOA ns hostmaster 1 2 3 4 5
+            NAPTR 100 10 "S" "SIP+D2U" "" _sip._udp
+            NAPTR 102 10 A SIP+D2T "!^.*$!sip:info@example.com!" .
+_sip._udp   SRV 10 60 5060 bigbox
+_ftp._tcp   URI 10 1 "ftp://ftp1.example.com/public"
+bigbox      AAAA 2001:db8::1
+"#;
+        let zone = Zone::from_master_file(text, None).unwrap();
+        let rdatas = zone.records[1..]
+            .iter()
+            .map(|r| r.rdata.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            rdatas,
+            vec![
+                RData::NAPTR(Naptr {
+                    order: 100,
+                    preference: 10,
+                    flags: b"S".to_vec(),
+                    services: b"SIP+D2U".to_vec(),
+                    regexp: Vec::new(),
+                    replacement: name("_sip._udp.example.com"),
+                }),
+                RData::NAPTR(Naptr {
+                    order: 102,
+                    preference: 10,
+                    flags: b"A".to_vec(),
+                    services: b"SIP+D2T".to_vec(),
+                    regexp: b"!^.*$!sip:info@example.com!".to_vec(),
+                    replacement: Vec::new(),
+                }),
+                RData::SRV(Srv {
+                    priority: 10,
+                    weight: 60,
+                    port: 5060,
+                    target: name("bigbox.example.com"),
+                }),
+                RData::URI(Uri {
+                    priority: 10,
+                    weight: 1,
+                    target: b"ftp://ftp1.example.com/public".to_vec(),
+                }),
+                RData::AAAA(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)),
+            ]
+        );
+        assert_eq!(
+            Zone::from_master_file(&zone.to_master_file(), None).unwrap(),
+            zone
+        );
+    }
+
+    #[test]
+    fn svcb_records_test() {
+        let soa = "example.com. 60 SOA ns.example.com. h.example.com. 1 2 3 4 5\n";
+        let text = format!(
+            "{}{}",
+            soa,
+            r#"@ 300 HTTPS 1 . port=8443 alpn="h2,h3" mandatory=port ech=AEU= ipv4hint=192.0.2.1,192.0.2.2
+www 300 HTTPS 0 svc.example.net.
+_dns 300 SVCB 2 dns key7=a\,b no-default-alpn alpn=dot ipv6hint=2001:db8::53"#
+        );
+        let origin = name("example.com");
+        let zone = Zone::from_master_file(&text, Some(&origin)).unwrap();
+        assert_eq!(
+            zone.records[1].rdata,
+            RData::HTTPS(Svcb {
+                priority: 1,
+                target: Vec::new(),
+                params: vec![
+                    SvcParam::Mandatory(vec![3]),
+                    SvcParam::Alpn(vec![b"h2".to_vec(), b"h3".to_vec()]),
+                    SvcParam::Port(8443),
+                    SvcParam::Ipv4Hint(vec![
+                        Ipv4Addr::new(192, 0, 2, 1),
+                        Ipv4Addr::new(192, 0, 2, 2)
+                    ]),
+                    SvcParam::Ech(vec![0, 0x45]),
+                ],
+            })
+        );
+        assert_eq!(
+            zone.records[2].rdata,
+            RData::HTTPS(Svcb {
+                priority: 0,
+                target: name("svc.example.net"),
+                params: Vec::new(),
+            })
+        );
+        let RData::SVCB(svcb) = &zone.records[3].rdata else {
+            panic!("not SVCB");
+        };
+        assert_eq!(svcb.target, name("dns.example.com"));
+        assert_eq!(
+            svcb.params.iter().map(|x| x.key()).collect::<Vec<_>>(),
+            vec![1, 2, 6, 7]
+        );
+        assert_eq!(
+            svcb.params[3],
+            SvcParam::Unknown {
+                key: 7,
+                value: b"a,b".to_vec()
+            }
+        );
+        assert_eq!(
+            Zone::from_master_file(&zone.to_master_file(), None).unwrap(),
+            zone
+        );
+
+        let error = |rdata: &str| {
+            let text = format!("{}@ HTTPS {}", soa, rdata);
+            format!(
+                "{:#}",
+                Zone::from_master_file(&text, Some(&origin)).unwrap_err()
+            )
+        };
+        assert_eq!(
+            error("1 . mandatory=alpn port=443"),
+            "line 2: mandatory key alpn is missing"
+        );
+        assert_eq!(
+            error("1 . port=443 port=80"),
+            "line 2: repeated SvcParamKey"
+        );
+        assert_eq!(
+            error("1 . no-default-alpn=h2"),
+            "line 2: no-default-alpn takes no value"
+        );
+        assert_eq!(error("1 . color=red"), "line 2: unknown SvcParamKey color");
+    }
+
+    #[test]
+    fn security_records_test() {
+        let text = r#"
+$ORIGIN example.com.
+$TTL 3600
+@           SOA ns hostmaster 1 2 3 4 5
+            CAA 0 issue "letsencrypt.org"
+            CAA 128 tbs Unknown
+host        SSHFP 1 1 ( 123456789abcdef67890
+                        123456789abcdef67890 )
+_443._tcp   TLSA 3 1 1 0C72AC70B745AC19998811B131D662C9AC69DBDBE7CB23E5B514B56664C5D3D6
+key         OPENPGPKEY mQENBFV ( AAAA )
+"#;
+        let zone = Zone::from_master_file(text, None).unwrap();
+        let rdatas = zone.records[1..]
+            .iter()
+            .map(|r| r.rdata.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            rdatas[..4],
+            [
+                RData::CAA(Caa {
+                    flags: 0,
+                    tag: "issue".to_string(),
+                    value: b"letsencrypt.org".to_vec(),
+                }),
+                RData::CAA(Caa {
+                    flags: 128,
+                    tag: "tbs".to_string(),
+                    value: b"Unknown".to_vec(),
+                }),
+                RData::SSHFP(Sshfp {
+                    algorithm: 1,
+                    fingerprint_type: 1,
+                    fingerprint: [0x12, 0x34, 0x56, 0x78, 0x9a, 0xbc, 0xde, 0xf6, 0x78, 0x90]
+                        .repeat(2),
+                }),
+                RData::TLSA(Tlsa {
+                    usage: 3,
+                    selector: 1,
+                    matching_type: 1,
+                    data: hex_decode(
+                        "0C72AC70B745AC19998811B131D662C9AC69DBDBE7CB23E5B514B56664C5D3D6"
+                    )
+                    .unwrap(),
+                }),
+            ]
+        );
+        assert_eq!(rdatas[4].as_bytes().len(), 8);
+        assert_eq!(
+            Zone::from_master_file(&zone.to_master_file(), None).unwrap(),
+            zone
+        );
+
+        let error = |rdata: &str| {
+            let text = format!(
+                "example.com. 60 SOA ns.example.com. h.example.com. 1 2 3 4 5\nexample.com. {}",
+                rdata
+            );
+            format!("{:#}", Zone::from_master_file(&text, None).unwrap_err())
+        };
+        assert_eq!(
+            error("SSHFP 1 2 123456789abcdef67890123456789abcdef67890"),
+            "line 2: fingerprint of type 2 is 20 octets, not 32"
+        );
+        assert_eq!(
+            error("TLSA 3 1 2 0C72"),
+            "line 2: data of matching type 2 is 2 octets, not 64"
+        );
+        assert_eq!(
+            error("CAA 0 issue-wild \"ca.example.net\""),
+            "line 2: CAA tag issue-wild is not only letters and digits"
+        );
+        assert_eq!(
+            error("CAA 0 issue ca.example.net extra"),
+            "line 2: CAA takes 3 fields, not 4"
+        );
+    }
+
+    #[test]
+    fn master_file_errors_test() {
+        let error = |text: &str, origin: Option<&[String]>| {
+            format!("{:#}", Zone::from_master_file(text, origin).unwrap_err())
+        };
+        assert_eq!(
+            error("www 60 A 192.0.2.1", Some(&name("example.com"))),
+            "the zone does not start with its SOA record"
+        );
+        assert_eq!(
+            error("www 60 A 192.0.2.1", None),
+            "line 1: relative name www without $ORIGIN"
+        );
+        let soa = "example.com. 60 SOA ns.example.com. h.example.com. 1 2 3 4 5\n";
+        assert_eq!(
+            error(&format!("{}www.example.org. A 192.0.2.1", soa), None),
+            "www.example.org. is outside the zone example.com."
+        );
+        assert_eq!(
+            error(&format!("{}\nwww.example.com. A 192.0.2", soa), None),
+            "line 3: invalid IPv4 address: invalid IPv4 address syntax"
+        );
+        assert_eq!(
+            error(&format!("{}www.example.com. HINFO a b", soa), None),
+            "line 2: no presentation format for HINFO, use \\# instead"
+        );
+        assert_eq!(
+            error(
+                "example.com. 60 SOA ( ns.example.com. h.example.com. 1 2 3 4 5",
+                None
+            ),
+            "line 1: unbalanced ("
+        );
+    }
+
+    #[test]
+    fn presentation_from_str_test() {
+        let lines = [
+            "www.example.com. 3600 IN A 192.0.2.1",
+            "example.com. 300 IN MX 10 mail.example.com.",
+            "txt.example.com. 60 IN TXT \"v=spf1 -all\" \"a\\\\b\\000\"",
+            "_sip._udp.example.com. 3600 IN SRV 10 60 5060 bigbox.example.com.",
+            "example.com. 3600 IN HINFO \\# 2 ABCD",
+        ];
+        for line in lines {
+            let record = line.parse::<DnsRecord>().unwrap();
+            assert_eq!(record.to_string(), line);
+        }
+        assert_eq!(
+            "www.example.com. 60 A ( 192.0.2.1 )"
+                .parse::<DnsRecord>()
+                .unwrap()
+                .rdata,
+            RData::A(Ipv4Addr::new(192, 0, 2, 1))
+        );
+
+        let question = "www.example.com. CH TXT".parse::<DnsQuestion>().unwrap();
+        assert_eq!(
+            question,
+            DnsQuestion {
+                qname: name("www.example.com"),
+                qtype: QType::TXT,
+                qclass: QClass::CH,
+            }
+        );
+        assert_eq!(question.to_string(), "www.example.com. CH TXT");
+        assert_eq!(
+            "example.com. AAAA".parse::<DnsQuestion>().unwrap().qclass,
+            QClass::IN
+        );
+
+        let error = |text: &str| format!("{:#}", text.parse::<DnsRecord>().unwrap_err());
+        assert_eq!(
+            error("www 60 A 192.0.2.1"),
+            "relative name www without $ORIGIN"
+        );
+        assert_eq!(error("www.example.com. A 192.0.2.1"), "missing TTL");
+        assert_eq!(error(""), "expected one record, got 0");
+        assert_eq!(
+            format!("{:#}", "example.com.".parse::<DnsQuestion>().unwrap_err()),
+            "expected <name> [<class>] <type>, got example.com."
+        );
+    }
+}
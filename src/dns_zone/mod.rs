@@ -0,0 +1,13 @@
+pub mod journal;
+pub(crate) mod journal_impl;
+pub(crate) mod master_file_impl;
+pub(crate) mod notifier;
+pub(crate) mod notifier_impl;
+pub(crate) mod query_impl;
+pub(crate) mod secondary;
+pub(crate) mod secondary_impl;
+pub mod transfer;
+pub(crate) mod transfer_impl;
+pub(crate) mod update_impl;
+pub mod zone;
+pub(crate) mod zone_impl;
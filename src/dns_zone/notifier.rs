@@ -0,0 +1,35 @@
+use std::{collections::HashMap, net::SocketAddr, net::UdpSocket};
+
+/// Tells secondaries about new versions of the zones served here with NOTIFY
+/// messages (RFC 1996), so they refresh without waiting for their SOA timers.
+///
+/// Changes are noticed by watching the serial of each zone, whether they come
+/// from a dynamic update or a reload. Each secondary is sent a NOTIFY until it
+/// acknowledges it, waiting twice as long after each unanswered attempt.
+#[derive(Debug)]
+pub struct Notifier {
+    /// Sends the NOTIFY messages to IPv4 secondaries and receives their
+    /// acknowledgements.
+    pub socket: UdpSocket,
+    /// The same for IPv6 secondaries, `None` when the host has no IPv6.
+    pub socket6: Option<UdpSocket>,
+    /// Secondaries notified of every zone change, besides the NS hosts of the zone.
+    pub also_notify: Vec<SocketAddr>,
+    /// Last serial seen for each zone origin.
+    pub serials: HashMap<Vec<String>, u32>,
+    pub pending: Vec<PendingNotify>,
+}
+
+/// A NOTIFY not acknowledged yet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingNotify {
+    pub origin: Vec<String>,
+    pub serial: u32,
+    pub target: SocketAddr,
+    /// ID of the last NOTIFY sent, which the acknowledgement must carry.
+    pub packet_id: u16,
+    /// How many times the NOTIFY was sent.
+    pub attempts: u32,
+    /// Unix time of the next attempt.
+    pub next_attempt: u64,
+}
@@ -0,0 +1,340 @@
+use super::{
+    notifier::{Notifier, PendingNotify},
+    zone::{Zone, ZoneStore},
+};
+use crate::{
+    dns_header::header::{AuthoritativeAnswer, OpCode, QueryResponse},
+    dns_message::message::DnsMessage,
+    dns_name::name::{name_to_string, names_equal},
+    dns_record::record::RData,
+    resrec::QType,
+};
+use std::{
+    io,
+    net::{IpAddr, SocketAddr, UdpSocket},
+    sync::Mutex,
+};
+
+/// Port NOTIFY messages are sent to on the NS hosts of a zone.
+const DNS_PORT: u16 = 53;
+
+/// Seconds before the first retry, doubled after each unanswered attempt.
+const INITIAL_RETRY: u64 = 2;
+
+/// Attempts after which a secondary that never answered is given up on.
+const MAX_NOTIFY_ATTEMPTS: u32 = 5;
+
+impl Notifier {
+    pub fn new(also_notify: Vec<SocketAddr>) -> io::Result<Self> {
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+        socket.set_nonblocking(true)?;
+        let socket6 = UdpSocket::bind("[::]:0").ok();
+        if let Some(socket6) = &socket6 {
+            socket6.set_nonblocking(true)?;
+        }
+        Ok(Notifier {
+            socket,
+            socket6,
+            also_notify,
+            serials: Default::default(),
+            pending: Vec::new(),
+        })
+    }
+
+    /// Queues NOTIFY messages for the zones whose serial changed since the last
+    /// call, sends those that are due and collects acknowledgements. Zones seen for
+    /// the first time are only remembered.
+    pub fn tick(&mut self, zones: &Mutex<ZoneStore>, now: u64) {
+        self.watch(&zones.lock().unwrap(), now);
+        self.receive();
+        self.send_due(now);
+    }
+
+    fn watch(&mut self, zones: &ZoneStore, now: u64) {
+        for zone in zones.zones.iter().filter(|z| !z.expired) {
+            let Some(serial) = zone.serial() else {
+                continue;
+            };
+            let previous = self.serials.insert(zone.origin.clone(), serial);
+            if previous.is_none() || previous == Some(serial) {
+                continue;
+            }
+
+            // A newer version supersedes notifications still pending for the zone.
+            self.pending
+                .retain(|p| !names_equal(&p.origin, &zone.origin));
+            for target in self.targets(zones, zone) {
+                self.pending.push(PendingNotify {
+                    origin: zone.origin.clone(),
+                    serial,
+                    target,
+                    packet_id: 0,
+                    attempts: 0,
+                    next_attempt: now,
+                });
+            }
+        }
+    }
+
+    /// The secondaries to notify of a change to `zone`: the configured ones, and
+    /// the NS hosts of the zone other than the primary named in its SOA, at the
+    /// addresses the served zones hold for them.
+    fn targets(&self, zones: &ZoneStore, zone: &Zone) -> Vec<SocketAddr> {
+        let mut targets = self.also_notify.clone();
+        let primary = zone.soa().map(|soa| soa.mname.clone()).unwrap_or_default();
+        let hosts = zone
+            .rrset(&zone.origin, QType::NS)
+            .filter_map(|r| match &r.rdata {
+                RData::NS(host) if !names_equal(host, &primary) => Some(host.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        for host in hosts {
+            let Some(host_zone) = zones.authoritative_for(&host) else {
+                continue;
+            };
+            let addresses = host_zone
+                .records
+                .iter()
+                .filter(|r| names_equal(&r.name, &host))
+                .filter_map(|r| match r.rdata {
+                    RData::A(address) => Some(IpAddr::V4(address)),
+                    RData::AAAA(address) => Some(IpAddr::V6(address)),
+                    _ => None,
+                });
+            for address in addresses {
+                let target = SocketAddr::new(address, DNS_PORT);
+                if !targets.contains(&target) {
+                    targets.push(target);
+                }
+            }
+        }
+        targets
+    }
+
+    /// Drops the notifications acknowledged by their secondary.
+    fn receive(&mut self) {
+        let mut buf = [0; 512];
+        let mut received = Vec::new();
+        for socket in std::iter::once(&self.socket).chain(&self.socket6) {
+            while let Ok((size, source)) = socket.recv_from(&mut buf) {
+                received.extend(DnsMessage::parse(&buf[..size]).map(|x| (x, source)));
+            }
+        }
+
+        for (response, source) in received {
+            if response.header.qr != QueryResponse::Reply
+                || response.header.opcode != OpCode::Notify
+            {
+                continue;
+            }
+            let acknowledged = self
+                .pending
+                .iter()
+                .position(|p| p.target == source && p.packet_id == response.header.packet_id);
+            if let Some(i) = acknowledged {
+                let pending = self.pending.remove(i);
+                log::info!(
+                    "{} acknowledged NOTIFY for {} serial {} with {}",
+                    source,
+                    name_to_string(&pending.origin),
+                    pending.serial,
+                    response.header.rcode
+                );
+            }
+        }
+    }
+
+    /// Sends the notifications whose next attempt is due, giving up on those
+    /// which ran out of attempts.
+    fn send_due(&mut self, now: u64) {
+        self.pending.retain(|p| {
+            let expired = p.attempts >= MAX_NOTIFY_ATTEMPTS && p.next_attempt <= now;
+            if expired {
+                log::warn!(
+                    "{} never acknowledged NOTIFY for {} serial {}",
+                    p.target,
+                    name_to_string(&p.origin),
+                    p.serial
+                );
+            }
+            !expired
+        });
+
+        for pending in self.pending.iter_mut().filter(|p| p.next_attempt <= now) {
+            let message = notify_message(pending);
+            pending.packet_id = message.header.packet_id;
+            pending.attempts += 1;
+            pending.next_attempt = now + (INITIAL_RETRY << (pending.attempts - 1));
+            let socket = match pending.target {
+                SocketAddr::V4(_) => Some(&self.socket),
+                SocketAddr::V6(_) => self.socket6.as_ref(),
+            };
+            let Some(socket) = socket else {
+                log::warn!("No IPv6 socket to send NOTIFY to {}", pending.target);
+                continue;
+            };
+            if let Err(e) = socket.send_to(&message.as_bytes(), pending.target) {
+                log::warn!("Failed to send NOTIFY to {}: {}", pending.target, e);
+            }
+        }
+    }
+}
+
+/// A NOTIFY for the SOA of the zone, authoritative as RFC 1996 requires.
+fn notify_message(pending: &PendingNotify) -> DnsMessage {
+    let mut message = DnsMessage::query(pending.origin.clone(), QType::SOA);
+    message.header.opcode = OpCode::Notify;
+    message.header.aa = AuthoritativeAnswer::Authoritative;
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Notifier;
+    use crate::{
+        dns_header::header::OpCode,
+        dns_message::message::DnsMessage,
+        dns_record::record::{DnsRecord, RData},
+        dns_zone::zone::{Zone, ZoneStore},
+        resrec::{QClass, QType},
+    };
+    use std::{
+        net::{Ipv4Addr, Ipv6Addr, UdpSocket},
+        sync::Mutex,
+        thread,
+        time::Duration,
+    };
+
+    fn name(labels: &[&str]) -> Vec<String> {
+        labels.iter().map(|x| x.to_string()).collect()
+    }
+
+    fn record(owner: &[&str], rdata: RData) -> DnsRecord {
+        let typ = match rdata {
+            RData::A(_) => QType::A,
+            RData::AAAA(_) => QType::AAAA,
+            _ => QType::NS,
+        };
+        DnsRecord {
+            name: name(owner),
+            typ,
+            class: QClass::IN,
+            ttl: 60,
+            rdata,
+        }
+    }
+
+    fn zones() -> ZoneStore {
+        let mut zone = Zone::with_default_soa(name(&["example", "com"]));
+        zone.records.extend([
+            record(
+                &["example", "com"],
+                RData::NS(name(&["ns", "example", "com"])),
+            ),
+            record(
+                &["example", "com"],
+                RData::NS(name(&["ns2", "example", "com"])),
+            ),
+            record(
+                &["ns", "example", "com"],
+                RData::A(Ipv4Addr::new(10, 0, 0, 1)),
+            ),
+            record(
+                &["ns2", "example", "com"],
+                RData::A(Ipv4Addr::new(10, 0, 0, 2)),
+            ),
+            record(
+                &["ns2", "example", "com"],
+                RData::AAAA(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 2)),
+            ),
+        ]);
+        ZoneStore { zones: vec![zone] }
+    }
+
+    #[test]
+    fn targets_test() {
+        let also = "192.0.2.1:5300".parse().unwrap();
+        let notifier = Notifier::new(vec![also]).unwrap();
+        let zones = zones();
+
+        let targets = notifier.targets(&zones, &zones.zones[0]);
+        let ns2 = ["10.0.0.2:53", "[2001:db8::2]:53"].map(|x| x.parse().unwrap());
+        assert_eq!(targets, [also, ns2[0], ns2[1]]);
+    }
+
+    #[test]
+    fn serial_change_test() {
+        let mut notifier = Notifier::new(vec![]).unwrap();
+        let mut zones = zones();
+
+        notifier.watch(&zones, 100);
+        assert!(notifier.pending.is_empty());
+
+        zones.zones[0].set_serial(2);
+        notifier.watch(&zones, 100);
+        assert_eq!(notifier.pending.len(), 2);
+        assert_eq!(notifier.pending[0].serial, 2);
+
+        notifier.watch(&zones, 100);
+        assert_eq!(notifier.pending.len(), 2);
+    }
+
+    #[test]
+    fn backoff_test() {
+        let secondary = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut notifier = Notifier::new(vec![secondary.local_addr().unwrap()]).unwrap();
+        let mut zones = zones();
+        zones.zones[0].records.truncate(1);
+        notifier.watch(&zones, 100);
+        zones.zones[0].set_serial(2);
+        notifier.watch(&zones, 100);
+
+        notifier.send_due(100);
+        assert_eq!(notifier.pending[0].attempts, 1);
+        assert_eq!(notifier.pending[0].next_attempt, 102);
+        notifier.send_due(101);
+        assert_eq!(notifier.pending[0].attempts, 1);
+        notifier.send_due(102);
+        assert_eq!(notifier.pending[0].next_attempt, 106);
+
+        for now in [106, 114, 130] {
+            notifier.send_due(now);
+        }
+        assert_eq!(notifier.pending[0].attempts, 5);
+        notifier.send_due(162);
+        assert!(notifier.pending.is_empty());
+    }
+
+    #[test]
+    fn acknowledgement_test() {
+        let secondary = UdpSocket::bind("127.0.0.1:0").unwrap();
+        secondary
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let mut notifier = Notifier::new(vec![secondary.local_addr().unwrap()]).unwrap();
+        let zones = Mutex::new(zones());
+        zones.lock().unwrap().zones[0].records.truncate(1);
+        notifier.tick(&zones, 100);
+        zones.lock().unwrap().zones[0].set_serial(2);
+        notifier.tick(&zones, 100);
+
+        let mut buf = [0; 512];
+        let (size, source) = secondary.recv_from(&mut buf).unwrap();
+        let mut notify = DnsMessage::parse(&buf[..size]).unwrap();
+        assert_eq!(notify.header.opcode, OpCode::Notify);
+        assert_eq!(notify.questions[0].qname, name(&["example", "com"]));
+
+        notify.to_response();
+        secondary.send_to(&notify.as_bytes(), source).unwrap();
+        for _ in 0..100 {
+            notifier.receive();
+            if notifier.pending.is_empty() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(notifier.pending.is_empty());
+    }
+}
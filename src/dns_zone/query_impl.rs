@@ -0,0 +1,392 @@
+use super::zone::{Zone, ZoneStore};
+use crate::{
+    dns_header::header::{AuthoritativeAnswer, ResponseCode},
+    dns_message::message::DnsMessage,
+    dns_name::name::names_equal,
+    dns_record::record::{DnsRecord, RData},
+    resrec::QType,
+};
+
+/// Upper bound on CNAME records followed within a zone, so an alias loop cannot
+/// grow the answer forever.
+const MAX_CNAME_CHAIN: usize = 8;
+
+impl ZoneStore {
+    /// The zone `name` belongs to, the one with the longest origin when zones nest.
+    pub fn authoritative_for(&self, name: &[String]) -> Option<&Zone> {
+        self.zones
+            .iter()
+            .filter(|z| z.contains(name))
+            .max_by_key(|z| z.origin.len())
+    }
+}
+
+impl Zone {
+    /// Turns a query for a name of this zone into its authoritative response:
+    /// the matching RRset (following CNAMEs within the zone), NODATA or NXDOMAIN
+    /// with the SOA in the Authority section, or SERVFAIL once the zone expired.
+    /// Answers leading to other names of the zone bring their records along in
+    /// the Additional section, as described by [`Zone::additional_records`].
+    pub fn answer(&self, message: &mut DnsMessage) {
+        message.answers.clear();
+        message.authorities.clear();
+        message.additionals.clear();
+
+        if self.expired {
+            message.header.rcode = ResponseCode::ServerFailure;
+            message.to_response();
+            return;
+        }
+        message.header.aa = AuthoritativeAnswer::Authoritative;
+        message.header.rcode = ResponseCode::NoErrorCondition;
+
+        let Some(question) = message.questions.first() else {
+            message.to_response();
+            return;
+        };
+        let qtype = question.qtype;
+        let mut name = question.qname.clone();
+        let mut negative = false;
+        for _ in 0..MAX_CNAME_CHAIN {
+            let matching = self
+                .records
+                .iter()
+                .filter(|r| names_equal(&r.name, &name))
+                .filter(|r| qtype == QType::WildCard || r.typ == qtype)
+                .cloned()
+                .collect::<Vec<_>>();
+            if !matching.is_empty() {
+                message.answers.extend(matching);
+                break;
+            }
+
+            let cname = self
+                .rrset(&name, QType::CNAME)
+                .find_map(|r| match &r.rdata {
+                    RData::CNAME(target) => Some((r.clone(), target.clone())),
+                    _ => None,
+                });
+            // After a CNAME, the RCODE is about its target (RFC 6604).
+            let Some((record, target)) = cname else {
+                if !self.node_exists(&name) {
+                    message.header.rcode = ResponseCode::NameError;
+                }
+                negative = true;
+                break;
+            };
+            message.answers.push(record);
+            if !self.contains(&target) {
+                break;
+            }
+            name = target;
+        }
+
+        if negative {
+            message
+                .authorities
+                .extend(self.records.iter().filter(|r| r.typ == QType::SOA).cloned());
+        }
+        message.additionals = self.additional_records(&message.answers);
+        message.to_response();
+    }
+
+    /// The records of the zone a client will look up next after `answers`: the
+    /// addresses of SRV targets (RFC 2782); for NAPTR replacements the SRV
+    /// records with the `S` flag or the addresses with the `A` flag (RFC 3403
+    /// section 4.2); for SVCB and HTTPS the records AliasMode points at and the
+    /// addresses of ServiceMode targets (RFC 9460 section 4.1). Indirections are
+    /// followed as far as CNAMEs are, records already answered are left out.
+    pub fn additional_records(&self, answers: &[DnsRecord]) -> Vec<DnsRecord> {
+        const ADDRESSES: &[QType] = &[QType::A, QType::AAAA];
+        let mut additionals: Vec<DnsRecord> = Vec::new();
+        let mut pending = answers.to_vec();
+        for _ in 0..MAX_CNAME_CHAIN {
+            let mut next = Vec::new();
+            for record in &pending {
+                let (name, types): (&[String], &[QType]) = match &record.rdata {
+                    RData::SRV(srv) => (&srv.target, ADDRESSES),
+                    RData::NAPTR(naptr) => {
+                        let flags = naptr.flags.to_ascii_uppercase();
+                        if flags.contains(&b'S') {
+                            (&naptr.replacement, &[QType::SRV])
+                        } else if flags.contains(&b'A') {
+                            (&naptr.replacement, ADDRESSES)
+                        } else {
+                            continue;
+                        }
+                    }
+                    RData::SVCB(svcb) | RData::HTTPS(svcb) => {
+                        match (svcb.is_alias(), svcb.target.is_empty()) {
+                            // The service does not exist.
+                            (true, true) => continue,
+                            (true, false) => (&svcb.target, std::slice::from_ref(&record.typ)),
+                            // The endpoint is the owner name itself.
+                            (false, true) => (&record.name, ADDRESSES),
+                            (false, false) => (&svcb.target, ADDRESSES),
+                        }
+                    }
+                    _ => continue,
+                };
+                for &typ in types {
+                    for found in self.rrset(name, typ) {
+                        if !answers.contains(found)
+                            && !additionals.contains(found)
+                            && !next.contains(found)
+                        {
+                            next.push(found.clone());
+                        }
+                    }
+                }
+            }
+            if next.is_empty() {
+                break;
+            }
+            additionals.extend(next.iter().cloned());
+            pending = next;
+        }
+        additionals
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        dns_header::header::{AuthoritativeAnswer, ResponseCode},
+        dns_message::message::DnsMessage,
+        dns_record::record::{DnsRecord, Naptr, RData, Srv, Svcb},
+        dns_zone::zone::{Zone, ZoneStore},
+        resrec::{QClass, QType},
+    };
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    fn name(labels: &[&str]) -> Vec<String> {
+        labels.iter().map(|x| x.to_string()).collect()
+    }
+
+    fn zone() -> Zone {
+        let mut zone = Zone::with_default_soa(name(&["example", "com"]));
+        zone.records.push(DnsRecord {
+            name: name(&["www", "example", "com"]),
+            typ: QType::A,
+            class: QClass::IN,
+            ttl: 60,
+            rdata: RData::A(Ipv4Addr::new(10, 0, 0, 1)),
+        });
+        zone.records.push(DnsRecord {
+            name: name(&["web", "example", "com"]),
+            typ: QType::CNAME,
+            class: QClass::IN,
+            ttl: 60,
+            rdata: RData::CNAME(name(&["www", "example", "com"])),
+        });
+        zone
+    }
+
+    fn answer(qname: &[&str], qtype: QType) -> DnsMessage {
+        let mut message = DnsMessage::query(name(qname), qtype);
+        zone().answer(&mut message);
+        message
+    }
+
+    #[test]
+    fn answer_test() {
+        let message = answer(&["www", "example", "com"], QType::A);
+        assert_eq!(message.header.rcode, ResponseCode::NoErrorCondition);
+        assert_eq!(message.header.aa, AuthoritativeAnswer::Authoritative);
+        assert_eq!(message.header.ancount, 1);
+        assert_eq!(
+            message.answers[0].rdata,
+            RData::A(Ipv4Addr::new(10, 0, 0, 1))
+        );
+    }
+
+    #[test]
+    fn cname_test() {
+        let message = answer(&["web", "example", "com"], QType::A);
+        let types = message.answers.iter().map(|r| r.typ).collect::<Vec<_>>();
+        assert_eq!(types, vec![QType::CNAME, QType::A]);
+    }
+
+    #[test]
+    fn nodata_test() {
+        let message = answer(&["www", "example", "com"], QType::MX);
+        assert_eq!(message.header.rcode, ResponseCode::NoErrorCondition);
+        assert!(message.answers.is_empty());
+        assert_eq!(message.authorities[0].typ, QType::SOA);
+    }
+
+    #[test]
+    fn empty_non_terminal_test() {
+        let mut zone = zone();
+        zone.records[1].name = name(&["a", "b", "example", "com"]);
+        let mut message = DnsMessage::query(name(&["b", "example", "com"]), QType::A);
+        zone.answer(&mut message);
+        assert_eq!(message.header.rcode, ResponseCode::NoErrorCondition);
+        assert_eq!(message.authorities[0].typ, QType::SOA);
+    }
+
+    #[test]
+    fn cname_to_nxdomain_test() {
+        let mut zone = zone();
+        zone.records[2].rdata = RData::CNAME(name(&["ftp", "example", "com"]));
+        let mut message = DnsMessage::query(name(&["web", "example", "com"]), QType::A);
+        zone.answer(&mut message);
+        assert_eq!(message.header.rcode, ResponseCode::NameError);
+        assert_eq!(message.answers.len(), 1);
+        assert_eq!(message.authorities[0].typ, QType::SOA);
+    }
+
+    #[test]
+    fn nxdomain_test() {
+        let message = answer(&["ftp", "example", "com"], QType::A);
+        assert_eq!(message.header.rcode, ResponseCode::NameError);
+        assert_eq!(message.header.nscount, 1);
+    }
+
+    #[test]
+    fn expired_test() {
+        let mut zone = zone();
+        zone.expired = true;
+        let mut message = DnsMessage::query(name(&["www", "example", "com"]), QType::A);
+        zone.answer(&mut message);
+        assert_eq!(message.header.rcode, ResponseCode::ServerFailure);
+        assert!(message.answers.is_empty());
+    }
+
+    #[test]
+    fn additional_records_test() {
+        let mut zone = zone();
+        let mut add = |labels: &[&str], typ, rdata| {
+            zone.records.push(DnsRecord {
+                name: name(labels),
+                typ,
+                class: QClass::IN,
+                ttl: 60,
+                rdata,
+            })
+        };
+        add(
+            &["_sip", "_udp", "example", "com"],
+            QType::SRV,
+            RData::SRV(Srv {
+                priority: 10,
+                weight: 0,
+                port: 5060,
+                target: name(&["www", "example", "com"]),
+            }),
+        );
+        add(
+            &["_sip", "_udp", "example", "com"],
+            QType::SRV,
+            RData::SRV(Srv {
+                priority: 20,
+                weight: 0,
+                port: 5060,
+                target: name(&["backup", "example", "org"]),
+            }),
+        );
+        add(
+            &["www", "example", "com"],
+            QType::AAAA,
+            RData::AAAA(Ipv6Addr::LOCALHOST),
+        );
+        add(
+            &["example", "com"],
+            QType::NAPTR,
+            RData::NAPTR(Naptr {
+                order: 100,
+                preference: 10,
+                flags: b"s".to_vec(),
+                services: b"SIP+D2U".to_vec(),
+                regexp: Vec::new(),
+                replacement: name(&["_sip", "_udp", "example", "com"]),
+            }),
+        );
+
+        let mut message = DnsMessage::query(name(&["_sip", "_udp", "example", "com"]), QType::SRV);
+        zone.answer(&mut message);
+        assert_eq!(message.header.ancount, 2);
+        let types = message
+            .additionals
+            .iter()
+            .map(|r| r.typ)
+            .collect::<Vec<_>>();
+        assert_eq!(types, vec![QType::A, QType::AAAA]);
+        assert_eq!(message.header.arcount, 2);
+
+        let mut message = DnsMessage::query(name(&["example", "com"]), QType::NAPTR);
+        zone.answer(&mut message);
+        let types = message
+            .additionals
+            .iter()
+            .map(|r| r.typ)
+            .collect::<Vec<_>>();
+        assert_eq!(types, vec![QType::SRV, QType::SRV, QType::A, QType::AAAA]);
+
+        let message = answer(&["www", "example", "com"], QType::A);
+        assert!(message.additionals.is_empty());
+    }
+
+    #[test]
+    fn svcb_additional_records_test() {
+        let mut zone = zone();
+        let https = |owner: &[&str], priority, target: &[&str]| DnsRecord {
+            name: name(owner),
+            typ: QType::HTTPS,
+            class: QClass::IN,
+            ttl: 60,
+            rdata: RData::HTTPS(Svcb {
+                priority,
+                target: name(target),
+                params: Vec::new(),
+            }),
+        };
+        zone.records
+            .push(https(&["example", "com"], 0, &["cdn", "example", "com"]));
+        zone.records.push(https(&["cdn", "example", "com"], 1, &[]));
+        zone.records.push(https(&["web", "example", "com"], 0, &[]));
+        zone.records.push(DnsRecord {
+            name: name(&["cdn", "example", "com"]),
+            typ: QType::A,
+            class: QClass::IN,
+            ttl: 60,
+            rdata: RData::A(Ipv4Addr::new(10, 0, 0, 2)),
+        });
+
+        let message = {
+            let mut message = DnsMessage::query(name(&["example", "com"]), QType::HTTPS);
+            zone.answer(&mut message);
+            message
+        };
+        let types = message
+            .additionals
+            .iter()
+            .map(|r| r.typ)
+            .collect::<Vec<_>>();
+        assert_eq!(types, vec![QType::HTTPS, QType::A]);
+
+        // AliasMode to the root: the service does not exist.
+        let mut message = DnsMessage::query(name(&["web", "example", "com"]), QType::HTTPS);
+        zone.answer(&mut message);
+        assert_eq!(message.answers.len(), 1);
+        assert!(message.additionals.is_empty());
+    }
+
+    #[test]
+    fn authoritative_for_test() {
+        let mut store = ZoneStore {
+            zones: vec![
+                zone(),
+                Zone::with_default_soa(name(&["sub", "example", "com"])),
+            ],
+        };
+        let found = store.authoritative_for(&name(&["www", "sub", "example", "com"]));
+        assert_eq!(found.unwrap().origin, name(&["sub", "example", "com"]));
+        store.zones.pop();
+        let found = store.authoritative_for(&name(&["www", "sub", "example", "com"]));
+        assert_eq!(found.unwrap().origin, name(&["example", "com"]));
+        assert!(store
+            .authoritative_for(&name(&["example", "org"]))
+            .is_none());
+    }
+}
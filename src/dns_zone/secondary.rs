@@ -0,0 +1,34 @@
+use super::journal::ZoneDiff;
+use crate::{dns_message::tsig::TsigKey, dns_record::record::DnsRecord};
+use std::{net::SocketAddr, path::PathBuf};
+
+/// A zone served as a secondary: copied from its primary server with zone
+/// transfers, and kept fresh following the timers of its SOA record
+/// (RFC 1034 section 4.3.5) or right away when the primary sends a NOTIFY.
+#[derive(Debug, Clone)]
+pub struct SecondaryZone {
+    pub origin: Vec<String>,
+    pub primary: SocketAddr,
+    /// The key signing SOA checks and transfers, when the primary requires one.
+    pub key: Option<TsigKey>,
+    /// Where the zone is persisted between restarts.
+    pub file: PathBuf,
+    /// Unix time of the last successful refresh, `None` while the zone was never
+    /// transferred nor loaded.
+    pub last_refresh: Option<u64>,
+    /// Unix time at which the primary is next checked for a newer serial.
+    pub next_check: u64,
+    /// Set by a NOTIFY from the primary, checks the primary on the next tick.
+    pub notified: bool,
+}
+
+/// The records received in answer to an AXFR or IXFR request, once complete.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Transfer {
+    /// A single SOA no newer than ours: nothing to transfer.
+    UpToDate,
+    /// The whole zone, without the closing SOA.
+    Full(Vec<DnsRecord>),
+    /// The changes since our version, oldest first.
+    Incremental(Vec<ZoneDiff>),
+}
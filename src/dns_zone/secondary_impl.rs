@@ -0,0 +1,554 @@
+use super::{
+    journal::ZoneDiff,
+    secondary::{SecondaryZone, Transfer},
+    zone::{Zone, ZoneStore},
+    zone_impl::serial_gt,
+};
+use crate::{
+    dns_header::header::ResponseCode,
+    dns_message::{message::DnsMessage, tsig::TsigSession},
+    dns_name::name::names_equal,
+    dns_record::record::{DnsRecord, RData},
+    resrec::{QClass, QType},
+    tcp::{read_message, write_message},
+};
+use anyhow::{anyhow, bail, ensure, Context};
+use std::{
+    fs,
+    net::{IpAddr, TcpStream, UdpSocket},
+    path::PathBuf,
+    sync::Mutex,
+    time::{Duration, UNIX_EPOCH},
+};
+
+/// Seconds between checks of a zone whose SOA is not known yet.
+const DEFAULT_RETRY: u64 = 60;
+
+/// How long to wait on the primary before giving up on a check or transfer.
+const PRIMARY_TIMEOUT: Duration = Duration::from_secs(5);
+
+impl SecondaryZone {
+    pub fn new(origin: Vec<String>, primary: std::net::SocketAddr, file: PathBuf) -> Self {
+        SecondaryZone {
+            origin,
+            primary,
+            key: None,
+            file,
+            last_refresh: None,
+            next_check: 0,
+            notified: false,
+        }
+    }
+
+    /// Loads the persisted copy of the zone into `zones`, counting its last
+    /// refresh from the file's modification time. Without one, an expired empty
+    /// zone stands in so queries fail with SERVFAIL until the first transfer.
+    pub fn load(&mut self, zones: &mut ZoneStore) {
+        match read_zone_file(&self.origin, &self.file) {
+            Ok((zone, modified)) => {
+                self.last_refresh = Some(modified);
+                zones.reload(zone);
+            }
+            Err(e) => {
+                log::warn!("No usable copy of {} on disk: {:#}", self.file.display(), e);
+                zones.reload(Zone {
+                    origin: self.origin.clone(),
+                    class: QClass::IN,
+                    records: Vec::new(),
+                    journal: Default::default(),
+                    expired: true,
+                });
+            }
+        }
+    }
+
+    /// Checks the primary for a newer version of the zone and transfers it if
+    /// there is one, then schedules the next check after the SOA refresh interval,
+    /// or the retry interval on failure. The zone expires once it could not be
+    /// refreshed for the SOA expire interval.
+    pub fn check(&mut self, zones: &Mutex<ZoneStore>, now: u64) {
+        let current = zones.lock().unwrap().find(&self.origin).cloned();
+        let soa = current.as_ref().and_then(|z| z.soa().cloned());
+        let (refresh, retry, expire) = match &soa {
+            Some(soa) => (soa.refresh.into(), soa.retry.into(), soa.expire.into()),
+            None => (DEFAULT_RETRY, DEFAULT_RETRY, 0),
+        };
+
+        match self.refresh(current.as_ref(), now) {
+            Ok(updated) => {
+                let mut zones = zones.lock().unwrap();
+                match updated {
+                    Some(zone) => {
+                        if let Err(e) = write_zone_file(&zone, &self.file) {
+                            log::error!("Failed to persist {}: {:#}", self.file.display(), e);
+                        }
+                        log::info!(
+                            "Transferred {} serial {:?}",
+                            self.file.display(),
+                            zone.serial()
+                        );
+                        zones.reload(zone);
+                    }
+                    None => {
+                        if let Some(zone) = zones.find_mut(&self.origin) {
+                            zone.expired = zone.records.is_empty();
+                        }
+                    }
+                }
+                self.last_refresh = Some(now);
+                self.next_check = now + refresh;
+            }
+            Err(e) => {
+                log::warn!("Failed to refresh {}: {:#}", self.file.display(), e);
+                self.next_check = now + retry;
+                let expired = match self.last_refresh {
+                    Some(last_refresh) => now >= last_refresh + expire,
+                    None => true,
+                };
+                if let (true, Some(zone)) = (expired, zones.lock().unwrap().find_mut(&self.origin))
+                {
+                    zone.expired = true;
+                }
+            }
+        }
+    }
+
+    /// Fetches the zone from the primary when its serial is newer than ours,
+    /// incrementally when we already hold a copy. `None` when we are up to date.
+    fn refresh(&self, current: Option<&Zone>, now: u64) -> anyhow::Result<Option<Zone>> {
+        let current = current.filter(|z| z.soa().is_some());
+        if let Some(serial) = current.and_then(Zone::serial) {
+            if !serial_gt(self.primary_serial(now)?, serial) {
+                return Ok(None);
+            }
+        }
+        let transfer = self.transfer(current, now)?;
+        apply_transfer(&self.origin, current, transfer)
+    }
+
+    /// Queries the primary for the SOA serial of the zone over UDP.
+    fn primary_serial(&self, now: u64) -> anyhow::Result<u32> {
+        let mut request = DnsMessage::query(self.origin.clone(), QType::SOA);
+        let mut session = self.sign(&mut request, now);
+
+        let local = match self.primary.ip() {
+            IpAddr::V4(_) => "0.0.0.0:0",
+            IpAddr::V6(_) => "[::]:0",
+        };
+        let socket = UdpSocket::bind(local)?;
+        socket.set_read_timeout(Some(PRIMARY_TIMEOUT))?;
+        socket.send_to(&request.as_bytes(), self.primary)?;
+
+        let mut buf = [0; 512];
+        loop {
+            let (size, source) = socket.recv_from(&mut buf)?;
+            if source != self.primary {
+                continue;
+            }
+            let response = self.read_response(&request, &buf[..size], session.as_mut(), now)?;
+            return response
+                .answers
+                .iter()
+                .find_map(|r| match &r.rdata {
+                    RData::SOA(soa) => Some(soa.serial),
+                    _ => None,
+                })
+                .ok_or_else(|| anyhow!("primary answered without an SOA"));
+        }
+    }
+
+    /// Transfers the zone over TCP, with IXFR from our serial when we have a copy
+    /// and AXFR otherwise.
+    fn transfer(&self, current: Option<&Zone>, now: u64) -> anyhow::Result<Transfer> {
+        let mut request = DnsMessage::query(self.origin.clone(), QType::AXFR);
+        let current_soa = current.and_then(|z| z.records.iter().find(|r| r.typ == QType::SOA));
+        if let Some(soa) = current_soa {
+            request.questions[0].qtype = QType::IXFR;
+            request.authorities.push(soa.clone());
+            request.update_counts();
+        }
+        let mut session = self.sign(&mut request, now);
+
+        let mut stream = TcpStream::connect_timeout(&self.primary, PRIMARY_TIMEOUT)?;
+        stream.set_read_timeout(Some(PRIMARY_TIMEOUT))?;
+        write_message(&mut stream, &request.as_bytes())?;
+
+        let current_serial = current.and_then(Zone::serial);
+        let mut records = Vec::new();
+        let mut first = true;
+        loop {
+            let bytes = read_message(&mut stream)?;
+            let response = self.read_response(&request, &bytes, session.as_mut(), now)?;
+            records.extend(response.answers);
+            if let Some(transfer) =
+                read_transfer(&records, first.then_some(current_serial).flatten())
+            {
+                return Ok(transfer);
+            }
+            first = false;
+        }
+    }
+
+    fn sign(&self, request: &mut DnsMessage, now: u64) -> Option<TsigSession> {
+        let mut session = self.key.as_ref()?.session();
+        session.sign(request, now);
+        Some(session)
+    }
+
+    /// Parses a response from the primary, checking it answers `request` and is
+    /// signed when the request was.
+    fn read_response(
+        &self,
+        request: &DnsMessage,
+        bytes: &[u8],
+        session: Option<&mut TsigSession>,
+        now: u64,
+    ) -> anyhow::Result<DnsMessage> {
+        if let Some(session) = session {
+            session
+                .verify(bytes, now)
+                .context("bad TSIG from primary")?;
+        }
+        let response = DnsMessage::parse(bytes).ok_or_else(|| anyhow!("malformed response"))?;
+        ensure!(
+            response.header.packet_id == request.header.packet_id,
+            "response ID does not match"
+        );
+        ensure!(
+            response.header.rcode == ResponseCode::NoErrorCondition,
+            "primary answered {}",
+            response.header.rcode
+        );
+        Ok(response)
+    }
+}
+
+/// Handles a NOTIFY (RFC 1996): a notification for one of `secondaries` coming
+/// from its primary schedules an immediate check. Returns the RCODE to reply with.
+pub fn notify(
+    secondaries: &mut [SecondaryZone],
+    message: &DnsMessage,
+    source: IpAddr,
+) -> ResponseCode {
+    let [question] = &message.questions[..] else {
+        return ResponseCode::FormatError;
+    };
+    let secondary = secondaries
+        .iter_mut()
+        .find(|s| names_equal(&s.origin, &question.qname));
+    match secondary {
+        Some(secondary) if secondary.primary.ip() == source => {
+            secondary.notified = true;
+            ResponseCode::NoErrorCondition
+        }
+        Some(_) => ResponseCode::Refused,
+        None => ResponseCode::NotAuth,
+    }
+}
+
+/// Checks every secondary zone that is due or was notified. Checks run on a copy
+/// of each secondary so the lock is not held while talking to primaries, and a
+/// NOTIFY arriving meanwhile is kept for the next call.
+pub fn check_due(secondaries: &Mutex<Vec<SecondaryZone>>, zones: &Mutex<ZoneStore>, now: u64) {
+    let due = secondaries
+        .lock()
+        .unwrap()
+        .iter_mut()
+        .enumerate()
+        .filter(|(_, s)| s.notified || s.next_check <= now)
+        .map(|(i, s)| {
+            s.notified = false;
+            (i, s.clone())
+        })
+        .collect::<Vec<_>>();
+
+    for (i, mut secondary) in due {
+        secondary.check(zones, now);
+        if let Some(shared) = secondaries.lock().unwrap().get_mut(i) {
+            shared.last_refresh = secondary.last_refresh;
+            shared.next_check = secondary.next_check;
+        }
+    }
+}
+
+/// Interprets the records received so far in answer to an AXFR or IXFR request.
+/// `None` while the transfer is incomplete. `current_serial` is only given while
+/// looking at the first message, where a lone SOA no newer than ours means there
+/// is nothing to transfer.
+pub fn read_transfer(records: &[DnsRecord], current_serial: Option<u32>) -> Option<Transfer> {
+    let serial = soa_serial(records.first()?)?;
+    if records.len() == 1 {
+        return match current_serial {
+            Some(current) if !serial_gt(serial, current) => Some(Transfer::UpToDate),
+            _ => None,
+        };
+    }
+
+    // An IXFR response follows the first SOA with the SOA of an older version.
+    let incremental = soa_serial(&records[1]).is_some_and(|x| x != serial);
+    if !incremental {
+        let end = records[1..].iter().position(|r| r.typ == QType::SOA)? + 1;
+        return Some(Transfer::Full(records[..end].to_vec()));
+    }
+
+    let mut diffs = Vec::new();
+    let mut rest = &records[1..];
+    loop {
+        let old_soa = rest.first()?;
+        if soa_serial(old_soa)? == serial {
+            return Some(Transfer::Incremental(diffs));
+        }
+        let deleted_len = rest[1..].iter().position(|r| r.typ == QType::SOA)?;
+        let new_soa = &rest[1 + deleted_len];
+        let after_new = &rest[2 + deleted_len..];
+        let added_len = after_new.iter().position(|r| r.typ == QType::SOA)?;
+        diffs.push(ZoneDiff {
+            old_soa: old_soa.clone(),
+            deleted: rest[1..1 + deleted_len].to_vec(),
+            new_soa: new_soa.clone(),
+            added: after_new[..added_len].to_vec(),
+        });
+        rest = &after_new[added_len..];
+    }
+}
+
+/// Builds the new version of a zone from a completed transfer, `None` when there
+/// is nothing new.
+pub fn apply_transfer(
+    origin: &[String],
+    current: Option<&Zone>,
+    transfer: Transfer,
+) -> anyhow::Result<Option<Zone>> {
+    let records = match transfer {
+        Transfer::UpToDate => return Ok(None),
+        Transfer::Full(records) => records,
+        Transfer::Incremental(diffs) => {
+            let mut records = current
+                .ok_or_else(|| anyhow!("incremental transfer without a current zone"))?
+                .records
+                .clone();
+            for diff in diffs {
+                let serial = records.iter().find_map(soa_serial);
+                ensure!(
+                    serial == Some(diff.old_serial()),
+                    "incremental transfer does not start at our serial"
+                );
+                for record in &diff.deleted {
+                    if let Some(i) = records.iter().position(|r| r == record) {
+                        records.remove(i);
+                    }
+                }
+                records.retain(|r| r.typ != QType::SOA);
+                records.insert(0, diff.new_soa.clone());
+                records.extend(diff.added.iter().cloned());
+            }
+            records
+        }
+    };
+
+    ensure!(
+        records.first().is_some_and(|r| r.typ == QType::SOA),
+        "transferred zone does not start with an SOA"
+    );
+    Ok(Some(Zone {
+        origin: origin.to_vec(),
+        class: QClass::IN,
+        records,
+        journal: Default::default(),
+        expired: false,
+    }))
+}
+
+/// Persists the records of a zone as a master file, writing to a temporary file
+/// first so a crash never leaves a truncated copy behind.
+pub fn write_zone_file(zone: &Zone, file: &PathBuf) -> anyhow::Result<()> {
+    let temporary = file.with_extension("tmp");
+    fs::write(&temporary, zone.to_master_file())?;
+    fs::rename(&temporary, file)?;
+    Ok(())
+}
+
+/// Reads a zone persisted by [`write_zone_file`], with the file's modification
+/// time in seconds since the epoch.
+pub fn read_zone_file(origin: &[String], file: &PathBuf) -> anyhow::Result<(Zone, u64)> {
+    let text = fs::read_to_string(file)?;
+    let modified = fs::metadata(file)?
+        .modified()?
+        .duration_since(UNIX_EPOCH)?
+        .as_secs();
+
+    let records = Zone::from_master_file(&text, Some(origin))?.records;
+    let Some(zone) = apply_transfer(origin, None, Transfer::Full(records))? else {
+        bail!("empty zone file");
+    };
+    Ok((zone, modified))
+}
+
+fn soa_serial(record: &DnsRecord) -> Option<u32> {
+    match &record.rdata {
+        RData::SOA(soa) if record.typ == QType::SOA => Some(soa.serial),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_transfer, notify, read_transfer, read_zone_file, write_zone_file};
+    use crate::{
+        dns_header::header::ResponseCode,
+        dns_message::message::DnsMessage,
+        dns_record::record::{DnsRecord, RData},
+        dns_zone::{
+            secondary::{SecondaryZone, Transfer},
+            zone::{Zone, ZoneStore},
+        },
+        resrec::{QClass, QType},
+    };
+    use std::net::{IpAddr, Ipv4Addr};
+
+    fn name(labels: &[&str]) -> Vec<String> {
+        labels.iter().map(|x| x.to_string()).collect()
+    }
+
+    fn origin() -> Vec<String> {
+        name(&["example", "com"])
+    }
+
+    fn soa(serial: u32) -> DnsRecord {
+        let mut zone = Zone::with_default_soa(origin());
+        zone.set_serial(serial);
+        zone.records.remove(0)
+    }
+
+    fn a(host: &str, last: u8) -> DnsRecord {
+        DnsRecord {
+            name: name(&[host, "example", "com"]),
+            typ: QType::A,
+            class: QClass::IN,
+            ttl: 60,
+            rdata: RData::A(Ipv4Addr::new(10, 0, 0, last)),
+        }
+    }
+
+    #[test]
+    fn read_full_transfer_test() {
+        let records = vec![soa(5), a("www", 1), a("ftp", 2), soa(5)];
+        assert_eq!(read_transfer(&records[..3], Some(1)), None);
+        assert_eq!(
+            read_transfer(&records, Some(1)),
+            Some(Transfer::Full(records[..3].to_vec()))
+        );
+    }
+
+    #[test]
+    fn read_empty_full_transfer_test() {
+        let records = vec![soa(5), soa(5)];
+        assert_eq!(
+            read_transfer(&records, None),
+            Some(Transfer::Full(vec![soa(5)]))
+        );
+    }
+
+    #[test]
+    fn read_up_to_date_test() {
+        assert_eq!(read_transfer(&[soa(5)], Some(5)), Some(Transfer::UpToDate));
+        assert_eq!(read_transfer(&[soa(5)], Some(4)), None);
+        assert_eq!(read_transfer(&[soa(5)], None), None);
+    }
+
+    #[test]
+    fn read_incremental_transfer_test() {
+        let records = vec![
+            soa(3),
+            soa(1),
+            a("www", 1),
+            soa(2),
+            a("www", 2),
+            soa(2),
+            soa(3),
+            a("ftp", 3),
+            soa(3),
+        ];
+        assert_eq!(read_transfer(&records[..8], None), None);
+
+        let Some(Transfer::Incremental(diffs)) = read_transfer(&records, None) else {
+            panic!("expected an incremental transfer");
+        };
+        assert_eq!(diffs.len(), 2);
+        assert_eq!(diffs[0].deleted, vec![a("www", 1)]);
+        assert_eq!(diffs[0].added, vec![a("www", 2)]);
+        assert!(diffs[1].deleted.is_empty());
+        assert_eq!(diffs[1].added, vec![a("ftp", 3)]);
+
+        let mut current = Zone::with_default_soa(origin());
+        current.records.push(a("www", 1));
+        let zone = apply_transfer(&origin(), Some(&current), Transfer::Incremental(diffs))
+            .unwrap()
+            .unwrap();
+        assert_eq!(zone.serial(), Some(3));
+        assert_eq!(zone.records[1..], [a("www", 2), a("ftp", 3)]);
+    }
+
+    #[test]
+    fn incremental_transfer_from_wrong_serial_test() {
+        let records = vec![soa(3), soa(2), soa(3), a("ftp", 3), soa(3)];
+        let transfer = read_transfer(&records, None).unwrap();
+        let current = Zone::with_default_soa(origin());
+        assert!(apply_transfer(&origin(), Some(&current), transfer).is_err());
+    }
+
+    #[test]
+    fn zone_file_round_trip_test() {
+        let mut zone = Zone::with_default_soa(origin());
+        zone.records.push(a("www", 1));
+        zone.records.push(DnsRecord {
+            name: origin(),
+            typ: QType::Unknown(59),
+            class: QClass::IN,
+            ttl: 3600,
+            rdata: RData::Raw(vec![0xe5, 0x8e, 13, 2, 0xab]),
+        });
+        let file = std::env::temp_dir().join(format!("secondary-test-{}.db", std::process::id()));
+
+        write_zone_file(&zone, &file).unwrap();
+        let (loaded, _) = read_zone_file(&origin(), &file).unwrap();
+        std::fs::remove_file(&file).unwrap();
+        assert_eq!(loaded.records, zone.records);
+    }
+
+    #[test]
+    fn load_without_file_test() {
+        let file = std::env::temp_dir().join("secondary-test-missing.db");
+        let primary = "127.0.0.1:53".parse().unwrap();
+        let mut secondary = SecondaryZone::new(origin(), primary, file);
+        let mut zones = ZoneStore::default();
+        secondary.load(&mut zones);
+
+        assert!(zones.zones[0].expired);
+        assert_eq!(secondary.last_refresh, None);
+    }
+
+    #[test]
+    fn notify_test() {
+        let primary = "10.0.0.1:53".parse().unwrap();
+        let mut secondaries = vec![SecondaryZone::new(origin(), primary, "x.db".into())];
+        let message = DnsMessage::query(origin(), QType::SOA);
+
+        let stranger = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 9));
+        assert_eq!(
+            notify(&mut secondaries, &message, stranger),
+            ResponseCode::Refused
+        );
+        assert!(!secondaries[0].notified);
+
+        let rcode = notify(&mut secondaries, &message, primary.ip());
+        assert_eq!(rcode, ResponseCode::NoErrorCondition);
+        assert!(secondaries[0].notified);
+
+        let other = DnsMessage::query(name(&["example", "org"]), QType::SOA);
+        assert_eq!(
+            notify(&mut secondaries, &other, primary.ip()),
+            ResponseCode::NotAuth
+        );
+    }
+}
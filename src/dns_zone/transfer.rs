@@ -0,0 +1,10 @@
+use std::net::IpAddr;
+
+/// Who may transfer zones: clients connecting from one of `addresses`, or signing
+/// their request with one of the TSIG `keys`. Empty lists allow nobody.
+#[derive(Debug, Clone, Default)]
+pub struct TransferAcl {
+    pub addresses: Vec<IpAddr>,
+    /// Names of the TSIG keys granting access.
+    pub keys: Vec<Vec<String>>,
+}
@@ -0,0 +1,362 @@
+use super::{
+    transfer::TransferAcl,
+    zone::{Zone, ZoneStore},
+    zone_impl::serial_gt,
+};
+use crate::{
+    dns_header::header::{AuthoritativeAnswer, ResponseCode},
+    dns_message::{message::DnsMessage, tsig::TsigSession},
+    dns_name::name::names_equal,
+    dns_record::record::{DnsRecord, RData},
+    resrec::QType,
+};
+use std::net::IpAddr;
+
+/// Largest message that fits the two-byte length prefix used over TCP.
+pub const MAX_TCP_MESSAGE_SIZE: usize = 65535;
+
+/// Room kept free in each transfer message for the TSIG record signing it.
+const TSIG_RESERVE_IN_BYTES: usize = 512;
+
+impl TransferAcl {
+    /// Whether `client` may transfer zones, given the TSIG session of its request.
+    pub fn allows(&self, client: IpAddr, tsig: Option<&TsigSession>) -> bool {
+        let signed_by_allowed_key = tsig.is_some_and(|session| {
+            session.error.is_none() && self.keys.iter().any(|k| names_equal(k, &session.key_name))
+        });
+        signed_by_allowed_key || self.addresses.contains(&client)
+    }
+}
+
+impl Zone {
+    /// The records of the zone in AXFR order: the SOA record, every other record,
+    /// then the SOA record again to mark the end of the transfer.
+    pub fn axfr_records(&self) -> Vec<DnsRecord> {
+        let Some(soa) = self.records.iter().find(|r| r.typ == QType::SOA) else {
+            return Vec::new();
+        };
+        let mut records = vec![soa.clone()];
+        records.extend(self.records.iter().filter(|r| r.typ != QType::SOA).cloned());
+        records.push(soa.clone());
+        records
+    }
+}
+
+impl ZoneStore {
+    /// Builds the messages answering an AXFR request (RFC 5936), spreading the zone
+    /// over as many messages as needed to fit each in a TCP frame. Fails with
+    /// NOTAUTH for zones not served here, and SERVFAIL once a secondary zone
+    /// expired.
+    pub fn axfr(&self, request: &DnsMessage) -> Result<Vec<DnsMessage>, ResponseCode> {
+        let [question] = &request.questions[..] else {
+            return Err(ResponseCode::FormatError);
+        };
+        let zone = self.find(&question.qname).ok_or(ResponseCode::NotAuth)?;
+        if zone.expired {
+            return Err(ResponseCode::ServerFailure);
+        }
+        Ok(transfer_messages(request, zone.axfr_records()))
+    }
+
+    /// Builds the messages answering an IXFR request (RFC 1995), whose Authority
+    /// section holds the SOA of the client's version. Clients up to date get the
+    /// current SOA alone, others the condensed changes since their version, or the
+    /// whole zone when the journal does not reach back that far. Expired zones fail
+    /// with SERVFAIL.
+    ///
+    /// Over UDP the answer must fit a single message of `udp_limit` bytes, a single
+    /// SOA telling the client to retry over TCP is sent when it does not.
+    pub fn ixfr(
+        &self,
+        request: &DnsMessage,
+        udp_limit: Option<usize>,
+    ) -> Result<Vec<DnsMessage>, ResponseCode> {
+        let [question] = &request.questions[..] else {
+            return Err(ResponseCode::FormatError);
+        };
+        let client_serial = request
+            .authorities
+            .iter()
+            .find_map(|r| match &r.rdata {
+                RData::SOA(soa) if r.typ == QType::SOA => Some(soa.serial),
+                _ => None,
+            })
+            .ok_or(ResponseCode::FormatError)?;
+        let zone = self.find(&question.qname).ok_or(ResponseCode::NotAuth)?;
+        if zone.expired {
+            return Err(ResponseCode::ServerFailure);
+        }
+        let soa = zone
+            .records
+            .iter()
+            .find(|r| r.typ == QType::SOA)
+            .ok_or(ResponseCode::ServerFailure)?;
+        let current_soa_only = || transfer_messages(request, vec![soa.clone()]);
+
+        let serial = zone.serial().unwrap_or_default();
+        if !serial_gt(serial, client_serial) {
+            return Ok(current_soa_only());
+        }
+        let records = match zone.journal.condensed_since(client_serial) {
+            Some(diff) => {
+                let mut records = vec![soa.clone()];
+                records.extend(diff.ixfr_records());
+                records.push(soa.clone());
+                records
+            }
+            None => zone.axfr_records(),
+        };
+
+        let messages = transfer_messages(request, records);
+        match udp_limit {
+            Some(limit) if messages.len() > 1 || messages[0].as_bytes().len() > limit => {
+                Ok(current_soa_only())
+            }
+            _ => Ok(messages),
+        }
+    }
+}
+
+/// Spreads the records of a transfer over as many authoritative responses to
+/// `request` as needed to fit each in a TCP frame. Only the first message carries
+/// the question.
+fn transfer_messages(request: &DnsMessage, records: Vec<DnsRecord>) -> Vec<DnsMessage> {
+    let mut chunks: Vec<Vec<DnsRecord>> = vec![Vec::new()];
+    let mut size = 0;
+    let budget = MAX_TCP_MESSAGE_SIZE - TSIG_RESERVE_IN_BYTES;
+    for record in records {
+        let len = record.as_bytes().len();
+        let chunk = chunks.last_mut().expect("chunks is never empty");
+        if !chunk.is_empty() && size + len > budget {
+            chunks.push(vec![record]);
+            size = len;
+        } else {
+            chunk.push(record);
+            size += len;
+        }
+    }
+
+    chunks
+        .into_iter()
+        .enumerate()
+        .map(|(i, answers)| {
+            let mut message = DnsMessage {
+                header: request.header.clone(),
+                questions: if i == 0 {
+                    request.questions.clone()
+                } else {
+                    vec![]
+                },
+                answers,
+                authorities: vec![],
+                additionals: vec![],
+            };
+            message.header.aa = AuthoritativeAnswer::Authoritative;
+            message.header.rcode = ResponseCode::NoErrorCondition;
+            message.to_response();
+            message
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        dns_header::header::{AuthoritativeAnswer, ResponseCode},
+        dns_message::{
+            builder::MessageBuilder,
+            message::DnsMessage,
+            tsig::{TsigAlgorithm, TsigKey, TsigKeyring},
+        },
+        dns_record::record::{DnsRecord, RData},
+        dns_zone::{
+            transfer::TransferAcl,
+            transfer_impl::MAX_TCP_MESSAGE_SIZE,
+            zone::{Zone, ZoneStore},
+        },
+        resrec::{QClass, QType},
+    };
+    use std::net::{IpAddr, Ipv4Addr};
+
+    fn name(labels: &[&str]) -> Vec<String> {
+        labels.iter().map(|x| x.to_string()).collect()
+    }
+
+    fn store(hosts: u32) -> ZoneStore {
+        let mut zone = Zone::with_default_soa(name(&["example", "com"]));
+        zone.records.extend((0..hosts).map(host));
+        ZoneStore { zones: vec![zone] }
+    }
+
+    fn host(i: u32) -> DnsRecord {
+        DnsRecord {
+            name: name(&[&format!("host{}", i), "example", "com"]),
+            typ: QType::A,
+            class: QClass::IN,
+            ttl: 60,
+            rdata: RData::A(Ipv4Addr::from(0x0a00_0000 + i)),
+        }
+    }
+
+    fn request(zone: &[&str]) -> DnsMessage {
+        MessageBuilder::query(name(zone), QType::AXFR)
+            .id(99)
+            .build()
+    }
+
+    #[test]
+    fn axfr_single_message_test() {
+        let messages = store(2).axfr(&request(&["example", "com"])).unwrap();
+        assert_eq!(messages.len(), 1);
+
+        let answers = &messages[0].answers;
+        let types = answers.iter().map(|r| r.typ).collect::<Vec<_>>();
+        assert_eq!(types, vec![QType::SOA, QType::A, QType::A, QType::SOA]);
+        assert_eq!(messages[0].header.packet_id, 99);
+        assert_eq!(messages[0].header.aa, AuthoritativeAnswer::Authoritative);
+        assert_eq!(messages[0].header.ancount, 4);
+    }
+
+    #[test]
+    fn axfr_multiple_messages_test() {
+        let messages = store(5000).axfr(&request(&["example", "com"])).unwrap();
+        assert!(messages.len() > 1);
+        assert!(messages
+            .iter()
+            .all(|m| m.as_bytes().len() <= MAX_TCP_MESSAGE_SIZE));
+        assert_eq!(messages[0].header.qdcount, 1);
+        assert!(messages[1..].iter().all(|m| m.questions.is_empty()));
+
+        let records = messages
+            .iter()
+            .flat_map(|m| m.answers.iter())
+            .collect::<Vec<_>>();
+        assert_eq!(records.len(), 5002);
+        assert_eq!(records.first().unwrap().typ, QType::SOA);
+        assert_eq!(records.last().unwrap().typ, QType::SOA);
+    }
+
+    #[test]
+    fn axfr_unknown_zone_test() {
+        let result = store(0).axfr(&request(&["example", "org"]));
+        assert_eq!(result.err(), Some(ResponseCode::NotAuth));
+    }
+
+    #[test]
+    fn expired_zone_test() {
+        let mut store = updated_store();
+        store.zones[0].expired = true;
+        let result = store.axfr(&request(&["example", "com"]));
+        assert_eq!(result.err(), Some(ResponseCode::ServerFailure));
+        let result = store.ixfr(&ixfr_request(1), None);
+        assert_eq!(result.err(), Some(ResponseCode::ServerFailure));
+    }
+
+    fn ixfr_request(serial: u32) -> DnsMessage {
+        let mut request = request(&["example", "com"]);
+        request.questions[0].qtype = QType::IXFR;
+        let mut soa = store(0).zones[0].records[0].clone();
+        if let RData::SOA(rdata) = &mut soa.rdata {
+            rdata.serial = serial;
+        }
+        request.authorities.push(soa);
+        request.header.nscount = 1;
+        request
+    }
+
+    /// A zone at serial 3, with host0 added in serial 2 and host1 in serial 3.
+    fn updated_store() -> ZoneStore {
+        let mut store = store(0);
+        for i in 0..2 {
+            let mut zone = store.zones[0].clone();
+            zone.records.push(host(i));
+            zone.set_serial(zone.serial().unwrap() + 1);
+            store.reload(zone);
+        }
+        store
+    }
+
+    #[test]
+    fn ixfr_up_to_date_test() {
+        let messages = updated_store().ixfr(&ixfr_request(3), None).unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].answers.len(), 1);
+        assert_eq!(messages[0].answers[0].typ, QType::SOA);
+    }
+
+    #[test]
+    fn ixfr_incremental_test() {
+        let messages = updated_store().ixfr(&ixfr_request(1), None).unwrap();
+        let answers = &messages[0].answers;
+        let types = answers.iter().map(|r| r.typ).collect::<Vec<_>>();
+        assert_eq!(
+            types,
+            vec![
+                QType::SOA,
+                QType::SOA,
+                QType::SOA,
+                QType::A,
+                QType::A,
+                QType::SOA
+            ]
+        );
+        let serials = answers
+            .iter()
+            .filter_map(|r| match &r.rdata {
+                RData::SOA(soa) => Some(soa.serial),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(serials, vec![3, 1, 3, 3]);
+    }
+
+    #[test]
+    fn ixfr_falls_back_to_axfr_test() {
+        let store = updated_store();
+        let messages = store.ixfr(&ixfr_request(0), None).unwrap();
+        assert_eq!(messages[0].answers, store.zones[0].axfr_records(),);
+    }
+
+    #[test]
+    fn ixfr_over_udp_test() {
+        let messages = updated_store().ixfr(&ixfr_request(1), Some(512)).unwrap();
+        assert_eq!(messages[0].answers.len(), 6);
+
+        let messages = updated_store().ixfr(&ixfr_request(1), Some(64)).unwrap();
+        assert_eq!(messages[0].answers.len(), 1);
+    }
+
+    #[test]
+    fn ixfr_without_client_soa_test() {
+        let mut request = ixfr_request(1);
+        request.authorities.clear();
+        assert_eq!(
+            updated_store().ixfr(&request, None).err(),
+            Some(ResponseCode::FormatError)
+        );
+    }
+
+    #[test]
+    fn acl_test() {
+        let key = TsigKey {
+            name: name(&["transfer"]),
+            algorithm: TsigAlgorithm::HmacSha256,
+            secret: b"secret".to_vec(),
+        };
+        let acl = TransferAcl {
+            addresses: vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2))],
+            keys: vec![name(&["transfer"])],
+        };
+        let stranger = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 3));
+
+        assert!(acl.allows(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)), None));
+        assert!(!acl.allows(stranger, None));
+
+        let mut signed = request(&["example", "com"]);
+        key.session().sign(&mut signed, 0);
+        let keyring = TsigKeyring { keys: vec![key] };
+        let session = keyring.verify(&signed.as_bytes(), 0).unwrap().unwrap();
+        assert!(acl.allows(stranger, Some(&session)));
+        assert!(!TransferAcl::default().allows(stranger, Some(&session)));
+    }
+}
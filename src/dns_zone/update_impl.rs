@@ -0,0 +1,540 @@
+use super::{
+    journal::ZoneDiff,
+    zone::{Zone, ZoneStore},
+    zone_impl::serial_gt,
+};
+use crate::{
+    dns_header::header::ResponseCode,
+    dns_message::message::DnsMessage,
+    dns_name::name::names_equal,
+    dns_record::record::{DnsRecord, RData},
+    resrec::{QClass, QType},
+};
+
+impl ZoneStore {
+    /// Processes an UPDATE message (RFC 2136) against the zone named in its Zone
+    /// section. Prerequisites are checked first, then the updates are applied to a
+    /// copy of the zone which replaces the original only once all of them went
+    /// through, so a failed update leaves the zone untouched.
+    pub fn apply_update(&mut self, message: &DnsMessage) -> ResponseCode {
+        let [zone_section] = &message.questions[..] else {
+            return ResponseCode::FormatError;
+        };
+        if zone_section.qtype != QType::SOA {
+            return ResponseCode::FormatError;
+        }
+        let Some(zone) = self.find_mut(&zone_section.qname) else {
+            return ResponseCode::NotAuth;
+        };
+        if zone_section.qclass != zone.class {
+            return ResponseCode::NotAuth;
+        }
+
+        let result = zone
+            .check_prerequisites(&message.answers)
+            .and_then(|_| zone.prescan(&message.authorities));
+        if let Err(rcode) = result {
+            return rcode;
+        }
+
+        let mut updated = zone.clone();
+        updated.apply(&message.authorities);
+        if updated.records != zone.records {
+            if updated.serial() == zone.serial() {
+                let serial = updated.serial().unwrap_or_default().wrapping_add(1);
+                updated.set_serial(serial);
+            }
+            if let Some(diff) = ZoneDiff::between(&zone.records, &updated.records) {
+                updated.journal.record(diff);
+            }
+            *zone = updated;
+        }
+        ResponseCode::NoErrorCondition
+    }
+}
+
+impl Zone {
+    /// RFC 2136 section 3.2: every prerequisite must hold for the update to proceed.
+    fn check_prerequisites(&self, prerequisites: &[DnsRecord]) -> Result<(), ResponseCode> {
+        let mut rrsets = Vec::new();
+        for rr in prerequisites {
+            if rr.ttl != 0 {
+                return Err(ResponseCode::FormatError);
+            }
+            if !self.contains(&rr.name) {
+                return Err(ResponseCode::NotZone);
+            }
+
+            match rr.class {
+                QClass::WildCard | QClass::NONE if !rr.rdata.is_empty() => {
+                    return Err(ResponseCode::FormatError);
+                }
+                // Name is in use / RRset exists (value independent)
+                QClass::WildCard => {
+                    if rr.typ == QType::WildCard {
+                        if !self.name_exists(&rr.name) {
+                            return Err(ResponseCode::NameError);
+                        }
+                    } else if self.rrset(&rr.name, rr.typ).next().is_none() {
+                        return Err(ResponseCode::NXRRSet);
+                    }
+                }
+                // Name is not in use / RRset does not exist
+                QClass::NONE => {
+                    if rr.typ == QType::WildCard {
+                        if self.name_exists(&rr.name) {
+                            return Err(ResponseCode::YXDomain);
+                        }
+                    } else if self.rrset(&rr.name, rr.typ).next().is_some() {
+                        return Err(ResponseCode::YXRRSet);
+                    }
+                }
+                // RRset exists (value dependent), checked below once all are collected
+                class if class == self.class => rrsets.push(rr),
+                _ => return Err(ResponseCode::FormatError),
+            }
+        }
+
+        for rr in &rrsets {
+            let expected = rrsets
+                .iter()
+                .filter(|x| x.typ == rr.typ && names_equal(&x.name, &rr.name))
+                .map(|x| &x.rdata)
+                .collect::<Vec<_>>();
+            let actual = self
+                .rrset(&rr.name, rr.typ)
+                .map(|x| &x.rdata)
+                .collect::<Vec<_>>();
+            let same = expected.iter().all(|x| actual.contains(x))
+                && actual.iter().all(|x| expected.contains(x));
+            if !same {
+                return Err(ResponseCode::NXRRSet);
+            }
+        }
+        Ok(())
+    }
+
+    /// RFC 2136 section 3.4.1: the whole Update section is validated before any of
+    /// it is applied.
+    fn prescan(&self, updates: &[DnsRecord]) -> Result<(), ResponseCode> {
+        for rr in updates {
+            if !self.contains(&rr.name) {
+                return Err(ResponseCode::NotZone);
+            }
+            let valid = match rr.class {
+                class if class == self.class => !is_meta_type(rr.typ),
+                QClass::WildCard => {
+                    rr.ttl == 0
+                        && rr.rdata.is_empty()
+                        && (rr.typ == QType::WildCard || !is_meta_type(rr.typ))
+                }
+                QClass::NONE => rr.ttl == 0 && !is_meta_type(rr.typ),
+                _ => false,
+            };
+            if !valid {
+                return Err(ResponseCode::FormatError);
+            }
+        }
+        Ok(())
+    }
+
+    /// RFC 2136 section 3.4.2: applies a prescanned Update section. Updates that
+    /// would break the zone's invariants are silently ignored, as the RFC mandates.
+    fn apply(&mut self, updates: &[DnsRecord]) {
+        for rr in updates {
+            match rr.class {
+                QClass::WildCard if rr.typ == QType::WildCard => self.delete_name(&rr.name),
+                QClass::WildCard => {
+                    let protected = matches!(rr.typ, QType::SOA | QType::NS);
+                    if !(protected && self.is_apex(&rr.name)) {
+                        self.records
+                            .retain(|x| !(x.typ == rr.typ && names_equal(&x.name, &rr.name)));
+                    }
+                }
+                QClass::NONE => self.delete_rr(rr),
+                _ => self.add(rr),
+            }
+        }
+    }
+
+    fn add(&mut self, rr: &DnsRecord) {
+        let at_name = self
+            .records
+            .iter()
+            .filter(|x| names_equal(&x.name, &rr.name))
+            .collect::<Vec<_>>();
+        let has_cname = at_name.iter().any(|x| x.typ == QType::CNAME);
+        let has_other = at_name.iter().any(|x| x.typ != QType::CNAME);
+        if (rr.typ == QType::CNAME && has_other) || (rr.typ != QType::CNAME && has_cname) {
+            return;
+        }
+
+        match rr.typ {
+            QType::SOA => {
+                let RData::SOA(soa) = &rr.rdata else {
+                    return;
+                };
+                let newer = match self.serial() {
+                    Some(serial) => serial_gt(soa.serial, serial),
+                    None => true,
+                };
+                if self.is_apex(&rr.name) && newer {
+                    self.records.retain(|x| x.typ != QType::SOA);
+                    self.records.insert(0, rr.clone());
+                }
+            }
+            QType::CNAME => {
+                self.records
+                    .retain(|x| !(x.typ == QType::CNAME && names_equal(&x.name, &rr.name)));
+                self.records.push(rr.clone());
+            }
+            _ => {
+                let existing = self.records.iter_mut().find(|x| {
+                    x.typ == rr.typ && x.rdata == rr.rdata && names_equal(&x.name, &rr.name)
+                });
+                match existing {
+                    Some(existing) => existing.ttl = rr.ttl,
+                    None => self.records.push(rr.clone()),
+                }
+            }
+        }
+    }
+
+    /// Deletes every RRset owned by `name`, except the SOA and NS RRsets at the apex.
+    fn delete_name(&mut self, name: &[String]) {
+        let apex = self.is_apex(name);
+        self.records.retain(|x| {
+            !names_equal(&x.name, name) || (apex && matches!(x.typ, QType::SOA | QType::NS))
+        });
+    }
+
+    /// Deletes a single RR, refusing to remove the SOA or the last NS of the apex.
+    fn delete_rr(&mut self, rr: &DnsRecord) {
+        if rr.typ == QType::SOA {
+            return;
+        }
+        if rr.typ == QType::NS
+            && self.is_apex(&rr.name)
+            && self.rrset(&rr.name, QType::NS).count() <= 1
+        {
+            return;
+        }
+        self.records.retain(|x| {
+            !(x.typ == rr.typ && x.rdata == rr.rdata && names_equal(&x.name, &rr.name))
+        });
+    }
+}
+
+/// The types only valid in questions or as pseudo-records of a message, which
+/// cannot be added to a zone (RFC 2136 section 3.4.1.3).
+fn is_meta_type(typ: QType) -> bool {
+    matches!(
+        typ,
+        QType::AXFR
+            | QType::IXFR
+            | QType::MAILA
+            | QType::MAILB
+            | QType::WildCard
+            | QType::OPT
+            | QType::TSIG
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        dns_header::header::{OpCode, ResponseCode},
+        dns_message::{builder::MessageBuilder, message::DnsMessage},
+        dns_record::record::{DnsRecord, RData},
+        dns_zone::zone::{Zone, ZoneStore},
+        resrec::{QClass, QType},
+    };
+    use std::net::Ipv4Addr;
+
+    fn name(labels: &[&str]) -> Vec<String> {
+        labels.iter().map(|x| x.to_string()).collect()
+    }
+
+    fn store() -> ZoneStore {
+        let mut zone = Zone::with_default_soa(name(&["example", "com"]));
+        zone.records.push(DnsRecord {
+            name: name(&["example", "com"]),
+            typ: QType::NS,
+            class: QClass::IN,
+            ttl: 3600,
+            rdata: RData::NS(name(&["ns", "example", "com"])),
+        });
+        zone.records
+            .push(a(&["www", "example", "com"], QClass::IN, 60, [10, 0, 0, 1]));
+        ZoneStore { zones: vec![zone] }
+    }
+
+    fn a(owner: &[&str], class: QClass, ttl: u32, octets: [u8; 4]) -> DnsRecord {
+        DnsRecord {
+            name: name(owner),
+            typ: QType::A,
+            class,
+            ttl,
+            rdata: RData::A(Ipv4Addr::from(octets)),
+        }
+    }
+
+    fn empty(owner: &[&str], typ: QType, class: QClass) -> DnsRecord {
+        DnsRecord {
+            name: name(owner),
+            typ,
+            class,
+            ttl: 0,
+            rdata: RData::Raw(vec![]),
+        }
+    }
+
+    fn update(zone: &[&str], prerequisites: Vec<DnsRecord>, updates: Vec<DnsRecord>) -> DnsMessage {
+        MessageBuilder::query(name(zone), QType::SOA)
+            .id(1)
+            .opcode(OpCode::Update)
+            .answers(prerequisites)
+            .authorities(updates)
+            .build()
+    }
+
+    #[test]
+    fn add_record_bumps_serial_test() {
+        let mut store = store();
+        let message = update(
+            &["example", "com"],
+            vec![],
+            vec![a(&["ftp", "example", "com"], QClass::IN, 60, [10, 0, 0, 2])],
+        );
+
+        assert_eq!(store.apply_update(&message), ResponseCode::NoErrorCondition);
+        let zone = &store.zones[0];
+        assert_eq!(
+            zone.rrset(&name(&["ftp", "example", "com"]), QType::A)
+                .count(),
+            1
+        );
+        assert_eq!(zone.serial(), Some(2));
+        assert_eq!(zone.journal.diffs.len(), 1);
+        assert_eq!(zone.journal.diffs[0].old_serial(), 1);
+        assert_eq!(zone.journal.diffs[0].added.len(), 1);
+    }
+
+    #[test]
+    fn noop_update_keeps_serial_test() {
+        let mut store = store();
+        let message = update(
+            &["example", "com"],
+            vec![],
+            vec![a(&["www", "example", "com"], QClass::IN, 60, [10, 0, 0, 1])],
+        );
+
+        assert_eq!(store.apply_update(&message), ResponseCode::NoErrorCondition);
+        assert_eq!(store.zones[0].serial(), Some(1));
+    }
+
+    #[test]
+    fn delete_rrset_and_name_test() {
+        let mut store = store();
+        let message = update(
+            &["example", "com"],
+            vec![],
+            vec![empty(
+                &["www", "example", "com"],
+                QType::A,
+                QClass::WildCard,
+            )],
+        );
+        assert_eq!(store.apply_update(&message), ResponseCode::NoErrorCondition);
+        assert!(!store.zones[0].name_exists(&name(&["www", "example", "com"])));
+
+        let message = update(
+            &["example", "com"],
+            vec![],
+            vec![empty(
+                &["example", "com"],
+                QType::WildCard,
+                QClass::WildCard,
+            )],
+        );
+        assert_eq!(store.apply_update(&message), ResponseCode::NoErrorCondition);
+        let zone = &store.zones[0];
+        assert!(zone.soa().is_some());
+        assert_eq!(zone.rrset(&name(&["example", "com"]), QType::NS).count(), 1);
+    }
+
+    #[test]
+    fn delete_rr_keeps_last_ns_test() {
+        let mut store = store();
+        let mut ns = store.zones[0].records[1].clone();
+        ns.class = QClass::NONE;
+        ns.ttl = 0;
+        let message = update(&["example", "com"], vec![], vec![ns]);
+
+        assert_eq!(store.apply_update(&message), ResponseCode::NoErrorCondition);
+        assert_eq!(
+            store.zones[0]
+                .rrset(&name(&["example", "com"]), QType::NS)
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn cname_conflict_is_ignored_test() {
+        let mut store = store();
+        let message = update(
+            &["example", "com"],
+            vec![],
+            vec![DnsRecord {
+                name: name(&["www", "example", "com"]),
+                typ: QType::CNAME,
+                class: QClass::IN,
+                ttl: 60,
+                rdata: RData::CNAME(name(&["example", "com"])),
+            }],
+        );
+
+        assert_eq!(store.apply_update(&message), ResponseCode::NoErrorCondition);
+        assert_eq!(
+            store.zones[0]
+                .rrset(&name(&["www", "example", "com"]), QType::CNAME)
+                .count(),
+            0
+        );
+        assert_eq!(store.zones[0].serial(), Some(1));
+    }
+
+    #[test]
+    fn prerequisite_rcodes_test() {
+        let cases = vec![
+            (
+                empty(
+                    &["ftp", "example", "com"],
+                    QType::WildCard,
+                    QClass::WildCard,
+                ),
+                ResponseCode::NameError,
+            ),
+            (
+                empty(&["www", "example", "com"], QType::MX, QClass::WildCard),
+                ResponseCode::NXRRSet,
+            ),
+            (
+                empty(&["www", "example", "com"], QType::WildCard, QClass::NONE),
+                ResponseCode::YXDomain,
+            ),
+            (
+                empty(&["www", "example", "com"], QType::A, QClass::NONE),
+                ResponseCode::YXRRSet,
+            ),
+            (
+                a(&["www", "example", "com"], QClass::IN, 0, [10, 0, 0, 9]),
+                ResponseCode::NXRRSet,
+            ),
+            (
+                a(&["www", "example", "com"], QClass::IN, 60, [10, 0, 0, 1]),
+                ResponseCode::FormatError,
+            ),
+            (
+                empty(&["www", "example", "org"], QType::A, QClass::WildCard),
+                ResponseCode::NotZone,
+            ),
+        ];
+
+        for (prerequisite, rcode) in cases {
+            let mut store = store();
+            let message = update(
+                &["example", "com"],
+                vec![prerequisite],
+                vec![a(&["ftp", "example", "com"], QClass::IN, 60, [10, 0, 0, 2])],
+            );
+            assert_eq!(store.apply_update(&message), rcode);
+            assert!(!store.zones[0].name_exists(&name(&["ftp", "example", "com"])));
+        }
+    }
+
+    #[test]
+    fn value_dependent_prerequisite_test() {
+        let mut store = store();
+        let message = update(
+            &["example", "com"],
+            vec![a(&["www", "example", "com"], QClass::IN, 0, [10, 0, 0, 1])],
+            vec![a(&["ftp", "example", "com"], QClass::IN, 60, [10, 0, 0, 2])],
+        );
+        assert_eq!(store.apply_update(&message), ResponseCode::NoErrorCondition);
+    }
+
+    #[test]
+    fn update_is_atomic_test() {
+        let mut store = store();
+        let message = update(
+            &["example", "com"],
+            vec![],
+            vec![
+                a(&["ftp", "example", "com"], QClass::IN, 60, [10, 0, 0, 2]),
+                a(&["www", "example", "org"], QClass::IN, 60, [10, 0, 0, 3]),
+            ],
+        );
+
+        assert_eq!(store.apply_update(&message), ResponseCode::NotZone);
+        assert!(!store.zones[0].name_exists(&name(&["ftp", "example", "com"])));
+        assert_eq!(store.zones[0].serial(), Some(1));
+    }
+
+    #[test]
+    fn zone_section_rcodes_test() {
+        let mut store = store();
+        let message = update(&["example", "org"], vec![], vec![]);
+        assert_eq!(store.apply_update(&message), ResponseCode::NotAuth);
+
+        let mut message = update(&["example", "com"], vec![], vec![]);
+        message.questions[0].qtype = QType::A;
+        assert_eq!(store.apply_update(&message), ResponseCode::FormatError);
+    }
+
+    /// Adding, deleting the RRset of or deleting an RR of type `typ` is refused
+    /// with FORMERR, leaving the zone untouched.
+    fn assert_meta_type_refused(typ: QType) {
+        let owner = ["www", "example", "com"];
+        let mut add = a(&owner, QClass::IN, 60, [10, 0, 0, 2]);
+        add.typ = typ;
+        let mut delete = add.clone();
+        delete.class = QClass::NONE;
+        delete.ttl = 0;
+        for updates in [add, empty(&owner, typ, QClass::WildCard), delete] {
+            let mut store = store();
+            let message = update(&["example", "com"], vec![], vec![updates]);
+            assert_eq!(store.apply_update(&message), ResponseCode::FormatError);
+            assert_eq!(store.zones[0].serial(), Some(1));
+        }
+    }
+
+    #[test]
+    fn ixfr_update_test() {
+        assert_meta_type_refused(QType::IXFR);
+    }
+
+    #[test]
+    fn opt_update_test() {
+        assert_meta_type_refused(QType::OPT);
+    }
+
+    #[test]
+    fn tsig_update_test() {
+        assert_meta_type_refused(QType::TSIG);
+    }
+
+    #[test]
+    fn explicit_soa_serial_test() {
+        let mut store = store();
+        let mut soa = store.zones[0].records[0].clone();
+        if let RData::SOA(rdata) = &mut soa.rdata {
+            rdata.serial = 100;
+        }
+        let message = update(&["example", "com"], vec![], vec![soa]);
+
+        assert_eq!(store.apply_update(&message), ResponseCode::NoErrorCondition);
+        assert_eq!(store.zones[0].serial(), Some(100));
+    }
+}
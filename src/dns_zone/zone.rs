@@ -0,0 +1,26 @@
+use super::journal::Journal;
+use crate::{dns_record::record::DnsRecord, resrec::QClass};
+
+/// A zone this server is authoritative for: every record at or below `origin`,
+/// starting with the SOA record at the zone apex.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Zone {
+    /// The name of the zone apex.
+    pub origin: Vec<String>,
+    /// The class all records of the zone belong to.
+    pub class: QClass,
+    /// The records of the zone, in insertion order.
+    pub records: Vec<DnsRecord>,
+    /// The latest changes of the zone, for incremental transfers.
+    pub journal: Journal,
+    /// Set on secondary zones that could not be refreshed from their primary within
+    /// the SOA expire interval. Their data is no longer trusted and queries for them
+    /// fail with SERVFAIL.
+    pub expired: bool,
+}
+
+/// All the zones served by the authoritative mode.
+#[derive(Debug, Clone, Default)]
+pub struct ZoneStore {
+    pub zones: Vec<Zone>,
+}
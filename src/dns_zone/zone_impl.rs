@@ -0,0 +1,239 @@
+use super::{
+    journal::ZoneDiff,
+    zone::{Zone, ZoneStore},
+};
+use crate::{
+    dns_name::name::{is_subdomain, names_equal},
+    dns_record::record::{DnsRecord, RData, Soa},
+    resrec::{QClass, QType},
+};
+
+/// TTL given to the SOA record of zones created without a zone file
+const DEFAULT_SOA_TTL: u32 = 3600;
+
+impl Zone {
+    /// Creates a zone holding nothing but the SOA record at `origin`.
+    pub fn new(origin: Vec<String>, soa: Soa) -> Self {
+        let record = DnsRecord {
+            name: origin.clone(),
+            typ: QType::SOA,
+            class: QClass::IN,
+            ttl: DEFAULT_SOA_TTL,
+            rdata: RData::SOA(soa),
+        };
+        Zone {
+            origin,
+            class: QClass::IN,
+            records: vec![record],
+            journal: Default::default(),
+            expired: false,
+        }
+    }
+
+    /// Creates an empty zone with a conventional SOA record, with `ns` and
+    /// `hostmaster` under the origin as primary server and responsible mailbox.
+    pub fn with_default_soa(origin: Vec<String>) -> Self {
+        let under_origin = |label: &str| {
+            let mut name = vec![label.to_string()];
+            name.extend(origin.iter().cloned());
+            name
+        };
+        let soa = Soa {
+            mname: under_origin("ns"),
+            rname: under_origin("hostmaster"),
+            serial: 1,
+            refresh: 3600,
+            retry: 600,
+            expire: 604800,
+            minimum: 300,
+        };
+        Zone::new(origin, soa)
+    }
+
+    pub fn soa(&self) -> Option<&Soa> {
+        self.records.iter().find_map(|r| match &r.rdata {
+            RData::SOA(soa) if self.is_apex(&r.name) => Some(soa),
+            _ => None,
+        })
+    }
+
+    pub fn serial(&self) -> Option<u32> {
+        self.soa().map(|soa| soa.serial)
+    }
+
+    pub fn set_serial(&mut self, serial: u32) {
+        let origin = self.origin.clone();
+        for record in self.records.iter_mut() {
+            if let RData::SOA(soa) = &mut record.rdata {
+                if names_equal(&record.name, &origin) {
+                    soa.serial = serial;
+                }
+            }
+        }
+    }
+
+    /// Whether `name` belongs to this zone.
+    pub fn contains(&self, name: &[String]) -> bool {
+        is_subdomain(name, &self.origin)
+    }
+
+    pub fn is_apex(&self, name: &[String]) -> bool {
+        names_equal(name, &self.origin)
+    }
+
+    /// Whether any record is owned by `name`.
+    pub fn name_exists(&self, name: &[String]) -> bool {
+        self.records.iter().any(|r| names_equal(&r.name, name))
+    }
+
+    /// Whether `name` owns records or lies above names that do: empty
+    /// non-terminals exist too (RFC 8020).
+    pub fn node_exists(&self, name: &[String]) -> bool {
+        self.records.iter().any(|r| is_subdomain(&r.name, name))
+    }
+
+    /// The records owned by `name` with type `typ`.
+    pub fn rrset<'a>(
+        &'a self,
+        name: &'a [String],
+        typ: QType,
+    ) -> impl Iterator<Item = &'a DnsRecord> + 'a {
+        self.records
+            .iter()
+            .filter(move |r| r.typ == typ && names_equal(&r.name, name))
+    }
+}
+
+impl ZoneStore {
+    /// The zone whose apex is exactly `origin`.
+    pub fn find(&self, origin: &[String]) -> Option<&Zone> {
+        self.zones.iter().find(|z| z.is_apex(origin))
+    }
+
+    pub fn find_mut(&mut self, origin: &[String]) -> Option<&mut Zone> {
+        self.zones.iter_mut().find(|z| z.is_apex(origin))
+    }
+
+    /// Replaces the zone with the same origin as `zone`, or adds it. The journal of
+    /// the replaced zone is kept, extended with the changes if the serial moved.
+    pub fn reload(&mut self, mut zone: Zone) {
+        let Some(existing) = self.find_mut(&zone.origin) else {
+            self.zones.push(zone);
+            return;
+        };
+        zone.journal = std::mem::take(&mut existing.journal);
+        if zone.serial() != existing.serial() {
+            if let Some(diff) = ZoneDiff::between(&existing.records, &zone.records) {
+                zone.journal.record(diff);
+            }
+        }
+        *existing = zone;
+    }
+}
+
+/// Whether serial `a` is greater than serial `b` in the sequence space arithmetic
+/// of RFC 1982.
+pub fn serial_gt(a: u32, b: u32) -> bool {
+    a != b && (a.wrapping_sub(b) as i32) > 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::serial_gt;
+    use crate::{
+        dns_record::record::{DnsRecord, RData},
+        dns_zone::zone::{Zone, ZoneStore},
+        resrec::{QClass, QType},
+    };
+    use std::net::Ipv4Addr;
+
+    fn name(labels: &[&str]) -> Vec<String> {
+        labels.iter().map(|x| x.to_string()).collect()
+    }
+
+    #[test]
+    fn default_soa_test() {
+        let zone = Zone::with_default_soa(name(&["example", "com"]));
+        let soa = zone.soa().unwrap();
+        assert_eq!(soa.mname, name(&["ns", "example", "com"]));
+        assert_eq!(soa.rname, name(&["hostmaster", "example", "com"]));
+        assert_eq!(zone.serial(), Some(1));
+    }
+
+    #[test]
+    fn set_serial_test() {
+        let mut zone = Zone::with_default_soa(name(&["example", "com"]));
+        zone.set_serial(42);
+        assert_eq!(zone.serial(), Some(42));
+    }
+
+    #[test]
+    fn lookup_test() {
+        let mut zone = Zone::with_default_soa(name(&["example", "com"]));
+        zone.records.push(DnsRecord {
+            name: name(&["www", "example", "com"]),
+            typ: QType::A,
+            class: QClass::IN,
+            ttl: 60,
+            rdata: RData::A(Ipv4Addr::new(10, 0, 0, 1)),
+        });
+
+        assert!(zone.contains(&name(&["www", "Example", "com"])));
+        assert!(!zone.contains(&name(&["example", "org"])));
+        assert!(zone.name_exists(&name(&["WWW", "example", "com"])));
+        assert!(!zone.name_exists(&name(&["ftp", "example", "com"])));
+        assert_eq!(
+            zone.rrset(&name(&["www", "example", "com"]), QType::A)
+                .count(),
+            1
+        );
+        assert_eq!(
+            zone.rrset(&name(&["www", "example", "com"]), QType::MX)
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn store_find_test() {
+        let store = ZoneStore {
+            zones: vec![Zone::with_default_soa(name(&["example", "com"]))],
+        };
+        assert!(store.find(&name(&["EXAMPLE", "com"])).is_some());
+        assert!(store.find(&name(&["www", "example", "com"])).is_none());
+    }
+
+    #[test]
+    fn reload_test() {
+        let mut store = ZoneStore::default();
+        let zone = Zone::with_default_soa(name(&["example", "com"]));
+        store.reload(zone.clone());
+        assert_eq!(store.zones.len(), 1);
+
+        store.reload(zone.clone());
+        assert!(store.zones[0].journal.diffs.is_empty());
+
+        let mut newer = zone;
+        newer.set_serial(2);
+        newer.records.push(DnsRecord {
+            name: name(&["www", "example", "com"]),
+            typ: QType::A,
+            class: QClass::IN,
+            ttl: 60,
+            rdata: RData::A(Ipv4Addr::new(10, 0, 0, 1)),
+        });
+        store.reload(newer);
+        assert_eq!(store.zones.len(), 1);
+        assert_eq!(store.zones[0].serial(), Some(2));
+        assert_eq!(store.zones[0].journal.diffs.len(), 1);
+        assert_eq!(store.zones[0].journal.diffs[0].added.len(), 1);
+    }
+
+    #[test]
+    fn serial_gt_test() {
+        assert!(serial_gt(2, 1));
+        assert!(!serial_gt(1, 2));
+        assert!(!serial_gt(1, 1));
+        assert!(serial_gt(0, u32::MAX));
+    }
+}
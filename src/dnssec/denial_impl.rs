@@ -0,0 +1,454 @@
+//! Authenticated denial of existence with NSEC (RFC 4035 section 5.4) and NSEC3
+//! (RFC 5155 section 8) records.
+//!
+//! The proofs only look at NSEC and NSEC3 records whose signatures were already
+//! verified, given along with the RRSIGs covering them so each record is only
+//! trusted within the zone that signed it.
+
+use super::signature_impl::canonical_name;
+use crate::{
+    dns_name::name::{is_subdomain, names_equal},
+    dns_record::record::{DnsRecord, Nsec, Nsec3, RData},
+    encoding::base32hex_decode,
+    resrec::QType,
+};
+use sha1::{Digest, Sha1};
+use std::cmp::Ordering;
+
+/// The NSEC3 hash algorithm, SHA-1.
+const NSEC3_SHA1: u8 = 1;
+
+/// The NSEC3 flag allowing unsigned delegations to be left out of the chain.
+const OPT_OUT_FLAG: u8 = 0x01;
+
+/// Orders names canonically (RFC 4034 section 6.1): label by label from the
+/// root, comparing lowercase labels as octet strings.
+pub fn canonical_cmp(a: &[String], b: &[String]) -> Ordering {
+    let lower = |x: &String| x.to_ascii_lowercase().into_bytes();
+    a.iter().rev().map(lower).cmp(b.iter().rev().map(lower))
+}
+
+/// Hashes a name as NSEC3 does: SHA-1 of the canonical name and the salt,
+/// rehashed with the salt `iterations` more times.
+pub fn nsec3_hash(name: &[String], salt: &[u8], iterations: u16) -> Vec<u8> {
+    let mut hash = Sha1::new()
+        .chain_update(canonical_name(name))
+        .chain_update(salt)
+        .finalize();
+    for _ in 0..iterations {
+        hash = Sha1::new().chain_update(hash).chain_update(salt).finalize();
+    }
+    hash.to_vec()
+}
+
+/// Proves `name` does not exist: no name covers it, and no wildcard at its
+/// closest encloser could have been expanded instead.
+pub fn proves_nxdomain(name: &[String], records: &[DnsRecord]) -> bool {
+    let nsec = nsecs(records).any(|(owner, nsec, zone)| {
+        if !nsec_covers(owner, nsec, name) {
+            return false;
+        }
+        let encloser = longer(
+            common_ancestor(name, owner),
+            common_ancestor(name, &nsec.next),
+        );
+        is_subdomain(&encloser, zone) && !wildcard_may_exist(&encloser, records)
+    });
+    nsec || closest_encloser(name, records)
+        .is_some_and(|encloser| nsec3_covers_any(&wildcard(&encloser), records))
+}
+
+/// Proves `name` exists without records of type `qtype`, nor a CNAME that would
+/// have been followed instead. For DS, an opt-out NSEC3 covering the name also
+/// proves the delegation unsigned (RFC 5155 section 8.6).
+///
+/// The parent's NSEC at a delegation only speaks for DS, and the child's at its
+/// apex never does (RFC 6840 section 4.1).
+pub fn proves_nodata(name: &[String], qtype: QType, records: &[DnsRecord]) -> bool {
+    let has = |types: &[u16], typ: QType| types.contains(&u16::from(typ));
+    let absent = |types: &[u16]| {
+        let delegation = has(types, QType::NS) && !has(types, QType::SOA);
+        let from_right_side = match qtype {
+            QType::DS => !has(types, QType::SOA),
+            _ => !delegation,
+        };
+        from_right_side && !has(types, qtype) && !has(types, QType::CNAME)
+    };
+    let nsec = nsecs(records).any(|(owner, nsec, _)| {
+        (names_equal(owner, name) && absent(&nsec.types))
+            || proves_empty_non_terminal(owner, nsec, name)
+    });
+    let nsec3 = nsec3s(records).any(|(owner, nsec3, zone)| {
+        nsec3_matches(owner, nsec3, zone, name) && absent(&nsec3.types)
+    });
+    nsec || nsec3 || (qtype == QType::DS && proves_opt_out(name, records))
+}
+
+/// An empty non-terminal has no NSEC of its own, but is covered by one leading to
+/// a name below it (RFC 4035 section 3.1.3.2).
+fn proves_empty_non_terminal(owner: &[String], nsec: &Nsec, name: &[String]) -> bool {
+    nsec_covers(owner, nsec, name)
+        && is_subdomain(&nsec.next, name)
+        && !names_equal(&nsec.next, name)
+}
+
+/// Proves `name` is a delegation without DS records: its NSEC or NSEC3 has the
+/// NS bit but neither DS nor SOA, or an opt-out NSEC3 covers it.
+pub fn proves_insecure_delegation(name: &[String], records: &[DnsRecord]) -> bool {
+    let delegation = |types: &[u16]| {
+        types.contains(&u16::from(QType::NS))
+            && !types.contains(&u16::from(QType::DS))
+            && !types.contains(&u16::from(QType::SOA))
+    };
+    let nsec =
+        nsecs(records).any(|(owner, nsec, _)| names_equal(owner, name) && delegation(&nsec.types));
+    let nsec3 = nsec3s(records).any(|(owner, nsec3, zone)| {
+        nsec3_matches(owner, nsec3, zone, name) && delegation(&nsec3.types)
+    });
+    nsec || nsec3 || proves_opt_out(name, records)
+}
+
+/// Proves an RRset synthesized from a wildcard with `labels` labels could not
+/// have been answered from `name` itself, which must not exist.
+pub fn proves_wildcard_expansion(name: &[String], labels: usize, records: &[DnsRecord]) -> bool {
+    if labels >= name.len() {
+        return true;
+    }
+    let next_closer = &name[name.len() - labels - 1..];
+    let nsec = nsecs(records)
+        .any(|(owner, nsec, zone)| is_subdomain(name, zone) && nsec_covers(owner, nsec, name));
+    nsec || nsec3_covers_any(next_closer, records)
+}
+
+/// Whether an NSEC record owned by `owner` covers `name`, i.e. `name` falls
+/// strictly between the owner and the next name, the last NSEC of the zone
+/// wrapping around to the apex.
+pub fn nsec_covers(owner: &[String], nsec: &Nsec, name: &[String]) -> bool {
+    let after_owner = canonical_cmp(owner, name) == Ordering::Less;
+    let before_next = canonical_cmp(name, &nsec.next) == Ordering::Less;
+    if canonical_cmp(owner, &nsec.next) == Ordering::Less {
+        after_owner && before_next
+    } else {
+        after_owner || before_next
+    }
+}
+
+/// Whether a wildcard at `encloser` might exist: an NSEC matches or does not
+/// cover it.
+fn wildcard_may_exist(encloser: &[String], records: &[DnsRecord]) -> bool {
+    let wildcard = wildcard(encloser);
+    !nsecs(records).any(|(owner, nsec, _)| nsec_covers(owner, nsec, &wildcard))
+}
+
+/// The closest encloser proof of RFC 5155 section 8.3: the longest existing
+/// ancestor of `name`, matched by an NSEC3, whose child towards `name` (the next
+/// closer name) is covered by an NSEC3.
+fn closest_encloser(name: &[String], records: &[DnsRecord]) -> Option<Vec<String>> {
+    (1..name.len()).find_map(|skip| {
+        let encloser = &name[skip..];
+        let next_closer = &name[skip - 1..];
+        let matched =
+            nsec3s(records).any(|(owner, nsec3, zone)| nsec3_matches(owner, nsec3, zone, encloser));
+        (matched && nsec3_covers_any(next_closer, records)).then(|| encloser.to_vec())
+    })
+}
+
+/// Whether an opt-out NSEC3 covers the next closer name of `name`, so that `name`
+/// may be an unsigned delegation.
+fn proves_opt_out(name: &[String], records: &[DnsRecord]) -> bool {
+    (1..name.len()).any(|skip| {
+        let encloser = &name[skip..];
+        let next_closer = &name[skip - 1..];
+        nsec3s(records).any(|(owner, nsec3, zone)| nsec3_matches(owner, nsec3, zone, encloser))
+            && nsec3s(records).any(|(owner, nsec3, zone)| {
+                nsec3.flags & OPT_OUT_FLAG != 0 && nsec3_covers(owner, nsec3, zone, next_closer)
+            })
+    })
+}
+
+fn nsec3_covers_any(name: &[String], records: &[DnsRecord]) -> bool {
+    nsec3s(records).any(|(owner, nsec3, zone)| nsec3_covers(owner, nsec3, zone, name))
+}
+
+pub fn nsec3_matches(owner: &[String], nsec3: &Nsec3, zone: &[String], name: &[String]) -> bool {
+    match (owner_hash(owner), name_hash(nsec3, zone, name)) {
+        (Some(owner), Some(name)) => owner == name,
+        _ => false,
+    }
+}
+
+/// Whether the hash of `name` falls strictly between the owner's hash and the
+/// next hashed owner name, wrapping around after the last one.
+pub fn nsec3_covers(owner: &[String], nsec3: &Nsec3, zone: &[String], name: &[String]) -> bool {
+    let (Some(owner), Some(hash)) = (owner_hash(owner), name_hash(nsec3, zone, name)) else {
+        return false;
+    };
+    let next = &nsec3.next_hashed;
+    if owner < *next {
+        owner < hash && hash < *next
+    } else {
+        owner < hash || hash < *next
+    }
+}
+
+/// The hash in the first label of an NSEC3 owner name.
+fn owner_hash(owner: &[String]) -> Option<Vec<u8>> {
+    base32hex_decode(owner.first()?)
+}
+
+/// The hash of `name` with the parameters of `nsec3`, if the record belongs to
+/// the zone of `name` and uses a known hash algorithm.
+fn name_hash(nsec3: &Nsec3, zone: &[String], name: &[String]) -> Option<Vec<u8>> {
+    (nsec3.hash_algorithm == NSEC3_SHA1 && is_subdomain(name, zone))
+        .then(|| nsec3_hash(name, &nsec3.salt, nsec3.iterations))
+}
+
+/// The NSEC records with their owner and the zone which signed them.
+fn nsecs(records: &[DnsRecord]) -> impl Iterator<Item = (&[String], &Nsec, &[String])> {
+    records.iter().filter_map(move |r| match &r.rdata {
+        RData::NSEC(nsec) => Some((&r.name[..], nsec, signer(r, records)?)),
+        _ => None,
+    })
+}
+
+/// The NSEC3 records with their owner and the zone which signed them, which must
+/// be the parent of the owner.
+fn nsec3s(records: &[DnsRecord]) -> impl Iterator<Item = (&[String], &Nsec3, &[String])> {
+    records.iter().filter_map(move |r| match &r.rdata {
+        RData::NSEC3(nsec3) => {
+            let zone = signer(r, records)?;
+            names_equal(r.name.get(1..)?, zone).then_some((&r.name[..], nsec3, zone))
+        }
+        _ => None,
+    })
+}
+
+/// The signer of the RRSIG covering `record`.
+fn signer<'a>(record: &DnsRecord, records: &'a [DnsRecord]) -> Option<&'a [String]> {
+    records.iter().find_map(|r| match &r.rdata {
+        RData::RRSIG(rrsig)
+            if rrsig.type_covered == u16::from(record.typ)
+                && names_equal(&r.name, &record.name) =>
+        {
+            Some(&rrsig.signer[..])
+        }
+        _ => None,
+    })
+}
+
+fn common_ancestor(a: &[String], b: &[String]) -> Vec<String> {
+    let common = a
+        .iter()
+        .rev()
+        .zip(b.iter().rev())
+        .take_while(|(x, y)| x.eq_ignore_ascii_case(y))
+        .count();
+    a[a.len() - common..].to_vec()
+}
+
+fn longer(a: Vec<String>, b: Vec<String>) -> Vec<String> {
+    if a.len() >= b.len() {
+        a
+    } else {
+        b
+    }
+}
+
+fn wildcard(encloser: &[String]) -> Vec<String> {
+    let mut wildcard = vec!["*".to_string()];
+    wildcard.extend_from_slice(encloser);
+    wildcard
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        dns_record::record::{DnsRecord, Nsec, Nsec3, RData, Rrsig},
+        dnssec::denial_impl::{
+            canonical_cmp, nsec3_hash, proves_insecure_delegation, proves_nodata, proves_nxdomain,
+            proves_wildcard_expansion,
+        },
+        encoding::{base32hex_encode, hex_decode},
+        resrec::{QClass, QType},
+    };
+    use std::cmp::Ordering;
+
+    fn name(text: &str) -> Vec<String> {
+        text.split('.')
+            .filter(|x| !x.is_empty())
+            .map(String::from)
+            .collect()
+    }
+
+    fn record(owner: Vec<String>, rdata: RData) -> DnsRecord {
+        let typ = match &rdata {
+            RData::NSEC(_) => QType::NSEC,
+            _ => QType::NSEC3,
+        };
+        DnsRecord {
+            name: owner,
+            typ,
+            class: QClass::IN,
+            ttl: 3600,
+            rdata,
+        }
+    }
+
+    /// An RRSIG naming the signer of a denial record, which is all the proofs
+    /// look at: signatures are verified beforehand.
+    fn rrsig(owner: Vec<String>, typ: QType, signer: &str) -> DnsRecord {
+        DnsRecord {
+            name: owner,
+            typ: QType::RRSIG,
+            class: QClass::IN,
+            ttl: 3600,
+            rdata: RData::RRSIG(Rrsig {
+                type_covered: u16::from(typ),
+                algorithm: 13,
+                labels: 0,
+                original_ttl: 3600,
+                expiration: 0,
+                inception: 0,
+                key_tag: 0,
+                signer: name(signer),
+                signature: vec![],
+            }),
+        }
+    }
+
+    fn nsec(owner: &str, next: &str, types: &[QType]) -> Vec<DnsRecord> {
+        let rdata = RData::NSEC(Nsec {
+            next: name(next),
+            types: types.iter().map(|x| u16::from(*x)).collect(),
+        });
+        vec![
+            record(name(owner), rdata),
+            rrsig(name(owner), QType::NSEC, "example"),
+        ]
+    }
+
+    /// An NSEC3 chain of `example` with the RFC 5155 appendix A parameters, one
+    /// record per name in `names`.
+    fn nsec3_chain(names: &[(&str, &[QType])]) -> Vec<DnsRecord> {
+        let salt = hex_decode("aabbccdd").unwrap();
+        let mut hashes = names
+            .iter()
+            .map(|(owner, types)| (nsec3_hash(&name(owner), &salt, 12), *types))
+            .collect::<Vec<_>>();
+        hashes.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut records = Vec::new();
+        for (i, (hash, types)) in hashes.iter().enumerate() {
+            let owner = vec![base32hex_encode(hash), "example".to_string()];
+            let rdata = RData::NSEC3(Nsec3 {
+                hash_algorithm: 1,
+                flags: 0,
+                iterations: 12,
+                salt: salt.clone(),
+                next_hashed: hashes[(i + 1) % hashes.len()].0.clone(),
+                types: types.iter().map(|x| u16::from(*x)).collect(),
+            });
+            records.push(record(owner.clone(), rdata));
+            records.push(rrsig(owner, QType::NSEC3, "example"));
+        }
+        records
+    }
+
+    #[test]
+    fn canonical_order_test() {
+        // RFC 4034 section 6.1
+        let ordered = [
+            "example",
+            "a.example",
+            "yljkjljk.a.example",
+            "Z.a.example",
+            "zABC.a.EXAMPLE",
+            "z.example",
+            "\u{1}.z.example",
+            "*.z.example",
+            "\u{c8}.z.example",
+        ];
+        for pair in ordered.windows(2) {
+            assert_eq!(
+                canonical_cmp(&name(pair[0]), &name(pair[1])),
+                Ordering::Less
+            );
+        }
+        assert_eq!(
+            canonical_cmp(&name("A.Example"), &name("a.example")),
+            Ordering::Equal
+        );
+    }
+
+    #[test]
+    fn nsec3_hash_test() {
+        // RFC 5155 appendix A
+        let salt = hex_decode("aabbccdd").unwrap();
+        let hash = |owner: &str| base32hex_encode(&nsec3_hash(&name(owner), &salt, 12));
+        assert_eq!(hash("example"), "0P9MHAVEQVM6T7VBL5LOP2U3T2RP3TOM");
+        assert_eq!(hash("a.example"), "35MTHGPGCU1QG68FAB165KLNSNK3DPVL");
+    }
+
+    #[test]
+    fn nsec_nxdomain_test() {
+        let records = [
+            nsec(
+                "example",
+                "a.example",
+                &[QType::SOA, QType::NS, QType::NSEC],
+            ),
+            nsec("a.example", "example", &[QType::A, QType::NSEC]),
+        ]
+        .concat();
+        assert!(proves_nxdomain(&name("b.example"), &records));
+        assert!(!proves_nxdomain(&name("a.example"), &records));
+        // The wildcard at the closest encloser is not covered.
+        assert!(!proves_nxdomain(&name("b.example"), &records[2..]));
+        // Names outside the zone signing the NSEC records.
+        assert!(!proves_nxdomain(&name("b.other"), &records));
+    }
+
+    #[test]
+    fn nsec_nodata_test() {
+        let records = [
+            nsec(
+                "example",
+                "a.b.example",
+                &[QType::SOA, QType::NS, QType::NSEC],
+            ),
+            nsec("a.b.example", "c.example", &[QType::A, QType::NSEC]),
+            nsec("c.example", "example", &[QType::NS, QType::NSEC]),
+        ]
+        .concat();
+        assert!(proves_nodata(&name("a.b.example"), QType::MX, &records));
+        assert!(!proves_nodata(&name("a.b.example"), QType::A, &records));
+        assert!(proves_nodata(&name("b.example"), QType::A, &records));
+        // The NSEC of a delegation only proves the absence of DS.
+        assert!(!proves_nodata(&name("c.example"), QType::A, &records));
+        assert!(proves_nodata(&name("c.example"), QType::DS, &records));
+        assert!(proves_insecure_delegation(&name("c.example"), &records));
+        assert!(!proves_insecure_delegation(&name("a.b.example"), &records));
+    }
+
+    #[test]
+    fn nsec_wildcard_expansion_test() {
+        let records = nsec("a.example", "z.example", &[QType::A, QType::NSEC]);
+        assert!(proves_wildcard_expansion(&name("b.example"), 1, &records));
+        assert!(!proves_wildcard_expansion(&name("zz.example"), 1, &records));
+        assert!(proves_wildcard_expansion(&name("a.example"), 2, &[]));
+    }
+
+    #[test]
+    fn nsec3_denial_test() {
+        let records = nsec3_chain(&[
+            ("example", &[QType::SOA, QType::NS, QType::NSEC3PARAM]),
+            ("a.example", &[QType::A]),
+            ("d.example", &[QType::NS]),
+        ]);
+        assert!(proves_nxdomain(&name("nope.example"), &records));
+        assert!(!proves_nxdomain(&name("a.example"), &records));
+        assert!(proves_nodata(&name("a.example"), QType::MX, &records));
+        assert!(!proves_nodata(&name("a.example"), QType::A, &records));
+        assert!(proves_insecure_delegation(&name("d.example"), &records));
+        assert!(proves_wildcard_expansion(&name("b.example"), 1, &records));
+    }
+}
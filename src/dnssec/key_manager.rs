@@ -0,0 +1,75 @@
+use super::signer::ZoneKey;
+use std::path::PathBuf;
+
+/// Rolls the keys of a zone over on its own, following the timelines of RFC 7583:
+/// a new zone signing key is published ahead of use and takes over when the old
+/// one ends its lifetime (pre-publication, section 3.2.1), a new key signing key
+/// signs the DNSKEY RRset alongside the old one until the parent serves its DS
+/// (double-KSK, section 3.3.2).
+///
+/// Keys live in `directory` as the `.key` and `.private` files of BIND, each with
+/// a `.state` file holding its timeline, so rollovers continue across restarts.
+/// The DS records to submit to the parent are written to `dsset-<origin>`.
+#[derive(Debug)]
+pub struct KeyManager {
+    pub origin: Vec<String>,
+    pub directory: PathBuf,
+    pub policy: KeyPolicy,
+    /// Every key of the zone, removed ones included, oldest first.
+    pub keys: Vec<ManagedKey>,
+}
+
+/// The lifetimes of keys and the delays of RFC 7583 section 3 rollovers wait for,
+/// in seconds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyPolicy {
+    /// The DNSSEC algorithm of new keys.
+    pub algorithm: u8,
+    /// How long a key signing key stays active (Lksk).
+    pub ksk_lifetime: u64,
+    /// How long a zone signing key stays active (Lzsk).
+    pub zsk_lifetime: u64,
+    /// The TTL of the DNSKEY RRset (TTLkey).
+    pub dnskey_ttl: u64,
+    /// The longest TTL of the signed RRsets of the zone (TTLsig).
+    pub max_zone_ttl: u64,
+    /// How long a change of the zone takes to reach every secondary (Dprp).
+    pub propagation_delay: u64,
+    /// How long a new DS takes to be served and the old one to leave caches: the
+    /// registration and propagation delays of the parent and its DS TTL (Dreg +
+    /// DprpP + TTLds).
+    pub parent_delay: u64,
+    /// Added to each interval against delays longer than planned.
+    pub safety_margin: u64,
+}
+
+/// A key and its timeline, as Unix times.
+#[derive(Debug, Clone)]
+pub struct ManagedKey {
+    pub key: ZoneKey,
+    /// The path of the key files, without suffix.
+    pub file: PathBuf,
+    pub state: KeyState,
+    /// When the DNSKEY was added to the zone (Tpub).
+    pub published: u64,
+    /// When the key starts signing (Tact).
+    pub active: u64,
+    /// When the key stops signing (Tret), once a successor is planned.
+    pub retired: Option<u64>,
+    /// When the DNSKEY leaves the zone (Trem), once a successor is planned.
+    pub removed: Option<u64>,
+}
+
+/// Where a key is in its timeline (RFC 7583 section 3.1).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum KeyState {
+    /// The DNSKEY is in the zone, waiting for caches to learn it.
+    Published,
+    /// The key signs the zone.
+    Active,
+    /// The key no longer signs, its DNSKEY stays until signatures made with it
+    /// expire from caches.
+    Retired,
+    /// The DNSKEY left the zone.
+    Removed,
+}
@@ -0,0 +1,541 @@
+use super::{
+    key_manager::{KeyManager, KeyPolicy, KeyState, ManagedKey},
+    signature_impl::{DIGEST_SHA256, ECDSAP256SHA256, SECURE_ENTRY_POINT_FLAG},
+    signer::{ZoneKey, ZoneSigner},
+    signer_impl::DNSKEY_TTL,
+};
+use crate::{
+    dns_name::name::name_to_string,
+    dns_record::record::{DnsRecord, RData},
+    dns_zone::zone::ZoneStore,
+    encoding::{format_timestamp, parse_timestamp},
+    resrec::{QClass, QType},
+};
+use anyhow::{anyhow, bail, Context};
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    fmt, fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// ECDSA P-256 keys, rolled yearly for key signing keys and monthly for zone
+/// signing keys.
+impl Default for KeyPolicy {
+    fn default() -> Self {
+        KeyPolicy {
+            algorithm: ECDSAP256SHA256,
+            ksk_lifetime: 365 * 86400,
+            zsk_lifetime: 30 * 86400,
+            dnskey_ttl: u64::from(DNSKEY_TTL),
+            max_zone_ttl: 86400,
+            propagation_delay: 3600,
+            parent_delay: 2 * 86400,
+            safety_margin: 3600,
+        }
+    }
+}
+
+impl KeyPolicy {
+    pub fn lifetime(&self, ksk: bool) -> u64 {
+        match ksk {
+            true => self.ksk_lifetime,
+            false => self.zsk_lifetime,
+        }
+    }
+
+    /// How long a new DNSKEY is published before it signs, so that validators
+    /// holding the DNSKEY RRset in cache learn it first (Ipub, RFC 7583 section
+    /// 3.2.1).
+    pub fn publish_interval(&self) -> u64 {
+        self.propagation_delay + self.dnskey_ttl + self.safety_margin
+    }
+
+    /// How long a DNSKEY stays after its key stopped signing, until signatures
+    /// made with it left caches (Iret): those over the DNSKEY RRset for key
+    /// signing keys, over any RRset for zone signing keys.
+    pub fn retire_interval(&self, ksk: bool) -> u64 {
+        let signed_ttl = match ksk {
+            true => self.dnskey_ttl,
+            false => self.max_zone_ttl,
+        };
+        self.propagation_delay + signed_ttl + self.safety_margin
+    }
+}
+
+impl KeyManager {
+    /// The keys of the zone `origin` kept in `directory`, found by their `.state`
+    /// files. The directory is created when missing.
+    pub fn load(
+        origin: Vec<String>,
+        directory: PathBuf,
+        policy: KeyPolicy,
+    ) -> anyhow::Result<Self> {
+        fs::create_dir_all(&directory)
+            .with_context(|| format!("creating {}", directory.display()))?;
+        let prefix = format!("K{}+", name_to_string(&origin));
+        let mut keys = Vec::new();
+        for entry in fs::read_dir(&directory)? {
+            let path = entry?.path();
+            let Some(name) = path.file_name().and_then(|x| x.to_str()) else {
+                continue;
+            };
+            if name.starts_with(&prefix) && name.ends_with(".state") {
+                keys.push(ManagedKey::load(&path.with_extension(""))?);
+            }
+        }
+        // Key signing keys first among those published together, as generated.
+        keys.sort_by_key(|k| (k.published, !k.key.dnskey.is_secure_entry_point()));
+        Ok(KeyManager {
+            origin,
+            directory,
+            policy,
+            keys,
+        })
+    }
+
+    /// Moves the rollovers along at the Unix time `now`: makes a key of each kind
+    /// sign the zone, publishes successors ahead of the end of their lifetime and
+    /// moves keys through their states. Returns whether keys were published,
+    /// activated, retired or removed, and rewrites the DS set when they were.
+    pub fn tick(&mut self, now: u64) -> anyhow::Result<bool> {
+        let mut changed = false;
+        for ksk in [true, false] {
+            changed |= self.plan(ksk, now)?;
+        }
+        for key in &mut self.keys {
+            let state = key.state_at(now);
+            if state != key.state {
+                key.state = state;
+                key.save()?;
+                changed = true;
+            }
+        }
+        if changed {
+            self.write_ds_set()?;
+        }
+        Ok(changed)
+    }
+
+    /// Makes sure a key of a kind signs the zone, and that a successor is
+    /// published a publish interval before the current key's lifetime ends. The
+    /// current key retires when its successor starts signing, key signing keys
+    /// once the parent had time to serve the successor's DS too.
+    fn plan(&mut self, ksk: bool, now: u64) -> anyhow::Result<bool> {
+        let policy = &self.policy;
+        let current = self
+            .keys
+            .iter()
+            .position(|k| k.key.dnskey.is_secure_entry_point() == ksk && k.retired.is_none());
+        let active = match current.map(|i| &self.keys[i]) {
+            None => now,
+            Some(key) if now + policy.publish_interval() >= key.active + policy.lifetime(ksk) => {
+                (now + policy.publish_interval()).max(key.active + policy.lifetime(ksk))
+            }
+            Some(_) => return Ok(false),
+        };
+        let retired = match ksk {
+            true => active + policy.parent_delay,
+            false => active,
+        };
+        let removed = retired + policy.retire_interval(ksk);
+
+        let key = self.generate(ksk)?;
+        let file = key
+            .write_files(&self.directory)
+            .with_context(|| format!("writing key of {}", name_to_string(&self.origin)))?;
+        let successor = ManagedKey {
+            key,
+            file,
+            state: KeyState::Published,
+            published: now,
+            active,
+            retired: None,
+            removed: None,
+        };
+        successor.save()?;
+        if let Some(i) = current {
+            let predecessor = &mut self.keys[i];
+            predecessor.retired = Some(retired);
+            predecessor.removed = Some(removed);
+            predecessor.save()?;
+        }
+        self.keys.push(successor);
+        Ok(true)
+    }
+
+    /// A new key with a key tag no other key of the zone has.
+    fn generate(&self, ksk: bool) -> anyhow::Result<ZoneKey> {
+        let flags = match ksk {
+            true => SECURE_ENTRY_POINT_FLAG,
+            false => 0,
+        };
+        loop {
+            let key = ZoneKey::generate(self.origin.clone(), flags, self.policy.algorithm)?;
+            let tag = key.dnskey.key_tag();
+            if !self.keys.iter().any(|k| k.key.dnskey.key_tag() == tag) {
+                return Ok(key);
+            }
+        }
+    }
+
+    /// The DS records the parent zone should serve: those of the active key
+    /// signing keys.
+    pub fn ds_records(&self) -> Vec<DnsRecord> {
+        self.keys
+            .iter()
+            .filter(|k| k.state == KeyState::Active && k.key.dnskey.is_secure_entry_point())
+            .filter_map(|k| k.key.dnskey.to_ds(&self.origin, DIGEST_SHA256))
+            .map(|ds| DnsRecord {
+                name: self.origin.clone(),
+                typ: QType::DS,
+                class: QClass::IN,
+                ttl: self.policy.dnskey_ttl as u32,
+                rdata: RData::DS(ds),
+            })
+            .collect()
+    }
+
+    /// Writes the DS records to submit to the parent to `dsset-<origin>`, as
+    /// BIND's `dnssec-signzone` does.
+    fn write_ds_set(&self) -> anyhow::Result<()> {
+        let file = self
+            .directory
+            .join(format!("dsset-{}", name_to_string(&self.origin)));
+        let text = self
+            .ds_records()
+            .iter()
+            .map(|ds| format!("{}\n", ds))
+            .collect::<String>();
+        fs::write(&file, text).with_context(|| format!("writing {}", file.display()))
+    }
+
+    fn keys_in<'a>(&'a self, states: &'a [KeyState]) -> impl Iterator<Item = &'a ZoneKey> {
+        self.keys
+            .iter()
+            .filter(|k| states.contains(&k.state))
+            .map(|k| &k.key)
+    }
+
+    /// Hands the active keys to the signer of the zone and publishes the DNSKEY
+    /// RRset of the keys not removed yet, bumping the serial of the zone when it
+    /// changed.
+    pub fn apply(&self, signer: &mut ZoneSigner, zones: &mut ZoneStore) {
+        signer.keys = self.keys_in(&[KeyState::Active]).cloned().collect();
+        signer.standby = self
+            .keys_in(&[KeyState::Published, KeyState::Retired])
+            .map(|k| k.dnskey.clone())
+            .collect();
+        signer.signatures.clear();
+
+        let Some(zone) = zones.find(&self.origin) else {
+            return;
+        };
+        let removed = self
+            .keys_in(&[KeyState::Removed])
+            .map(|k| &k.dnskey)
+            .collect::<Vec<_>>();
+        let mut updated = zone.clone();
+        updated.records.retain(|r| match &r.rdata {
+            RData::DNSKEY(dnskey) => !(zone.is_apex(&r.name) && removed.contains(&dnskey)),
+            _ => true,
+        });
+        signer.publish(&mut updated);
+        if updated.records != zone.records {
+            let serial = updated.serial().unwrap_or_default().wrapping_add(1);
+            updated.set_serial(serial);
+            zones.reload(updated);
+        }
+    }
+}
+
+impl ManagedKey {
+    /// The state of the key at `now` by its timeline.
+    pub fn state_at(&self, now: u64) -> KeyState {
+        let reached = |time: Option<u64>| time.is_some_and(|x| x <= now);
+        if reached(self.removed) {
+            KeyState::Removed
+        } else if reached(self.retired) {
+            KeyState::Retired
+        } else if self.active <= now {
+            KeyState::Active
+        } else {
+            KeyState::Published
+        }
+    }
+
+    /// Reads a key from its files and its timeline from `<file>.state`.
+    pub fn load(file: &Path) -> anyhow::Result<Self> {
+        let key = ZoneKey::from_files(file)?;
+        let state_file = state_file(file);
+        let text = fs::read_to_string(&state_file)
+            .with_context(|| format!("reading {}", state_file.display()))?;
+        let fields = text
+            .lines()
+            .filter(|line| !line.starts_with(';'))
+            .filter_map(|line| line.split_once(':'))
+            .map(|(field, value)| (field.trim(), value.trim()))
+            .collect::<HashMap<_, _>>();
+        let time = |name: &str| {
+            fields
+                .get(name)
+                .map(|x| parse_timestamp(x).map(u64::from))
+                .map(|x| x.ok_or_else(|| anyhow!("invalid {} in {}", name, state_file.display())))
+                .transpose()
+        };
+        let required = |name: &str| {
+            time(name)?.ok_or_else(|| anyhow!("missing {} in {}", name, state_file.display()))
+        };
+        let state = fields
+            .get("State")
+            .ok_or_else(|| anyhow!("missing State in {}", state_file.display()))?
+            .parse()?;
+        Ok(ManagedKey {
+            key,
+            file: file.to_path_buf(),
+            state,
+            published: required("Published")?,
+            active: required("Active")?,
+            retired: time("Retired")?,
+            removed: time("Removed")?,
+        })
+    }
+
+    /// Writes the timeline of the key to `<file>.state`, in the `Field: value`
+    /// style of BIND's key files.
+    pub fn save(&self) -> anyhow::Result<()> {
+        let kind = match self.key.dnskey.is_secure_entry_point() {
+            true => "key-signing",
+            false => "zone-signing",
+        };
+        let mut text = format!(
+            "; This is the state of {} key {}, for {}\nState: {}\n",
+            kind,
+            self.key.dnskey.key_tag(),
+            name_to_string(&self.key.owner),
+            self.state
+        );
+        let times = [
+            ("Published", Some(self.published)),
+            ("Active", Some(self.active)),
+            ("Retired", self.retired),
+            ("Removed", self.removed),
+        ];
+        for (field, time) in times {
+            if let Some(time) = time {
+                text.push_str(&format!("{}: {}\n", field, format_timestamp(time as u32)));
+            }
+        }
+        let file = state_file(&self.file);
+        fs::write(&file, text).with_context(|| format!("writing {}", file.display()))
+    }
+}
+
+fn state_file(file: &Path) -> PathBuf {
+    let mut state = OsString::from(file.as_os_str());
+    state.push(".state");
+    PathBuf::from(state)
+}
+
+impl fmt::Display for KeyState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            KeyState::Published => "published",
+            KeyState::Active => "active",
+            KeyState::Retired => "retired",
+            KeyState::Removed => "removed",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for KeyState {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let state = match s {
+            "published" => KeyState::Published,
+            "active" => KeyState::Active,
+            "retired" => KeyState::Retired,
+            "removed" => KeyState::Removed,
+            _ => bail!("unknown key state {}", s),
+        };
+        Ok(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        dns_record::record::RData,
+        dns_zone::zone::{Zone, ZoneStore},
+        dnssec::{
+            key_manager::{KeyManager, KeyPolicy, KeyState},
+            signer::{Denial, ZoneSigner},
+        },
+    };
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+    };
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn name(text: &str) -> Vec<String> {
+        text.split('.')
+            .filter(|x| !x.is_empty())
+            .map(String::from)
+            .collect()
+    }
+
+    fn directory(test: &str) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("key-manager-{}-{}", test, std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        directory
+    }
+
+    fn manager(directory: &Path) -> KeyManager {
+        KeyManager::load(
+            name("example.com"),
+            directory.to_path_buf(),
+            KeyPolicy::default(),
+        )
+        .unwrap()
+    }
+
+    /// The states of the zone signing keys, or of the key signing keys.
+    fn states(manager: &KeyManager, ksk: bool) -> Vec<KeyState> {
+        manager
+            .keys
+            .iter()
+            .filter(|k| k.key.dnskey.is_secure_entry_point() == ksk)
+            .map(|k| k.state)
+            .collect()
+    }
+
+    #[test]
+    fn zsk_rollover_test() {
+        let directory = directory("zsk");
+        let mut manager = manager(&directory);
+        let policy = manager.policy.clone();
+        assert!(manager.tick(NOW).unwrap());
+        assert_eq!(states(&manager, true), [KeyState::Active]);
+        assert_eq!(states(&manager, false), [KeyState::Active]);
+        assert!(!manager.tick(NOW + 86400).unwrap());
+
+        // The successor is published ahead of the end of the lifetime.
+        let end = NOW + policy.zsk_lifetime;
+        assert!(manager.tick(end - policy.publish_interval()).unwrap());
+        assert_eq!(
+            states(&manager, false),
+            [KeyState::Active, KeyState::Published]
+        );
+        assert!(manager.tick(end).unwrap());
+        assert_eq!(
+            states(&manager, false),
+            [KeyState::Retired, KeyState::Active]
+        );
+        assert!(manager.tick(end + policy.retire_interval(false)).unwrap());
+        assert_eq!(
+            states(&manager, false),
+            [KeyState::Removed, KeyState::Active]
+        );
+        assert_eq!(states(&manager, true), [KeyState::Active]);
+
+        // The timelines survive a restart.
+        let reloaded = self::manager(&directory);
+        let timeline = |m: &KeyManager| {
+            m.keys
+                .iter()
+                .map(|k| {
+                    (
+                        k.key.dnskey.clone(),
+                        k.state,
+                        k.active,
+                        k.retired,
+                        k.removed,
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(timeline(&reloaded), timeline(&manager));
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn ksk_rollover_test() {
+        let directory = directory("ksk");
+        let mut manager = manager(&directory);
+        let policy = manager.policy.clone();
+        manager.tick(NOW).unwrap();
+        let ds_set = || fs::read_to_string(directory.join("dsset-example.com.")).unwrap();
+        let first = manager.ds_records();
+        assert_eq!(first.len(), 1);
+        assert_eq!(ds_set(), format!("{}\n", first[0]));
+
+        // Both keys sign the DNSKEY RRset until the parent serves the new DS.
+        let end = NOW + policy.ksk_lifetime;
+        manager.tick(end - policy.publish_interval()).unwrap();
+        manager.tick(end).unwrap();
+        assert_eq!(states(&manager, true), [KeyState::Active, KeyState::Active]);
+        assert_eq!(manager.ds_records().len(), 2);
+        manager.tick(end + policy.parent_delay).unwrap();
+        assert_eq!(
+            states(&manager, true),
+            [KeyState::Retired, KeyState::Active]
+        );
+        assert_eq!(manager.ds_records().len(), 1);
+        assert_ne!(manager.ds_records(), first);
+        assert_eq!(ds_set(), format!("{}\n", manager.ds_records()[0]));
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn apply_test() {
+        let directory = directory("apply");
+        let mut manager = manager(&directory);
+        let policy = manager.policy.clone();
+        let mut zones = ZoneStore::default();
+        zones
+            .zones
+            .push(Zone::with_default_soa(name("example.com")));
+        let mut signer = ZoneSigner::new(name("example.com"), vec![], Denial::Nsec);
+        let dnskeys = |zones: &ZoneStore| {
+            zones.zones[0]
+                .records
+                .iter()
+                .filter_map(|r| match &r.rdata {
+                    RData::DNSKEY(x) => Some(x.key_tag()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        manager.tick(NOW).unwrap();
+        manager.apply(&mut signer, &mut zones);
+        assert_eq!(signer.keys.len(), 2);
+        assert_eq!(dnskeys(&zones).len(), 2);
+        assert_eq!(zones.zones[0].serial(), Some(2));
+
+        let end = NOW + policy.zsk_lifetime;
+        manager.tick(end - policy.publish_interval()).unwrap();
+        manager.apply(&mut signer, &mut zones);
+        assert_eq!((signer.keys.len(), signer.standby.len()), (2, 1));
+        assert_eq!(dnskeys(&zones).len(), 3);
+        assert_eq!(zones.zones[0].serial(), Some(3));
+        assert_eq!(zones.zones[0].journal.diffs.len(), 2);
+
+        manager.tick(end + policy.retire_interval(false)).unwrap();
+        manager.apply(&mut signer, &mut zones);
+        assert_eq!((signer.keys.len(), signer.standby.len()), (2, 0));
+        let signing = signer
+            .keys
+            .iter()
+            .map(|k| k.dnskey.key_tag())
+            .collect::<Vec<_>>();
+        assert_eq!(dnskeys(&zones), signing);
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
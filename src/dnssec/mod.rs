@@ -0,0 +1,9 @@
+pub(crate) mod denial_impl;
+pub(crate) mod key_manager;
+pub(crate) mod key_manager_impl;
+pub(crate) mod signature_impl;
+pub mod signer;
+pub(crate) mod signer_impl;
+pub mod validator;
+pub(crate) mod validator_impl;
+pub(crate) mod zone_signing_impl;
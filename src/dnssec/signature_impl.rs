@@ -0,0 +1,472 @@
+//! Verification of RRSIG records over RRsets (RFC 4034 section 3.1.8), with
+//! the canonical forms of names and records they are computed over.
+
+use super::validator::ValidationError;
+use crate::{
+    dns_name::name::{name_as_bytes, names_equal},
+    dns_record::record::{DnsRecord, Dnskey, Ds, RData, Rrsig},
+    dns_zone::zone_impl::serial_gt,
+    resrec::QType,
+};
+use bytes::BufMut;
+use p256::ecdsa::signature::Verifier;
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha384};
+
+pub const RSASHA256: u8 = 8;
+pub const ECDSAP256SHA256: u8 = 13;
+pub const ECDSAP384SHA384: u8 = 14;
+pub const ED25519: u8 = 15;
+
+pub const DIGEST_SHA1: u8 = 1;
+pub const DIGEST_SHA256: u8 = 2;
+pub const DIGEST_SHA384: u8 = 4;
+
+/// The DNSKEY flag marking keys that sign zone data.
+pub const ZONE_KEY_FLAG: u16 = 0x0100;
+
+/// The DNSKEY flag marking key signing keys.
+pub const SECURE_ENTRY_POINT_FLAG: u16 = 0x0001;
+
+/// The only valid value of the DNSKEY protocol field.
+pub const DNSKEY_PROTOCOL: u8 = 3;
+
+/// Whether signatures of the algorithm can be verified.
+pub fn is_supported_algorithm(algorithm: u8) -> bool {
+    matches!(
+        algorithm,
+        RSASHA256 | ECDSAP256SHA256 | ECDSAP384SHA384 | ED25519
+    )
+}
+
+/// A name in canonical form: uncompressed and lowercase (RFC 4034 section 6.2).
+pub fn canonical_name(name: &[String]) -> Vec<u8> {
+    name_as_bytes(&lowercase(name))
+}
+
+pub fn lowercase(name: &[String]) -> Vec<String> {
+    name.iter().map(|x| x.to_ascii_lowercase()).collect()
+}
+
+/// RDATA in canonical form, with the embedded names of the types listed in RFC
+/// 4034 section 6.2 (as amended by RFC 6840 section 5.1) lowercased.
+pub fn canonical_rdata(rdata: &RData) -> Vec<u8> {
+    match rdata {
+        RData::NS(name) => RData::NS(lowercase(name)).as_bytes(),
+        RData::CNAME(name) => RData::CNAME(lowercase(name)).as_bytes(),
+        RData::PTR(name) => RData::PTR(lowercase(name)).as_bytes(),
+        RData::MX {
+            preference,
+            exchange,
+        } => RData::MX {
+            preference: *preference,
+            exchange: lowercase(exchange),
+        }
+        .as_bytes(),
+        RData::SRV(srv) => {
+            let mut srv = srv.clone();
+            srv.target = lowercase(&srv.target);
+            srv.as_bytes()
+        }
+        RData::NAPTR(naptr) => {
+            let mut naptr = naptr.clone();
+            naptr.replacement = lowercase(&naptr.replacement);
+            naptr.as_bytes()
+        }
+        RData::SOA(soa) => {
+            let mut soa = soa.clone();
+            soa.mname = lowercase(&soa.mname);
+            soa.rname = lowercase(&soa.rname);
+            soa.as_bytes()
+        }
+        RData::RRSIG(rrsig) => {
+            let mut rrsig = rrsig.clone();
+            rrsig.signer = lowercase(&rrsig.signer);
+            rrsig.as_bytes()
+        }
+        rdata => rdata.as_bytes(),
+    }
+}
+
+/// Groups records into RRsets by owner and type, leaving out RRSIG records and
+/// the OPT and TSIG pseudo-records.
+pub fn rrsets(records: &[DnsRecord]) -> Vec<Vec<DnsRecord>> {
+    let mut rrsets: Vec<Vec<DnsRecord>> = Vec::new();
+    let data = records
+        .iter()
+        .filter(|r| !matches!(r.typ, QType::RRSIG | QType::OPT | QType::TSIG));
+    for record in data {
+        let existing = rrsets
+            .iter_mut()
+            .find(|x| x[0].typ == record.typ && names_equal(&x[0].name, &record.name));
+        match existing {
+            Some(rrset) => rrset.push(record.clone()),
+            None => rrsets.push(vec![record.clone()]),
+        }
+    }
+    rrsets
+}
+
+/// The data an RRSIG signs: its own RDATA without the signature, followed by the
+/// records of the RRset in canonical form and order, with the original TTL and
+/// the wildcard owner they were expanded from.
+pub fn signed_data(rrsig: &Rrsig, rrset: &[DnsRecord]) -> Vec<u8> {
+    let mut unsigned = rrsig.clone();
+    unsigned.signer = lowercase(&rrsig.signer);
+    unsigned.signature.clear();
+    let mut data = unsigned.as_bytes();
+
+    let Some(first) = rrset.first() else {
+        return data;
+    };
+    let labels = usize::from(rrsig.labels);
+    let owner = if labels < first.name.len() {
+        let mut wildcard = vec!["*".to_string()];
+        wildcard.extend_from_slice(&first.name[first.name.len() - labels..]);
+        canonical_name(&wildcard)
+    } else {
+        canonical_name(&first.name)
+    };
+
+    let mut rdatas = rrset
+        .iter()
+        .map(|r| canonical_rdata(&r.rdata))
+        .collect::<Vec<_>>();
+    rdatas.sort();
+    rdatas.dedup();
+    for rdata in rdatas {
+        data.put(&owner[..]);
+        data.put_u16(u16::from(first.typ));
+        data.put_u16(u16::from(first.class));
+        data.put_u32(rrsig.original_ttl);
+        data.put_u16(rdata.len() as u16);
+        data.put(&rdata[..]);
+    }
+    data
+}
+
+/// Checks that `rrsig` is a valid signature of `rrset` by `key` at Unix time `now`.
+pub fn verify_rrsig(
+    rrsig: &Rrsig,
+    rrset: &[DnsRecord],
+    key: &Dnskey,
+    now: u64,
+) -> Result<(), ValidationError> {
+    let now = now as u32;
+    if serial_gt(rrsig.inception, now) || serial_gt(now, rrsig.expiration) {
+        return Err(ValidationError::SignatureExpired);
+    }
+    let data = signed_data(rrsig, rrset);
+    if key.algorithm == rrsig.algorithm && key.verify(&data, &rrsig.signature) {
+        Ok(())
+    } else {
+        Err(ValidationError::BadSignature)
+    }
+}
+
+impl Dnskey {
+    /// The key tag identifying the key in RRSIG and DS records (RFC 4034
+    /// appendix B).
+    pub fn key_tag(&self) -> u16 {
+        let mut acc = 0u32;
+        for (i, byte) in self.as_bytes().into_iter().enumerate() {
+            acc += if i % 2 == 0 {
+                u32::from(byte) << 8
+            } else {
+                u32::from(byte)
+            };
+        }
+        acc += (acc >> 16) & 0xffff;
+        (acc & 0xffff) as u16
+    }
+
+    /// Whether the key may verify signatures over zone data.
+    pub fn is_zone_key(&self) -> bool {
+        self.flags & ZONE_KEY_FLAG != 0 && self.protocol == DNSKEY_PROTOCOL
+    }
+
+    /// Whether the key is meant to sign the DNSKEY RRset, as referenced by DS.
+    pub fn is_secure_entry_point(&self) -> bool {
+        self.flags & SECURE_ENTRY_POINT_FLAG != 0
+    }
+
+    /// The digest of the key owned by `owner`, as published in DS records.
+    pub fn ds_digest(&self, owner: &[String], digest_type: u8) -> Option<Vec<u8>> {
+        let mut data = canonical_name(owner);
+        data.put(&self.as_bytes()[..]);
+        match digest_type {
+            DIGEST_SHA1 => Some(Sha1::digest(&data).to_vec()),
+            DIGEST_SHA256 => Some(Sha256::digest(&data).to_vec()),
+            DIGEST_SHA384 => Some(Sha384::digest(&data).to_vec()),
+            _ => None,
+        }
+    }
+
+    /// The DS record referencing this key, owned by `owner`.
+    pub fn to_ds(&self, owner: &[String], digest_type: u8) -> Option<Ds> {
+        Some(Ds {
+            key_tag: self.key_tag(),
+            algorithm: self.algorithm,
+            digest_type,
+            digest: self.ds_digest(owner, digest_type)?,
+        })
+    }
+
+    /// Whether `ds` references this key owned by `owner`.
+    pub fn matches_ds(&self, owner: &[String], ds: &Ds) -> bool {
+        ds.key_tag == self.key_tag()
+            && ds.algorithm == self.algorithm
+            && self.ds_digest(owner, ds.digest_type).as_deref() == Some(&ds.digest[..])
+    }
+
+    /// Verifies `signature` over `data` with the key's algorithm.
+    fn verify(&self, data: &[u8], signature: &[u8]) -> bool {
+        let key = &self.public_key[..];
+        match self.algorithm {
+            RSASHA256 => verify_rsa(key, data, signature).is_some(),
+            ECDSAP256SHA256 => {
+                let point = [&[0x04], key].concat();
+                let (Ok(key), Ok(signature)) = (
+                    p256::ecdsa::VerifyingKey::from_sec1_bytes(&point),
+                    p256::ecdsa::Signature::from_slice(signature),
+                ) else {
+                    return false;
+                };
+                key.verify(data, &signature).is_ok()
+            }
+            ECDSAP384SHA384 => {
+                let point = [&[0x04], key].concat();
+                let (Ok(key), Ok(signature)) = (
+                    p384::ecdsa::VerifyingKey::from_sec1_bytes(&point),
+                    p384::ecdsa::Signature::from_slice(signature),
+                ) else {
+                    return false;
+                };
+                key.verify(data, &signature).is_ok()
+            }
+            ED25519 => {
+                let (Ok(key), Ok(signature)) = (
+                    <&[u8; 32]>::try_from(key),
+                    ed25519_dalek::Signature::from_slice(signature),
+                ) else {
+                    return false;
+                };
+                ed25519_dalek::VerifyingKey::from_bytes(key)
+                    .is_ok_and(|key| key.verify(data, &signature).is_ok())
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Verifies an RSASSA-PKCS1-v1_5 signature with SHA-256, the key being encoded as
+/// in RFC 3110: the exponent length, the exponent and the modulus.
+fn verify_rsa(key: &[u8], data: &[u8], signature: &[u8]) -> Option<()> {
+    let (exponent_len, rest) = match key.split_first()? {
+        (0, rest) => (
+            usize::from(u16::from_be_bytes([*rest.first()?, *rest.get(1)?])),
+            &rest[2..],
+        ),
+        (len, rest) => (usize::from(*len), rest),
+    };
+    if rest.len() <= exponent_len {
+        return None;
+    }
+    let (exponent, modulus) = rest.split_at(exponent_len);
+    let key = rsa::RsaPublicKey::new(
+        rsa::BigUint::from_bytes_be(modulus),
+        rsa::BigUint::from_bytes_be(exponent),
+    )
+    .ok()?;
+    let scheme = rsa::Pkcs1v15Sign::new::<Sha256>();
+    key.verify(scheme, &Sha256::digest(data), signature).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        dns_record::record::{DnsRecord, Dnskey, Ds, RData, Rrsig},
+        dnssec::{
+            signature_impl::{
+                signed_data, verify_rrsig, DIGEST_SHA1, ECDSAP384SHA384, ED25519, RSASHA256,
+            },
+            validator::ValidationError,
+        },
+        encoding::{base64_decode, hex_decode},
+        resrec::{QClass, QType},
+    };
+    use rsa::traits::PublicKeyParts;
+    use sha2::{Digest, Sha256};
+    use std::net::Ipv4Addr;
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn name(labels: &[&str]) -> Vec<String> {
+        labels.iter().map(|x| x.to_string()).collect()
+    }
+
+    fn rrset() -> Vec<DnsRecord> {
+        [1, 2]
+            .into_iter()
+            .map(|last| DnsRecord {
+                name: name(&["www", "example"]),
+                typ: QType::A,
+                class: QClass::IN,
+                ttl: 3600,
+                rdata: RData::A(Ipv4Addr::new(192, 0, 2, last)),
+            })
+            .collect()
+    }
+
+    fn rrsig(key: &Dnskey) -> Rrsig {
+        Rrsig {
+            type_covered: u16::from(QType::A),
+            algorithm: key.algorithm,
+            labels: 2,
+            original_ttl: 3600,
+            expiration: (NOW + 3600) as u32,
+            inception: (NOW - 3600) as u32,
+            key_tag: key.key_tag(),
+            signer: name(&["example"]),
+            signature: vec![],
+        }
+    }
+
+    /// Signs the test RRset with `sign`, then checks the signature verifies and
+    /// fails once the RRset changes.
+    fn check_algorithm(key: Dnskey, sign: impl Fn(&[u8]) -> Vec<u8>) {
+        let mut rrsig = rrsig(&key);
+        let mut rrset = rrset();
+        rrsig.signature = sign(&signed_data(&rrsig, &rrset));
+        assert_eq!(verify_rrsig(&rrsig, &rrset, &key, NOW), Ok(()));
+
+        rrset.reverse();
+        assert_eq!(verify_rrsig(&rrsig, &rrset, &key, NOW), Ok(()));
+        rrset.pop();
+        assert_eq!(
+            verify_rrsig(&rrsig, &rrset, &key, NOW),
+            Err(ValidationError::BadSignature)
+        );
+    }
+
+    #[test]
+    fn key_tag_and_ds_test() {
+        // RFC 4034 section 5.4
+        let key = Dnskey {
+            flags: 256,
+            protocol: 3,
+            algorithm: 5,
+            public_key: base64_decode(
+                "AQOeiiR0GOMYkDshWoSKz9XzfwJr1AYtsmx3TGkJaNXVbfi/2pHm822aJ5iI9BMzNXxeYCmZDRD99WYwYqUSdjMmmAphXdvxegXd/M5+X7OrzKBaMbCVdFLUUh6DhweJBjEVv5f2wwjM9XzcnOf+EPbtG9DMBmADjFDc2w/rljwvFw==",
+            )
+            .unwrap(),
+        };
+        assert_eq!(key.key_tag(), 60485);
+        assert!(key.is_zone_key());
+        assert!(!key.is_secure_entry_point());
+
+        let ds = Ds {
+            key_tag: 60485,
+            algorithm: 5,
+            digest_type: DIGEST_SHA1,
+            digest: hex_decode("2BB183AF5F22588179A53B0A98631FAD1A292118").unwrap(),
+        };
+        let owner = name(&["dskey", "example", "com"]);
+        assert_eq!(key.to_ds(&owner, DIGEST_SHA1), Some(ds.clone()));
+        assert!(key.matches_ds(&name(&["DSKEY", "Example", "COM"]), &ds));
+        assert!(!key.matches_ds(&name(&["example", "com"]), &ds));
+    }
+
+    #[test]
+    fn ed25519_test() {
+        use ed25519_dalek::Signer;
+        let signing_key = ed25519_dalek::SigningKey::from_bytes(&[7; 32]);
+        let key = Dnskey {
+            flags: 256,
+            protocol: 3,
+            algorithm: ED25519,
+            public_key: signing_key.verifying_key().to_bytes().to_vec(),
+        };
+        check_algorithm(key, |data| signing_key.sign(data).to_bytes().to_vec());
+    }
+
+    #[test]
+    fn ecdsa_p384_test() {
+        use p384::ecdsa::{signature::Signer, Signature, SigningKey};
+        let signing_key = SigningKey::from_slice(&[7; 48]).unwrap();
+        let point = signing_key.verifying_key().to_encoded_point(false);
+        let key = Dnskey {
+            flags: 256,
+            protocol: 3,
+            algorithm: ECDSAP384SHA384,
+            public_key: point.as_bytes()[1..].to_vec(),
+        };
+        check_algorithm(key, |data| {
+            let signature: Signature = signing_key.sign(data);
+            signature.to_bytes().to_vec()
+        });
+    }
+
+    #[test]
+    fn rsa_sha256_test() {
+        let private_key = rsa::RsaPrivateKey::new(&mut rand::thread_rng(), 1024).unwrap();
+        let exponent = private_key.e().to_bytes_be();
+        let mut public_key = vec![exponent.len() as u8];
+        public_key.extend(exponent);
+        public_key.extend(private_key.n().to_bytes_be());
+        let key = Dnskey {
+            flags: 256,
+            protocol: 3,
+            algorithm: RSASHA256,
+            public_key,
+        };
+        check_algorithm(key, |data| {
+            let scheme = rsa::Pkcs1v15Sign::new::<Sha256>();
+            private_key.sign(scheme, &Sha256::digest(data)).unwrap()
+        });
+    }
+
+    #[test]
+    fn validity_period_test() {
+        let signing_key = ed25519_dalek::SigningKey::from_bytes(&[7; 32]);
+        let key = Dnskey {
+            flags: 256,
+            protocol: 3,
+            algorithm: ED25519,
+            public_key: signing_key.verifying_key().to_bytes().to_vec(),
+        };
+        let rrsig = rrsig(&key);
+        for now in [NOW - 7200, NOW + 7200] {
+            assert_eq!(
+                verify_rrsig(&rrsig, &rrset(), &key, now),
+                Err(ValidationError::SignatureExpired)
+            );
+        }
+    }
+
+    #[test]
+    fn wildcard_signed_data_test() {
+        let key = Dnskey {
+            flags: 256,
+            protocol: 3,
+            algorithm: ED25519,
+            public_key: vec![0; 32],
+        };
+        let rrsig = Rrsig {
+            labels: 1,
+            ..rrsig(&key)
+        };
+        let mut wildcard = rrset();
+        wildcard
+            .iter_mut()
+            .for_each(|r| r.name = name(&["*", "example"]));
+        let mut expanded = rrset();
+        expanded
+            .iter_mut()
+            .for_each(|r| r.name = name(&["a", "B", "example"]));
+        assert_eq!(
+            signed_data(&rrsig, &expanded),
+            signed_data(&rrsig, &wildcard)
+        );
+    }
+}
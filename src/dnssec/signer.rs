@@ -0,0 +1,77 @@
+use crate::dns_record::record::{DnsRecord, Dnskey, Nsec3Param};
+use std::{collections::HashMap, path::PathBuf};
+
+/// Signatures start being valid this long before they are made, for validators
+/// whose clock runs late.
+pub const INCEPTION_OFFSET: u64 = 3600;
+
+/// Signs the responses of an authoritative zone on the fly (RFC 4035 section
+/// 3.1), when the query sets the DO bit: RRSIGs over the RRsets sent, and
+/// denials of existence made up for the name queried (RFC 4470, RFC 7129
+/// appendix B) rather than a precomputed chain. It also signs whole zones
+/// ahead of time for `dns-sign`.
+#[derive(Debug)]
+pub struct ZoneSigner {
+    pub origin: Vec<String>,
+    /// The keys signing the zone.
+    pub keys: Vec<ZoneKey>,
+    /// Keys published without signing anything: introduced ahead of use, or
+    /// retired while signatures made with them may still be cached.
+    pub standby: Vec<Dnskey>,
+    pub denial: Denial,
+    /// Signatures made so far, by lowercase owner and type, with the canonical
+    /// RDATA and TTL of the RRset they sign.
+    pub signatures: HashMap<(Vec<String>, u16), SignedRrset>,
+}
+
+/// A master file signed ahead of time, as `dns-sign` is asked to.
+#[derive(Debug, Clone)]
+pub struct SignJob {
+    pub input: PathBuf,
+    /// Where the signed zone is written, `<input>.signed` by default.
+    pub output: Option<PathBuf>,
+    /// The origin of a master file without `$ORIGIN`.
+    pub origin: Option<Vec<String>>,
+    pub keys: Vec<ZoneKey>,
+    pub denial: Denial,
+    /// When signatures become valid, an hour before signing by default.
+    pub inception: Option<u32>,
+    /// When signatures expire, 30 days after signing by default.
+    pub expiration: Option<u32>,
+}
+
+/// How nonexistence is proven in signed responses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Denial {
+    /// NSEC records from the closest existing name to one right after the name
+    /// queried.
+    Nsec,
+    /// NSEC3 records matching existing names, or covering a single hash for
+    /// names that do not exist.
+    Nsec3(Nsec3Param),
+}
+
+#[derive(Debug, Clone)]
+pub struct SignedRrset {
+    pub rdatas: Vec<Vec<u8>>,
+    pub ttl: u32,
+    pub rrsigs: Vec<DnsRecord>,
+    /// The earliest expiration of the signatures, as a Unix time.
+    pub expiration: u64,
+}
+
+/// A key of a zone with its private half.
+#[derive(Debug, Clone)]
+pub struct ZoneKey {
+    pub owner: Vec<String>,
+    pub dnskey: Dnskey,
+    pub private: PrivateKey,
+}
+
+#[derive(Clone)]
+pub enum PrivateKey {
+    RsaSha256(Box<rsa::RsaPrivateKey>),
+    EcdsaP256Sha256(p256::ecdsa::SigningKey),
+    EcdsaP384Sha384(p384::ecdsa::SigningKey),
+    Ed25519(ed25519_dalek::SigningKey),
+}
@@ -0,0 +1,1043 @@
+use super::{
+    denial_impl::{canonical_cmp, nsec3_hash},
+    signature_impl::{
+        canonical_rdata, lowercase, rrsets, signed_data, DNSKEY_PROTOCOL, ECDSAP256SHA256,
+        ECDSAP384SHA384, ED25519, RSASHA256, ZONE_KEY_FLAG,
+    },
+    signer::{Denial, PrivateKey, SignedRrset, ZoneKey, ZoneSigner, INCEPTION_OFFSET},
+    validator_impl::cname_target,
+};
+use crate::{
+    dns_header::header::ResponseCode,
+    dns_message::message::DnsMessage,
+    dns_name::name::{name_to_string, names_equal},
+    dns_record::record::{DnsRecord, Dnskey, Nsec, Nsec3, Nsec3Param, RData, Rrsig},
+    dns_zone::zone::Zone,
+    encoding::{base32hex_encode, base64_decode, base64_encode},
+    resrec::{QClass, QType},
+};
+use anyhow::{anyhow, bail, ensure, Context};
+use rsa::traits::{PrivateKeyParts, PublicKeyParts};
+use sha2::{Digest, Sha256};
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    ffi::OsString,
+    fmt, fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// How long signatures are valid for.
+const SIGNATURE_VALIDITY: u64 = 7 * 86400;
+
+/// A cached signature is replaced once it has less than this left to live.
+const SIGNATURE_REFRESH: u64 = 2 * 86400;
+
+/// Upper bound on cached signatures: NSEC3 white lies differ for every
+/// nonexistent name queried.
+const MAX_CACHED_RRSETS: usize = 10_000;
+
+/// Size of the RSA keys generated.
+const RSA_KEY_BITS: usize = 2048;
+
+/// TTL of the DNSKEY and NSEC3PARAM records published at the apex.
+pub const DNSKEY_TTL: u32 = 3600;
+
+impl Denial {
+    /// NSEC3 without extra iterations nor salt, as RFC 9276 recommends.
+    pub fn nsec3() -> Self {
+        Denial::Nsec3(Nsec3Param {
+            hash_algorithm: 1,
+            flags: 0,
+            iterations: 0,
+            salt: vec![],
+        })
+    }
+}
+
+impl ZoneSigner {
+    pub fn new(origin: Vec<String>, keys: Vec<ZoneKey>, denial: Denial) -> Self {
+        ZoneSigner {
+            origin,
+            keys,
+            standby: Vec::new(),
+            denial,
+            signatures: HashMap::new(),
+        }
+    }
+
+    /// Adds the DNSKEY records of the keys, standby ones included, to the zone
+    /// apex, and the NSEC3PARAM record when denials use NSEC3.
+    pub fn publish(&self, zone: &mut Zone) {
+        let mut records = self
+            .keys
+            .iter()
+            .map(|key| &key.dnskey)
+            .chain(&self.standby)
+            .map(|dnskey| DnsRecord {
+                name: self.origin.clone(),
+                typ: QType::DNSKEY,
+                class: zone.class,
+                ttl: DNSKEY_TTL,
+                rdata: RData::DNSKEY(dnskey.clone()),
+            })
+            .collect::<Vec<_>>();
+        if let Denial::Nsec3(param) = &self.denial {
+            records.push(DnsRecord {
+                name: self.origin.clone(),
+                typ: QType::NSEC3PARAM,
+                class: zone.class,
+                ttl: DNSKEY_TTL,
+                rdata: RData::NSEC3PARAM(param.clone()),
+            });
+        }
+        for record in records {
+            if !zone.records.contains(&record) {
+                zone.records.push(record);
+            }
+        }
+    }
+
+    /// Adds signatures to a response of `zone` made by [`Zone::answer`], with the
+    /// proof that the name or type queried does not exist when it is negative.
+    pub fn sign_response(&mut self, zone: &Zone, message: &mut DnsMessage, now: u64) {
+        if let Some(target) = denied_name(zone, message) {
+            let denial = match message.header.rcode {
+                ResponseCode::NameError => self.deny_name(zone, &target),
+                _ => self.deny_type(zone, &target),
+            };
+            message.authorities.extend(denial);
+        }
+
+        for section in [
+            &mut message.answers,
+            &mut message.authorities,
+            &mut message.additionals,
+        ] {
+            let rrsigs = rrsets(section)
+                .iter()
+                .filter(|rrset| zone.contains(&rrset[0].name))
+                .flat_map(|rrset| self.signatures(rrset, now))
+                .collect::<Vec<_>>();
+            section.extend(rrsigs);
+        }
+        message.update_counts();
+    }
+
+    /// The RRSIGs over `rrset`, from the cache while they are far from expiring.
+    fn signatures(&mut self, rrset: &[DnsRecord], now: u64) -> Vec<DnsRecord> {
+        let typ = rrset[0].typ;
+        let ttl = rrset[0].ttl;
+        let mut rdatas = rrset
+            .iter()
+            .map(|r| canonical_rdata(&r.rdata))
+            .collect::<Vec<_>>();
+        rdatas.sort();
+        rdatas.dedup();
+
+        let cache_key = (lowercase(&rrset[0].name), u16::from(typ));
+        if let Some(signed) = self.signatures.get(&cache_key) {
+            if signed.rdatas == rdatas
+                && signed.ttl == ttl
+                && signed.expiration > now + SIGNATURE_REFRESH
+            {
+                return signed.rrsigs.clone();
+            }
+        }
+
+        let inception = now.saturating_sub(INCEPTION_OFFSET) as u32;
+        let expiration = now + SIGNATURE_VALIDITY;
+        let rrsigs = self
+            .signing_keys(typ)
+            .map(|key| key.sign(rrset, inception, expiration as u32))
+            .collect::<Vec<_>>();
+
+        if self.signatures.len() >= MAX_CACHED_RRSETS {
+            self.signatures
+                .retain(|_, x| x.expiration > now + SIGNATURE_REFRESH);
+            if self.signatures.len() >= MAX_CACHED_RRSETS {
+                self.signatures.clear();
+            }
+        }
+        self.signatures.insert(
+            cache_key,
+            SignedRrset {
+                rdatas,
+                ttl,
+                rrsigs: rrsigs.clone(),
+                expiration,
+            },
+        );
+        rrsigs
+    }
+
+    /// Key signing keys sign the DNSKEY RRset and zone signing keys everything
+    /// else, any key doing both when the zone has a single kind.
+    pub fn signing_keys(&self, typ: QType) -> impl Iterator<Item = &ZoneKey> {
+        let has_ksk = self.keys.iter().any(|k| k.dnskey.is_secure_entry_point());
+        let has_zsk = self.keys.iter().any(|k| !k.dnskey.is_secure_entry_point());
+        self.keys.iter().filter(move |k| {
+            let ksk = k.dnskey.is_secure_entry_point();
+            match typ {
+                QType::DNSKEY => ksk || !has_ksk,
+                _ => !ksk || !has_zsk,
+            }
+        })
+    }
+
+    /// Proves `name` exists without the type queried.
+    fn deny_type(&self, zone: &Zone, name: &[String]) -> Vec<DnsRecord> {
+        match &self.denial {
+            Denial::Nsec if zone.name_exists(name) => {
+                vec![self.nsec(zone, name, successor(name))]
+            }
+            // An empty non-terminal is proven by an NSEC covering it that leads
+            // below it.
+            Denial::Nsec => {
+                let owner = predecessor(zone, name);
+                vec![self.nsec(zone, &owner, successor(name))]
+            }
+            Denial::Nsec3(_) => vec![self.matching_nsec3(zone, name)],
+        }
+    }
+
+    /// Proves neither `name` nor a wildcard that could have produced it exists.
+    fn deny_name(&self, zone: &Zone, name: &[String]) -> Vec<DnsRecord> {
+        let encloser = closest_encloser(zone, name);
+        let mut wildcard = vec!["*".to_string()];
+        wildcard.extend_from_slice(&encloser);
+
+        match &self.denial {
+            Denial::Nsec => self.covering_nsecs(zone, &[name.to_vec(), wildcard]),
+            Denial::Nsec3(_) => {
+                let next_closer = &name[name.len() - encloser.len() - 1..];
+                let mut records = vec![
+                    self.matching_nsec3(zone, &encloser),
+                    self.covering_nsec3(zone, next_closer),
+                    self.covering_nsec3(zone, &wildcard),
+                ];
+                records.dedup_by(|a, b| names_equal(&a.name, &b.name));
+                records
+            }
+        }
+    }
+
+    /// NSEC records owned by the existing names right before each of `names`,
+    /// leading right after it and its descendants, merged when names share a
+    /// predecessor.
+    fn covering_nsecs(&self, zone: &Zone, names: &[Vec<String>]) -> Vec<DnsRecord> {
+        let mut covering: Vec<(Vec<String>, Vec<String>)> = Vec::new();
+        for name in names {
+            let owner = predecessor(zone, name);
+            match covering.iter_mut().find(|(x, _)| names_equal(x, &owner)) {
+                Some((_, last)) if canonical_cmp(name, last) == Ordering::Greater => {
+                    *last = name.clone()
+                }
+                Some(_) => {}
+                None => covering.push((owner, name.clone())),
+            }
+        }
+        covering
+            .into_iter()
+            .map(|(owner, last)| self.nsec(zone, &owner, sibling_successor(&last)))
+            .collect()
+    }
+
+    fn nsec(&self, zone: &Zone, owner: &[String], next: Vec<String>) -> DnsRecord {
+        let mut types = types_at(zone, owner);
+        types.extend([u16::from(QType::RRSIG), u16::from(QType::NSEC)]);
+        types.sort();
+        DnsRecord {
+            name: owner.to_vec(),
+            typ: QType::NSEC,
+            class: zone.class,
+            ttl: negative_ttl(zone),
+            rdata: RData::NSEC(Nsec { next, types }),
+        }
+    }
+
+    /// The NSEC3 record of an existing name, leading to the hash right after its
+    /// own.
+    fn matching_nsec3(&self, zone: &Zone, name: &[String]) -> DnsRecord {
+        let hash = self.hash(name);
+        let mut types = types_at(zone, name);
+        if !types.is_empty() {
+            types.push(u16::from(QType::RRSIG));
+            types.sort();
+        }
+        self.nsec3(zone, &hash, step(&hash, true), types)
+    }
+
+    /// An NSEC3 record covering the hash of `name` and nothing else.
+    fn covering_nsec3(&self, zone: &Zone, name: &[String]) -> DnsRecord {
+        let hash = self.hash(name);
+        self.nsec3(zone, &step(&hash, false), step(&hash, true), vec![])
+    }
+
+    fn nsec3(&self, zone: &Zone, hash: &[u8], next_hashed: Vec<u8>, types: Vec<u16>) -> DnsRecord {
+        let Denial::Nsec3(param) = &self.denial else {
+            unreachable!("NSEC3 record of a zone denying with NSEC");
+        };
+        let mut owner = vec![base32hex_encode(hash).to_ascii_lowercase()];
+        owner.extend_from_slice(&self.origin);
+        DnsRecord {
+            name: owner,
+            typ: QType::NSEC3,
+            class: zone.class,
+            ttl: negative_ttl(zone),
+            rdata: RData::NSEC3(Nsec3 {
+                hash_algorithm: param.hash_algorithm,
+                flags: 0,
+                iterations: param.iterations,
+                salt: param.salt.clone(),
+                next_hashed,
+                types,
+            }),
+        }
+    }
+
+    fn hash(&self, name: &[String]) -> Vec<u8> {
+        match &self.denial {
+            Denial::Nsec3(param) => nsec3_hash(name, &param.salt, param.iterations),
+            Denial::Nsec => vec![],
+        }
+    }
+}
+
+impl ZoneKey {
+    /// A key of the zone `owner`, a key signing key when `flags` has the SEP bit.
+    pub fn new(owner: Vec<String>, flags: u16, private: PrivateKey) -> Self {
+        let dnskey = Dnskey {
+            flags: flags | ZONE_KEY_FLAG,
+            protocol: DNSKEY_PROTOCOL,
+            algorithm: private.algorithm(),
+            public_key: private.public_key(),
+        };
+        ZoneKey {
+            owner,
+            dnskey,
+            private,
+        }
+    }
+
+    /// Reads a key from the pair of files written by BIND's `dnssec-keygen`,
+    /// `<path>.key` holding the DNSKEY record and `<path>.private` the private
+    /// key. `path` may name either file.
+    pub fn from_files(path: &Path) -> anyhow::Result<Self> {
+        let base = match path.extension().and_then(|x| x.to_str()) {
+            Some("key" | "private") => path.with_extension(""),
+            _ => path.to_path_buf(),
+        };
+        let with_suffix = |suffix: &str| {
+            let mut file = OsString::from(base.as_os_str());
+            file.push(suffix);
+            PathBuf::from(file)
+        };
+
+        let key_file = with_suffix(".key");
+        let text = fs::read_to_string(&key_file)
+            .with_context(|| format!("reading {}", key_file.display()))?;
+        let (owner, dnskey) = parse_dnskey_record(&text)
+            .with_context(|| format!("parsing {}", key_file.display()))?;
+        let private_file = with_suffix(".private");
+        let private = fs::read_to_string(&private_file)
+            .with_context(|| format!("reading {}", private_file.display()))?
+            .parse::<PrivateKey>()
+            .with_context(|| format!("parsing {}", private_file.display()))?;
+
+        ensure!(
+            dnskey.algorithm == private.algorithm() && dnskey.public_key == private.public_key(),
+            "{} does not match {}",
+            private_file.display(),
+            key_file.display()
+        );
+        Ok(ZoneKey {
+            owner,
+            dnskey,
+            private,
+        })
+    }
+
+    /// A new key of the zone `owner` with a random private key, a key signing key
+    /// when `flags` has the SEP bit.
+    pub fn generate(owner: Vec<String>, flags: u16, algorithm: u8) -> anyhow::Result<Self> {
+        Ok(ZoneKey::new(owner, flags, PrivateKey::generate(algorithm)?))
+    }
+
+    /// The base name of the key files in BIND's naming: `K<owner>+<algorithm>+<key
+    /// tag>`.
+    pub fn file_name(&self) -> String {
+        format!(
+            "K{}+{:03}+{:05}",
+            name_to_string(&self.owner),
+            self.dnskey.algorithm,
+            self.dnskey.key_tag()
+        )
+    }
+
+    /// Writes the pair of files [`ZoneKey::from_files`] reads into `directory`,
+    /// returning their common path without suffix.
+    pub fn write_files(&self, directory: &Path) -> anyhow::Result<PathBuf> {
+        let base = directory.join(self.file_name());
+        let with_suffix = |suffix: &str| {
+            let mut file = OsString::from(base.as_os_str());
+            file.push(suffix);
+            PathBuf::from(file)
+        };
+        let role = match self.dnskey.is_secure_entry_point() {
+            true => "key-signing",
+            false => "zone-signing",
+        };
+        let record = DnsRecord {
+            name: self.owner.clone(),
+            typ: QType::DNSKEY,
+            class: QClass::IN,
+            ttl: DNSKEY_TTL,
+            rdata: RData::DNSKEY(self.dnskey.clone()),
+        };
+        let key_file = with_suffix(".key");
+        fs::write(
+            &key_file,
+            format!(
+                "; This is a {} key, keyid {}, for {}\n{}\n",
+                role,
+                self.dnskey.key_tag(),
+                name_to_string(&self.owner),
+                record
+            ),
+        )
+        .with_context(|| format!("writing {}", key_file.display()))?;
+        let private_file = with_suffix(".private");
+        fs::write(&private_file, self.private.to_private_file())
+            .with_context(|| format!("writing {}", private_file.display()))?;
+        Ok(base)
+    }
+
+    /// The RRSIG record of this key over `rrset`, valid between the Unix times
+    /// `inception` and `expiration`.
+    pub fn sign(&self, rrset: &[DnsRecord], inception: u32, expiration: u32) -> DnsRecord {
+        let first = &rrset[0];
+        let wildcard = first.name.first().is_some_and(|x| x == "*");
+        let mut rrsig = Rrsig {
+            type_covered: u16::from(first.typ),
+            algorithm: self.dnskey.algorithm,
+            labels: (first.name.len() - usize::from(wildcard)) as u8,
+            original_ttl: first.ttl,
+            expiration,
+            inception,
+            key_tag: self.dnskey.key_tag(),
+            signer: self.owner.clone(),
+            signature: vec![],
+        };
+        rrsig.signature = self.private.sign(&signed_data(&rrsig, rrset));
+        DnsRecord {
+            name: first.name.clone(),
+            typ: QType::RRSIG,
+            class: first.class,
+            ttl: first.ttl,
+            rdata: RData::RRSIG(rrsig),
+        }
+    }
+}
+
+impl PrivateKey {
+    /// A random key of a DNSSEC algorithm, RSA ones 2048 bits long.
+    pub fn generate(algorithm: u8) -> anyhow::Result<Self> {
+        let mut rng = rand::rngs::OsRng;
+        let key = match algorithm {
+            RSASHA256 => {
+                PrivateKey::RsaSha256(Box::new(rsa::RsaPrivateKey::new(&mut rng, RSA_KEY_BITS)?))
+            }
+            ECDSAP256SHA256 => {
+                PrivateKey::EcdsaP256Sha256(p256::ecdsa::SigningKey::random(&mut rng))
+            }
+            ECDSAP384SHA384 => {
+                PrivateKey::EcdsaP384Sha384(p384::ecdsa::SigningKey::random(&mut rng))
+            }
+            ED25519 => PrivateKey::Ed25519(ed25519_dalek::SigningKey::from_bytes(&rand::Rng::gen(
+                &mut rng,
+            ))),
+            _ => bail!("unsupported algorithm {}", algorithm),
+        };
+        Ok(key)
+    }
+
+    pub fn algorithm(&self) -> u8 {
+        match self {
+            PrivateKey::RsaSha256(_) => RSASHA256,
+            PrivateKey::EcdsaP256Sha256(_) => ECDSAP256SHA256,
+            PrivateKey::EcdsaP384Sha384(_) => ECDSAP384SHA384,
+            PrivateKey::Ed25519(_) => ED25519,
+        }
+    }
+
+    /// The public key in the format of DNSKEY records: RFC 3110 for RSA, the
+    /// uncompressed point without its prefix for ECDSA (RFC 6605), the raw key
+    /// for Ed25519 (RFC 8080).
+    pub fn public_key(&self) -> Vec<u8> {
+        match self {
+            PrivateKey::RsaSha256(key) => {
+                let exponent = key.e().to_bytes_be();
+                let mut public_key = match u8::try_from(exponent.len()) {
+                    Ok(len) => vec![len],
+                    Err(_) => [&[0], &(exponent.len() as u16).to_be_bytes()[..]].concat(),
+                };
+                public_key.extend(exponent);
+                public_key.extend(key.n().to_bytes_be());
+                public_key
+            }
+            PrivateKey::EcdsaP256Sha256(key) => {
+                key.verifying_key().to_encoded_point(false).as_bytes()[1..].to_vec()
+            }
+            PrivateKey::EcdsaP384Sha384(key) => {
+                key.verifying_key().to_encoded_point(false).as_bytes()[1..].to_vec()
+            }
+            PrivateKey::Ed25519(key) => key.verifying_key().to_bytes().to_vec(),
+        }
+    }
+
+    pub fn sign(&self, data: &[u8]) -> Vec<u8> {
+        use p256::ecdsa::signature::Signer;
+        match self {
+            PrivateKey::RsaSha256(key) => key
+                .sign(rsa::Pkcs1v15Sign::new::<Sha256>(), &Sha256::digest(data))
+                .expect("RSA key too small for a SHA-256 signature"),
+            PrivateKey::EcdsaP256Sha256(key) => {
+                let signature: p256::ecdsa::Signature = key.sign(data);
+                signature.to_bytes().to_vec()
+            }
+            PrivateKey::EcdsaP384Sha384(key) => {
+                let signature: p384::ecdsa::Signature = key.sign(data);
+                signature.to_bytes().to_vec()
+            }
+            PrivateKey::Ed25519(key) => key.sign(data).to_bytes().to_vec(),
+        }
+    }
+
+    /// The key in the private key file format of BIND, which [`PrivateKey::from_str`]
+    /// reads back.
+    pub fn to_private_file(&self) -> String {
+        let (mnemonic, fields) = match self {
+            PrivateKey::RsaSha256(key) => {
+                let primes = key.primes();
+                let number = |x: &rsa::BigUint| base64_encode(&x.to_bytes_be());
+                let mut fields = vec![
+                    ("Modulus", number(key.n())),
+                    ("PublicExponent", number(key.e())),
+                    ("PrivateExponent", number(key.d())),
+                ];
+                fields.extend(
+                    ["Prime1", "Prime2"]
+                        .into_iter()
+                        .zip(primes.iter().map(number)),
+                );
+                ("RSASHA256", fields)
+            }
+            PrivateKey::EcdsaP256Sha256(key) => (
+                "ECDSAP256SHA256",
+                vec![("PrivateKey", base64_encode(&key.to_bytes()))],
+            ),
+            PrivateKey::EcdsaP384Sha384(key) => (
+                "ECDSAP384SHA384",
+                vec![("PrivateKey", base64_encode(&key.to_bytes()))],
+            ),
+            PrivateKey::Ed25519(key) => (
+                "ED25519",
+                vec![("PrivateKey", base64_encode(&key.to_bytes()))],
+            ),
+        };
+        let mut text = format!(
+            "Private-key-format: v1.3\nAlgorithm: {} ({})\n",
+            self.algorithm(),
+            mnemonic
+        );
+        for (field, value) in fields {
+            text.push_str(&format!("{}: {}\n", field, value));
+        }
+        text
+    }
+}
+
+/// Keeps private keys out of logs.
+impl fmt::Debug for PrivateKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PrivateKey(algorithm {})", self.algorithm())
+    }
+}
+
+/// The private key file format of BIND (`Private-key-format: v1.3`): one
+/// `Field: value` per line, with base64 values.
+impl FromStr for PrivateKey {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields = s
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .map(|(field, value)| (field.trim(), value.trim()))
+            .collect::<HashMap<_, _>>();
+        let field = |name: &str| {
+            let value = fields
+                .get(name)
+                .ok_or_else(|| anyhow!("missing {}", name))?;
+            base64_decode(value).ok_or_else(|| anyhow!("invalid {}", name))
+        };
+        let algorithm = fields
+            .get("Algorithm")
+            .and_then(|x| x.split_whitespace().next())
+            .ok_or_else(|| anyhow!("missing Algorithm"))?
+            .parse::<u8>()
+            .context("invalid Algorithm")?;
+
+        let key = match algorithm {
+            RSASHA256 => {
+                let number = |name: &str| field(name).map(|x| rsa::BigUint::from_bytes_be(&x));
+                let key = rsa::RsaPrivateKey::from_components(
+                    number("Modulus")?,
+                    number("PublicExponent")?,
+                    number("PrivateExponent")?,
+                    vec![number("Prime1")?, number("Prime2")?],
+                )?;
+                PrivateKey::RsaSha256(Box::new(key))
+            }
+            ECDSAP256SHA256 => PrivateKey::EcdsaP256Sha256(p256::ecdsa::SigningKey::from_slice(
+                &field("PrivateKey")?,
+            )?),
+            ECDSAP384SHA384 => PrivateKey::EcdsaP384Sha384(p384::ecdsa::SigningKey::from_slice(
+                &field("PrivateKey")?,
+            )?),
+            ED25519 => {
+                let seed = <[u8; 32]>::try_from(field("PrivateKey")?)
+                    .map_err(|_| anyhow!("invalid PrivateKey length"))?;
+                PrivateKey::Ed25519(ed25519_dalek::SigningKey::from_bytes(&seed))
+            }
+            _ => bail!("unsupported algorithm {}", algorithm),
+        };
+        Ok(key)
+    }
+}
+
+/// Reads the DNSKEY record of a `.key` file: `<owner> [<ttl>] [IN] DNSKEY <flags>
+/// <protocol> <algorithm> <base64 key>`, after `;` comments.
+fn parse_dnskey_record(text: &str) -> anyhow::Result<(Vec<String>, Dnskey)> {
+    let fields = text
+        .lines()
+        .map(|line| line.split(';').next().unwrap_or_default())
+        .flat_map(str::split_whitespace)
+        .collect::<Vec<_>>();
+    let position = fields
+        .iter()
+        .position(|x| x.eq_ignore_ascii_case("DNSKEY"))
+        .ok_or_else(|| anyhow!("no DNSKEY record"))?;
+    let owner = fields
+        .first()
+        .filter(|_| position > 0)
+        .ok_or_else(|| anyhow!("missing owner"))?;
+    let [flags, protocol, algorithm] = fields
+        .get(position + 1..position + 4)
+        .and_then(|x| <[&str; 3]>::try_from(x).ok())
+        .ok_or_else(|| anyhow!("truncated DNSKEY record"))?;
+    let public_key = base64_decode(&fields[position + 4..].concat())
+        .ok_or_else(|| anyhow!("invalid public key"))?;
+
+    let owner = owner
+        .split('.')
+        .filter(|x| !x.is_empty())
+        .map(String::from)
+        .collect();
+    let dnskey = Dnskey {
+        flags: flags.parse().context("invalid flags")?,
+        protocol: protocol.parse().context("invalid protocol")?,
+        algorithm: algorithm.parse().context("invalid algorithm")?,
+        public_key,
+    };
+    Ok((owner, dnskey))
+}
+
+/// The name a negative response of `zone` must prove nonexistent, or that has
+/// no record of the type queried: the target of the CNAME chain answered.
+/// `None` for positive responses, errors and names outside the zone.
+pub fn denied_name(zone: &Zone, message: &DnsMessage) -> Option<Vec<String>> {
+    let question = message.questions.first()?;
+    let target = cname_target(message);
+    let answered = message.answers.iter().any(|r| {
+        names_equal(&r.name, &target)
+            && (r.typ == question.qtype || question.qtype == QType::WildCard)
+    });
+    let negative = matches!(
+        message.header.rcode,
+        ResponseCode::NoErrorCondition | ResponseCode::NameError
+    );
+    (!answered && negative && zone.contains(&target)).then_some(target)
+}
+
+/// The types of the records owned by `name`, in ascending order.
+fn types_at(zone: &Zone, name: &[String]) -> Vec<u16> {
+    let mut types = zone
+        .records
+        .iter()
+        .filter(|r| names_equal(&r.name, name))
+        .map(|r| u16::from(r.typ))
+        .collect::<Vec<_>>();
+    types.sort();
+    types.dedup();
+    types
+}
+
+/// The closest ancestor of a nonexistent `name` that exists in the zone.
+pub fn closest_encloser(zone: &Zone, name: &[String]) -> Vec<String> {
+    (zone.origin.len()..name.len())
+        .rev()
+        .map(|len| name[name.len() - len..].to_vec())
+        .find(|candidate| zone.node_exists(candidate))
+        .unwrap_or_else(|| zone.origin.clone())
+}
+
+/// The last name owning records of the zone before `name` in canonical order,
+/// at worst the apex.
+fn predecessor(zone: &Zone, name: &[String]) -> Vec<String> {
+    zone.records
+        .iter()
+        .map(|r| &r.name)
+        .filter(|x| canonical_cmp(x, name) == Ordering::Less)
+        .max_by(|a, b| canonical_cmp(a, b))
+        .cloned()
+        .unwrap_or_else(|| zone.origin.clone())
+}
+
+/// The name right after `name` in canonical order: its child labelled `\000`.
+fn successor(name: &[String]) -> Vec<String> {
+    let mut next = vec!["\u{0}".to_string()];
+    next.extend_from_slice(name);
+    next
+}
+
+/// The first name after `name` and its descendants in canonical order: `name`
+/// with a `\000` octet appended to its first label (RFC 4471 section 3.1.2).
+fn sibling_successor(name: &[String]) -> Vec<String> {
+    match name.split_first() {
+        Some((first, rest)) if first.len() < 63 => {
+            let mut next = vec![format!("{}\0", first)];
+            next.extend_from_slice(rest);
+            next
+        }
+        _ => successor(name),
+    }
+}
+
+/// The hash right after (`up`) or right before `hash`, read as a big-endian
+/// number, wrapping around.
+fn step(hash: &[u8], up: bool) -> Vec<u8> {
+    let mut hash = hash.to_vec();
+    for byte in hash.iter_mut().rev() {
+        let (value, carry) = match up {
+            true => byte.overflowing_add(1),
+            false => byte.overflowing_sub(1),
+        };
+        *byte = value;
+        if !carry {
+            break;
+        }
+    }
+    hash
+}
+
+/// The TTL of denial records, that of negative answers (RFC 9077).
+pub fn negative_ttl(zone: &Zone) -> u32 {
+    zone.records
+        .iter()
+        .find_map(|r| match &r.rdata {
+            RData::SOA(soa) if zone.is_apex(&r.name) => Some(r.ttl.min(soa.minimum)),
+            _ => None,
+        })
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        dns_header::header::ResponseCode,
+        dns_message::message::DnsMessage,
+        dns_record::record::{DnsRecord, Nsec3Param, Opt, RData},
+        dns_zone::zone::Zone,
+        dnssec::{
+            signature_impl::{DIGEST_SHA256, SECURE_ENTRY_POINT_FLAG},
+            signer::{Denial, PrivateKey, ZoneKey, ZoneSigner},
+            validator::{Security, TrustAnchor, Validator},
+        },
+        encoding::base64_encode,
+        resrec::{QClass, QType},
+    };
+    use std::{fs, net::Ipv4Addr};
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn name(text: &str) -> Vec<String> {
+        text.split('.')
+            .filter(|x| !x.is_empty())
+            .map(String::from)
+            .collect()
+    }
+
+    fn zone() -> Zone {
+        let mut zone = Zone::with_default_soa(name("example.com"));
+        for (owner, rdata) in [
+            ("www.example.com", RData::A(Ipv4Addr::new(192, 0, 2, 1))),
+            ("a.b.example.com", RData::A(Ipv4Addr::new(192, 0, 2, 2))),
+            ("web.example.com", RData::CNAME(name("www.example.com"))),
+            ("old.example.com", RData::CNAME(name("gone.example.com"))),
+        ] {
+            let typ = match rdata {
+                RData::A(_) => QType::A,
+                _ => QType::CNAME,
+            };
+            zone.records.push(DnsRecord {
+                name: name(owner),
+                typ,
+                class: QClass::IN,
+                ttl: 300,
+                rdata,
+            });
+        }
+        zone
+    }
+
+    fn signer(denial: Denial) -> ZoneSigner {
+        let ksk =
+            PrivateKey::EcdsaP256Sha256(p256::ecdsa::SigningKey::from_slice(&[1; 32]).unwrap());
+        let zsk = PrivateKey::Ed25519(ed25519_dalek::SigningKey::from_bytes(&[2; 32]));
+        ZoneSigner::new(
+            name("example.com"),
+            vec![
+                ZoneKey::new(name("example.com"), SECURE_ENTRY_POINT_FLAG, ksk),
+                ZoneKey::new(name("example.com"), 0, zsk),
+            ],
+            denial,
+        )
+    }
+
+    fn nsec3() -> Denial {
+        Denial::Nsec3(Nsec3Param {
+            hash_algorithm: 1,
+            flags: 0,
+            iterations: 0,
+            salt: vec![0xab],
+        })
+    }
+
+    /// A signed zone answering queries with the DO bit, as the server does.
+    struct SignedZone {
+        zone: Zone,
+        signer: ZoneSigner,
+    }
+
+    impl SignedZone {
+        fn new(denial: Denial) -> Self {
+            let mut zone = zone();
+            let signer = signer(denial);
+            signer.publish(&mut zone);
+            SignedZone { zone, signer }
+        }
+
+        fn query(&mut self, owner: &str, qtype: QType) -> DnsMessage {
+            let mut message = DnsMessage::query(name(owner), qtype);
+            message.set_edns(Opt::new(1232, true));
+            self.zone.answer(&mut message);
+            self.signer.sign_response(&self.zone, &mut message, NOW);
+            message
+        }
+
+        /// Validates the answer to a query from a trust anchor for the key
+        /// signing key of the zone.
+        fn validate(&mut self, owner: &str, qtype: QType) -> (ResponseCode, Security) {
+            let response = self.query(owner, qtype);
+            let ksk = &self.signer.keys[0];
+            let mut validator = Validator::new(vec![TrustAnchor {
+                owner: name("example.com"),
+                ds: ksk.dnskey.to_ds(&ksk.owner, DIGEST_SHA256).unwrap(),
+            }]);
+            let security = validator.validate(&response, NOW, &mut |owner, qtype| {
+                let mut message = DnsMessage::query(owner.to_vec(), qtype);
+                self.zone.answer(&mut message);
+                self.signer.sign_response(&self.zone, &mut message, NOW);
+                Ok(message)
+            });
+            (response.header.rcode, security)
+        }
+    }
+
+    #[test]
+    fn signed_answers_test() {
+        for denial in [Denial::Nsec, nsec3()] {
+            let mut zone = SignedZone::new(denial);
+            for (owner, qtype, rcode) in [
+                ("www.example.com", QType::A, ResponseCode::NoErrorCondition),
+                ("web.example.com", QType::A, ResponseCode::NoErrorCondition),
+                ("example.com", QType::DNSKEY, ResponseCode::NoErrorCondition),
+                ("www.example.com", QType::MX, ResponseCode::NoErrorCondition),
+                ("b.example.com", QType::A, ResponseCode::NoErrorCondition),
+                ("nope.example.com", QType::A, ResponseCode::NameError),
+                ("x.y.b.example.com", QType::A, ResponseCode::NameError),
+                ("old.example.com", QType::A, ResponseCode::NameError),
+            ] {
+                assert_eq!(
+                    zone.validate(owner, qtype),
+                    (rcode, Security::Secure),
+                    "{} {} with {:?}",
+                    owner,
+                    qtype,
+                    zone.signer.denial
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn key_roles_test() {
+        let mut zone = SignedZone::new(Denial::Nsec);
+        let signers = |message: &DnsMessage| {
+            message
+                .answers
+                .iter()
+                .filter_map(|r| match &r.rdata {
+                    RData::RRSIG(sig) => Some(sig.algorithm),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(signers(&zone.query("example.com", QType::DNSKEY)), vec![13]);
+        assert_eq!(signers(&zone.query("www.example.com", QType::A)), vec![15]);
+    }
+
+    #[test]
+    fn unsigned_without_do_test() {
+        let mut zone = SignedZone::new(Denial::Nsec);
+        let mut message = DnsMessage::query(name("www.example.com"), QType::A);
+        zone.zone.answer(&mut message);
+        assert!(message.answers.iter().all(|r| r.typ == QType::A));
+        assert_eq!(zone.query("www.example.com", QType::A).answers.len(), 2);
+    }
+
+    #[test]
+    fn signature_cache_test() {
+        let mut zone = SignedZone::new(Denial::Nsec);
+        let rrsig = |message: DnsMessage| message.answers[1].clone();
+        let first = rrsig(zone.query("www.example.com", QType::A));
+        assert_eq!(zone.signer.signatures.len(), 1);
+
+        let mut message = DnsMessage::query(name("www.example.com"), QType::A);
+        zone.zone.answer(&mut message);
+        zone.signer
+            .sign_response(&zone.zone, &mut message, NOW + 86400);
+        assert_eq!(message.answers[1], first);
+
+        // Close to expiry, or once the RRset changed, it is signed again.
+        let mut message = DnsMessage::query(name("www.example.com"), QType::A);
+        zone.zone.answer(&mut message);
+        zone.signer
+            .sign_response(&zone.zone, &mut message, NOW + 6 * 86400);
+        assert_ne!(message.answers[1], first);
+
+        zone.zone.records[1].ttl = 60;
+        assert_ne!(rrsig(zone.query("www.example.com", QType::A)), first);
+    }
+
+    #[test]
+    fn key_files_test() {
+        let private = ed25519_dalek::SigningKey::from_bytes(&[3; 32]);
+        let key = ZoneKey::new(
+            name("example.com"),
+            SECURE_ENTRY_POINT_FLAG,
+            PrivateKey::Ed25519(private.clone()),
+        );
+        let directory = std::env::temp_dir().join(format!("dnssec-keys-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let base = directory.join("Kexample.com.+015+12345");
+        fs::write(
+            directory.join("Kexample.com.+015+12345.key"),
+            format!(
+                "; This is a key-signing key\nexample.com. 3600 IN DNSKEY 257 3 15 {}\n",
+                base64_encode(&key.dnskey.public_key)
+            ),
+        )
+        .unwrap();
+        fs::write(
+            directory.join("Kexample.com.+015+12345.private"),
+            format!(
+                "Private-key-format: v1.3\nAlgorithm: 15 (ED25519)\nPrivateKey: {}\n",
+                base64_encode(&private.to_bytes())
+            ),
+        )
+        .unwrap();
+
+        let loaded = ZoneKey::from_files(&base).unwrap();
+        assert_eq!(loaded.owner, name("example.com"));
+        assert_eq!(loaded.dnskey, key.dnskey);
+        let loaded =
+            ZoneKey::from_files(&directory.join("Kexample.com.+015+12345.private")).unwrap();
+        assert_eq!(loaded.dnskey, key.dnskey);
+
+        fs::write(
+            directory.join("Kexample.com.+015+12345.private"),
+            format!(
+                "Private-key-format: v1.3\nAlgorithm: 15 (ED25519)\nPrivateKey: {}\n",
+                base64_encode(&[4; 32])
+            ),
+        )
+        .unwrap();
+        assert!(ZoneKey::from_files(&base).is_err());
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn generated_key_files_test() {
+        let directory =
+            std::env::temp_dir().join(format!("dnssec-new-keys-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        for algorithm in [13, 14, 15] {
+            let key =
+                ZoneKey::generate(name("example.com"), SECURE_ENTRY_POINT_FLAG, algorithm).unwrap();
+            let base = key.write_files(&directory).unwrap();
+            assert!(base.ends_with(format!(
+                "Kexample.com.+{:03}+{:05}",
+                algorithm,
+                key.dnskey.key_tag()
+            )));
+            let loaded = ZoneKey::from_files(&base).unwrap();
+            assert_eq!(loaded.dnskey, key.dnskey);
+            assert_eq!(loaded.private.public_key(), key.private.public_key());
+        }
+        assert!(ZoneKey::generate(name("example.com"), 0, 5).is_err());
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn private_key_formats_test() {
+        let p384 = format!(
+            "Private-key-format: v1.3\nAlgorithm: 14 (ECDSAP384SHA384)\nPrivateKey: {}\n",
+            base64_encode(&[5; 48])
+        );
+        let key = p384.parse::<PrivateKey>().unwrap();
+        assert_eq!(key.algorithm(), 14);
+        assert_eq!(key.public_key().len(), 96);
+
+        let rsa = rsa::RsaPrivateKey::new(&mut rand::thread_rng(), 1024).unwrap();
+        let field = |x: &rsa::BigUint| base64_encode(&x.to_bytes_be());
+        use rsa::traits::{PrivateKeyParts, PublicKeyParts};
+        let text = format!(
+            "Private-key-format: v1.3\nAlgorithm: 8 (RSASHA256)\nModulus: {}\nPublicExponent: {}\nPrivateExponent: {}\nPrime1: {}\nPrime2: {}\n",
+            field(rsa.n()),
+            field(rsa.e()),
+            field(rsa.d()),
+            field(&rsa.primes()[0]),
+            field(&rsa.primes()[1]),
+        );
+        let key = text.parse::<PrivateKey>().unwrap();
+        assert_eq!(key.algorithm(), 8);
+        assert_eq!(key.public_key()[..4], [3, 1, 0, 1]);
+
+        assert!("Algorithm: 5 (RSASHA1)\n".parse::<PrivateKey>().is_err());
+        assert!("Algorithm: 15 (ED25519)\n".parse::<PrivateKey>().is_err());
+    }
+}
@@ -0,0 +1,56 @@
+use crate::dns_record::record::{Dnskey, Ds};
+use std::collections::HashMap;
+use thiserror::Error;
+
+/// Validates responses with DNSSEC (RFC 4035 section 5), building chains of
+/// trust from the trust anchors down to the zone signing each RRset, with the
+/// DS and DNSKEY records it needs fetched through a lookup function.
+#[derive(Debug, Clone, Default)]
+pub struct Validator {
+    pub trust_anchors: Vec<TrustAnchor>,
+    /// Domains below which validation is disabled, e.g. while one of their zones
+    /// is known to be broken (RFC 7646).
+    pub negative_trust_anchors: Vec<Vec<String>>,
+    /// The validated DNSKEY RRsets of zones, by lowercase apex, with the Unix time
+    /// they expire. `None` for zones proven unsigned.
+    pub keys: HashMap<Vec<String>, (Option<Vec<Dnskey>>, u64)>,
+}
+
+/// A DS record trusted without validation, usually for the root zone.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrustAnchor {
+    pub owner: Vec<String>,
+    pub ds: Ds,
+}
+
+/// The outcome of validating a response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Security {
+    /// Every RRset and denial of existence was verified up to a trust anchor.
+    Secure,
+    /// Part of the response comes from a zone proven unsigned, or below a
+    /// negative trust anchor.
+    Insecure,
+    /// The response should be signed but fails validation.
+    Bogus(ValidationError),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ValidationError {
+    #[error("no valid signature over {0}")]
+    MissingSignature(String),
+    #[error("signature outside its validity period")]
+    SignatureExpired,
+    #[error("signature does not verify")]
+    BadSignature,
+    #[error("no DNSKEY of {0} matches a DS or trust anchor")]
+    NoTrustedKey(String),
+    #[error("missing proof of nonexistence for {0}")]
+    MissingDenial(String),
+    #[error("wildcard expansion of {0} not proven")]
+    UnprovenWildcard(String),
+    #[error("chain of trust too long")]
+    ChainTooLong,
+    #[error("lookup failed: {0}")]
+    Lookup(String),
+}
@@ -0,0 +1,847 @@
+use super::{
+    denial_impl::{
+        proves_insecure_delegation, proves_nodata, proves_nxdomain, proves_wildcard_expansion,
+    },
+    signature_impl::{
+        is_supported_algorithm, lowercase, rrsets, verify_rrsig, DIGEST_SHA1, DIGEST_SHA256,
+        DIGEST_SHA384,
+    },
+    validator::{Security, TrustAnchor, ValidationError, Validator},
+};
+use crate::{
+    dns_header::header::ResponseCode,
+    dns_message::message::DnsMessage,
+    dns_name::name::{is_subdomain, name_to_string, names_equal},
+    dns_record::record::{DnsRecord, Dnskey, Ds, RData, Rrsig},
+    encoding::hex_decode,
+    resrec::{type_name, QType},
+};
+use anyhow::{anyhow, Context};
+use std::str::FromStr;
+
+/// Fetches the records of a name and type, e.g. by querying an upstream server
+/// with the DO bit set.
+pub type Lookup<'a> = dyn FnMut(&[String], QType) -> anyhow::Result<DnsMessage> + 'a;
+
+/// Upper bound on nested validations of DS and DNSKEY lookups for one response,
+/// deeper than any legitimate chain of trust.
+const MAX_CHAIN_DEPTH: usize = 32;
+
+/// How long a zone proven unsigned is remembered.
+const INSECURE_CACHE_SECONDS: u64 = 300;
+
+/// The key signing keys of the root zone, KSK-2017 and KSK-2024, as published in
+/// the IANA root anchors.
+const ROOT_TRUST_ANCHORS: [&str; 2] = [
+    ". 20326 8 2 E06D44B80B8F1D39A95C0B0D7C65D08458E880409BBC683457104237C7F8EC8D",
+    ". 38696 8 2 683D2D0ACB8C9B712A1948B27F741219298D0A450D612C483AF444A4C0FB2B16",
+];
+
+impl Validator {
+    pub fn new(trust_anchors: Vec<TrustAnchor>) -> Self {
+        Validator {
+            trust_anchors,
+            ..Default::default()
+        }
+    }
+
+    /// A validator trusting the keys of the root zone.
+    pub fn with_root_anchors() -> Self {
+        let anchors = ROOT_TRUST_ANCHORS
+            .iter()
+            .map(|x| x.parse().expect("Invalid built-in trust anchor"))
+            .collect();
+        Validator::new(anchors)
+    }
+
+    /// Validates the RRsets in the Answer section of `response` and the SOA, NSEC
+    /// and NSEC3 records of its Authority section, and checks negative answers and
+    /// wildcard expansions are proven. Unsigned NS and glue records, which DNSSEC
+    /// does not cover, are left alone.
+    pub fn validate(&mut self, response: &DnsMessage, now: u64, lookup: &mut Lookup) -> Security {
+        match self.check(response, now, lookup, 0) {
+            Ok(true) => Security::Secure,
+            Ok(false) => Security::Insecure,
+            Err(e) => Security::Bogus(e),
+        }
+    }
+
+    /// Whether `name` lies below a negative trust anchor.
+    pub fn is_negatively_anchored(&self, name: &[String]) -> bool {
+        self.negative_trust_anchors
+            .iter()
+            .any(|x| is_subdomain(name, x))
+    }
+
+    /// Whether `response` is secure (`true`) or insecure (`false`).
+    fn check(
+        &mut self,
+        response: &DnsMessage,
+        now: u64,
+        lookup: &mut Lookup,
+        depth: usize,
+    ) -> Result<bool, ValidationError> {
+        if depth > MAX_CHAIN_DEPTH {
+            return Err(ValidationError::ChainTooLong);
+        }
+        let Some(question) = response.questions.first() else {
+            return Ok(false);
+        };
+        let rcode = response.header.rcode;
+        if self.is_negatively_anchored(&question.qname)
+            || !matches!(
+                rcode,
+                ResponseCode::NoErrorCondition | ResponseCode::NameError
+            )
+        {
+            return Ok(false);
+        }
+
+        let mut secure = true;
+        let mut wildcards = Vec::new();
+        for (rrset, sigs) in signed_rrsets(&response.answers) {
+            match self.check_rrset(&rrset, &sigs, now, lookup, depth)? {
+                Some(sig) => wildcards.push((rrset[0].name.clone(), usize::from(sig.labels))),
+                None => secure = false,
+            }
+        }
+
+        let mut denial = Vec::new();
+        let authorities = signed_rrsets(&response.authorities)
+            .into_iter()
+            .filter(|(rrset, _)| matches!(rrset[0].typ, QType::SOA | QType::NSEC | QType::NSEC3));
+        for (rrset, sigs) in authorities {
+            match self.check_rrset(&rrset, &sigs, now, lookup, depth)? {
+                Some(_) if rrset[0].typ != QType::SOA => {
+                    denial.extend(rrset);
+                    denial.extend(sigs);
+                }
+                Some(_) => {}
+                None => secure = false,
+            }
+        }
+
+        for (owner, labels) in wildcards {
+            if !proves_wildcard_expansion(&owner, labels, &denial) {
+                return Err(ValidationError::UnprovenWildcard(name_to_string(&owner)));
+            }
+        }
+
+        let target = cname_target(response);
+        let answered = response.answers.iter().any(|r| {
+            names_equal(&r.name, &target)
+                && (r.typ == question.qtype || question.qtype == QType::WildCard)
+        });
+        if !answered && secure {
+            let proven = match rcode {
+                ResponseCode::NameError => proves_nxdomain(&target, &denial),
+                _ => proves_nodata(&target, question.qtype, &denial),
+            };
+            if !proven {
+                // Unsigned zones have no denial of existence to offer.
+                if !self.is_insecure(&target, now, lookup, depth)? {
+                    return Err(ValidationError::MissingDenial(name_to_string(&target)));
+                }
+                secure = false;
+            }
+        }
+        Ok(secure)
+    }
+
+    /// Verifies an RRset with one of its signatures, returning it. `None` when the
+    /// RRset comes from a zone proven unsigned.
+    fn check_rrset(
+        &mut self,
+        rrset: &[DnsRecord],
+        sigs: &[DnsRecord],
+        now: u64,
+        lookup: &mut Lookup,
+        depth: usize,
+    ) -> Result<Option<Rrsig>, ValidationError> {
+        let owner = &rrset[0].name;
+        let typ = rrset[0].typ;
+        let mut error = ValidationError::MissingSignature(format!(
+            "{} {}",
+            name_to_string(owner),
+            type_name(u16::from(typ))
+        ));
+        if sigs.is_empty() {
+            return match self.is_insecure(owner, now, lookup, depth)? {
+                true => Ok(None),
+                false => Err(error),
+            };
+        }
+
+        for sig in sigs.iter().filter_map(|r| match &r.rdata {
+            RData::RRSIG(sig) => Some(sig),
+            _ => None,
+        }) {
+            // DS records are signed by the parent, everything else by the zone
+            // the owner belongs to.
+            let signer_allowed = is_subdomain(owner, &sig.signer)
+                && !(typ == QType::DS && names_equal(owner, &sig.signer))
+                && usize::from(sig.labels) <= owner.len();
+            if !signer_allowed {
+                continue;
+            }
+            let keys = match self.zone_keys(&sig.signer, now, lookup, depth + 1) {
+                Ok(Some(keys)) => keys,
+                Ok(None) => return Ok(None),
+                Err(e) => {
+                    error = e;
+                    continue;
+                }
+            };
+            let candidates = keys.iter().filter(|k| {
+                k.is_zone_key() && k.algorithm == sig.algorithm && k.key_tag() == sig.key_tag
+            });
+            for key in candidates {
+                match verify_rrsig(sig, rrset, key, now) {
+                    Ok(()) => return Ok(Some(sig.clone())),
+                    Err(e) => error = e,
+                }
+            }
+        }
+        Err(error)
+    }
+
+    /// The validated DNSKEY RRset of `zone`, authenticated by a trust anchor or by
+    /// the DS RRset its parent serves, or `None` when the zone is proven unsigned.
+    fn zone_keys(
+        &mut self,
+        zone: &[String],
+        now: u64,
+        lookup: &mut Lookup,
+        depth: usize,
+    ) -> Result<Option<Vec<Dnskey>>, ValidationError> {
+        if depth > MAX_CHAIN_DEPTH {
+            return Err(ValidationError::ChainTooLong);
+        }
+        let cache_key = lowercase(zone);
+        if let Some((keys, expires)) = self.keys.get(&cache_key) {
+            if *expires > now {
+                return Ok(keys.clone());
+            }
+        }
+        if self.is_negatively_anchored(zone) {
+            return Ok(None);
+        }
+
+        let anchors = self
+            .trust_anchors
+            .iter()
+            .filter(|x| names_equal(&x.owner, zone))
+            .map(|x| x.ds.clone())
+            .collect::<Vec<_>>();
+        let ds = if !anchors.is_empty() {
+            anchors
+        } else if zone.is_empty() {
+            return Err(ValidationError::NoTrustedKey(".".to_string()));
+        } else {
+            let response = fetch(lookup, zone, QType::DS)?;
+            if !self.check(&response, now, lookup, depth)? {
+                return Ok(self.cache_insecure(cache_key, now));
+            }
+            let ds = response
+                .answers
+                .iter()
+                .filter(|r| names_equal(&r.name, zone))
+                .filter_map(|r| match &r.rdata {
+                    RData::DS(ds) => Some(ds.clone()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            if ds.is_empty() {
+                return match proves_insecure_delegation(zone, &response.authorities) {
+                    true => Ok(self.cache_insecure(cache_key, now)),
+                    false => Err(ValidationError::NoTrustedKey(name_to_string(zone))),
+                };
+            }
+            ds
+        };
+
+        // A zone signed only with algorithms we cannot verify is treated as
+        // unsigned (RFC 4035 section 5.2).
+        let ds = ds
+            .into_iter()
+            .filter(|x| is_supported_algorithm(x.algorithm))
+            .filter(|x| matches!(x.digest_type, DIGEST_SHA1 | DIGEST_SHA256 | DIGEST_SHA384))
+            .collect::<Vec<_>>();
+        if ds.is_empty() {
+            return Ok(self.cache_insecure(cache_key, now));
+        }
+
+        let response = fetch(lookup, zone, QType::DNSKEY)?;
+        let (rrset, sigs) = signed_rrsets(&response.answers)
+            .into_iter()
+            .find(|(rrset, _)| rrset[0].typ == QType::DNSKEY && names_equal(&rrset[0].name, zone))
+            .ok_or_else(|| ValidationError::NoTrustedKey(name_to_string(zone)))?;
+        let keys = rrset
+            .iter()
+            .filter_map(|r| match &r.rdata {
+                RData::DNSKEY(key) => Some(key.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        let trusted = keys
+            .iter()
+            .filter(|k| k.is_zone_key() && ds.iter().any(|x| k.matches_ds(zone, x)));
+        for key in trusted {
+            let verified = sigs.iter().any(|r| match &r.rdata {
+                RData::RRSIG(sig) => {
+                    sig.key_tag == key.key_tag()
+                        && names_equal(&sig.signer, zone)
+                        && verify_rrsig(sig, &rrset, key, now).is_ok()
+                }
+                _ => false,
+            });
+            if verified {
+                let ttl = rrset.iter().map(|r| u64::from(r.ttl)).min().unwrap_or(0);
+                self.keys.insert(cache_key, (Some(keys.clone()), now + ttl));
+                return Ok(Some(keys));
+            }
+        }
+        Err(ValidationError::NoTrustedKey(name_to_string(zone)))
+    }
+
+    /// Whether an unsigned record owned by `name` is legitimately so: walking down
+    /// from the root, some ancestor is a delegation proven to have no DS, or a
+    /// zone signed with algorithms we cannot verify.
+    fn is_insecure(
+        &mut self,
+        name: &[String],
+        now: u64,
+        lookup: &mut Lookup,
+        depth: usize,
+    ) -> Result<bool, ValidationError> {
+        if self.is_negatively_anchored(name) {
+            return Ok(true);
+        }
+        for len in 1..=name.len() {
+            let candidate = &name[name.len() - len..];
+            if let Some((keys, expires)) = self.keys.get(&lowercase(candidate)) {
+                if *expires > now {
+                    match keys {
+                        Some(_) => continue,
+                        None => return Ok(true),
+                    }
+                }
+            }
+
+            let response = fetch(lookup, candidate, QType::DS)?;
+            if !self.check(&response, now, lookup, depth + 1)? {
+                return Ok(true);
+            }
+            let has_ds = response
+                .answers
+                .iter()
+                .any(|r| r.typ == QType::DS && names_equal(&r.name, candidate));
+            if has_ds {
+                // A secure delegation, unless signed with unknown algorithms.
+                if self.zone_keys(candidate, now, lookup, depth + 1)?.is_none() {
+                    return Ok(true);
+                }
+            } else if proves_insecure_delegation(candidate, &response.authorities) {
+                self.cache_insecure(lowercase(candidate), now);
+                return Ok(true);
+            } else if response.header.rcode == ResponseCode::NameError {
+                return Ok(false);
+            }
+        }
+        Ok(false)
+    }
+
+    fn cache_insecure(&mut self, zone: Vec<String>, now: u64) -> Option<Vec<Dnskey>> {
+        self.keys.insert(zone, (None, now + INSECURE_CACHE_SECONDS));
+        None
+    }
+}
+
+/// `<owner> <key tag> <algorithm> <digest type> <digest>`, as in the RDATA of a DS
+/// record, optionally preceded by `IN DS` as in a zone file.
+impl FromStr for TrustAnchor {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fields = s
+            .split_whitespace()
+            .filter(|x| !x.eq_ignore_ascii_case("IN") && !x.eq_ignore_ascii_case("DS"));
+        let owner = fields.next().ok_or_else(|| anyhow!("missing owner"))?;
+        let mut number = |field: &str| {
+            fields
+                .next()
+                .ok_or_else(|| anyhow!("missing {}", field))
+                .and_then(|x| {
+                    x.parse::<u16>()
+                        .with_context(|| format!("invalid {}", field))
+                })
+        };
+        let key_tag = number("key tag")?;
+        let algorithm = u8::try_from(number("algorithm")?)?;
+        let digest_type = u8::try_from(number("digest type")?)?;
+        let digest =
+            hex_decode(&fields.collect::<String>()).ok_or_else(|| anyhow!("invalid digest"))?;
+
+        Ok(TrustAnchor {
+            owner: owner
+                .split('.')
+                .filter(|x| !x.is_empty())
+                .map(String::from)
+                .collect(),
+            ds: Ds {
+                key_tag,
+                algorithm,
+                digest_type,
+                digest,
+            },
+        })
+    }
+}
+
+fn fetch(lookup: &mut Lookup, name: &[String], typ: QType) -> Result<DnsMessage, ValidationError> {
+    lookup(name, typ).map_err(|e| ValidationError::Lookup(format!("{:#}", e)))
+}
+
+/// Groups records into RRsets, each with the RRSIG records covering it.
+fn signed_rrsets(records: &[DnsRecord]) -> Vec<(Vec<DnsRecord>, Vec<DnsRecord>)> {
+    rrsets(records)
+        .into_iter()
+        .map(|rrset| {
+            let sigs = records
+                .iter()
+                .filter(|r| names_equal(&r.name, &rrset[0].name))
+                .filter(|r| matches!(&r.rdata, RData::RRSIG(x) if x.type_covered == u16::from(rrset[0].typ)))
+                .cloned()
+                .collect();
+            (rrset, sigs)
+        })
+        .collect()
+}
+
+/// The name the answer is about once the CNAME chain in the Answer section is
+/// followed from the question.
+pub fn cname_target(response: &DnsMessage) -> Vec<String> {
+    let question = &response.questions[0];
+    let mut target = question.qname.clone();
+    if question.qtype == QType::CNAME {
+        return target;
+    }
+    for _ in 0..response.answers.len() {
+        let next = response.answers.iter().find_map(|r| match &r.rdata {
+            RData::CNAME(next) if names_equal(&r.name, &target) => Some(next.clone()),
+            _ => None,
+        });
+        match next {
+            Some(next) => target = next,
+            None => break,
+        }
+    }
+    target
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        dns_header::header::ResponseCode,
+        dns_message::message::DnsMessage,
+        dns_name::name::{is_subdomain, names_equal},
+        dns_record::record::{DnsRecord, Dnskey, Nsec, RData, Rrsig},
+        dnssec::{
+            signature_impl::{signed_data, DIGEST_SHA256, ECDSAP256SHA256},
+            validator::{Security, TrustAnchor, ValidationError, Validator},
+        },
+        resrec::{QClass, QType},
+    };
+    use p256::ecdsa::{signature::Signer, Signature, SigningKey};
+    use std::net::Ipv4Addr;
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn name(text: &str) -> Vec<String> {
+        text.split('.')
+            .filter(|x| !x.is_empty())
+            .map(String::from)
+            .collect()
+    }
+
+    fn record(owner: &str, rdata: RData) -> DnsRecord {
+        let typ = match &rdata {
+            RData::A(_) => QType::A,
+            RData::DS(_) => QType::DS,
+            RData::DNSKEY(_) => QType::DNSKEY,
+            RData::NSEC(_) => QType::NSEC,
+            _ => unreachable!(),
+        };
+        DnsRecord {
+            name: name(owner),
+            typ,
+            class: QClass::IN,
+            ttl: 3600,
+            rdata,
+        }
+    }
+
+    fn a(owner: &str) -> DnsRecord {
+        record(owner, RData::A(Ipv4Addr::new(192, 0, 2, 1)))
+    }
+
+    fn nsec(owner: &str, next: &str, types: &[QType]) -> DnsRecord {
+        let types = types.iter().map(|x| u16::from(*x)).collect();
+        record(
+            owner,
+            RData::NSEC(Nsec {
+                next: name(next),
+                types,
+            }),
+        )
+    }
+
+    /// A zone signed with a single P-256 key.
+    struct TestZone {
+        origin: Vec<String>,
+        key: SigningKey,
+        dnskey: Dnskey,
+        records: Vec<DnsRecord>,
+    }
+
+    impl TestZone {
+        fn new(origin: &str, seed: u8) -> Self {
+            let key = SigningKey::from_slice(&[seed; 32]).unwrap();
+            let point = key.verifying_key().to_encoded_point(false);
+            let dnskey = Dnskey {
+                flags: 257,
+                protocol: 3,
+                algorithm: ECDSAP256SHA256,
+                public_key: point.as_bytes()[1..].to_vec(),
+            };
+            let records = vec![record(origin, RData::DNSKEY(dnskey.clone()))];
+            TestZone {
+                origin: name(origin),
+                key,
+                dnskey,
+                records,
+            }
+        }
+
+        fn ds_of(&self, child: &TestZone) -> DnsRecord {
+            let ds = child.dnskey.to_ds(&child.origin, DIGEST_SHA256).unwrap();
+            DnsRecord {
+                name: child.origin.clone(),
+                ..record("", RData::DS(ds))
+            }
+        }
+
+        fn sign(&self, rrset: &[DnsRecord]) -> DnsRecord {
+            let owner = &rrset[0].name;
+            let labels = owner.iter().filter(|x| *x != "*").count();
+            let mut rrsig = Rrsig {
+                type_covered: u16::from(rrset[0].typ),
+                algorithm: ECDSAP256SHA256,
+                labels: labels as u8,
+                original_ttl: 3600,
+                expiration: (NOW + 86400) as u32,
+                inception: (NOW - 86400) as u32,
+                key_tag: self.dnskey.key_tag(),
+                signer: self.origin.clone(),
+                signature: vec![],
+            };
+            let signature: Signature = self.key.sign(&signed_data(&rrsig, rrset));
+            rrsig.signature = signature.to_bytes().to_vec();
+            DnsRecord {
+                name: owner.clone(),
+                typ: QType::RRSIG,
+                class: QClass::IN,
+                ttl: 3600,
+                rdata: RData::RRSIG(rrsig),
+            }
+        }
+
+        /// The records of the zone with a signature over each RRset.
+        fn signed(&self) -> Vec<DnsRecord> {
+            let mut records = self.records.clone();
+            for (i, record) in self.records.iter().enumerate() {
+                let first = self.records[..i]
+                    .iter()
+                    .all(|r| !(r.typ == record.typ && names_equal(&r.name, &record.name)));
+                if first {
+                    let rrset = self
+                        .records
+                        .iter()
+                        .filter(|r| r.typ == record.typ && names_equal(&r.name, &record.name))
+                        .cloned()
+                        .collect::<Vec<_>>();
+                    records.push(self.sign(&rrset));
+                }
+            }
+            records
+        }
+    }
+
+    /// The signed zones of the root, `com` and `example.com`, with `insecure.com`
+    /// delegated without DS.
+    struct Hierarchy {
+        root: TestZone,
+        zones: Vec<(Vec<String>, Vec<DnsRecord>)>,
+    }
+
+    fn hierarchy() -> Hierarchy {
+        let mut root = TestZone::new(".", 1);
+        let mut com = TestZone::new("com", 2);
+        let mut example = TestZone::new("example.com", 3);
+
+        let nsec_types = [QType::SOA, QType::RRSIG, QType::NSEC, QType::DNSKEY];
+        root.records.push(root.ds_of(&com));
+        root.records.push(nsec(".", "com", &nsec_types));
+        root.records.push(nsec(
+            "com",
+            ".",
+            &[QType::NS, QType::DS, QType::RRSIG, QType::NSEC],
+        ));
+
+        com.records.push(com.ds_of(&example));
+        com.records.push(nsec("com", "example.com", &nsec_types));
+        com.records.push(nsec(
+            "example.com",
+            "insecure.com",
+            &[QType::NS, QType::DS, QType::RRSIG, QType::NSEC],
+        ));
+        com.records.push(nsec(
+            "insecure.com",
+            "com",
+            &[QType::NS, QType::RRSIG, QType::NSEC],
+        ));
+
+        example.records.extend([
+            a("www.example.com"),
+            a("*.wild.example.com"),
+            nsec("example.com", "*.wild.example.com", &nsec_types),
+            nsec(
+                "*.wild.example.com",
+                "www.example.com",
+                &[QType::A, QType::RRSIG, QType::NSEC],
+            ),
+            nsec(
+                "www.example.com",
+                "example.com",
+                &[QType::A, QType::RRSIG, QType::NSEC],
+            ),
+        ]);
+
+        let zones = [&root, &com, &example]
+            .iter()
+            .map(|zone| (zone.origin.clone(), zone.signed()))
+            .collect();
+        Hierarchy { root, zones }
+    }
+
+    impl Hierarchy {
+        fn validator(&self) -> Validator {
+            let anchor = TrustAnchor {
+                owner: vec![],
+                ds: self.root.dnskey.to_ds(&[], DIGEST_SHA256).unwrap(),
+            };
+            Validator::new(vec![anchor])
+        }
+
+        /// Answers like the authoritative server of the zone `owner` belongs to,
+        /// DS queries being answered by the parent.
+        fn respond(&self, owner: &[String], qtype: QType) -> DnsMessage {
+            let (_, records) = self
+                .zones
+                .iter()
+                .filter(|(origin, _)| is_subdomain(owner, origin))
+                .filter(|(origin, _)| !(qtype == QType::DS && names_equal(origin, owner)))
+                .max_by_key(|(origin, _)| origin.len())
+                .unwrap();
+            let covers = |r: &DnsRecord, typ: QType| match &r.rdata {
+                RData::RRSIG(sig) => sig.type_covered == u16::from(typ),
+                _ => r.typ == typ,
+            };
+
+            let mut response = DnsMessage::query(owner.to_vec(), qtype);
+            response.answers = records
+                .iter()
+                .filter(|r| names_equal(&r.name, owner) && covers(r, qtype))
+                .cloned()
+                .collect();
+            if response.answers.is_empty() {
+                response.authorities = records
+                    .iter()
+                    .filter(|r| covers(r, QType::NSEC))
+                    .cloned()
+                    .collect();
+                if !records.iter().any(|r| is_subdomain(&r.name, owner)) {
+                    response.header.rcode = ResponseCode::NameError;
+                }
+            }
+            response.to_response();
+            response
+        }
+
+        fn validate(&self, validator: &mut Validator, response: &DnsMessage) -> Security {
+            validator.validate(response, NOW, &mut |owner, qtype| {
+                Ok(self.respond(owner, qtype))
+            })
+        }
+    }
+
+    #[test]
+    fn secure_answer_test() {
+        let hierarchy = hierarchy();
+        let mut validator = hierarchy.validator();
+        let response = hierarchy.respond(&name("www.example.com"), QType::A);
+        assert_eq!(response.answers.len(), 2);
+        assert_eq!(
+            hierarchy.validate(&mut validator, &response),
+            Security::Secure
+        );
+
+        // The keys of the chain are cached.
+        let mut lookups = 0;
+        let security = validator.validate(&response, NOW, &mut |owner, qtype| {
+            lookups += 1;
+            Ok(hierarchy.respond(owner, qtype))
+        });
+        assert_eq!(security, Security::Secure);
+        assert_eq!(lookups, 0);
+    }
+
+    #[test]
+    fn tampered_answer_test() {
+        let hierarchy = hierarchy();
+        let mut response = hierarchy.respond(&name("www.example.com"), QType::A);
+        response.answers[0].rdata = RData::A(Ipv4Addr::new(203, 0, 113, 1));
+        assert_eq!(
+            hierarchy.validate(&mut hierarchy.validator(), &response),
+            Security::Bogus(ValidationError::BadSignature)
+        );
+    }
+
+    #[test]
+    fn stripped_signature_test() {
+        let hierarchy = hierarchy();
+        let mut response = hierarchy.respond(&name("www.example.com"), QType::A);
+        response.answers.retain(|r| r.typ != QType::RRSIG);
+        assert!(matches!(
+            hierarchy.validate(&mut hierarchy.validator(), &response),
+            Security::Bogus(ValidationError::MissingSignature(_))
+        ));
+    }
+
+    #[test]
+    fn untrusted_key_test() {
+        let hierarchy = hierarchy();
+        let other = TestZone::new(".", 9);
+        let mut validator = Validator::new(vec![TrustAnchor {
+            owner: vec![],
+            ds: other.dnskey.to_ds(&[], DIGEST_SHA256).unwrap(),
+        }]);
+        let response = hierarchy.respond(&name("www.example.com"), QType::A);
+        assert!(matches!(
+            hierarchy.validate(&mut validator, &response),
+            Security::Bogus(_)
+        ));
+    }
+
+    #[test]
+    fn nxdomain_test() {
+        let hierarchy = hierarchy();
+        let mut response = hierarchy.respond(&name("nope.example.com"), QType::A);
+        assert_eq!(response.header.rcode, ResponseCode::NameError);
+        assert_eq!(
+            hierarchy.validate(&mut hierarchy.validator(), &response),
+            Security::Secure
+        );
+
+        response.authorities.clear();
+        assert!(matches!(
+            hierarchy.validate(&mut hierarchy.validator(), &response),
+            Security::Bogus(ValidationError::MissingDenial(_))
+        ));
+    }
+
+    #[test]
+    fn nodata_test() {
+        let hierarchy = hierarchy();
+        for owner in ["www.example.com", "wild.example.com"] {
+            let response = hierarchy.respond(&name(owner), QType::MX);
+            assert_eq!(response.header.rcode, ResponseCode::NoErrorCondition);
+            assert_eq!(
+                hierarchy.validate(&mut hierarchy.validator(), &response),
+                Security::Secure
+            );
+        }
+    }
+
+    #[test]
+    fn wildcard_test() {
+        let hierarchy = hierarchy();
+        let wildcard = hierarchy.respond(&name("*.wild.example.com"), QType::A);
+        let mut response = DnsMessage::query(name("host.wild.example.com"), QType::A);
+        response.answers = wildcard.answers;
+        response
+            .answers
+            .iter_mut()
+            .for_each(|r| r.name = name("host.wild.example.com"));
+        response.to_response();
+        assert!(matches!(
+            hierarchy.validate(&mut hierarchy.validator(), &response),
+            Security::Bogus(ValidationError::UnprovenWildcard(_))
+        ));
+
+        let denial = hierarchy.respond(&name("*.wild.example.com"), QType::MX);
+        response.authorities = denial
+            .authorities
+            .into_iter()
+            .filter(|r| names_equal(&r.name, &name("*.wild.example.com")))
+            .collect();
+        response.to_response();
+        assert_eq!(
+            hierarchy.validate(&mut hierarchy.validator(), &response),
+            Security::Secure
+        );
+    }
+
+    #[test]
+    fn insecure_delegation_test() {
+        let hierarchy = hierarchy();
+        let mut response = DnsMessage::query(name("host.insecure.com"), QType::A);
+        response.answers.push(a("host.insecure.com"));
+        response.to_response();
+        assert_eq!(
+            hierarchy.validate(&mut hierarchy.validator(), &response),
+            Security::Insecure
+        );
+    }
+
+    #[test]
+    fn negative_trust_anchor_test() {
+        let hierarchy = hierarchy();
+        let mut response = hierarchy.respond(&name("www.example.com"), QType::A);
+        response.answers[0].rdata = RData::A(Ipv4Addr::new(203, 0, 113, 1));
+        let mut validator = hierarchy.validator();
+        validator.negative_trust_anchors.push(name("example.com"));
+        assert_eq!(
+            hierarchy.validate(&mut validator, &response),
+            Security::Insecure
+        );
+    }
+
+    #[test]
+    fn trust_anchor_parse_test() {
+        let anchor = "example.com. IN DS 60485 5 1 2BB183AF5F22588179A5 3B0A98631FAD1A292118"
+            .parse::<TrustAnchor>()
+            .unwrap();
+        assert_eq!(anchor.owner, name("example.com"));
+        assert_eq!(anchor.ds.key_tag, 60485);
+        assert_eq!(anchor.ds.algorithm, 5);
+        assert_eq!(anchor.ds.digest_type, 1);
+        assert_eq!(anchor.ds.digest.len(), 20);
+        assert!("example.com. 60485 5".parse::<TrustAnchor>().is_err());
+
+        let root = Validator::with_root_anchors();
+        assert_eq!(root.trust_anchors.len(), 2);
+        assert!(root.trust_anchors.iter().all(|x| x.owner.is_empty()));
+    }
+}
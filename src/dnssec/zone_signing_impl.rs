@@ -0,0 +1,702 @@
+use super::{
+    denial_impl::{canonical_cmp, nsec3_covers, nsec3_hash, nsec3_matches, nsec_covers},
+    signature_impl::{canonical_rdata, rrsets},
+    signer::{Denial, SignJob, ZoneKey, ZoneSigner, INCEPTION_OFFSET},
+    signer_impl::{closest_encloser, denied_name, negative_ttl},
+};
+use crate::{
+    dns_header::header::ResponseCode,
+    dns_message::message::DnsMessage,
+    dns_name::name::{is_subdomain, name_from_string, name_to_string, names_equal},
+    dns_record::record::{DnsRecord, Nsec, Nsec3, Nsec3Param, RData},
+    dns_zone::zone::Zone,
+    encoding::{base32hex_encode, parse_timestamp},
+    resrec::QType,
+};
+use anyhow::{bail, ensure, Context};
+use std::{cmp::Ordering, fs, path::PathBuf};
+
+/// How long the signatures made by `dns-sign` are valid for by default.
+const ZONE_SIGNATURE_VALIDITY: u64 = 30 * 86400;
+
+impl SignJob {
+    /// Reads the arguments of `dns-sign`: `<zone file> --key <key file>...
+    /// [--nsec3] [--origin <name>] [--inception <time>] [--expiration <time>]
+    /// [--output <file>]`, with keys written by BIND's `dnssec-keygen` and times
+    /// as `YYYYMMDDHHmmSS` or seconds since the epoch.
+    pub fn from_args(mut args: impl Iterator<Item = String>) -> anyhow::Result<Self> {
+        let mut input = None;
+        let mut job = SignJob {
+            input: PathBuf::new(),
+            output: None,
+            origin: None,
+            keys: Vec::new(),
+            denial: Denial::Nsec,
+            inception: None,
+            expiration: None,
+        };
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .with_context(|| format!("{} expects a value", arg))
+            };
+            match arg.as_str() {
+                "--key" => {
+                    let file = value()?;
+                    let key = ZoneKey::from_files(file.as_ref()).with_context(|| file.clone())?;
+                    job.keys.push(key);
+                }
+                "--nsec3" => job.denial = Denial::nsec3(),
+                "--origin" => {
+                    let name = value()?;
+                    let labels = name_from_string(&name, &[]);
+                    job.origin =
+                        Some(labels.with_context(|| format!("invalid --origin {}", name))?);
+                }
+                "--inception" => {
+                    job.inception = Some(parse_timestamp(&value()?).context("invalid --inception")?)
+                }
+                "--expiration" => {
+                    job.expiration =
+                        Some(parse_timestamp(&value()?).context("invalid --expiration")?)
+                }
+                "--output" => job.output = Some(PathBuf::from(value()?)),
+                _ if input.is_none() && !arg.starts_with("--") => input = Some(PathBuf::from(arg)),
+                _ => bail!("unexpected argument {}", arg),
+            }
+        }
+        job.input = input.context("missing zone file")?;
+        ensure!(!job.keys.is_empty(), "no --key given");
+        Ok(job)
+    }
+
+    /// Signs the zone at `now` and writes it out, returning its origin and the
+    /// file written. When the output already holds a signed version of the
+    /// zone, the signatures of unchanged RRsets are kept until they near expiry.
+    pub fn run(&self, now: u64) -> anyhow::Result<(Vec<String>, PathBuf)> {
+        let input = &self.input;
+        let text = fs::read_to_string(input).with_context(|| input.display().to_string())?;
+        let zone = Zone::from_master_file(&text, self.origin.as_deref())
+            .with_context(|| input.display().to_string())?;
+        if let Some(key) = self
+            .keys
+            .iter()
+            .find(|k| !names_equal(&k.owner, &zone.origin))
+        {
+            bail!(
+                "key of {} for the zone {}",
+                name_to_string(&key.owner),
+                name_to_string(&zone.origin)
+            );
+        }
+        let output = self.output.clone().unwrap_or_else(|| {
+            let mut file = input.clone().into_os_string();
+            file.push(".signed");
+            file.into()
+        });
+        let previous = fs::read_to_string(&output)
+            .ok()
+            .and_then(|text| Zone::from_master_file(&text, None).ok())
+            .filter(|previous| names_equal(&previous.origin, &zone.origin));
+
+        let inception = self
+            .inception
+            .unwrap_or(now.saturating_sub(INCEPTION_OFFSET) as u32);
+        let expiration = self
+            .expiration
+            .unwrap_or((now + ZONE_SIGNATURE_VALIDITY) as u32);
+        ensure!(
+            inception < expiration,
+            "signatures would expire before inception"
+        );
+        let signer = ZoneSigner::new(zone.origin.clone(), self.keys.clone(), self.denial.clone());
+        let signed = signer.sign_zone(&zone, previous.as_ref(), inception, expiration, now);
+        fs::write(&output, signed.to_master_file())
+            .with_context(|| output.display().to_string())?;
+        Ok((zone.origin, output))
+    }
+}
+
+impl ZoneSigner {
+    /// Signs a whole zone ahead of time (RFC 4035 section 2): publishes the keys,
+    /// links the names of the zone with a chain of NSEC or NSEC3 records and signs
+    /// every authoritative RRset, with signatures valid from `inception` to
+    /// `expiration`. Signatures from `previous`, an earlier signed version of the
+    /// zone, are kept for RRsets that did not change while more than a quarter of
+    /// the validity period is left at `now`, so a few changes only cost a few
+    /// signatures.
+    pub fn sign_zone(
+        &self,
+        zone: &Zone,
+        previous: Option<&Zone>,
+        inception: u32,
+        expiration: u32,
+        now: u64,
+    ) -> Zone {
+        let mut signed = zone.clone();
+        signed.records.retain(|r| {
+            !matches!(
+                r.typ,
+                QType::RRSIG | QType::NSEC | QType::NSEC3 | QType::NSEC3PARAM
+            )
+        });
+        self.publish(&mut signed);
+        let cuts = zone_cuts(&signed);
+        let chain = match &self.denial {
+            Denial::Nsec => nsec_chain(&signed, &cuts),
+            Denial::Nsec3(param) => nsec3_chain(&signed, &cuts, param),
+        };
+        signed.records.extend(chain);
+
+        let old = previous.map_or(&[][..], |x| &x.records[..]);
+        let renew_before = now + u64::from(expiration.saturating_sub(inception)) / 4;
+        let rrsigs = rrsets(&signed.records)
+            .iter()
+            .filter(|rrset| is_authoritative(&rrset[0], &cuts))
+            .flat_map(|rrset| {
+                let kept = kept_signatures(rrset, old, renew_before);
+                self.signing_keys(rrset[0].typ)
+                    .map(|key| {
+                        kept.iter()
+                            .find(|sig| match &sig.rdata {
+                                RData::RRSIG(x) => {
+                                    x.key_tag == key.dnskey.key_tag()
+                                        && x.algorithm == key.dnskey.algorithm
+                                        && names_equal(&x.signer, &key.owner)
+                                }
+                                _ => false,
+                            })
+                            .map(|sig| (*sig).clone())
+                            .unwrap_or_else(|| key.sign(rrset, inception, expiration))
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        signed.records.extend(rrsigs);
+
+        // The SOA first, then every RRset followed by its signatures.
+        let sort_key = |r: &DnsRecord| {
+            let covered = match &r.rdata {
+                RData::RRSIG(x) => x.type_covered,
+                _ => u16::from(r.typ),
+            };
+            (
+                covered != u16::from(QType::SOA),
+                covered,
+                r.typ == QType::RRSIG,
+            )
+        };
+        signed.records.sort_by(|a, b| {
+            canonical_cmp(&a.name, &b.name).then_with(|| sort_key(a).cmp(&sort_key(b)))
+        });
+        signed
+    }
+}
+
+/// Adds to a response of `zone` made by [`Zone::answer`] the RRSIGs of a zone
+/// signed ahead of time, with the NSEC or NSEC3 records proving that the name
+/// or type queried does not exist when it is negative. Zones without
+/// signatures are left as they are.
+pub fn add_presigned_records(zone: &Zone, message: &mut DnsMessage) {
+    if let Some(target) = denied_name(zone, message) {
+        let nxdomain = message.header.rcode == ResponseCode::NameError;
+        let mut denial = match zone.records.iter().any(|r| r.typ == QType::NSEC3) {
+            true => nsec3_denial(zone, &target, nxdomain),
+            false => nsec_denial(zone, &target, nxdomain),
+        };
+        denial.dedup();
+        message.authorities.extend(denial.into_iter().cloned());
+    }
+
+    for section in [
+        &mut message.answers,
+        &mut message.authorities,
+        &mut message.additionals,
+    ] {
+        let rrsigs = rrsets(section)
+            .iter()
+            .flat_map(|rrset| signatures_of(rrset, &zone.records))
+            .filter(|r| !section.contains(r))
+            .cloned()
+            .collect::<Vec<_>>();
+        section.extend(rrsigs);
+    }
+    message.update_counts();
+}
+
+/// The NSEC records of `zone` proving `name` has no record of the type queried,
+/// or does not exist along with any wildcard that could have produced it.
+fn nsec_denial<'a>(zone: &'a Zone, name: &[String], nxdomain: bool) -> Vec<&'a DnsRecord> {
+    let nsecs = || {
+        zone.records.iter().filter_map(|r| match &r.rdata {
+            RData::NSEC(nsec) => Some((r, nsec)),
+            _ => None,
+        })
+    };
+    let covering = |name: &[String]| nsecs().find(|(r, nsec)| nsec_covers(&r.name, nsec, name));
+    let matching = nsecs().find(|(r, _)| names_equal(&r.name, name));
+
+    let mut denial = Vec::new();
+    if nxdomain {
+        denial.extend(covering(name));
+        denial.extend(covering(&wildcard(&closest_encloser(zone, name))));
+    } else {
+        // An empty non-terminal has no NSEC of its own, only one covering it.
+        denial.extend(matching.or_else(|| covering(name)));
+    }
+    denial.into_iter().map(|(r, _)| r).collect()
+}
+
+/// The NSEC3 records of `zone` proving `name` has no record of the type queried,
+/// or the closest encloser proof that it does not exist (RFC 5155 section 7.2).
+fn nsec3_denial<'a>(zone: &'a Zone, name: &[String], nxdomain: bool) -> Vec<&'a DnsRecord> {
+    let nsec3s = || {
+        zone.records.iter().filter_map(|r| match &r.rdata {
+            RData::NSEC3(nsec3) => Some((r, nsec3)),
+            _ => None,
+        })
+    };
+    let matching = |name: &[String]| {
+        nsec3s().find(|(r, nsec3)| nsec3_matches(&r.name, nsec3, &zone.origin, name))
+    };
+    let covering = |name: &[String]| {
+        nsec3s().find(|(r, nsec3)| nsec3_covers(&r.name, nsec3, &zone.origin, name))
+    };
+
+    let mut denial = Vec::new();
+    if nxdomain {
+        let encloser = closest_encloser(zone, name);
+        let next_closer = &name[name.len() - encloser.len() - 1..];
+        denial.extend(matching(&encloser));
+        denial.extend(covering(next_closer));
+        denial.extend(covering(&wildcard(&encloser)));
+    } else {
+        denial.extend(matching(name));
+    }
+    denial.into_iter().map(|(r, _)| r).collect()
+}
+
+/// The RRSIG records among `records` over `rrset`.
+fn signatures_of<'a>(
+    rrset: &[DnsRecord],
+    records: &'a [DnsRecord],
+) -> impl Iterator<Item = &'a DnsRecord> {
+    let (owner, typ) = (rrset[0].name.clone(), u16::from(rrset[0].typ));
+    records.iter().filter(move |r| match &r.rdata {
+        RData::RRSIG(rrsig) => rrsig.type_covered == typ && names_equal(&r.name, &owner),
+        _ => false,
+    })
+}
+
+/// The signatures over `rrset` in `old` records that can be kept: the RRset is
+/// unchanged, and they expire after `renew_before`.
+fn kept_signatures<'a>(
+    rrset: &[DnsRecord],
+    old: &'a [DnsRecord],
+    renew_before: u64,
+) -> Vec<&'a DnsRecord> {
+    let canonical = |records: &[&DnsRecord]| {
+        let mut rdatas = records
+            .iter()
+            .map(|r| (r.ttl, canonical_rdata(&r.rdata)))
+            .collect::<Vec<_>>();
+        rdatas.sort();
+        rdatas.dedup();
+        rdatas
+    };
+    let old_rrset = old
+        .iter()
+        .filter(|r| r.typ == rrset[0].typ && names_equal(&r.name, &rrset[0].name))
+        .collect::<Vec<_>>();
+    if canonical(&old_rrset) != canonical(&rrset.iter().collect::<Vec<_>>()) {
+        return Vec::new();
+    }
+    signatures_of(rrset, old)
+        .filter(|r| match &r.rdata {
+            RData::RRSIG(rrsig) => {
+                u64::from(rrsig.expiration) > renew_before && rrsig.original_ttl == rrset[0].ttl
+            }
+            _ => false,
+        })
+        .collect()
+}
+
+/// The names below the apex owning NS records: delegations to child zones.
+fn zone_cuts(zone: &Zone) -> Vec<Vec<String>> {
+    let mut cuts = zone
+        .records
+        .iter()
+        .filter(|r| r.typ == QType::NS && !zone.is_apex(&r.name))
+        .map(|r| r.name.clone())
+        .collect::<Vec<_>>();
+    cuts.dedup_by(|a, b| names_equal(a, b));
+    cuts
+}
+
+fn below_cut(name: &[String], cuts: &[Vec<String>]) -> bool {
+    cuts.iter()
+        .any(|cut| is_subdomain(name, cut) && !names_equal(name, cut))
+}
+
+/// Whether the zone holds authoritative data for `record`: not glue below a
+/// delegation, nor the delegation's NS records, which belong to the child,
+/// only the DS and NSEC records there.
+fn is_authoritative(record: &DnsRecord, cuts: &[Vec<String>]) -> bool {
+    let at_cut = cuts.iter().any(|cut| names_equal(&record.name, cut));
+    !below_cut(&record.name, cuts) && (!at_cut || matches!(record.typ, QType::DS | QType::NSEC))
+}
+
+/// The types present at `name` for its NSEC or NSEC3 record: all of them, but
+/// only NS and DS at a delegation (RFC 4035 section 2.3).
+fn chain_types(zone: &Zone, name: &[String], cuts: &[Vec<String>]) -> Vec<u16> {
+    let at_cut = cuts.iter().any(|cut| names_equal(name, cut));
+    let mut types = zone
+        .records
+        .iter()
+        .filter(|r| names_equal(&r.name, name))
+        .filter(|r| !at_cut || matches!(r.typ, QType::NS | QType::DS))
+        .map(|r| u16::from(r.typ))
+        .collect::<Vec<_>>();
+    types.sort();
+    types.dedup();
+    types
+}
+
+/// The owner names of the authoritative data of the zone, delegations
+/// included, in canonical order.
+fn owner_names(zone: &Zone, cuts: &[Vec<String>]) -> Vec<Vec<String>> {
+    let mut names = zone
+        .records
+        .iter()
+        .filter(|r| !below_cut(&r.name, cuts))
+        .map(|r| r.name.clone())
+        .collect::<Vec<_>>();
+    names.sort_by(|a, b| canonical_cmp(a, b));
+    names.dedup_by(|a, b| canonical_cmp(a, b) == Ordering::Equal);
+    names
+}
+
+/// An NSEC record at every name, leading to the next one and from the last back
+/// to the apex.
+fn nsec_chain(zone: &Zone, cuts: &[Vec<String>]) -> Vec<DnsRecord> {
+    let names = owner_names(zone, cuts);
+    names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            let mut types = chain_types(zone, name, cuts);
+            types.extend([u16::from(QType::RRSIG), u16::from(QType::NSEC)]);
+            types.sort();
+            DnsRecord {
+                name: name.clone(),
+                typ: QType::NSEC,
+                class: zone.class,
+                ttl: negative_ttl(zone),
+                rdata: RData::NSEC(Nsec {
+                    next: names[(i + 1) % names.len()].clone(),
+                    types,
+                }),
+            }
+        })
+        .collect()
+}
+
+/// An NSEC3 record at the hash of every name and empty non-terminal, leading to
+/// the next hash and from the last back to the first (RFC 5155 section 7.1).
+fn nsec3_chain(zone: &Zone, cuts: &[Vec<String>], param: &Nsec3Param) -> Vec<DnsRecord> {
+    let mut names = owner_names(zone, cuts);
+    let empty_non_terminals = names
+        .iter()
+        .flat_map(|name| {
+            (zone.origin.len() + 1..name.len()).map(|len| name[name.len() - len..].to_vec())
+        })
+        .collect::<Vec<_>>();
+    names.extend(empty_non_terminals);
+    names.sort_by(|a, b| canonical_cmp(a, b));
+    names.dedup_by(|a, b| canonical_cmp(a, b) == Ordering::Equal);
+
+    let mut hashed = names
+        .iter()
+        .map(|name| {
+            let mut types = chain_types(zone, name, cuts);
+            let unsigned_delegation = cuts.iter().any(|cut| names_equal(name, cut))
+                && !types.contains(&u16::from(QType::DS));
+            if !types.is_empty() && !unsigned_delegation {
+                types.push(u16::from(QType::RRSIG));
+                types.sort();
+            }
+            (nsec3_hash(name, &param.salt, param.iterations), types)
+        })
+        .collect::<Vec<_>>();
+    hashed.sort();
+
+    hashed
+        .iter()
+        .enumerate()
+        .map(|(i, (hash, types))| {
+            let mut owner = vec![base32hex_encode(hash).to_ascii_lowercase()];
+            owner.extend_from_slice(&zone.origin);
+            DnsRecord {
+                name: owner,
+                typ: QType::NSEC3,
+                class: zone.class,
+                ttl: negative_ttl(zone),
+                rdata: RData::NSEC3(Nsec3 {
+                    hash_algorithm: param.hash_algorithm,
+                    flags: 0,
+                    iterations: param.iterations,
+                    salt: param.salt.clone(),
+                    next_hashed: hashed[(i + 1) % hashed.len()].0.clone(),
+                    types: types.clone(),
+                }),
+            }
+        })
+        .collect()
+}
+
+fn wildcard(encloser: &[String]) -> Vec<String> {
+    let mut wildcard = vec!["*".to_string()];
+    wildcard.extend_from_slice(encloser);
+    wildcard
+}
+
+#[cfg(test)]
+mod tests {
+    use super::add_presigned_records;
+    use crate::{
+        dns_header::header::ResponseCode,
+        dns_message::message::DnsMessage,
+        dns_record::record::{DnsRecord, Nsec3Param, RData},
+        dns_zone::zone::Zone,
+        dnssec::{
+            signature_impl::{DIGEST_SHA256, SECURE_ENTRY_POINT_FLAG},
+            signer::{Denial, PrivateKey, SignJob, ZoneKey, ZoneSigner},
+            validator::{Security, TrustAnchor, Validator},
+        },
+        resrec::{QClass, QType},
+    };
+    use std::net::Ipv4Addr;
+
+    const NOW: u64 = 1_700_000_000;
+    const INCEPTION: u32 = (NOW - 3600) as u32;
+    const EXPIRATION: u32 = (NOW + 7 * 86400) as u32;
+
+    fn name(text: &str) -> Vec<String> {
+        text.split('.')
+            .filter(|x| !x.is_empty())
+            .map(String::from)
+            .collect()
+    }
+
+    fn record(owner: &str, rdata: RData) -> DnsRecord {
+        let typ = match rdata {
+            RData::A(_) => QType::A,
+            RData::NS(_) => QType::NS,
+            _ => QType::CNAME,
+        };
+        DnsRecord {
+            name: name(owner),
+            typ,
+            class: QClass::IN,
+            ttl: 300,
+            rdata,
+        }
+    }
+
+    fn zone() -> Zone {
+        let mut zone = Zone::with_default_soa(name("example.com"));
+        zone.records.extend([
+            record("www.example.com", RData::A(Ipv4Addr::new(192, 0, 2, 1))),
+            record("a.b.example.com", RData::A(Ipv4Addr::new(192, 0, 2, 2))),
+            record("web.example.com", RData::CNAME(name("www.example.com"))),
+            record("old.example.com", RData::CNAME(name("gone.example.com"))),
+            record("sub.example.com", RData::NS(name("ns.sub.example.com"))),
+            record("ns.sub.example.com", RData::A(Ipv4Addr::new(192, 0, 2, 3))),
+        ]);
+        zone
+    }
+
+    fn signer(denial: Denial) -> ZoneSigner {
+        let ksk =
+            PrivateKey::EcdsaP256Sha256(p256::ecdsa::SigningKey::from_slice(&[1; 32]).unwrap());
+        let zsk = PrivateKey::Ed25519(ed25519_dalek::SigningKey::from_bytes(&[2; 32]));
+        ZoneSigner::new(
+            name("example.com"),
+            vec![
+                ZoneKey::new(name("example.com"), SECURE_ENTRY_POINT_FLAG, ksk),
+                ZoneKey::new(name("example.com"), 0, zsk),
+            ],
+            denial,
+        )
+    }
+
+    fn nsec3() -> Denial {
+        Denial::Nsec3(Nsec3Param {
+            hash_algorithm: 1,
+            flags: 0,
+            iterations: 0,
+            salt: vec![0xab],
+        })
+    }
+
+    fn answer(zone: &Zone, owner: &[String], qtype: QType) -> DnsMessage {
+        let mut message = DnsMessage::query(owner.to_vec(), qtype);
+        zone.answer(&mut message);
+        add_presigned_records(zone, &mut message);
+        message
+    }
+
+    fn rrsig_count(zone: &Zone, owner: &str) -> usize {
+        zone.records
+            .iter()
+            .filter(|r| r.typ == QType::RRSIG && r.name == name(owner))
+            .count()
+    }
+
+    #[test]
+    fn presigned_answers_test() {
+        for denial in [Denial::Nsec, nsec3()] {
+            let signer = signer(denial);
+            let zone = signer.sign_zone(&zone(), None, INCEPTION, EXPIRATION, NOW);
+            let ksk = &signer.keys[0];
+            let anchor = TrustAnchor {
+                owner: name("example.com"),
+                ds: ksk.dnskey.to_ds(&ksk.owner, DIGEST_SHA256).unwrap(),
+            };
+            for (owner, qtype, rcode) in [
+                ("www.example.com", QType::A, ResponseCode::NoErrorCondition),
+                ("web.example.com", QType::A, ResponseCode::NoErrorCondition),
+                ("example.com", QType::DNSKEY, ResponseCode::NoErrorCondition),
+                ("www.example.com", QType::MX, ResponseCode::NoErrorCondition),
+                ("b.example.com", QType::A, ResponseCode::NoErrorCondition),
+                ("nope.example.com", QType::A, ResponseCode::NameError),
+                ("x.y.b.example.com", QType::A, ResponseCode::NameError),
+                ("old.example.com", QType::A, ResponseCode::NameError),
+            ] {
+                let response = answer(&zone, &name(owner), qtype);
+                let mut validator = Validator::new(vec![anchor.clone()]);
+                let security = validator.validate(&response, NOW, &mut |owner, qtype| {
+                    Ok(answer(&zone, owner, qtype))
+                });
+                assert_eq!(
+                    (response.header.rcode, security),
+                    (rcode, Security::Secure),
+                    "{} {} with {:?}",
+                    owner,
+                    qtype,
+                    signer.denial
+                );
+            }
+
+            // Delegations and glue belong to the child zone.
+            assert_eq!(rrsig_count(&zone, "ns.sub.example.com"), 0);
+            let nsec_at_delegation = usize::from(signer.denial == Denial::Nsec);
+            assert_eq!(rrsig_count(&zone, "sub.example.com"), nsec_at_delegation);
+            assert_eq!(zone.records[0].typ, QType::SOA);
+        }
+    }
+
+    #[test]
+    fn nsec_chain_test() {
+        let zone = signer(Denial::Nsec).sign_zone(&zone(), None, INCEPTION, EXPIRATION, NOW);
+        let chain = zone
+            .records
+            .iter()
+            .filter_map(|r| match &r.rdata {
+                RData::NSEC(nsec) => Some((r.name.join("."), nsec.next.join("."))),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let link = |owner: &str, next: &str| (owner.to_string(), next.to_string());
+        assert_eq!(
+            chain,
+            [
+                link("example.com", "a.b.example.com"),
+                link("a.b.example.com", "old.example.com"),
+                link("old.example.com", "sub.example.com"),
+                link("sub.example.com", "web.example.com"),
+                link("web.example.com", "www.example.com"),
+                link("www.example.com", "example.com"),
+            ]
+        );
+        let delegation = zone
+            .records
+            .iter()
+            .find_map(|r| match &r.rdata {
+                RData::NSEC(nsec) if r.name == name("sub.example.com") => Some(nsec.types.clone()),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(
+            delegation,
+            [
+                u16::from(QType::NS),
+                u16::from(QType::RRSIG),
+                u16::from(QType::NSEC)
+            ]
+        );
+    }
+
+    #[test]
+    fn incremental_signing_test() {
+        let signer = signer(nsec3());
+        let first = signer.sign_zone(&zone(), None, INCEPTION, EXPIRATION, NOW);
+        let rrsigs = |zone: &Zone, owner: &str| {
+            zone.records
+                .iter()
+                .filter(|r| r.typ == QType::RRSIG && r.name == name(owner))
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+
+        // Signing again a day later only signs the RRset that changed.
+        let mut changed = zone();
+        changed.records[1].rdata = RData::A(Ipv4Addr::new(192, 0, 2, 9));
+        let later = NOW + 86400;
+        let (inception, expiration) = (INCEPTION + 86400, EXPIRATION + 86400);
+        let second = signer.sign_zone(&changed, Some(&first), inception, expiration, later);
+        assert_eq!(
+            rrsigs(&second, "web.example.com"),
+            rrsigs(&first, "web.example.com")
+        );
+        assert_eq!(
+            rrsigs(&second, "example.com"),
+            rrsigs(&first, "example.com")
+        );
+        assert_ne!(
+            rrsigs(&second, "www.example.com"),
+            rrsigs(&first, "www.example.com")
+        );
+
+        // Signatures close to expiring are all replaced.
+        let late = NOW + 6 * 86400;
+        let third = signer.sign_zone(&changed, Some(&second), inception, expiration, late);
+        assert_ne!(
+            rrsigs(&third, "web.example.com"),
+            rrsigs(&first, "web.example.com")
+        );
+    }
+
+    #[test]
+    fn signed_master_file_test() {
+        let zone = signer(nsec3()).sign_zone(&zone(), None, INCEPTION, EXPIRATION, NOW);
+        let text = zone.to_master_file();
+        assert_eq!(Zone::from_master_file(&text, None).unwrap(), zone);
+    }
+
+    #[test]
+    fn sign_job_args_test() {
+        let error = |args: &[&str]| {
+            let args = args.iter().map(|x| x.to_string());
+            SignJob::from_args(args).unwrap_err().to_string()
+        };
+        assert_eq!(error(&[]), "missing zone file");
+        assert_eq!(error(&["zone.db"]), "no --key given");
+        assert_eq!(error(&["zone.db", "--key"]), "--key expects a value");
+        assert_eq!(error(&["zone.db", "--sign"]), "unexpected argument --sign");
+        assert_eq!(
+            error(&["zone.db", "other.db"]),
+            "unexpected argument other.db"
+        );
+    }
+}
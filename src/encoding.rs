@@ -0,0 +1,239 @@
+//! Text encodings of binary data used by keys and record presentation formats.
+
+const BASE32HEX_ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHIJKLMNOPQRSTUV";
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Encodes `bytes` as padded base64 (RFC 4648 section 4).
+pub fn base64_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64_ALPHABET[(n >> (18 - 6 * i) & 0b11_1111) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Decodes padded or unpadded base64, ignoring whitespace as zone files split long
+/// base64 fields across several words.
+pub fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(text.len() / 4 * 3);
+    let mut acc = 0u32;
+    let mut bits = 0;
+    let mut padding = false;
+    for c in text.bytes().filter(|c| !c.is_ascii_whitespace()) {
+        if c == b'=' {
+            padding = true;
+            continue;
+        }
+        if padding {
+            return None;
+        }
+        let value = BASE64_ALPHABET.iter().position(|x| *x == c)? as u32;
+        acc = (acc << 6) | value;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+            acc &= (1 << bits) - 1;
+        }
+    }
+    Some(out)
+}
+
+/// Encodes `bytes` as uppercase hexadecimal.
+pub fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+/// Decodes hexadecimal of either case, ignoring whitespace.
+pub fn hex_decode(text: &str) -> Option<Vec<u8>> {
+    let digits = text
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace())
+        .map(|c| c.to_digit(16).map(|d| d as u8))
+        .collect::<Option<Vec<_>>>()?;
+    if digits.len() % 2 != 0 {
+        return None;
+    }
+    Some(digits.chunks(2).map(|x| (x[0] << 4) | x[1]).collect())
+}
+
+/// Encodes `bytes` as unpadded base32 with the extended hex alphabet (RFC 4648
+/// section 7), as NSEC3 presents hashed owner names.
+pub fn base32hex_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(5) * 8);
+    let mut acc = 0u16;
+    let mut bits = 0;
+    for &byte in bytes {
+        acc = (acc << 8) | u16::from(byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(BASE32HEX_ALPHABET[usize::from((acc >> bits) & 0x1f)] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(BASE32HEX_ALPHABET[usize::from((acc << (5 - bits)) & 0x1f)] as char);
+    }
+    out
+}
+
+/// Decodes unpadded base32hex of either case.
+pub fn base32hex_decode(text: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(text.len() * 5 / 8);
+    let mut acc = 0u16;
+    let mut bits = 0;
+    for c in text.bytes() {
+        let value = BASE32HEX_ALPHABET
+            .iter()
+            .position(|&x| x == c.to_ascii_uppercase())?;
+        acc = (acc << 5) | value as u16;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+    // Leftover bits are padding and must be zero.
+    (acc & ((1 << bits) - 1) == 0).then_some(out)
+}
+
+/// Formats seconds since the epoch as `YYYYMMDDHHmmSS` in UTC, the way RRSIG
+/// presents its validity period (RFC 4034 section 3.2).
+pub fn format_timestamp(time: u32) -> String {
+    let days = i64::from(time / 86400);
+    let seconds = time % 86400;
+
+    // Civil date from days since 1970-01-01, after Howard Hinnant's algorithm.
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}{:02}{:02}{:02}{:02}{:02}",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// Parses a `YYYYMMDDHHmmSS` UTC timestamp into seconds since the epoch, or a
+/// plain number of seconds as RFC 4034 also allows.
+pub fn parse_timestamp(text: &str) -> Option<u32> {
+    if text.len() != 14 {
+        return text.parse().ok();
+    }
+    let field = |range: std::ops::Range<usize>| text.get(range)?.parse::<i64>().ok();
+    let (year, month, day) = (field(0..4)?, field(4..6)?, field(6..8)?);
+    let (hour, minute, second) = (field(8..10)?, field(10..12)?, field(12..14)?);
+    if !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 60
+    {
+        return None;
+    }
+
+    // Days since 1970-01-01 from a civil date, the inverse of `format_timestamp`.
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400);
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146097 + doe - 719468;
+
+    u32::try_from(days * 86400 + hour * 3600 + minute * 60 + second).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        base32hex_decode, base32hex_encode, base64_decode, base64_encode, format_timestamp,
+        hex_decode, hex_encode, parse_timestamp,
+    };
+
+    #[test]
+    fn base64_test() {
+        let cases: [(&[u8], &str); 4] = [
+            (b"", ""),
+            (b"f", "Zg=="),
+            (b"fo", "Zm8="),
+            (b"foobar", "Zm9vYmFy"),
+        ];
+        for (bytes, text) in cases {
+            assert_eq!(base64_encode(bytes), text);
+            assert_eq!(base64_decode(text).as_deref(), Some(bytes));
+        }
+        assert_eq!(base64_decode("Zm9v YmFy").as_deref(), Some(&b"foobar"[..]));
+        assert_eq!(base64_decode("Zm9v!"), None);
+        assert_eq!(base64_decode("Zg==Zg"), None);
+    }
+
+    #[test]
+    fn hex_test() {
+        assert_eq!(hex_encode(&[0x00, 0xab, 0x10]), "00AB10");
+        assert_eq!(hex_decode("00ab 10"), Some(vec![0x00, 0xab, 0x10]));
+        assert_eq!(hex_decode("abc"), None);
+        assert_eq!(hex_decode("zz"), None);
+    }
+
+    #[test]
+    fn base32hex_test() {
+        let cases: [(&[u8], &str); 4] = [
+            (b"", ""),
+            (b"f", "CO"),
+            (b"foob", "CPNMUOG"),
+            (b"foobar", "CPNMUOJ1E8"),
+        ];
+        for (bytes, text) in cases {
+            assert_eq!(base32hex_encode(bytes), text);
+            assert_eq!(base32hex_decode(text).as_deref(), Some(bytes));
+        }
+        assert_eq!(
+            base32hex_decode("cpnmuoj1e8").as_deref(),
+            Some(&b"foobar"[..])
+        );
+        assert_eq!(base32hex_decode("CPNMUOJ1E9"), None);
+        assert_eq!(base32hex_decode("W0"), None);
+    }
+
+    #[test]
+    fn timestamp_test() {
+        let cases = [
+            (0, "19700101000000"),
+            (951782400, "20000229000000"),
+            (1_700_000_000, "20231114221320"),
+            (u32::MAX, "21060207062815"),
+        ];
+        for (time, text) in cases {
+            assert_eq!(format_timestamp(time), text);
+            assert_eq!(parse_timestamp(text), Some(time));
+        }
+        assert_eq!(parse_timestamp("1700000000"), Some(1_700_000_000));
+        assert_eq!(parse_timestamp("20231314221320"), None);
+    }
+}
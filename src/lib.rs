@@ -0,0 +1,41 @@
+//! A DNS codec and the authoritative server, forwarder and DNSSEC signer built
+//! on it.
+//!
+//! The wire format is read and written by [`DnsMessage`] and the types of its
+//! sections: [`DnsHeader`], [`DnsQuestion`] and [`DnsRecord`], whose data is an
+//! [`RData`]. Names are vectors of labels, converted with the functions of
+//! [`dns_name::name`]. Queries are sent to other servers with a
+//! [`dns_client::client::Client`], and answered by a
+//! [`dns_server::server::Server`] configured with a
+//! [`dns_server::server::ServerConfig`].
+//!
+//! ```
+//! use dns_starter_rust::{DnsMessage, QType, RData};
+//!
+//! let query = DnsMessage::query(vec!["example".into(), "com".into()], QType::A);
+//! let parsed = DnsMessage::parse(&query.as_bytes()).unwrap();
+//! assert_eq!(parsed.questions, query.questions);
+//!
+//! let record = "example.com. 300 IN A 192.0.2.1".parse::<dns_starter_rust::DnsRecord>();
+//! assert!(matches!(record.unwrap().rdata, RData::A(_)));
+//! ```
+
+pub mod dns_client;
+pub mod dns_header;
+pub mod dns_message;
+pub mod dns_name;
+pub mod dns_question;
+pub mod dns_record;
+pub mod dns_resolver;
+pub mod dns_server;
+pub mod dns_zone;
+pub mod dnssec;
+pub mod encoding;
+pub mod resrec;
+pub mod tcp;
+
+pub use dns_header::header::DnsHeader;
+pub use dns_message::message::DnsMessage;
+pub use dns_question::question::DnsQuestion;
+pub use dns_record::record::{DnsRecord, RData};
+pub use resrec::{QClass, QType};
@@ -1,31 +1,233 @@
-pub mod dns_answer;
-pub mod dns_header;
-pub mod dns_message;
-pub mod dns_question;
-pub mod resrec;
+use dns_starter_rust::{
+    dns_message::tsig::TsigKey,
+    dns_name::name::name_to_string,
+    dns_resolver::resolver::ForwardRule,
+    dns_server::server::{SecondaryConfig, Server, ServerConfig},
+    dns_zone::zone::Zone,
+    dnssec::{
+        signer::{Denial, SignJob, ZoneKey},
+        validator::{TrustAnchor, Validator},
+    },
+};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::{
+    fs,
+    net::IpAddr,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
-use dns_message::message::DnsMessage;
-use std::net::UdpSocket;
+const ADDRESS: &str = "127.0.0.1:2053";
+
+/// Prints what the server logs: warnings and errors to stderr, the rest to
+/// stdout.
+struct Logger;
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        match record.level() {
+            Level::Error | Level::Warn => eprintln!("{}", record.args()),
+            _ => println!("{}", record.args()),
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: Logger = Logger;
 
 fn main() {
-    let udp_socket = UdpSocket::bind("127.0.0.1:2053").expect("Failed to bind to address");
-    let mut buf = [0; 512];
+    if std::env::args().nth(1).as_deref() == Some("dns-sign") {
+        return dns_sign();
+    }
+    log::set_logger(&LOGGER).expect("Failed to set the logger");
+    log::set_max_level(LevelFilter::Debug);
+    let server = Server::new(config_from_args())
+        .unwrap_or_else(|e| panic!("Invalid configuration: {:#}", e));
+    server.run(ADDRESS).expect("Failed to serve");
+}
 
-    loop {
-        match udp_socket.recv_from(&mut buf) {
-            Ok((size, source)) => {
-                println!("Received {} bytes from {}", size, source);
-                let mut message = DnsMessage::from(&buf[..]);
-                message.to_response();
+/// Signs a master file as the `dns-sign` binary does, with the arguments
+/// following the subcommand.
+fn dns_sign() {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |x| x.as_secs());
+    match SignJob::from_args(std::env::args().skip(2)).and_then(|job| job.run(now)) {
+        Ok((origin, output)) => {
+            println!(
+                "Signed {} into {}",
+                name_to_string(&origin),
+                output.display()
+            )
+        }
+        Err(e) => {
+            eprintln!("dns-sign: {:#}", e);
+            std::process::exit(1);
+        }
+    }
+}
 
-                udp_socket
-                    .send_to(&message.as_bytes(), source)
-                    .expect("Failed to send response");
+/// Reads the server configuration from the command line.
+///
+/// Each `--zone <origin>` creates an empty zone accepting dynamic updates, and
+/// each `--zone-file <file>` serves a zone read from a master file, such as one
+/// signed by `dns-sign`. Each `--tsig-key [algorithm:]name:secret` adds a key
+/// updates can be signed with: unsigned updates are always refused.
+///
+/// Each `--allow-transfer <address|key name>` lets a client address or the
+/// holders of a key transfer zones over TCP. Each
+/// `--secondary <origin>@<primary address>[@<key name>]` serves a copy of a zone
+/// transferred from its primary, persisted in the directory given by
+/// `--zone-dir` (the working directory by default). Each
+/// `--also-notify <address:port>` adds a secondary sent NOTIFY messages when a
+/// zone changes, besides its NS hosts.
+///
+/// Queries outside the served zones are forwarded to `--resolver <address:port>`,
+/// or by the longest domain of each `--forward <domain>@<address:port>[,...]`
+/// rule holding their name. A rule falls back to enclosing rules unless it ends
+/// with `@forward-only`, and caches answers unless it ends with `@no-cache`. Its
+/// upstreams are tried in turn, ordered by a `@sequential`, `@round-robin`,
+/// `@random` or `@lowest-latency` option, those that keep failing being skipped
+/// until a probe finds them answering again.
+///
+/// Forwarded answers are validated with DNSSEC from the root trust anchor with
+/// `--dnssec`, or from each
+/// `--trust-anchor "<owner> <key tag> <algorithm> <digest type> <digest>"`. Each
+/// `--negative-trust-anchor <domain>` disables validation below a domain.
+///
+/// Each `--dnssec-key <key file>` signs the zone of a key read from the pair of
+/// files BIND's `dnssec-keygen` writes, proving nonexistence with NSEC records,
+/// or NSEC3 records with `--nsec3`. Each `--manage-keys <origin>` signs a zone
+/// with keys generated and rolled over automatically, kept in the directory
+/// given by `--key-dir` (the working directory by default), which replace its
+/// `--dnssec-key` keys.
+fn config_from_args() -> ServerConfig {
+    let mut config = ServerConfig::default();
+    let mut upstream = None;
+    let mut validator: Option<Validator> = None;
+    let mut negative_trust_anchors = Vec::new();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--zone" => {
+                let origin = args.next().expect("--zone expects a zone name");
+                config
+                    .zones
+                    .zones
+                    .push(Zone::with_default_soa(labels(&origin)));
+            }
+            "--tsig-key" => {
+                let key = args.next().expect("--tsig-key expects a key");
+                let key = key.parse::<TsigKey>().expect("Invalid TSIG key");
+                config.keyring.keys.push(key);
+            }
+            "--allow-transfer" => {
+                let allowed = args
+                    .next()
+                    .expect("--allow-transfer expects an address or key");
+                match allowed.parse::<IpAddr>() {
+                    Ok(address) => config.transfer_acl.addresses.push(address),
+                    Err(_) => config.transfer_acl.keys.push(labels(&allowed)),
+                }
             }
-            Err(e) => {
-                eprintln!("Error receiving data: {}", e);
-                break;
+            "--secondary" => {
+                let secondary = args.next().expect("--secondary expects a zone and primary");
+                let mut parts = secondary.split('@');
+                let (Some(origin), Some(primary)) = (parts.next(), parts.next()) else {
+                    panic!("--secondary expects <zone>@<primary address>[@<key name>]");
+                };
+                let primary = primary.parse().expect("Invalid primary address");
+                config.secondaries.push(SecondaryConfig {
+                    origin: labels(origin),
+                    primary,
+                    key_name: parts.next().map(labels),
+                });
             }
+            "--also-notify" => {
+                let address = args.next().expect("--also-notify expects an address");
+                config
+                    .also_notify
+                    .push(address.parse().expect("Invalid --also-notify address"));
+            }
+            "--resolver" => {
+                let address = args.next().expect("--resolver expects an address");
+                upstream = Some(address.parse().expect("Invalid --resolver address"));
+            }
+            "--forward" => {
+                let rule = args.next().expect("--forward expects a forwarding rule");
+                config
+                    .forwarders
+                    .push(rule.parse::<ForwardRule>().expect("Invalid --forward rule"));
+            }
+            "--dnssec" => {
+                let root = Validator::with_root_anchors();
+                validator
+                    .get_or_insert_with(Validator::default)
+                    .trust_anchors
+                    .extend(root.trust_anchors);
+            }
+            "--trust-anchor" => {
+                let anchor = args.next().expect("--trust-anchor expects a DS record");
+                let anchor = anchor.parse::<TrustAnchor>().expect("Invalid trust anchor");
+                validator
+                    .get_or_insert_with(Validator::default)
+                    .trust_anchors
+                    .push(anchor);
+            }
+            "--negative-trust-anchor" => {
+                let domain = args
+                    .next()
+                    .expect("--negative-trust-anchor expects a domain");
+                negative_trust_anchors.push(labels(&domain));
+            }
+            "--dnssec-key" => {
+                let file = args.next().expect("--dnssec-key expects a key file");
+                let key = ZoneKey::from_files(file.as_ref())
+                    .unwrap_or_else(|e| panic!("Invalid DNSSEC key {}: {:#}", file, e));
+                config.zone_keys.push(key);
+            }
+            "--nsec3" => config.denial = Denial::nsec3(),
+            "--zone-file" => {
+                let file = args.next().expect("--zone-file expects a master file");
+                let zone = fs::read_to_string(&file)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|text| Zone::from_master_file(&text, None))
+                    .unwrap_or_else(|e| panic!("Invalid zone file {}: {:#}", file, e));
+                config.zones.zones.push(zone);
+            }
+            "--manage-keys" => {
+                let origin = args.next().expect("--manage-keys expects a zone name");
+                config.managed_zones.push(labels(&origin));
+            }
+            "--key-dir" => {
+                config.key_dir = args.next().expect("--key-dir expects a directory").into();
+            }
+            "--zone-dir" => {
+                config.zone_dir = args.next().expect("--zone-dir expects a directory").into();
+            }
+            _ => panic!("Unknown argument {}", arg),
         }
     }
+
+    if let Some(validator) = &mut validator {
+        validator.negative_trust_anchors = negative_trust_anchors;
+    }
+    if let Some(upstream) = upstream {
+        config
+            .forwarders
+            .push(ForwardRule::new(Vec::new(), vec![upstream]));
+    }
+    config.validator = validator;
+    config
+}
+
+fn labels(name: &str) -> Vec<String> {
+    name.split('.')
+        .filter(|x| !x.is_empty())
+        .map(String::from)
+        .collect()
 }
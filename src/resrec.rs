@@ -1,63 +1,110 @@
+use std::{fmt, str::FromStr};
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum QType {
     /// a host address
-    A = 1,
+    A,
     /// an authoritative name server
-    NS = 2,
+    NS,
     /// a mail destination (Obsolete - use MX)
-    MD = 3,
+    MD,
     /// a mail forwarder (Obsolete - use MX)
-    MF = 4,
+    MF,
     /// the canonical name for an alias
-    CNAME = 5,
+    CNAME,
     /// marks the start of a zone of authority
-    SOA = 6,
+    SOA,
     /// a mailbox domain name (EXPERIMENTAL)
-    MB = 7,
+    MB,
     /// a mail group member (EXPERIMENTAL)
-    MG = 8,
+    MG,
     /// a mail rename domain name (EXPERIMENTAL)
-    MR = 9,
+    MR,
     /// a null RR (EXPERIMENTAL)
-    NULL = 10,
+    NULL,
     /// a well known service description
-    WKS = 11,
+    WKS,
     /// a domain name pointer
-    PTR = 12,
+    PTR,
     /// host information
-    HINFO = 13,
+    HINFO,
     /// mailbox or mail list information
-    MINFO = 14,
+    MINFO,
     /// mail exchange
-    MX = 15,
+    MX,
     /// text strings
-    TXT = 16,
+    TXT,
+    /// an IPv6 host address (RFC 3596)
+    AAAA,
+    /// the location of a service: its host, port, priority and weight (RFC 2782)
+    SRV,
+    /// a rewrite rule of the Dynamic Delegation Discovery System (RFC 3403)
+    NAPTR,
+    /// EDNS(0) pseudo-record, found in the Additional section (RFC 6891)
+    OPT,
+    /// Delegation signer, the digest of a child zone's key (RFC 4034)
+    DS,
+    /// The fingerprint of an SSH host key (RFC 4255)
+    SSHFP,
+    /// A signature over an RRset (RFC 4034)
+    RRSIG,
+    /// The next owner name in the zone and the types at this one (RFC 4034)
+    NSEC,
+    /// A public key of the zone (RFC 4034)
+    DNSKEY,
+    /// The next hashed owner name and the types at this one (RFC 5155)
+    NSEC3,
+    /// The parameters used to hash owner names for NSEC3 (RFC 5155)
+    NSEC3PARAM,
+    /// The certificate or public key of a TLS server (RFC 6698)
+    TLSA,
+    /// The OpenPGP key of an email address (RFC 7929)
+    OPENPGPKEY,
+    /// The endpoints of a service and how to connect to them (RFC 9460)
+    SVCB,
+    /// SVCB for HTTPS origins, owned by the origin name itself (RFC 9460)
+    HTTPS,
+    /// Transaction signature (RFC 8945)
+    TSIG,
+    /// A request for an incremental transfer of a zone (RFC 1995)
+    IXFR,
     /// A request for a transfer of an entire zone
-    AXFR = 252,
+    AXFR,
     /// A request for mailbox-related records (MB, MG or MR)
-    MAILB = 253,
+    MAILB,
     /// A request for mail agent RRs (Obsolete - see MX)
-    MAILA = 254,
+    MAILA,
     /// A request for all records
-    WildCard = 255,
+    WildCard,
+    /// a URI a service is reachable at, with its priority and weight (RFC 7553)
+    URI,
+    /// The certification authorities allowed to issue certificates for a domain
+    /// (RFC 8659)
+    CAA,
+    /// A type without its own variant, kept as its code (RFC 3597)
+    Unknown(u16),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum QClass {
     /// the Internet
-    IN = 1,
+    IN,
     /// the CSNET class (Obsolete - used only for examples in some obsolete RFCs)
-    CS = 2,
+    CS,
     /// the CHAOS class
-    CH = 3,
+    CH,
     /// Hesiod [Dyer 87]
-    HS = 4,
+    HS,
+    /// none, used by dynamic updates to delete a specific RR (RFC 2136)
+    NONE,
     /// any class
-    WildCard = 255,
+    WildCard,
+    /// A class without its own variant, kept as its code (RFC 3597)
+    Unknown(u16),
 }
 
-impl From<u8> for QType {
-    fn from(val: u8) -> Self {
+impl From<u16> for QType {
+    fn from(val: u16) -> Self {
         match val {
             1 => QType::A,
             2 => QType::NS,
@@ -75,22 +122,178 @@ impl From<u8> for QType {
             14 => QType::MINFO,
             15 => QType::MX,
             16 => QType::TXT,
+            28 => QType::AAAA,
+            33 => QType::SRV,
+            35 => QType::NAPTR,
+            41 => QType::OPT,
+            43 => QType::DS,
+            44 => QType::SSHFP,
+            46 => QType::RRSIG,
+            47 => QType::NSEC,
+            48 => QType::DNSKEY,
+            50 => QType::NSEC3,
+            51 => QType::NSEC3PARAM,
+            52 => QType::TLSA,
+            61 => QType::OPENPGPKEY,
+            64 => QType::SVCB,
+            65 => QType::HTTPS,
+            250 => QType::TSIG,
+            251 => QType::IXFR,
             252 => QType::AXFR,
             253 => QType::MAILB,
             254 => QType::MAILA,
-            _ => QType::WildCard,
+            256 => QType::URI,
+            257 => QType::CAA,
+            255 => QType::WildCard,
+            code => QType::Unknown(code),
         }
     }
 }
 
-impl From<u8> for QClass {
-    fn from(val: u8) -> Self {
+impl From<u16> for QClass {
+    fn from(val: u16) -> Self {
         match val {
             1 => QClass::IN,
             2 => QClass::CS,
             3 => QClass::CH,
             4 => QClass::HS,
-            _ => QClass::WildCard,
+            254 => QClass::NONE,
+            255 => QClass::WildCard,
+            code => QClass::Unknown(code),
+        }
+    }
+}
+
+impl From<QType> for u16 {
+    fn from(val: QType) -> Self {
+        match val {
+            QType::A => 1,
+            QType::NS => 2,
+            QType::MD => 3,
+            QType::MF => 4,
+            QType::CNAME => 5,
+            QType::SOA => 6,
+            QType::MB => 7,
+            QType::MG => 8,
+            QType::MR => 9,
+            QType::NULL => 10,
+            QType::WKS => 11,
+            QType::PTR => 12,
+            QType::HINFO => 13,
+            QType::MINFO => 14,
+            QType::MX => 15,
+            QType::TXT => 16,
+            QType::AAAA => 28,
+            QType::SRV => 33,
+            QType::NAPTR => 35,
+            QType::OPT => 41,
+            QType::DS => 43,
+            QType::SSHFP => 44,
+            QType::RRSIG => 46,
+            QType::NSEC => 47,
+            QType::DNSKEY => 48,
+            QType::NSEC3 => 50,
+            QType::NSEC3PARAM => 51,
+            QType::TLSA => 52,
+            QType::OPENPGPKEY => 61,
+            QType::SVCB => 64,
+            QType::HTTPS => 65,
+            QType::TSIG => 250,
+            QType::IXFR => 251,
+            QType::AXFR => 252,
+            QType::MAILB => 253,
+            QType::MAILA => 254,
+            QType::WildCard => 255,
+            QType::URI => 256,
+            QType::CAA => 257,
+            QType::Unknown(code) => code,
+        }
+    }
+}
+
+impl From<QClass> for u16 {
+    fn from(val: QClass) -> Self {
+        match val {
+            QClass::IN => 1,
+            QClass::CS => 2,
+            QClass::CH => 3,
+            QClass::HS => 4,
+            QClass::NONE => 254,
+            QClass::WildCard => 255,
+            QClass::Unknown(code) => code,
+        }
+    }
+}
+
+/// The mnemonic of a type, `ANY` for the wildcard query type and `TYPE<n>` for
+/// types without a variant (RFC 3597).
+impl fmt::Display for QType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QType::WildCard => write!(f, "ANY"),
+            QType::Unknown(code) => write!(f, "TYPE{}", code),
+            typ => write!(f, "{:?}", typ),
+        }
+    }
+}
+
+/// The mnemonic of a type code, or `TYPE<n>` for types without one (RFC 3597).
+pub fn type_name(code: u16) -> String {
+    QType::from(code).to_string()
+}
+
+/// The code of a type mnemonic or of `TYPE<n>` (RFC 3597), ignoring case.
+pub fn type_code(name: &str) -> Option<u16> {
+    let upper = name.to_ascii_uppercase();
+    if let Some(code) = upper.strip_prefix("TYPE").and_then(|x| x.parse().ok()) {
+        return Some(code);
+    }
+    (1..=u16::from(QType::CAA)).find(|&code| type_name(code) == upper)
+}
+
+/// A type mnemonic or `TYPE<n>` (RFC 3597).
+impl FromStr for QType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let code = type_code(s).ok_or_else(|| anyhow::anyhow!("unknown type {}", s))?;
+        Ok(QType::from(code))
+    }
+}
+
+/// The mnemonic of a class, `ANY` for the wildcard class and `CLASS<n>` for
+/// classes without a variant (RFC 3597).
+impl fmt::Display for QClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QClass::WildCard => write!(f, "ANY"),
+            QClass::Unknown(code) => write!(f, "CLASS{}", code),
+            class => write!(f, "{:?}", class),
         }
     }
 }
+
+/// A class mnemonic or `CLASS<n>` (RFC 3597), ignoring case.
+impl FromStr for QClass {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let upper = s.to_ascii_uppercase();
+        let code = match upper.strip_prefix("CLASS") {
+            Some(code) => code.parse::<u16>()?,
+            None => [
+                QClass::IN,
+                QClass::CS,
+                QClass::CH,
+                QClass::HS,
+                QClass::NONE,
+                QClass::WildCard,
+            ]
+            .into_iter()
+            .find(|x| x.to_string() == upper)
+            .ok_or_else(|| anyhow::anyhow!("unknown class {}", s))?
+            .into(),
+        };
+        Ok(QClass::from(code))
+    }
+}
@@ -0,0 +1,37 @@
+//! Framing of DNS messages over TCP, where each message is preceded by its length
+//! as a two-byte integer (RFC 1035 section 4.2.2).
+
+use std::io::{self, Read, Write};
+
+pub fn read_message(stream: &mut impl Read) -> io::Result<Vec<u8>> {
+    let mut len = [0; 2];
+    stream.read_exact(&mut len)?;
+    let mut message = vec![0; usize::from(u16::from_be_bytes(len))];
+    stream.read_exact(&mut message)?;
+    Ok(message)
+}
+
+pub fn write_message(stream: &mut impl Write, message: &[u8]) -> io::Result<()> {
+    let len = u16::try_from(message.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "message too large"))?;
+    stream.write_all(&len.to_be_bytes())?;
+    stream.write_all(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_message, write_message};
+
+    #[test]
+    fn framing_round_trip_test() {
+        let mut stream = Vec::new();
+        write_message(&mut stream, b"first").unwrap();
+        write_message(&mut stream, b"").unwrap();
+        assert_eq!(stream[..2], [0, 5]);
+
+        let mut reader = &stream[..];
+        assert_eq!(read_message(&mut reader).unwrap(), b"first");
+        assert_eq!(read_message(&mut reader).unwrap(), b"");
+        assert!(read_message(&mut reader).is_err());
+    }
+}